@@ -0,0 +1,272 @@
+//! Benchmarks of the poll-parse-render path with large synthetic inputs: 50k jobs per poll
+//! and logs of 1M lines. The performance budget they are checked against is in the README.
+
+use std::collections::HashSet;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use ratatui::{buffer::Buffer, layout::Rect, widgets::ListState};
+
+use turm::bench::{
+    build_rows, extract_metrics, parse_sacct, parse_squeue, process_terminal_output,
+    render_job_list, string_for_paragraph, transitions, Column, Job, JobCache, JobListView, JobRow,
+    RestartTracker, ScrollAnchor, Timelines, Tres, FORMAT_FIELDS, SACCT_FIELDS, SEPARATOR,
+};
+
+const JOBS: usize = 50_000;
+const LOG_LINES: usize = 1_000_000;
+
+fn now() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, 1, 1)
+        .and_then(|d| d.and_hms_opt(12, 0, 0))
+        .unwrap()
+}
+
+/// A line of `squeue` output in the format turm requests, for a job of a sweep or array.
+fn squeue_line(i: usize) -> String {
+    let running = !i.is_multiple_of(3);
+    // every other job is a task of an array of 8
+    let array = i.is_multiple_of(2);
+    let value = |field: &str| match field {
+        "jobid" => (1_000_000 + i).to_string(),
+        "name" => format!("sweep-{}-lr{}", i % 100, i % 7),
+        "state" => if running { "RUNNING" } else { "PENDING" }.to_string(),
+        "username" => format!("user{}", i % 50),
+        "timeused" => if running { "1-02:03:04" } else { "0:00" }.to_string(),
+        "tres-alloc" => "cpu=4,mem=16G,node=1,billing=4,gres/gpu=2".to_string(),
+        "partition" => "gpu".to_string(),
+        "nodelist" | "NodeList" => if running { "gpu-[01-04]" } else { "" }.to_string(),
+        "stdout" => "/scratch/%u/%x-%A_%a.out".to_string(),
+        "stderr" => "/scratch/%u/%x-%A_%a.err".to_string(),
+        "command" => "/home/user/project/run.sh --lr 0.1".to_string(),
+        "statecompact" => if running { "R" } else { "PD" }.to_string(),
+        "reason" => if running { "None" } else { "Priority" }.to_string(),
+        "qos" => "normal".to_string(),
+        "ArrayJobID" => match array {
+            true => (1_000_000 + i - i % 8).to_string(),
+            false => (1_000_000 + i).to_string(),
+        },
+        "ArrayTaskID" => match array {
+            true => (i % 8).to_string(),
+            false => "N/A".to_string(),
+        },
+        "WorkDir" => "/home/user/project".to_string(),
+        "SubmitTime" => "2024-01-01T08:00:00".to_string(),
+        "StartTime" => "2024-01-01T09:00:00".to_string(),
+        "EndTime" => "2024-01-03T09:00:00".to_string(),
+        "TimeLimit" => "2-00:00:00".to_string(),
+        "RestartCnt" => "0".to_string(),
+        "dependency" => "(null)".to_string(),
+        _ => String::new(),
+    };
+    FORMAT_FIELDS
+        .iter()
+        .map(|(f, _)| value(f) + SEPARATOR)
+        .collect()
+}
+
+fn squeue_output() -> String {
+    (0..JOBS).map(|i| squeue_line(i) + "\n").collect()
+}
+
+fn sacct_output() -> String {
+    (0..JOBS)
+        .map(|i| {
+            let values = [
+                (2_000_000 + i).to_string(),
+                format!("sweep-{}-lr{}", i % 100, i % 7),
+                if i % 5 == 0 { "FAILED" } else { "COMPLETED" }.to_string(),
+                format!("user{}", i % 50),
+                "02:03:04".to_string(),
+                "billing=4,cpu=4,gres/gpu=2,mem=16G,node=1".to_string(),
+                "gpu".to_string(),
+                "gpu-07".to_string(),
+                "sbatch --time=2-0 --gres=gpu:2 run.sh --lr 0.1".to_string(),
+                "None".to_string(),
+                "normal".to_string(),
+                "2024-01-01T08:00:00".to_string(),
+                "2024-01-01T09:00:00".to_string(),
+                "2024-01-01T11:03:04".to_string(),
+                "2-00:00:00".to_string(),
+                "/home/user/project".to_string(),
+                "123456".to_string(),
+            ];
+            assert_eq!(values.len(), SACCT_FIELDS.len());
+            values
+                .iter()
+                .map(|v| v.clone() + SEPARATOR)
+                .collect::<String>()
+                + "\n"
+        })
+        .collect()
+}
+
+/// The jobs of the next poll, where every tenth job changed its state.
+fn next_poll(jobs: &[Job]) -> Vec<Job> {
+    jobs.iter()
+        .enumerate()
+        .map(|(i, job)| {
+            let mut job = job.clone();
+            if i % 10 == 0 {
+                job.state = match job.state.as_str() {
+                    "RUNNING" => "COMPLETED",
+                    _ => "RUNNING",
+                }
+                .into();
+                job.restarts += 1;
+            }
+            job
+        })
+        .collect()
+}
+
+/// A training log with metrics, progress bars redrawn with `\r`, and some control characters.
+fn log() -> String {
+    (0..LOG_LINES)
+        .map(|i| match i % 10 {
+            0 => format!(
+                "step {} loss={:.4} lr: {:e}\n",
+                i,
+                1.0 / (i + 1) as f64,
+                1e-3
+            ),
+            1 => format!(
+                "{}%|####      | {}/{}\r{}%|#####     |\n",
+                i % 100,
+                i,
+                LOG_LINES,
+                i % 100
+            ),
+            2 => "\x1b[32mINFO\x1b[0m checkpoint saved\n".to_string(),
+            _ => format!(
+                "batch {} of epoch {} took 0.{:03}s\n",
+                i,
+                i / 1000,
+                i % 1000
+            ),
+        })
+        .collect()
+}
+
+fn parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    let squeue = squeue_output();
+    let fields = FORMAT_FIELDS.iter().map(|(f, _)| *f).collect::<Vec<_>>();
+    assert_eq!(parse_squeue(&squeue, &fields).len(), JOBS);
+    group.bench_function("squeue 50k jobs", |b| {
+        b.iter(|| parse_squeue(black_box(&squeue), &fields))
+    });
+    // the next poll of a queue where the jobs did not change
+    let mut cache = JobCache::default();
+    cache.parse_squeue(&squeue, &fields);
+    group.bench_function("squeue 50k jobs, cached", |b| {
+        b.iter(|| cache.parse_squeue(black_box(&squeue), &fields))
+    });
+    let sacct = sacct_output();
+    assert_eq!(parse_sacct(&sacct).len(), JOBS);
+    group.bench_function("sacct 50k jobs", |b| {
+        b.iter(|| parse_sacct(black_box(&sacct)))
+    });
+    let jobs = parse_squeue(&squeue, &fields);
+    group.bench_function("tres 50k jobs", |b| {
+        b.iter(|| {
+            jobs.iter()
+                .map(|j| Tres::parse(black_box(&j.tres)).cpus())
+                .sum::<u64>()
+        })
+    });
+    group.finish();
+}
+
+fn diffing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("diff");
+    group.sample_size(10);
+    let fields = FORMAT_FIELDS.iter().map(|(f, _)| *f).collect::<Vec<_>>();
+    let old = parse_squeue(&squeue_output(), &fields);
+    let new = next_poll(&old);
+    assert_eq!(transitions(&old, &new).len(), JOBS / 10);
+    group.bench_function("transitions 50k jobs", |b| {
+        b.iter(|| transitions(black_box(&old), black_box(&new)))
+    });
+    group.bench_function("timelines 50k jobs", |b| {
+        b.iter_batched(
+            Timelines::default,
+            |mut timelines| timelines.observe(&old, &new, now()),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("restarts 50k jobs", |b| {
+        b.iter_batched(
+            RestartTracker::default,
+            |mut tracker| tracker.observe(&old, &new, now()),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn logs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("log");
+    group.sample_size(10);
+    let log = log();
+    group.bench_function("terminal output 1M lines", |b| {
+        b.iter(|| process_terminal_output(black_box(&log)))
+    });
+    group.bench_function("tail 1M lines", |b| {
+        b.iter(|| string_for_paragraph(black_box(&log), 50, ScrollAnchor::Bottom, 0))
+    });
+    let names = ["loss".to_string(), "lr".to_string()];
+    group.bench_function("metrics 1M lines", |b| {
+        b.iter(|| extract_metrics(black_box(&log), &names, Some("step")))
+    });
+    group.finish();
+}
+
+fn rendering(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    group.sample_size(10);
+    let fields = FORMAT_FIELDS.iter().map(|(f, _)| *f).collect::<Vec<_>>();
+    let jobs = parse_squeue(&squeue_output(), &fields);
+    let rows = build_rows(&jobs, &[], &HashSet::new(), false, |_| false);
+    let area = Rect::new(0, 0, 160, 50);
+    group.bench_function("job list 50k jobs", |b| {
+        b.iter(|| {
+            let view = JobListView {
+                columns: &Column::ALL,
+                jobs: jobs
+                    .iter()
+                    .map(|j| JobRow {
+                        id: j.id(),
+                        state: j.state_compact.to_string(),
+                        qos: j.qos.to_string(),
+                        user: j.user.to_string(),
+                        time_used: j.time_used_display(std::time::Duration::ZERO),
+                        time_left: j.time_left(std::time::Duration::ZERO),
+                        preemption: None,
+                        alert: false,
+                        script_values: Vec::new(),
+                        progress: None,
+                        name: j.name.clone(),
+                        tags: Vec::new(),
+                        pinned: false,
+                    })
+                    .collect(),
+                rows: &rows,
+                me: "user0",
+                team: false,
+                user_colors: true,
+                focused: true,
+            };
+            let mut buffer = Buffer::empty(area);
+            let mut state = ListState::default();
+            state.select(Some(JOBS / 2));
+            render_job_list(&view, area, &mut buffer, &mut state);
+            buffer
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parsing, diffing, logs, rendering);
+criterion_main!(benches);
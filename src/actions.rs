@@ -0,0 +1,744 @@
+use std::{
+    path::{Path, PathBuf},
+    thread,
+};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use crate::app::AppMessage;
+use crate::config::{ConfirmPolicies, ConfirmPolicy};
+use crate::hooks::{self, Hook};
+use crate::nodes::NodeAction;
+use crate::pipelines::PipelineRun;
+use crate::remote::{self, slurm_command};
+
+/// Something turm changes on the cluster on behalf of the user. All actions go through
+/// `App::perform`, which asks for confirmation according to the configured [`ConfirmPolicies`].
+pub enum Action {
+    Cancel {
+        id: String,
+        user: String,
+    },
+    /// Cancels all jobs of a group.
+    CancelGroup {
+        key: String,
+        /// Ids and users of the member jobs.
+        jobs: Vec<(String, String)>,
+    },
+    Rename {
+        id: String,
+        user: String,
+        name: String,
+    },
+    Requeue {
+        id: String,
+        user: String,
+    },
+    /// Replaces the dependency of a pending job, removing it if empty.
+    SetDependency {
+        id: String,
+        user: String,
+        dependency: String,
+    },
+    /// Submits the given tasks of an array job again, e.g. `3,7,19-22`, with the submit line
+    /// captured when the action runs.
+    ResubmitTasks {
+        array_id: String,
+        work_dir: PathBuf,
+        sacct: bool,
+        user: String,
+        tasks: String,
+    },
+    Node {
+        node: String,
+        action: NodeAction,
+    },
+}
+
+/// Actions that share a confirmation policy.
+pub enum ActionClass {
+    Cancel,
+    /// Changes to jobs, such as renaming them.
+    Update,
+    Node,
+}
+
+impl Action {
+    pub fn class(&self) -> ActionClass {
+        match self {
+            Action::Cancel { .. } | Action::CancelGroup { .. } => ActionClass::Cancel,
+            Action::Rename { .. }
+            | Action::Requeue { .. }
+            | Action::SetDependency { .. }
+            | Action::ResubmitTasks { .. } => ActionClass::Update,
+            Action::Node { .. } => ActionClass::Node,
+        }
+    }
+
+    /// Whether the action affects other users, i.e. their jobs or a shared node.
+    fn affects_others(&self) -> bool {
+        let user = remote::current_user();
+        match self {
+            Action::Cancel { user: u, .. }
+            | Action::Rename { user: u, .. }
+            | Action::Requeue { user: u, .. }
+            | Action::SetDependency { user: u, .. }
+            | Action::ResubmitTasks { user: u, .. } => u != user,
+            Action::CancelGroup { jobs, .. } => jobs.iter().any(|(_, u)| u != user),
+            Action::Node { .. } => true,
+        }
+    }
+
+    pub fn needs_confirmation(&self, policies: &ConfirmPolicies) -> bool {
+        let policy = match self.class() {
+            ActionClass::Cancel => policies.cancel,
+            ActionClass::Update => policies.update,
+            ActionClass::Node => policies.node,
+        };
+        match policy {
+            ConfirmPolicy::Always => true,
+            ConfirmPolicy::Never => false,
+            ConfirmPolicy::Others => self.affects_others(),
+        }
+    }
+
+    /// Name of the action in the audit log.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Cancel { .. } | Action::CancelGroup { .. } => "cancel",
+            Action::Rename { .. } => "rename",
+            Action::Requeue { .. } => "requeue",
+            Action::SetDependency { .. } => "dependency",
+            Action::ResubmitTasks { .. } => "resubmit",
+            Action::Node { action, .. } => action.name(),
+        }
+    }
+
+    /// The jobs or node the action applies to, for the audit log.
+    pub fn target(&self) -> String {
+        match self {
+            Action::Cancel { id, .. }
+            | Action::Rename { id, .. }
+            | Action::Requeue { id, .. }
+            | Action::SetDependency { id, .. } => id.clone(),
+            Action::CancelGroup { jobs, .. } => jobs
+                .iter()
+                .map(|(id, _)| id.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+            Action::ResubmitTasks { array_id, .. } => array_id.clone(),
+            Action::Node { node, .. } => node.clone(),
+        }
+    }
+
+    /// What has to be typed to confirm destructive batch operations, which are too easy
+    /// to confirm by accident with a single key.
+    pub fn confirmation_text(&self) -> Option<&str> {
+        match self {
+            Action::CancelGroup { key, .. } => Some(key),
+            _ => None,
+        }
+    }
+
+    /// The action as a question, e.g. "Cancel job 1234?".
+    pub fn question(&self) -> String {
+        match self {
+            Action::Cancel { id, .. } => format!("Cancel job {}?", id),
+            Action::CancelGroup { key, jobs } => {
+                format!("Cancel all {} jobs of group {}?", jobs.len(), key)
+            }
+            Action::Rename { id, name, .. } => format!("Rename job {} to {}?", id, name),
+            Action::Requeue { id, .. } => format!("Requeue job {}?", id),
+            Action::SetDependency { id, dependency, .. } if dependency.is_empty() => {
+                format!("Remove the dependency of job {}?", id)
+            }
+            Action::SetDependency { id, dependency, .. } => {
+                format!("Make job {} depend on {}?", id, dependency)
+            }
+            Action::ResubmitTasks {
+                array_id, tasks, ..
+            } => format!("Resubmit tasks {} of array job {}?", tasks, array_id),
+            Action::Node {
+                node,
+                action: NodeAction::Drain(reason),
+            } => format!("Drain node {} with reason \"{}\"?", node, reason),
+            Action::Node {
+                node,
+                action: NodeAction::Resume,
+            } => format!("Resume node {}?", node),
+        }
+    }
+
+    /// Runs the action, returning a message for the status line either way.
+    pub fn run(&self) -> Result<String, String> {
+        match self {
+            Action::Cancel { id, .. } => scancel(&[id]).map(|()| format!("Cancelled job {}", id)),
+            Action::CancelGroup { key, jobs } => {
+                let ids = jobs.iter().map(|(id, _)| id.as_str()).collect::<Vec<_>>();
+                scancel(&ids).map(|()| format!("Cancelled {} jobs of group {}", ids.len(), key))
+            }
+            Action::Rename { id, name, .. } => {
+                match slurm_command("scontrol")
+                    .arg("update")
+                    .arg(format!("JobId={}", id))
+                    .arg(format!("JobName={}", name))
+                    .output()
+                {
+                    Ok(output) if output.status.success() => {
+                        Ok(format!("Renamed job {} to {}", id, name))
+                    }
+                    Ok(output) => Err(format!(
+                        "Failed to rename job {}: {}",
+                        id,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )),
+                    Err(e) => Err(format!("Failed to execute scontrol: {}", e)),
+                }
+            }
+            Action::Requeue { id, .. } => {
+                match slurm_command("scontrol").args(["requeue", id]).output() {
+                    Ok(output) if output.status.success() => Ok(format!("Requeued job {}", id)),
+                    Ok(output) => Err(format!(
+                        "Failed to requeue job {}: {}",
+                        id,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )),
+                    Err(e) => Err(format!("Failed to execute scontrol: {}", e)),
+                }
+            }
+            Action::SetDependency { id, dependency, .. } => {
+                match slurm_command("scontrol")
+                    .arg("update")
+                    .arg(format!("JobId={}", id))
+                    .arg(format!("Dependency={}", dependency))
+                    .output()
+                {
+                    Ok(output) if output.status.success() && dependency.is_empty() => {
+                        Ok(format!("Removed the dependency of job {}", id))
+                    }
+                    Ok(output) if output.status.success() => {
+                        Ok(format!("Job {} now depends on {}", id, dependency))
+                    }
+                    Ok(output) => Err(format!(
+                        "Failed to update the dependency of job {}: {}",
+                        id,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )),
+                    Err(e) => Err(format!("Failed to execute scontrol: {}", e)),
+                }
+            }
+            Action::ResubmitTasks {
+                array_id,
+                work_dir,
+                sacct,
+                tasks,
+                ..
+            } => Resubmission::capture(array_id, work_dir, *sacct)
+                .ok_or_else(|| format!("The submit line of job {} is not known", array_id))?
+                .with_array(tasks)
+                .run()
+                .map(|message| format!("{} (tasks {})", message, tasks)),
+            Action::Node { node, action } => match action.run(node) {
+                Ok(()) => Ok(format!("{}: {} done", node, action.name())),
+                Err(e) => Err(format!("{}: {} failed: {}", node, action.name(), e)),
+            },
+        }
+    }
+}
+
+fn scancel(ids: &[&str]) -> Result<(), String> {
+    match slurm_command("scancel").args(ids).output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(format!(
+            "Failed to cancel {}: {}",
+            ids.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(e) => Err(format!("Failed to execute scancel: {}", e)),
+    }
+}
+
+/// How to submit a cancelled job again, captured before cancelling it.
+pub struct Resubmission {
+    pub id: String,
+    /// The command line the job was submitted with, e.g. `sbatch --mem=4G train.sh`.
+    submit_line: String,
+    /// The submit line split into arguments, which are run without a shell.
+    args: Vec<String>,
+    work_dir: PathBuf,
+}
+
+/// Joins arguments into a command line, quoting only those that need it.
+fn join(args: &[String]) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_=.,/:@%+".contains(c);
+    args.iter()
+        .map(|arg| match !arg.is_empty() && arg.chars().all(plain) {
+            true => arg.to_string(),
+            false => shell_words::quote(arg).into_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Short sbatch options that take no argument.
+const SHORT_FLAGS: &str = "hHIkOQsvVW";
+
+/// Long sbatch options that take no argument, or only an optional one after `=`.
+const LONG_FLAGS: &[&str] = &[
+    "--contiguous",
+    "--exclusive",
+    "--get-user-env",
+    "--help",
+    "--hold",
+    "--ignore-pbs",
+    "--immediate",
+    "--no-kill",
+    "--no-requeue",
+    "--overcommit",
+    "--oversubscribe",
+    "--parsable",
+    "--quiet",
+    "--requeue",
+    "--spread-job",
+    "--test-only",
+    "--usage",
+    "--use-min-nodes",
+    "--verbose",
+    "--version",
+    "--wait",
+];
+
+/// The index of the batch script in the arguments of sbatch, after the options and their
+/// values. Everything from there on belongs to the script. Without a script (`--wrap`), this
+/// is the number of arguments.
+fn script_index(args: &[String]) -> usize {
+    let mut i = 1;
+    while let Some(arg) = args.get(i) {
+        let takes_value = if arg.starts_with("--") {
+            !arg.contains('=') && !LONG_FLAGS.contains(&arg.as_str())
+        } else if let Some(option) = arg.strip_prefix('-').filter(|o| !o.is_empty()) {
+            // a short option followed by its value, e.g. `-p gpu`, not `-pgpu`
+            option.len() == 1 && !SHORT_FLAGS.contains(option)
+        } else {
+            return i;
+        };
+        i += if takes_value { 2 } else { 1 };
+    }
+    args.len()
+}
+
+impl Resubmission {
+    /// Captures the submit line from `sacct`, or from `scontrol` if accounting is not
+    /// available (only recent Slurm versions report it there).
+    pub fn capture(id: &str, work_dir: &Path, sacct: bool) -> Option<Resubmission> {
+        let from_sacct = || {
+            let output = slurm_command("sacct")
+                .args(["--jobs", id, "--allocations", "--noheader", "--parsable2"])
+                .args(["--format", "SubmitLine"])
+                .output()
+                .ok()?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            stdout.lines().next().map(|l| l.trim().to_string())
+        };
+        let from_scontrol = || {
+            let output = slurm_command("scontrol")
+                .args(["show", "job", id])
+                .output()
+                .ok()?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            stdout
+                .lines()
+                .find_map(|l| l.trim().strip_prefix("SubmitLine="))
+                .map(|l| l.to_string())
+        };
+        let submit_line = sacct
+            .then(from_sacct)
+            .flatten()
+            .filter(|l| !l.is_empty())
+            .or_else(from_scontrol)
+            .filter(|l| !l.is_empty())?;
+        Resubmission::new(id, submit_line, work_dir)
+    }
+
+    fn new(id: &str, submit_line: String, work_dir: &Path) -> Option<Resubmission> {
+        let args = shell_words::split(&submit_line).ok()?;
+        (!args.is_empty()).then(|| Resubmission {
+            id: id.to_string(),
+            submit_line,
+            args,
+            work_dir: work_dir.to_path_buf(),
+        })
+    }
+
+    fn with_args(&self, args: Vec<String>) -> Resubmission {
+        Resubmission {
+            id: self.id.clone(),
+            submit_line: join(&args),
+            args,
+            work_dir: self.work_dir.clone(),
+        }
+    }
+
+    /// The command line the job was submitted with.
+    pub fn submit_line(&self) -> &str {
+        &self.submit_line
+    }
+
+    /// The same submission for only the given array tasks, replacing the `--array` option of
+    /// the submit line.
+    pub fn with_array(&self, tasks: &str) -> Resubmission {
+        self.with_option("--array", "-a", tasks)
+    }
+
+    /// The same submission with the option (long and short form) set to the value, replacing
+    /// it among the options of sbatch. Options on the command line take precedence over
+    /// `#SBATCH` lines. The arguments of the script are left alone.
+    pub fn with_option(&self, long: &str, short: &str, value: &str) -> Resubmission {
+        let script = script_index(&self.args);
+        let mut args = vec![self.args[0].clone(), format!("{}={}", long, value)];
+        let mut options = self.args[1..script].iter();
+        while let Some(arg) = options.next() {
+            if arg == long || arg == short {
+                options.next();
+            } else if !(arg.starts_with(&format!("{}=", long)) || arg.starts_with(short)) {
+                args.push(arg.clone());
+            }
+        }
+        args.extend_from_slice(&self.args[script..]);
+        self.with_args(args)
+    }
+
+    /// Runs sbatch with the arguments in the original working directory, without a shell.
+    fn run_args(&self, args: &[String]) -> std::io::Result<std::process::Output> {
+        slurm_command(&args[0])
+            .args(&args[1..])
+            .current_dir(&self.work_dir)
+            .output()
+    }
+
+    /// Asks `sbatch --test-only` when the submission would start, without submitting it.
+    /// Returns sbatch's answer, e.g. `Job 1234 to start at 2024-05-01T12:00:00 using 8
+    /// processors on nodes gpu-17 in partition gpu`.
+    pub fn test_only(&self) -> Result<String, String> {
+        let mut args = self.args.clone();
+        args.insert(1, "--test-only".to_string());
+        let output = self
+            .run_args(&args)
+            .map_err(|e| format!("Failed to run sbatch: {}", e))?;
+        // sbatch answers on stderr
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let answer = stderr
+            .lines()
+            .map(|l| l.trim().strip_prefix("sbatch: ").unwrap_or(l.trim()))
+            .filter(|l| !l.is_empty());
+        if output.status.success() {
+            answer
+                .clone()
+                .find(|l| l.contains(" to start at "))
+                .or_else(|| answer.clone().next_back())
+                .map(|l| l.to_string())
+                .ok_or_else(|| "sbatch gave no estimate".to_string())
+        } else {
+            Err(answer
+                .map(|l| l.strip_prefix("error: ").unwrap_or(l))
+                .collect::<Vec<_>>()
+                .join(" "))
+        }
+    }
+
+    /// Runs the submit line again in the original working directory.
+    pub fn run(&self) -> Result<String, String> {
+        let output = self
+            .run_args(&self.args)
+            .map_err(|e| format!("Failed to resubmit job {}: {}", self.id, e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to resubmit job {}: {}",
+                self.id,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(match stdout.trim().strip_prefix("Submitted batch job ") {
+            Some(new_id) => format!("Resubmitted job {} as job {}", self.id, new_id),
+            None => format!("Resubmitted job {}", self.id),
+        })
+    }
+}
+
+/// What an action run by the [`ActionRunnerHandle`] did.
+pub struct ActionOutcome {
+    pub action: String,
+    pub target: String,
+    pub result: Result<String, String>,
+    /// What is left to do in the app if the action succeeded.
+    pub follow_up: Option<FollowUp>,
+    /// The node the action changed, whose list is shown again.
+    pub node: Option<String>,
+}
+
+pub enum FollowUp {
+    /// How to submit the job again, after cancelling a pending job.
+    Undo(Resubmission),
+    /// A submitted pipeline, to be remembered.
+    Pipeline(PipelineRun),
+}
+
+type ActionFn = Box<dyn FnOnce() -> (Result<String, String>, Option<FollowUp>) + Send>;
+
+enum Task {
+    Action(ActionTask),
+    /// Captures the submit line of a job for the app, answered with [`AppMessage::Captured`].
+    Capture {
+        id: String,
+        work_dir: PathBuf,
+        sacct: bool,
+    },
+}
+
+struct ActionTask {
+    action: String,
+    target: String,
+    env: Vec<(&'static str, String)>,
+    hooks: Vec<Hook>,
+    node: Option<String>,
+    run: ActionFn,
+}
+
+/// Runs actions and their hooks in the background, in the order they were started, since
+/// `scancel` and friends wait for slurmctld.
+struct ActionRunner {
+    app: Sender<AppMessage>,
+    receiver: Receiver<Task>,
+}
+
+impl ActionRunner {
+    fn run(&mut self) {
+        while let Ok(task) = self.receiver.recv() {
+            let task = match task {
+                Task::Action(task) => task,
+                Task::Capture {
+                    id,
+                    work_dir,
+                    sacct,
+                } => {
+                    let resubmission = Resubmission::capture(&id, &work_dir, sacct);
+                    let _ = self.app.send(AppMessage::Captured(id, resubmission));
+                    continue;
+                }
+            };
+            let (result, follow_up) = match hooks::run_pre(&task.hooks, &task.action, &task.env) {
+                Ok(()) => {
+                    let (result, follow_up) = (task.run)();
+                    hooks::run_post(&task.hooks, &task.action, &task.env, &result);
+                    (result, follow_up)
+                }
+                Err(e) => (Err(e), None),
+            };
+            let _ = self.app.send(AppMessage::ActionDone(ActionOutcome {
+                action: task.action,
+                target: task.target,
+                follow_up: follow_up.filter(|_| result.is_ok()),
+                result,
+                node: task.node,
+            }));
+        }
+    }
+}
+
+pub struct ActionRunnerHandle {
+    sender: Sender<Task>,
+}
+
+impl ActionRunnerHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = ActionRunner { app, receiver };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Runs the action between the hooks configured for it. `run` also returns what is left
+    /// to do afterwards, such as remembering how to undo it.
+    pub fn run(
+        &self,
+        action: &str,
+        target: &str,
+        env: Vec<(&'static str, String)>,
+        hooks: &[Hook],
+        node: Option<String>,
+        run: impl FnOnce() -> (Result<String, String>, Option<FollowUp>) + Send + 'static,
+    ) {
+        let _ = self.sender.send(Task::Action(ActionTask {
+            action: action.to_string(),
+            target: target.to_string(),
+            env,
+            hooks: hooks.to_vec(),
+            node,
+            run: Box::new(run),
+        }));
+    }
+
+    /// Captures how the job was submitted, in order with the actions.
+    pub fn capture(&self, id: String, work_dir: PathBuf, sacct: bool) {
+        let _ = self.sender.send(Task::Capture {
+            id,
+            work_dir,
+            sacct,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resubmission(submit_line: &str) -> Resubmission {
+        Resubmission::new("1234", submit_line.to_string(), Path::new("/home/alice")).unwrap()
+    }
+
+    #[test]
+    fn submit_line_arguments() {
+        let r = resubmission(r#"sbatch --wrap="python a.py; rm -rf ~" --mem=4G"#);
+        assert_eq!(
+            r.args,
+            ["sbatch", "--wrap=python a.py; rm -rf ~", "--mem=4G"]
+        );
+        assert!(Resubmission::new("1", "sbatch 'train.sh".to_string(), Path::new("/")).is_none());
+        assert!(Resubmission::new("1", "  ".to_string(), Path::new("/")).is_none());
+    }
+
+    #[test]
+    fn script_arguments() {
+        let args = |line: &str| shell_words::split(line).unwrap();
+        assert_eq!(script_index(&args("sbatch train.sh -p 1")), 1);
+        assert_eq!(script_index(&args("sbatch -p gpu -H --mem=4G train.sh")), 5);
+        assert_eq!(
+            script_index(&args("sbatch --qos normal --hold -pgpu train.sh")),
+            5
+        );
+        assert_eq!(
+            script_index(&args("sbatch --wrap 'sleep 1' --exclusive")),
+            4
+        );
+        assert_eq!(script_index(&args("sbatch -p")), 2);
+    }
+
+    #[test]
+    fn with_array() {
+        for line in [
+            "sbatch --array=0-9 train.sh",
+            "sbatch --array 0-9 train.sh",
+            "sbatch -a 0-9 train.sh",
+            "sbatch -a0-9 train.sh",
+        ] {
+            let r = resubmission(line).with_array("3,7");
+            assert_eq!(r.submit_line(), "sbatch --array=3,7 train.sh", "{line}");
+        }
+        // options of the script are its own
+        let r = resubmission("sbatch -a 0-9 -p gpu train.sh -a 5 --array=3 -pfoo");
+        assert_eq!(
+            r.with_array("3").args,
+            [
+                "sbatch",
+                "--array=3",
+                "-p",
+                "gpu",
+                "train.sh",
+                "-a",
+                "5",
+                "--array=3",
+                "-pfoo"
+            ]
+        );
+    }
+
+    #[test]
+    fn with_option() {
+        let r = resubmission("/usr/bin/sbatch -p cpu --qos=long -H train.sh --partition x");
+        let r = r.with_option("--partition", "-p", "gpu");
+        assert_eq!(
+            r.args,
+            [
+                "/usr/bin/sbatch",
+                "--partition=gpu",
+                "--qos=long",
+                "-H",
+                "train.sh",
+                "--partition",
+                "x"
+            ]
+        );
+        let r = r.with_option("--qos", "-q", "short");
+        assert_eq!(
+            r.submit_line(),
+            "/usr/bin/sbatch --qos=short --partition=gpu -H train.sh --partition x"
+        );
+
+        // quoted arguments stay intact
+        let r = resubmission(r#"sbatch -p cpu --wrap="python a.py --lr 0.1""#);
+        let r = r.with_option("--partition", "-p", "gpu");
+        assert_eq!(
+            r.args,
+            ["sbatch", "--partition=gpu", "--wrap=python a.py --lr 0.1"]
+        );
+        assert_eq!(
+            r.submit_line(),
+            "sbatch --partition=gpu '--wrap=python a.py --lr 0.1'"
+        );
+    }
+
+    #[test]
+    fn confirmation_policies() {
+        let me = remote::current_user().to_string();
+        let other = format!("{}-colleague", me);
+        let cancel = |user: &str| Action::Cancel {
+            id: "1".to_string(),
+            user: user.to_string(),
+        };
+        let rename = |user: &str| Action::Rename {
+            id: "1".to_string(),
+            user: user.to_string(),
+            name: "eval".to_string(),
+        };
+        let group = |users: &[&str]| Action::CancelGroup {
+            key: "train".to_string(),
+            jobs: users
+                .iter()
+                .enumerate()
+                .map(|(i, u)| (i.to_string(), u.to_string()))
+                .collect(),
+        };
+        let resume = Action::Node {
+            node: "gpu-01".to_string(),
+            action: NodeAction::Resume,
+        };
+
+        let defaults = ConfirmPolicies::default();
+        assert!(cancel(&me).needs_confirmation(&defaults));
+        assert!(!rename(&other).needs_confirmation(&defaults));
+        assert!(resume.needs_confirmation(&defaults));
+
+        let others = ConfirmPolicies {
+            cancel: ConfirmPolicy::Others,
+            update: ConfirmPolicy::Others,
+            node: ConfirmPolicy::Others,
+        };
+        assert!(!cancel(&me).needs_confirmation(&others));
+        assert!(cancel(&other).needs_confirmation(&others));
+        assert!(!rename(&me).needs_confirmation(&others));
+        assert!(rename(&other).needs_confirmation(&others));
+        assert!(!group(&[&me, &me]).needs_confirmation(&others));
+        assert!(group(&[&me, &other]).needs_confirmation(&others));
+        // nodes are shared with everyone
+        assert!(resume.needs_confirmation(&others));
+
+        let never = ConfirmPolicies {
+            cancel: ConfirmPolicy::Never,
+            update: ConfirmPolicy::Never,
+            node: ConfirmPolicy::Never,
+        };
+        assert!(!cancel(&other).needs_confirmation(&never));
+        assert!(!resume.needs_confirmation(&never));
+    }
+}
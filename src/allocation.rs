@@ -0,0 +1,123 @@
+use std::thread;
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use regex::Regex;
+
+use crate::app::AppMessage;
+use crate::hostlist;
+use crate::remote::slurm_command;
+
+/// What a job was allocated on one of its nodes.
+#[derive(Clone)]
+pub struct NodeAllocation {
+    pub node: String,
+    pub cpus: usize,
+    pub mem_mb: Option<u64>,
+    /// The indices of the GPUs, e.g. `0-1`.
+    pub gpus: Option<String>,
+}
+
+impl NodeAllocation {
+    /// The allocation in a few words, e.g. `gpu-01 (8 CPUs, 16G, GPU 0-1)`.
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("{} CPUs", self.cpus)];
+        if let Some(mem) = self.mem_mb {
+            // in Slurm's units, as in `--mem`
+            parts.push(match mem.is_multiple_of(1024) {
+                true => format!("{}G", mem / 1024),
+                false => format!("{}M", mem),
+            });
+        }
+        if let Some(gpus) = &self.gpus {
+            parts.push(format!("GPU {}", gpus));
+        }
+        format!("{} ({})", self.node, parts.join(", "))
+    }
+}
+
+/// The number of CPUs in a list of CPU ids such as `0-7,16-23`.
+fn count_ids(ids: &str) -> usize {
+    ids.split(',')
+        .filter_map(|range| {
+            let (first, last) = range.split_once('-').unwrap_or((range, range));
+            Some(last.parse::<usize>().ok()? + 1 - first.parse::<usize>().ok()?)
+        })
+        .sum()
+}
+
+/// The allocation per node from the detail lines of `scontrol show job -d`, e.g.
+/// `Nodes=gpu-[01-02] CPU_IDs=0-7 Mem=16384 GRES=gpu:a100:2(IDX:0-1)`.
+pub fn parse_allocation(output: &str) -> Vec<NodeAllocation> {
+    let gpu_ids = Regex::new(r"gpu[^(),]*\(IDX:([^)]*)\)").unwrap();
+    let mut allocation = Vec::new();
+    for line in output.lines().map(str::trim) {
+        if !line.starts_with("Nodes=") {
+            continue;
+        }
+        let field = |name: &str| {
+            line.split_whitespace()
+                .find_map(|f| f.strip_prefix(name)?.strip_prefix('='))
+        };
+        let cpus = field("CPU_IDs").map_or(0, count_ids);
+        let mem_mb = field("Mem").and_then(|m| m.parse().ok());
+        let gpus = field("GRES")
+            .and_then(|g| gpu_ids.captures(g))
+            .map(|c| c[1].to_string());
+        for node in hostlist::expand(field("Nodes").unwrap_or_default()) {
+            allocation.push(NodeAllocation {
+                node,
+                cpus,
+                mem_mb,
+                gpus: gpus.clone(),
+            });
+        }
+    }
+    allocation
+}
+
+fn allocation(job_id: &str) -> Result<Vec<NodeAllocation>, String> {
+    let output = slurm_command("scontrol")
+        .args(["show", "job", "-d", job_id])
+        .output()
+        .map_err(|e| format!("Failed to execute scontrol: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "scontrol failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_allocation(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Looks up the allocations of jobs in the background, one at a time.
+struct AllocationWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<String>,
+}
+
+impl AllocationWatcher {
+    fn run(&mut self) {
+        while let Ok(job_id) = self.receiver.recv() {
+            let result = allocation(&job_id);
+            let _ = self.app.send(AppMessage::Allocation(job_id, result));
+        }
+    }
+}
+
+pub struct AllocationWatcherHandle {
+    sender: Sender<String>,
+}
+
+impl AllocationWatcherHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = AllocationWatcher { app, receiver };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Looks up the nodes, CPUs and GPUs a running job was allocated.
+    pub fn request(&self, job_id: String) {
+        let _ = self.sender.send(job_id);
+    }
+}
@@ -0,0 +1,79 @@
+use std::{path::PathBuf, thread, time::Duration};
+
+use crossbeam::{
+    channel::{unbounded, Receiver, Sender},
+    select,
+};
+
+use crate::app::AppMessage;
+use crate::log_source::{log_source, LogSource, Throttle};
+
+/// How often the announcement file is re-read.
+const INTERVAL: Duration = Duration::from_secs(60);
+
+/// Watches the cluster-wide announcement file (e.g. about upcoming maintenance) that admins
+/// configure via `announcement_file`, and hands its content to the app whenever it changes.
+struct AnnouncementWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<Option<PathBuf>>,
+    path: Option<PathBuf>,
+    source: Box<dyn LogSource>,
+    content: Option<String>,
+}
+
+impl AnnouncementWatcher {
+    fn read(&mut self) -> Option<String> {
+        let path = self.path.as_ref()?;
+        // a missing file means there is nothing to announce
+        let content = self.source.read_from(path, 0).ok()?;
+        Some(String::from_utf8_lossy(&content).trim().to_string()).filter(|c| !c.is_empty())
+    }
+
+    fn run(&mut self) {
+        loop {
+            let content = self.read();
+            if content != self.content {
+                self.content = content.clone();
+                if self.app.send(AppMessage::Announcement(content)).is_err() {
+                    return;
+                }
+            }
+
+            select! {
+                recv(self.receiver) -> msg => match msg {
+                    Ok(path) => self.path = path,
+                    Err(_) => return,
+                },
+                default(INTERVAL) => {}
+            }
+        }
+    }
+}
+
+pub struct AnnouncementWatcherHandle {
+    sender: Sender<Option<PathBuf>>,
+    path: Option<PathBuf>,
+}
+
+impl AnnouncementWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, path: Option<PathBuf>, throttle: Throttle) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = AnnouncementWatcher {
+            app,
+            receiver,
+            path: path.clone(),
+            source: log_source(throttle),
+            content: None,
+        };
+        thread::spawn(move || actor.run());
+
+        Self { sender, path }
+    }
+
+    pub fn set_path(&mut self, path: Option<PathBuf>) {
+        if self.path != path {
+            self.path = path.clone();
+            let _ = self.sender.send(path);
+        }
+    }
+}
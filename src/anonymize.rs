@@ -0,0 +1,135 @@
+use std::{collections::BTreeMap, path::Path};
+
+use ratatui::buffer::Buffer;
+
+use crate::app::Job;
+
+/// Words of paths that reveal nothing and are kept, so that paths stay recognizable.
+const KEPT_WORDS: [&str; 16] = [
+    "home", "tmp", "scratch", "work", "data", "projects", "users", "var", "log", "logs", "out",
+    "err", "slurm", "sh", "py", "txt",
+];
+
+/// Characters of a word, which usernames, job names and path components are split into.
+/// Dots separate words, so that file extensions are kept.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || "-_+@%~".contains(c)
+}
+
+fn words(s: &str) -> impl Iterator<Item = &str> {
+    s.split(|c| !is_word_char(c)).filter(|w| !w.is_empty())
+}
+
+/// A stable pseudonym of the same length: letters are replaced by letters derived from the
+/// word (keeping their case), digits and punctuation are kept.
+fn pseudonym(word: &str) -> String {
+    // FNV-1a, since the std hashers may change between Rust versions
+    let mut state = word.bytes().fold(0x811c9dc5u32, |hash, b| {
+        (hash ^ b as u32).wrapping_mul(0x01000193)
+    });
+    word.chars()
+        .map(|c| {
+            if !c.is_alphabetic() {
+                return c;
+            }
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            let letter = (b'a' + ((state >> 16) % 26) as u8) as char;
+            if c.is_uppercase() {
+                letter.to_ascii_uppercase()
+            } else {
+                letter
+            }
+        })
+        .collect()
+}
+
+/// Masks usernames, job names and paths on screen with stable pseudonyms, e.g. for sharing
+/// screenshots. The rendered frame is masked word by word, so every view, log output and
+/// snapshot is covered while the jobs themselves keep their real values for actions.
+#[derive(Default)]
+pub struct Anonymizer {
+    pseudonyms: BTreeMap<String, String>,
+}
+
+impl Anonymizer {
+    fn add(&mut self, s: &str, min_len: usize) {
+        for word in words(s) {
+            let len = word.chars().count();
+            if len < min_len
+                || word.chars().all(|c| !c.is_alphabetic())
+                || KEPT_WORDS.contains(&word.to_lowercase().as_str())
+            {
+                continue;
+            }
+            self.pseudonyms
+                .entry(word.to_string())
+                .or_insert_with(|| pseudonym(word));
+        }
+    }
+
+    fn add_path(&mut self, path: &Path) {
+        for component in path.iter() {
+            self.add(&component.to_string_lossy(), 3);
+        }
+    }
+
+    /// Learns the users, names and paths of the jobs, besides my own username.
+    pub fn learn(&mut self, jobs: &[Job], me: &str) {
+        self.add(me, 2);
+        for job in jobs {
+            self.add(&job.user, 2);
+            self.add(&job.name, 3);
+            for path in [&job.stdout, &job.stderr, &job.work_dir]
+                .into_iter()
+                .flatten()
+            {
+                self.add_path(path);
+            }
+            self.add_path(Path::new(&job.command));
+        }
+    }
+
+    /// The pseudonym of a word on screen, or of its beginning if it was cut off, e.g. in a
+    /// narrow column.
+    fn lookup(&self, word: &str) -> Option<String> {
+        if let Some(pseudonym) = self.pseudonyms.get(word) {
+            return Some(pseudonym.clone());
+        }
+        if word.chars().count() < 4 {
+            return None;
+        }
+        let (original, pseudonym) = self.pseudonyms.range(word.to_string()..).next()?;
+        original
+            .starts_with(word)
+            .then(|| pseudonym.chars().take(word.chars().count()).collect())
+    }
+
+    /// Replaces the known words in the rendered frame.
+    pub fn apply(&self, buffer: &mut Buffer) {
+        let area = buffer.area;
+        for y in area.top()..area.bottom() {
+            let row = (area.left()..area.right())
+                .map(|x| buffer.get(x, y).symbol().chars().next().unwrap_or(' '))
+                .collect::<Vec<_>>();
+            let mut x = 0;
+            while x < row.len() {
+                if !is_word_char(row[x]) {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < row.len() && is_word_char(row[x]) {
+                    x += 1;
+                }
+                let word = row[start..x].iter().collect::<String>();
+                let Some(pseudonym) = self.lookup(&word) else {
+                    continue;
+                };
+                for (i, c) in pseudonym.chars().enumerate() {
+                    let cell = buffer.get_mut(area.left() + (start + i) as u16, y);
+                    cell.set_symbol(c.encode_utf8(&mut [0; 4]));
+                }
+            }
+        }
+    }
+}
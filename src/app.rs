@@ -1,31 +1,253 @@
 use crossbeam::{
-    channel::{unbounded, Receiver},
+    channel::{never, tick, unbounded, Receiver, Sender},
     select,
 };
-use std::{cmp::min, path::PathBuf, process::Command};
-use std::{process::Stdio, time::Duration};
+use std::time::{Duration, Instant};
+use std::{
+    cmp::min,
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+};
 
-use crate::file_watcher::{FileWatcherError, FileWatcherHandle};
+use crate::actions::{Action, ActionOutcome, ActionRunnerHandle, FollowUp, Resubmission};
+use crate::allocation::{AllocationWatcherHandle, NodeAllocation};
+use crate::announcement::AnnouncementWatcherHandle;
+use crate::anonymize::Anonymizer;
+use crate::archive::{self, ArchivedJob, ArchiverHandle};
+use crate::audit;
+use crate::backfill::{BackfillQuery, BackfillWatcherHandle};
+use crate::budget::{format_core_hours, BudgetStatus, BudgetWatcherHandle};
+use crate::capabilities::Capabilities;
+use crate::clock;
+use crate::completing::{self, CompletingTracker};
+use crate::config::{Config, ConfigError};
+use crate::config_watcher::ConfigWatcherHandle;
+use crate::dependency;
+use crate::dispatcher;
+use crate::duplicates::{Candidate, DuplicateWatcherHandle};
+use crate::efficiency::{self, Efficiency, EfficiencyQuerierHandle};
+use crate::email::MailerHandle;
+use crate::file_watcher::{format_bytes, FileWatcherError, FileWatcherHandle, JobOutput};
+use crate::gantt::timeline_lines;
+use crate::heatmap::{heatmap_lines, NodeWatcherHandle};
+use crate::history::HistoryRecorderHandle;
+use crate::hostlist;
+use crate::idle::{IdleQuery, IdleWatcherHandle};
+use crate::intern::Interned;
+use crate::ipc::{IpcRequest, IpcServerHandle};
+use crate::job_commands;
+use crate::job_list::{build_rows, JobListRow, RowId};
 use crate::job_watcher::JobWatcherHandle;
+use crate::jobs_file::{JobsFile, JobsFileWatcherHandle};
+use crate::leaderboard;
+use crate::links::{self, Hyperlink};
+use crate::log_diagnosis::{Diagnosis, LogDiagnosticsHandle, LogQuery};
+use crate::log_search::{self, LogSearchHandle, SearchResult};
+use crate::log_source::{is_node_local, JobNode};
+use crate::manifest::ManifestExporterHandle;
+use crate::matrix;
+use crate::metrics::{self, Series};
+use crate::node_health::{Health, HealthLevel, NodeHealthWatcherHandle};
+use crate::node_load::{self, NodeLoad, NodeLoadWatcherHandle};
+use crate::nodes::{Node, NodeAction, NodeListerHandle, PingerHandle};
+use crate::notifications::{
+    self, parse_snooze, HistoryEntry, QuietHours, RuleTarget, Transition, CRITICAL_STATES,
+    HISTORY_LEN,
+};
+use crate::pipelines;
+use crate::plugins::{PluginHostHandle, PluginOutput};
+use crate::preemption::{PreemptQuery, Preemptible, PreemptionWatcherHandle};
+use crate::prefetch::{format_exit_code, JobDetails, PrefetcherHandle, Step};
+use crate::processes::{ProcessList, ProcessWatcherHandle};
+use crate::progress::{Progress, ProgressWatcherHandle};
+use crate::remote;
+use crate::requeue::RestartTracker;
+use crate::sacctmgr::{Association, AssociationListerHandle};
+use crate::scripting::{ScriptEvaluatorHandle, ScriptValues};
+use crate::settings::Setting;
+use crate::slurm_time::{format_duration, format_relative, format_slurm_duration};
+use crate::snapshot::{self, FrameDumper};
+use crate::sparkline::QueueHistory;
+use crate::squeue_args::SqueueArgs;
+use crate::state::State;
+use crate::states;
+use crate::steps::{StepOutput, StepWatcherHandle};
+use crate::suggest::{self, SuggesterHandle, Suggestion};
+use crate::timeline::Timelines;
+use crate::tres::Tres;
+use crate::triage::{self, Triage, TriageHandle};
+use crate::usage::{Usage, UsageWatcherHandle};
+use crate::usage_report::{ReportPeriod, UsageReportHandle, Utilization};
+use crate::view::{
+    details_height, events_field, job_fields, progress_bar, render_details, render_job_list,
+    render_log, summary_line, Field, JobListView, JobRow, LogView, SummaryView,
+};
+use crate::what_if::{Estimate, WhatIfHandle};
+
+use chrono::NaiveDateTime;
 
 use crossterm::event::{Event, KeyCode, KeyEvent};
 use ratatui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    symbols,
+    text::{Line, Span},
+    widgets::{
+        Axis, Block, Borders, Chart, Clear, Dataset, GraphType, List, ListItem, ListState,
+        Paragraph, Wrap,
+    },
     Frame, Terminal,
 };
 use std::io;
 
+const STATUS_MESSAGE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a cancelled pending job can be resubmitted with `u`.
+const UNDO_WINDOW: Duration = Duration::from_secs(10);
+/// Longer announcements are cut off, so that they do not push the job list off the screen.
+const MAX_ANNOUNCEMENT_LINES: usize = 3;
+
 pub enum Focus {
     Jobs,
     Stdout,
 }
 
 pub enum Dialog {
-    ConfirmCancelJob(String),
+    /// Action awaiting confirmation.
+    Confirm(Action),
+    /// Action that is only run once its confirmation text was typed.
+    ConfirmTyped(Action, String),
+    Input(InputTarget, String),
+    /// My associations, `None` while sacctmgr is asked.
+    Associations(Option<io::Result<Vec<Association>>>, ListState),
+    Settings(ListState),
+    /// Gantt chart of my jobs, scrolled down by the given number of lines.
+    Timeline(u16),
+    /// Per-user summary of the jobs in the scope.
+    Users(ListState),
+    /// All nodes, `None` while sinfo is asked.
+    Nodes(Option<io::Result<Vec<Node>>>, ListState),
+    /// The nodes of a job, expanded from its host list.
+    JobNodes(String, Vec<String>, ListState),
+    /// Panels of the plugins, scrolled down by the given number of lines.
+    Plugins(u16),
+    /// Recent actions from the audit log.
+    Activity(io::Result<Vec<audit::Entry>>, ListState),
+    /// Command palette with the configured job commands.
+    Commands(ListState),
+    /// Quiet hours (first row) and notification rules.
+    Notifications(ListState),
+    /// All state transitions since the start, newest first.
+    History(ListState),
+    /// One of my jobs entered a critical state.
+    Alert(Box<Job>),
+    /// Failed tasks of an array job bucketed by error, once they are read.
+    Triage(String, Option<Triage>, ListState),
+    /// Message for the admins about a job stuck in COMPLETING, scrolled down by the given
+    /// number of lines.
+    Escalation(String, u16),
+    /// Requested vs used resources of finished jobs.
+    Efficiency(Option<Result<Vec<Efficiency>, String>>, ListState),
+    /// Tasks of an array job as a grid, with the index of the task under the cursor and the
+    /// number of columns of the last render.
+    Matrix(String, usize, usize),
+    /// Estimated starts of a pending job in other partitions and QOS, once they are known.
+    WhatIf(String, Option<Result<Vec<Estimate>, String>>, ListState),
+    /// The output of a job (first row) and of its steps, once they are known.
+    Steps(String, Option<Result<Vec<StepOutput>, String>>, ListState),
+    /// Hits of a search in the logs of past jobs, while they are searched `None`.
+    LogSearch(String, Option<SearchResult>, ListState),
+    /// Configured pipelines, followed by the submitted ones.
+    Pipelines(ListState),
+    /// CPU hours of my accounts and their users in a period, once `sreport` answered.
+    UsageReport(
+        ReportPeriod,
+        Option<Result<Vec<Utilization>, String>>,
+        ListState,
+    ),
+}
+
+#[derive(Clone, Copy)]
+pub enum ScopeField {
+    Partition,
+    Account,
+    States,
+}
+
+impl ScopeField {
+    fn title(&self) -> &'static str {
+        match self {
+            ScopeField::Partition => "Partitions",
+            ScopeField::Account => "Accounts",
+            ScopeField::States => "States",
+        }
+    }
+}
+
+pub enum InputTarget {
+    Scope(ScopeField),
+    RenameJob(String),
+    Dependency(String),
+    Tags(String),
+    Note(String),
+    TagFilter,
+    Search,
+    /// Index into [`Setting::all`].
+    Setting(usize),
+    /// Reason for draining the node.
+    DrainReason(String),
+    QuietHours,
+    /// Search query of a new notification rule.
+    NotificationFilter,
+    /// Index into the notification rules.
+    NotificationStates(usize),
+    Snooze(usize),
+    JobsFile,
+    LogSearch,
+    LogRank,
+}
+
+impl InputTarget {
+    fn title(&self) -> String {
+        match self {
+            InputTarget::Scope(field) => {
+                format!("{} (comma separated, empty for all)", field.title())
+            }
+            InputTarget::RenameJob(id) => format!("Rename job {}", id),
+            InputTarget::Dependency(id) => {
+                format!(
+                    "Dependency of job {}, e.g. afterok:1234 (empty to remove)",
+                    id
+                )
+            }
+            InputTarget::Tags(id) => format!("Tags of job {} (comma separated)", id),
+            InputTarget::Note(id) => format!("Note on job {}", id),
+            InputTarget::TagFilter => "Only show jobs with tag (empty for all)".to_string(),
+            InputTarget::Search => {
+                "Search id, name, command, nodes, paths, ... (empty for all)".to_string()
+            }
+            InputTarget::Setting(i) => Setting::all()[*i].label(),
+            InputTarget::DrainReason(node) => format!("Reason for draining {}", node),
+            InputTarget::QuietHours => "Quiet hours, e.g. 22:00-07:00 (empty for none)".to_string(),
+            InputTarget::NotificationFilter => "Notify about jobs matching".to_string(),
+            InputTarget::NotificationStates(_) => {
+                "Notify on states (comma separated, empty for all)".to_string()
+            }
+            InputTarget::JobsFile => {
+                "Only show the jobs with ids in file (empty for all jobs)".to_string()
+            }
+            InputTarget::Snooze(_) => {
+                "Snooze for 30m, 2h, 1d or until 08:00 (empty to wake up)".to_string()
+            }
+            InputTarget::LogSearch => "Search the logs of past jobs for".to_string(),
+            InputTarget::LogRank => "Only show the output of rank (empty for all)".to_string(),
+        }
+    }
+}
+
+pub enum StatusMessage {
+    Info(String),
+    Error(String),
 }
 
 #[derive(Clone, Copy)]
@@ -46,16 +268,139 @@ pub struct App {
     dialog: Option<Dialog>,
     jobs: Vec<Job>,
     job_list_state: ListState,
-    job_output: Result<String, FileWatcherError>,
+    job_output: Result<JobOutput, FileWatcherError>,
+    log_diagnostics: LogDiagnosticsHandle,
+    /// The log that could not be opened and why, once it was checked.
+    log_diagnosis: Option<(LogQuery, Option<Diagnosis>)>,
+    pinger: PingerHandle,
+    node_lister: NodeListerHandle,
+    action_runner: ActionRunnerHandle,
     job_output_anchor: ScrollAnchor,
     job_output_offset: u16,
-    _job_watcher: JobWatcherHandle,
+    /// Rank of `srun --label` output the log is filtered to.
+    log_rank: Option<u32>,
+    job_watcher: JobWatcherHandle,
+    squeue_args: SqueueArgs,
     job_output_watcher: FileWatcherHandle,
-    // sender: Sender<AppMessage>,
+    _config_watcher: ConfigWatcherHandle,
+    announcement_watcher: AnnouncementWatcherHandle,
+    announcement: Option<String>,
+    plugin_host: PluginHostHandle,
+    plugin_outputs: Vec<PluginOutput>,
+    progress_watcher: ProgressWatcherHandle,
+    /// Progress found in the logs of running jobs, by job id.
+    progress: HashMap<String, Progress>,
+    /// Experiment tracker runs linked in the log of the selected job.
+    run_links: Vec<String>,
+    /// Whether the metrics of the selected job are plotted below its log.
+    show_metrics: bool,
+    metrics: Vec<Series>,
+    process_watcher: ProcessWatcherHandle,
+    usage_watcher: UsageWatcherHandle,
+    node_health_watcher: NodeHealthWatcherHandle,
+    node_load_watcher: NodeLoadWatcherHandle,
+    node_loads: HashMap<String, NodeLoad>,
+    node_watcher: NodeWatcherHandle,
+    ipc_server: Option<IpcServerHandle>,
+    mailer: MailerHandle,
+    archiver: ArchiverHandle,
+    history_recorder: HistoryRecorderHandle,
+    log_search: LogSearchHandle,
+    duplicate_watcher: DuplicateWatcherHandle,
+    /// Groups of (array) job ids, see [`AppMessage::Duplicates`].
+    duplicates: Vec<Vec<String>>,
+    idle_watcher: IdleWatcherHandle,
+    preemption_watcher: PreemptionWatcherHandle,
+    preemptible: HashMap<String, Preemptible>,
+    allocation_watcher: AllocationWatcherHandle,
+    /// Allocations of running jobs by job id, `None` while they are looked up.
+    allocations: HashMap<String, Option<Result<Vec<NodeAllocation>, String>>>,
+    idle: HashMap<String, NaiveDateTime>,
+    budget_watcher: BudgetWatcherHandle,
+    budgets: Vec<BudgetStatus>,
+    /// Pending jobs whose dependency can never be satisfied.
+    dead_dependencies: HashSet<String>,
+    triage: TriageHandle,
+    suggester: SuggesterHandle,
+    /// Suggested sbatch options by name pattern, `None` while they are computed or if there are
+    /// too few similar jobs.
+    suggestions: HashMap<String, Option<Suggestion>>,
+    backfill_watcher: BackfillWatcherHandle,
+    /// Backfill hint for the selected pending job, by job id.
+    backfill: Option<(String, Option<String>)>,
+    what_if: WhatIfHandle,
+    step_watcher: StepWatcherHandle,
+    /// The step whose output the log shows, with the id of its job.
+    log_step: Option<(String, StepOutput)>,
+    usage_report: UsageReportHandle,
+    association_lister: AssociationListerHandle,
+    efficiency_querier: EfficiencyQuerierHandle,
+    manifest_exporter: ManifestExporterHandle,
+    /// Whether the node heatmap is shown below the summary.
+    show_heatmap: bool,
+    heatmap: Option<Result<Vec<Node>, String>>,
+    /// Health of the allocated nodes in the node view, by node name.
+    node_health: HashMap<String, Health>,
+    /// Resource usage of the selected running job, by job id without array index.
+    usage: Option<(String, Result<Usage, String>)>,
+    prefetcher: PrefetcherHandle,
+    /// Exit codes and steps from `sacct` of the jobs around the selection, by job id.
+    details: HashMap<String, JobDetails>,
+    /// Job whose processes are shown below its log and the processes, once they were listed.
+    processes: Option<(String, Option<ProcessList>)>,
+    /// Where the run links were drawn in the last frame.
+    hyperlinks: Vec<Hyperlink>,
+    /// Path given via `--config`, if any.
+    config_path: Option<PathBuf>,
+    /// Refresh rate given via `--slurm-refresh`, which takes precedence over the config.
+    slurm_refresh_override: Option<Duration>,
+    sender: Sender<AppMessage>,
+    /// Watches the jobs file (`--jobs-file` or `J`) the view is restricted to, if any.
+    jobs_file_watcher: Option<JobsFileWatcherHandle>,
     receiver: Receiver<AppMessage>,
     input_receiver: Receiver<std::io::Result<Event>>,
     output_file_view: OutputFileView,
-    selected_job_id: Option<String>,
+    selected_row: Option<RowId>,
+    rows: Vec<JobListRow>,
+    collapsed_groups: HashSet<String>,
+    jobs_updated_at: Instant,
+    status_message: Option<(StatusMessage, Instant)>,
+    /// Values of the script columns and whether the script filter matches, by job id.
+    script_values: ScriptValues,
+    script_evaluator: ScriptEvaluatorHandle,
+    /// Whether the next frame is saved as an HTML snapshot.
+    snapshot_requested: bool,
+    /// Writes the rendered frames as text (`--dump-frames`).
+    frame_dumper: Option<FrameDumper>,
+    /// Masks usernames, job names and paths on screen while set.
+    anonymizer: Option<Anonymizer>,
+    /// All state transitions, newest last.
+    notification_history: VecDeque<HistoryEntry>,
+    /// Notified transitions since the history was last opened.
+    unseen_notifications: usize,
+    /// Jobs in a critical state waiting for their alert, shown once no other dialog is open.
+    alerts: VecDeque<Job>,
+    /// Job command to run once the TUI is suspended.
+    pending_command: Option<String>,
+    /// The last cancelled pending job and when it was cancelled.
+    undo: Option<(Resubmission, Instant)>,
+    config: Config,
+    state: State,
+    all_jobs: Vec<Job>,
+    tag_filter: Option<String>,
+    show_finished: bool,
+    capabilities: Capabilities,
+    /// Whether node actions are allowed (`--admin`).
+    admin: bool,
+    timelines: Timelines,
+    restarts: RestartTracker,
+    /// Jobs that were restarted too often recently, see [`RestartTracker::flapping`].
+    flapping: Vec<String>,
+    completing: CompletingTracker,
+    /// Jobs that are completing for too long, see [`CompletingTracker::stuck`].
+    stuck: Vec<String>,
+    queue_history: QueueHistory,
+    search: Option<String>,
 }
 
 #[derive(Clone)]
@@ -64,91 +409,439 @@ pub struct Job {
     pub array_id: String,
     pub array_step: Option<String>,
     pub name: String,
-    pub state: String,
-    pub state_compact: String,
+    pub state: Interned,
+    pub state_compact: Interned,
     pub reason: Option<String>,
-    pub user: String,
+    /// Dependency of a pending job as `squeue` shows it, e.g. `afterok:1234(unfulfilled)`.
+    pub dependency: Option<String>,
+    pub user: Interned,
     pub time: String,
     pub tres: String,
-    pub partition: String,
+    pub partition: Interned,
     pub nodelist: String,
     pub stdout: Option<PathBuf>,
     pub stderr: Option<PathBuf>,
+    /// The stdout and stderr patterns as `squeue` reports them, e.g. `%x-%j.out`.
+    pub stdout_pattern: Option<String>,
+    pub stderr_pattern: Option<String>,
     pub command: String,
-    pub qos: String,
+    pub qos: Interned,
+    pub submit_time: Option<NaiveDateTime>,
+    pub start_time: Option<NaiveDateTime>,
+    pub end_time: Option<NaiveDateTime>,
+    pub time_used: Option<chrono::Duration>,
+    pub time_limit: Option<chrono::Duration>,
+    pub work_dir: Option<PathBuf>,
+    /// How often the job was requeued, e.g. after being preempted.
+    pub restarts: u32,
+    /// Energy consumed by a finished job in joules, on clusters with energy accounting.
+    pub energy: Option<u64>,
+}
+
+#[cfg(test)]
+impl Job {
+    /// A job for tests: running on `gpu-01` since 9:00, or pending until 14:30.
+    pub fn fixture(id: &str, name: &str, state: &str, user: &str) -> Job {
+        let at = |hour, minute| {
+            chrono::NaiveDate::from_ymd_opt(2024, 1, 1).and_then(|d| d.and_hms_opt(hour, minute, 0))
+        };
+        let running = state == "RUNNING";
+        Job {
+            job_id: id.to_string(),
+            array_id: id.to_string(),
+            array_step: None,
+            name: name.to_string(),
+            state: state.into(),
+            state_compact: states::compact(state).as_str().into(),
+            reason: (!running).then(|| "Priority".to_string()),
+            dependency: None,
+            user: user.into(),
+            time: String::new(),
+            tres: "cpu=4,mem=16G,node=1,gres/gpu=1".to_string(),
+            partition: "gpu".into(),
+            nodelist: if running { "gpu-01" } else { "" }.into(),
+            stdout: Some(PathBuf::from(format!("/scratch/{user}/slurm-{id}.out"))),
+            stderr: None,
+            stdout_pattern: None,
+            stderr_pattern: None,
+            command: format!("/home/{user}/{name}.sh"),
+            qos: "normal".into(),
+            submit_time: at(8, 0),
+            start_time: if running { at(9, 0) } else { at(14, 30) },
+            end_time: at(21, 0),
+            time_used: Some(chrono::Duration::minutes(if running { 180 } else { 0 })),
+            time_limit: Some(chrono::Duration::hours(12)),
+            work_dir: None,
+            restarts: 0,
+            energy: None,
+        }
+    }
 }
 
 impl Job {
-    fn id(&self) -> String {
+    pub fn id(&self) -> String {
         match self.array_step.as_ref() {
             Some(array_step) => format!("{}_{}", self.array_id, array_step),
             None => self.job_id.clone(),
         }
     }
+
+    /// The job as handed to plugins and IPC clients.
+    pub fn to_json(&self) -> serde_json::Value {
+        let path = |p: &Option<PathBuf>| {
+            p.as_ref()
+                .map_or(serde_json::Value::Null, |p| p.to_string_lossy().into())
+        };
+        serde_json::json!({
+            "id": self.id(),
+            "name": self.name,
+            "state": self.state,
+            "user": self.user,
+            "partition": self.partition,
+            "nodes": self.nodelist,
+            "command": self.command,
+            "work_dir": path(&self.work_dir),
+            "stdout": path(&self.stdout),
+            "stderr": path(&self.stderr),
+        })
+    }
+
+    /// Case-insensitive search across the textual fields of the job.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        let paths = [&self.stdout, &self.stderr, &self.work_dir];
+        let fields = [
+            self.name.as_str(),
+            self.state.as_str(),
+            self.user.as_str(),
+            self.partition.as_str(),
+            self.qos.as_str(),
+            self.nodelist.as_str(),
+            self.command.as_str(),
+        ];
+        // `gpu-17` finds the jobs on `gpu-[16-18]`
+        let hosts = match self.nodelist.contains('[') {
+            true => hostlist::expand(&self.nodelist),
+            false => Vec::new(),
+        };
+        self.id().to_lowercase().contains(&query)
+            || hosts.iter().any(|h| h.to_lowercase().contains(&query))
+            || fields
+                .into_iter()
+                .chain(self.reason.as_deref())
+                .chain(paths.into_iter().flatten().filter_map(|p| p.to_str()))
+                .any(|s| s.to_lowercase().contains(&query))
+    }
+
+    /// Whether the job reached a final state (`sacct` reports e.g. `CANCELLED by 1234`).
+    pub fn is_finished(&self) -> bool {
+        states::is_final(&self.state)
+    }
+
+    /// The start time, if the job has actually started (pending jobs may report an estimate).
+    pub fn started_at(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        self.start_time.filter(|&t| t <= now)
+    }
+
+    /// Time spent in the queue, i.e. until the job started or until now if it is still pending.
+    pub fn queue_wait(&self, now: NaiveDateTime) -> Option<chrono::Duration> {
+        let submit = self.submit_time?;
+        Some(self.started_at(now).unwrap_or(now) - submit)
+    }
+
+    /// Time spent running, i.e. from start until the job ended or until now if it is still running.
+    pub fn run_time(&self, now: NaiveDateTime) -> Option<chrono::Duration> {
+        let start = self.started_at(now)?;
+        let end = self.end_time.filter(|&t| t <= now).unwrap_or(now);
+        Some(end - start)
+    }
+
+    /// Elapsed time as reported by the last poll, advanced locally for running jobs
+    /// so that it keeps ticking between polls.
+    pub fn time_used_display(&self, since_poll: Duration) -> String {
+        match (self.state.as_str(), self.time_used) {
+            ("RUNNING", Some(used)) => format_slurm_duration(
+                used + chrono::Duration::from_std(since_poll).unwrap_or_default(),
+            ),
+            _ => self.time.clone(),
+        }
+    }
+
+    /// Remaining walltime, advanced by the time that passed since the last poll for running jobs.
+    /// Finished jobs have no time left.
+    pub fn time_left(&self, since_poll: Duration) -> Option<chrono::Duration> {
+        let elapsed = match self.state.as_str() {
+            "RUNNING" => chrono::Duration::from_std(since_poll).unwrap_or_default(),
+            "PENDING" | "SUSPENDED" => chrono::Duration::zero(),
+            _ => return None,
+        };
+        Some(self.time_limit? - self.time_used? - elapsed)
+    }
 }
 
 pub enum AppMessage {
     Jobs(Vec<Job>),
-    JobOutput(Result<String, FileWatcherError>),
+    Config(Result<Box<Config>, ConfigError>),
+    JobOutput(Result<JobOutput, FileWatcherError>),
+    LogDiagnosis(Diagnosis),
+    ActionDone(ActionOutcome),
+    /// How a job was submitted, if that is known.
+    Captured(String, Option<Resubmission>),
+    /// The answer of a node to `ping`, or why there was none.
+    Ping(Result<String, String>),
+    ScriptValues(ScriptValues, Option<String>),
+    Announcement(Option<String>),
+    Plugins(Vec<PluginOutput>),
+    Progress(HashMap<String, Progress>),
+    Processes(String, ProcessList),
+    Usage(String, Result<Usage, String>),
+    Details(HashMap<String, JobDetails>),
+    NodeHealth(HashMap<String, Health>),
+    /// The load of the nodes of the selected running job.
+    NodeLoad(HashMap<String, NodeLoad>),
+    NodeHeatmap(Result<Vec<Node>, String>),
+    Ipc(IpcRequest),
+    EmailFailed(String),
+    Archived(Result<ArchivedJob, String>),
+    Triage(String, Triage),
+    Suggestion(String, Option<Suggestion>),
+    Backfill(String, Option<String>),
+    JobsFile(io::Result<JobsFile>),
+    WhatIf(String, Result<Vec<Estimate>, String>),
+    Steps(String, Result<Vec<StepOutput>, String>),
+    UsageReport(ReportPeriod, Result<Vec<Utilization>, String>),
+    Associations(io::Result<Vec<Association>>),
+    Efficiency(Result<Vec<Efficiency>, String>),
+    /// All nodes, and the one to select.
+    Nodes(io::Result<Vec<Node>>, Option<String>),
+    /// Where the manifest of the given number of jobs was written.
+    Manifest(usize, io::Result<PathBuf>),
+    LogSearch(String, SearchResult),
+    /// Groups of my active jobs submitted with the same script and arguments.
+    Duplicates(Vec<Vec<String>>),
+    /// My running jobs that seem to hang, and since when.
+    Idle(HashMap<String, NaiveDateTime>),
+    /// The running jobs that may be preempted.
+    Preemption(HashMap<String, Preemptible>),
+    /// The nodes, CPUs and GPUs a job was allocated, by job id.
+    Allocation(String, Result<Vec<NodeAllocation>, String>),
+    /// Consumption of the configured budgets.
+    Budgets(Vec<BudgetStatus>),
     Key(KeyEvent),
 }
 
 impl App {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         input_receiver: Receiver<std::io::Result<Event>>,
-        slurm_refresh_rate: u64,
+        slurm_refresh_rate: Option<u64>,
         file_refresh_rate: u64,
-        squeue_args: Vec<String>,
-        sacct_args: Vec<String>,
+        mut squeue_args: SqueueArgs,
+        config_path: Option<PathBuf>,
+        config: Config,
+        state: State,
+        capabilities: Capabilities,
+        admin: bool,
+        jobs_file: Option<PathBuf>,
+        anonymize: bool,
+        frame_dumper: Option<FrameDumper>,
     ) -> App {
         let (sender, receiver) = unbounded();
-        Self {
+        if config.only_mine {
+            squeue_args.set_me(true);
+        }
+        let show_finished = !config.hide_finished;
+        let mut app = Self {
             focus: Focus::Jobs,
             dialog: None,
             jobs: Vec::new(),
-            _job_watcher: JobWatcherHandle::new(
+            job_watcher: JobWatcherHandle::new(
                 sender.clone(),
-                Duration::from_secs(slurm_refresh_rate),
-                squeue_args,
-                sacct_args,
+                slurm_refresh_rate
+                    .map(Duration::from_secs)
+                    .unwrap_or(config.slurm_refresh()),
+                squeue_args.to_vec(),
+                squeue_args.to_sacct_vec(),
+                config.finished_retention(),
+                capabilities.sacct,
             ),
+            squeue_args,
             job_list_state: {
                 let mut s = ListState::default();
                 s.select(Some(0));
                 s
             },
-            job_output: Ok("".to_string()),
+            job_output: Ok(JobOutput::default()),
+            log_diagnostics: LogDiagnosticsHandle::new(sender.clone()),
+            log_diagnosis: None,
+            pinger: PingerHandle::new(sender.clone()),
+            node_lister: NodeListerHandle::new(sender.clone()),
+            action_runner: ActionRunnerHandle::new(sender.clone()),
             job_output_anchor: ScrollAnchor::Bottom,
             job_output_offset: 0,
+            log_rank: None,
             job_output_watcher: FileWatcherHandle::new(
                 sender.clone(),
                 Duration::from_secs(file_refresh_rate),
+                config.log_settings(),
+            ),
+            _config_watcher: ConfigWatcherHandle::new(sender.clone(), config_path.clone()),
+            announcement_watcher: AnnouncementWatcherHandle::new(
+                sender.clone(),
+                config.announcement_file.clone(),
+                config.log_throttle(),
+            ),
+            announcement: None,
+            plugin_host: PluginHostHandle::new(sender.clone(), config.plugins.clone()),
+            plugin_outputs: Vec::new(),
+            progress_watcher: ProgressWatcherHandle::new(
+                sender.clone(),
+                config.progress_pattern.clone(),
+                config.log_throttle(),
             ),
-            // sender,
-            receiver: receiver,
-            input_receiver: input_receiver,
+            progress: HashMap::new(),
+            run_links: Vec::new(),
+            show_metrics: false,
+            metrics: Vec::new(),
+            process_watcher: ProcessWatcherHandle::new(sender.clone()),
+            usage_watcher: UsageWatcherHandle::new(sender.clone(), config.fs_client_stats),
+            usage: None,
+            prefetcher: PrefetcherHandle::new(sender.clone()),
+            details: HashMap::new(),
+            node_load_watcher: NodeLoadWatcherHandle::new(
+                sender.clone(),
+                config.node_load_command.clone(),
+            ),
+            node_loads: HashMap::new(),
+            node_health_watcher: NodeHealthWatcherHandle::new(
+                sender.clone(),
+                config.node_health.clone(),
+            ),
+            node_health: HashMap::new(),
+            node_watcher: NodeWatcherHandle::new(sender.clone(), config.node_heatmap),
+            mailer: MailerHandle::new(sender.clone(), config.log_throttle()),
+            archiver: ArchiverHandle::new(sender.clone(), config.log_throttle()),
+            history_recorder: HistoryRecorderHandle::new(config.history.clone()),
+            log_search: LogSearchHandle::new(sender.clone(), config.log_throttle()),
+            duplicate_watcher: DuplicateWatcherHandle::new(sender.clone(), capabilities.sacct),
+            duplicates: Vec::new(),
+            idle_watcher: IdleWatcherHandle::new(sender.clone(), config.log_throttle()),
+            preemption_watcher: PreemptionWatcherHandle::new(sender.clone()),
+            preemptible: HashMap::new(),
+            allocation_watcher: AllocationWatcherHandle::new(sender.clone()),
+            allocations: HashMap::new(),
+            idle: HashMap::new(),
+            budget_watcher: BudgetWatcherHandle::new(sender.clone(), config.budgets.clone()),
+            budgets: Vec::new(),
+            dead_dependencies: HashSet::new(),
+            triage: TriageHandle::new(sender.clone(), config.log_throttle()),
+            suggester: SuggesterHandle::new(sender.clone()),
+            suggestions: HashMap::new(),
+            backfill_watcher: BackfillWatcherHandle::new(sender.clone()),
+            backfill: None,
+            what_if: WhatIfHandle::new(sender.clone()),
+            step_watcher: StepWatcherHandle::new(sender.clone(), capabilities.sacct),
+            log_step: None,
+            usage_report: UsageReportHandle::new(sender.clone()),
+            association_lister: AssociationListerHandle::new(sender.clone()),
+            efficiency_querier: EfficiencyQuerierHandle::new(sender.clone()),
+            manifest_exporter: ManifestExporterHandle::new(sender.clone(), capabilities.sacct),
+            ipc_server: IpcServerHandle::new(sender.clone()).ok(),
+            show_heatmap: config.node_heatmap,
+            heatmap: None,
+            processes: None,
+            hyperlinks: Vec::new(),
+            config_path,
+            slurm_refresh_override: slurm_refresh_rate.map(Duration::from_secs),
+            sender: sender.clone(),
+            jobs_file_watcher: None,
+            receiver,
+            input_receiver,
             output_file_view: OutputFileView::default(),
-            selected_job_id: None,
+            selected_row: None,
+            rows: Vec::new(),
+            collapsed_groups: HashSet::new(),
+            jobs_updated_at: Instant::now(),
+            status_message: None,
+            snapshot_requested: false,
+            frame_dumper,
+            anonymizer: anonymize.then(Anonymizer::default),
+            notification_history: VecDeque::new(),
+            unseen_notifications: 0,
+            alerts: VecDeque::new(),
+            pending_command: None,
+            undo: None,
+            script_values: HashMap::new(),
+            script_evaluator: ScriptEvaluatorHandle::new(
+                sender.clone(),
+                config.script_columns.clone(),
+                config.script_filter.clone(),
+            ),
+            config,
+            state,
+            all_jobs: Vec::new(),
+            tag_filter: None,
+            show_finished,
+            capabilities,
+            admin,
+            timelines: Timelines::default(),
+            restarts: RestartTracker::default(),
+            flapping: Vec::new(),
+            completing: CompletingTracker::default(),
+            stuck: Vec::new(),
+            queue_history: QueueHistory::default(),
+            search: None,
+        };
+        app.job_watcher
+            .set_pinned(app.state.pinned.iter().cloned().collect());
+        // an import from the last run is kept even if the file is gone
+        match jobs_file.or_else(|| app.state.jobs_file.as_ref().map(|f| f.path.clone())) {
+            Some(path) if path.exists() || app.state.jobs_file.is_none() => {
+                app.import_jobs_file(path)
+            }
+            Some(path) => {
+                app.jobs_file_watcher = Some(JobsFileWatcherHandle::new(app.sender.clone(), path));
+                app.set_jobs_file(app.state.jobs_file.clone());
+            }
+            None => {}
         }
+        app
     }
 }
 
 impl App {
     pub fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> io::Result<()> {
-        terminal.draw(|f| self.ui(f))?;
+        self.draw(terminal)?;
+
+        // redraw every second so that time columns keep ticking between polls, which they
+        // do not in deterministic mode
+        let ticker = match clock::is_frozen() {
+            true => never(),
+            false => tick(Duration::from_secs(1)),
+        };
 
         loop {
             select! {
+                recv(ticker) -> _ => {}
                 recv(self.receiver) -> event => {
                     self.handle(event.unwrap());
                 }
                 recv(self.input_receiver) -> input_res => {
                     match input_res.unwrap().unwrap() {
                         Event::Key(key) => {
-                            if key.code == KeyCode::Char('q') {
+                            if key.code == KeyCode::Char('q') && !self.is_editing_text() {
                                 return Ok(());
                             }
                             self.handle(AppMessage::Key(key));
+                            if let Some(command) = self.pending_command.take() {
+                                let result =
+                                    job_commands::run_suspended(&command, self.socket())?;
+                                terminal.clear()?;
+                                self.set_status_message(match result {
+                                    Ok(message) => StatusMessage::Info(message),
+                                    Err(message) => StatusMessage::Error(message),
+                                });
+                            }
                         },
                         Event::Resize(_, _) => {},
                         _ => continue, // ignore and do not redraw
@@ -156,151 +849,2582 @@ impl App {
                 }
             };
 
-            terminal.draw(|f| self.ui(f))?;
+            self.draw(terminal)?;
+        }
+    }
+
+    /// Draws the UI, then the hyperlinks over it, and saves or dumps the frame if requested.
+    fn draw<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> io::Result<()> {
+        let frame = terminal.draw(|f| self.ui(f))?;
+        let snapshot = self
+            .snapshot_requested
+            .then(|| snapshot::save(frame.buffer));
+        if let Some(dumper) = &mut self.frame_dumper {
+            dumper.dump(frame.buffer)?;
+        }
+        links::write_hyperlinks(&mut io::stdout(), &self.hyperlinks)?;
+        if let Some(result) = snapshot {
+            self.snapshot_requested = false;
+            self.set_status_message(match result {
+                Ok(path) => StatusMessage::Info(format!("Saved {}", path.display())),
+                Err(e) => StatusMessage::Error(format!("Failed to save the snapshot: {}", e)),
+            });
+            return self.draw(terminal);
         }
+        Ok(())
     }
 
     fn handle(&mut self, msg: AppMessage) {
         match msg {
             AppMessage::Jobs(jobs) => {
+                let transitions = notifications::transitions(&self.all_jobs, &jobs);
+                self.notify(&transitions);
+                self.timelines.observe(&self.all_jobs, &jobs, clock::now());
+                self.queue_history.record(&jobs, remote::current_user());
+                self.history_recorder.record(&jobs);
+                let mut candidates: Vec<Candidate> = jobs
+                    .iter()
+                    .filter(|j| j.user == remote::current_user())
+                    .filter(|j| j.state == "PENDING" || j.state == "RUNNING")
+                    .filter_map(Candidate::of)
+                    .collect();
+                let mut seen = HashSet::new();
+                candidates.retain(|c| seen.insert(c.id.clone()));
+                self.duplicate_watcher.set_jobs(candidates);
+                self.idle_watcher.set_jobs(
+                    self.config.idle.clone(),
+                    jobs.iter()
+                        .filter(|j| j.user == remote::current_user() && j.state == "RUNNING")
+                        .map(|j| {
+                            let mut logs = j
+                                .stdout
+                                .iter()
+                                .chain(&j.stderr)
+                                .cloned()
+                                .collect::<Vec<_>>();
+                            logs.dedup();
+                            IdleQuery { id: j.id(), logs }
+                        })
+                        .collect(),
+                );
+                self.preemption_watcher.set_jobs(
+                    jobs.iter()
+                        .filter(|j| j.state == "RUNNING")
+                        .map(|j| PreemptQuery {
+                            id: j.id(),
+                            qos: j.qos.to_string(),
+                            partition: j.partition.to_string(),
+                            mine: j.user == remote::current_user(),
+                        })
+                        .collect(),
+                );
+                let now = clock::now();
+                self.restarts.observe(&self.all_jobs, &jobs, now);
+                let flapping = self.restarts.flapping(&self.config.requeue_storm, now);
+                let new = flapping
+                    .iter()
+                    .filter(|id| !self.flapping.contains(id))
+                    .filter(|id| {
+                        jobs.iter()
+                            .any(|j| j.id() == **id && j.user == remote::current_user())
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !new.is_empty() {
+                    self.set_status_message(StatusMessage::Error(format!(
+                        "Restarted more than {} times in {} minutes, burning allocation (c: cancel): {}",
+                        self.config.requeue_storm.restarts,
+                        self.config.requeue_storm.minutes,
+                        new.join(", ")
+                    )));
+                }
+                self.flapping = flapping;
+                self.completing.observe(&jobs, now);
+                let stuck = self.completing.stuck(&self.config.completing, now);
+                let new = stuck
+                    .iter()
+                    .filter(|id| !self.stuck.contains(id))
+                    .filter(|id| {
+                        jobs.iter()
+                            .any(|j| j.id() == **id && j.user == remote::current_user())
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !new.is_empty() {
+                    self.set_status_message(StatusMessage::Error(format!(
+                        "Completing for more than {} minutes, likely a hung epilog or filesystem (w: message for the admins): {}",
+                        self.config.completing.minutes,
+                        new.join(", ")
+                    )));
+                }
+                self.stuck = stuck;
+                let index = dependency::JobIndex::new(&jobs);
+                let dead_dependencies = jobs
+                    .iter()
+                    .filter(|j| dependency::never_satisfied(j, &index))
+                    .map(|j| j.id())
+                    .collect::<HashSet<_>>();
+                let new = jobs
+                    .iter()
+                    .filter(|j| j.user == remote::current_user())
+                    .map(|j| j.id())
+                    .filter(|id| {
+                        dead_dependencies.contains(id) && !self.dead_dependencies.contains(id)
+                    })
+                    .collect::<Vec<_>>();
+                if !new.is_empty() {
+                    self.set_status_message(StatusMessage::Error(format!(
+                        "Waiting for a dependency that can never be satisfied (e: fix, c: cancel): {}",
+                        new.join(", ")
+                    )));
+                }
+                self.dead_dependencies = dead_dependencies;
+                let ids = jobs.iter().map(Job::id).collect::<HashSet<_>>();
+                self.details.retain(|id, _| ids.contains(id));
+                // allocations only change when a job is requeued
+                let running = jobs
+                    .iter()
+                    .filter(|j| j.state == "RUNNING")
+                    .map(|j| j.job_id.as_str())
+                    .collect::<HashSet<_>>();
+                self.allocations
+                    .retain(|id, _| running.contains(id.as_str()));
+                self.follow_moved_output(&jobs);
                 // Update the job list and maintain selection
-                self.update_jobs_and_selection(jobs);
+                self.all_jobs = jobs;
+                if let Some(anonymizer) = &mut self.anonymizer {
+                    anonymizer.learn(&self.all_jobs, remote::current_user());
+                }
+                self.jobs_updated_at = Instant::now();
+                self.evaluate_scripts();
+                self.plugin_host.update(&self.all_jobs);
+                self.progress_watcher.set_jobs(
+                    self.all_jobs
+                        .iter()
+                        .filter(|j| j.state == "RUNNING")
+                        .filter_map(|j| Some((j.id(), j.stdout.clone()?)))
+                        .collect(),
+                );
+                self.refresh_jobs();
+            }
+            AppMessage::Config(Ok(config)) => {
+                self.config = *config;
+                self.job_watcher.set_settings(
+                    self.slurm_refresh_override
+                        .unwrap_or(self.config.slurm_refresh()),
+                    self.config.finished_retention(),
+                );
+                self.job_output_watcher
+                    .set_settings(self.config.log_settings());
+                self.announcement_watcher
+                    .set_path(self.config.announcement_file.clone());
+                self.plugin_host.set_plugins(&self.config.plugins);
+                self.progress_watcher
+                    .set_pattern(self.config.progress_pattern.clone());
+                self.usage_watcher.set_fs_stats(self.config.fs_client_stats);
+                dispatcher::configure(
+                    self.config.slurm_max_concurrent,
+                    self.config.slurm_min_interval(),
+                );
+                states::configure(&self.config.states);
+                self.script_evaluator.set_scripts(
+                    self.config.script_columns.clone(),
+                    self.config.script_filter.clone(),
+                );
+                self.history_recorder
+                    .set_config(self.config.history.clone());
+                self.node_load_watcher
+                    .set_command(self.config.node_load_command.clone());
+                self.node_health_watcher
+                    .set_config(self.config.node_health.clone());
+                self.budget_watcher.set_budgets(self.config.budgets.clone());
+                if self.squeue_args.team().is_some() {
+                    self.squeue_args
+                        .set_team(Some(self.config.team.clone()).filter(|team| !team.is_empty()));
+                    self.update_scope();
+                }
+                self.evaluate_scripts();
+                self.refresh_jobs();
+                self.set_status_message(StatusMessage::Info("Config reloaded".to_string()));
+            }
+            AppMessage::Config(Err(e)) => self.set_status_message(StatusMessage::Error(format!(
+                "{}, keeping the previous config",
+                e.summary()
+            ))),
+            AppMessage::JobOutput(output) => {
+                self.run_links = match &output {
+                    Ok(output) => {
+                        links::find_links(&self.config.run_link_patterns, &output.content)
+                    }
+                    Err(_) => Vec::new(),
+                };
+                self.job_output = output;
+                self.diagnose_log();
+                self.update_metrics();
+            }
+            AppMessage::LogDiagnosis(diagnosis) => {
+                if let Some((query, current)) = &mut self.log_diagnosis {
+                    if *query == diagnosis.query {
+                        *current = Some(diagnosis);
+                    }
+                }
+            }
+            AppMessage::ScriptValues(values, error) => {
+                if let Some(error) = error {
+                    self.set_status_message(StatusMessage::Error(error));
+                }
+                if values != self.script_values {
+                    self.script_values = values;
+                    self.refresh_jobs();
+                }
+            }
+            AppMessage::ActionDone(outcome) => {
+                match outcome.follow_up {
+                    Some(FollowUp::Undo(undo)) => self.undo = Some((undo, Instant::now())),
+                    Some(FollowUp::Pipeline(run)) => {
+                        self.state.pipeline_runs.insert(0, run);
+                        self.state.pipeline_runs.truncate(pipelines::MAX_RUNS);
+                        self.save_state();
+                    }
+                    None => {}
+                }
+                self.report(&outcome.action, &outcome.target, outcome.result);
+                if let (Some(node), Some(Dialog::Nodes(..))) = (&outcome.node, &self.dialog) {
+                    let node = node.clone();
+                    self.open_nodes(Some(&node));
+                }
+            }
+            // only comparing other queues captures submit lines so far
+            AppMessage::Captured(job_id, resubmission) if matches!(&self.dialog, Some(Dialog::WhatIf(id, None, _)) if *id == job_id) =>
+            {
+                let job = self.all_jobs.iter().find(|j| j.id() == job_id);
+                match (resubmission, job) {
+                    (Some(resubmission), Some(job)) => {
+                        // a pending array task is tested on its own
+                        let resubmission = match &job.array_step {
+                            Some(step) => resubmission.with_array(step),
+                            None => resubmission,
+                        };
+                        self.what_if.request(
+                            job_id,
+                            resubmission,
+                            job.partition.to_string(),
+                            job.qos.to_string(),
+                            self.capabilities.sacctmgr,
+                        );
+                    }
+                    _ => {
+                        self.dialog = None;
+                        self.set_status_message(StatusMessage::Error(format!(
+                            "The submit line of job {} is not known",
+                            job_id
+                        )));
+                    }
+                }
+            }
+            AppMessage::Captured(..) => {}
+            AppMessage::Ping(result) => self.set_status_message(match result {
+                Ok(message) => StatusMessage::Info(message),
+                Err(message) => StatusMessage::Error(message),
+            }),
+            AppMessage::Announcement(announcement) => self.announcement = announcement,
+            AppMessage::Plugins(outputs) => self.plugin_outputs = outputs,
+            AppMessage::Progress(progress) => self.progress = progress,
+            AppMessage::Usage(job_id, usage) => self.usage = Some((job_id, usage)),
+            AppMessage::Details(details) => self.details.extend(details),
+            AppMessage::NodeHealth(health) => self.node_health = health,
+            AppMessage::NodeLoad(loads) => self.node_loads = loads,
+            AppMessage::NodeHeatmap(nodes) => self.heatmap = Some(nodes),
+            AppMessage::Triage(array_id, triage) => {
+                if let Some(Dialog::Triage(id, current @ None, state)) = &mut self.dialog {
+                    if *id == array_id {
+                        if !triage.buckets.is_empty() {
+                            state.select(Some(0));
+                        }
+                        *current = Some(triage);
+                    }
+                }
+            }
+            AppMessage::Suggestion(pattern, suggestion) => {
+                self.suggestions.insert(pattern, suggestion);
+            }
+            AppMessage::Backfill(job_id, hint) => self.backfill = Some((job_id, hint)),
+            AppMessage::JobsFile(Ok(jobs_file)) => {
+                if self
+                    .state
+                    .jobs_file
+                    .as_ref()
+                    .is_some_and(|f| f.path == jobs_file.path)
+                {
+                    self.set_jobs_file(Some(jobs_file));
+                }
+            }
+            AppMessage::JobsFile(Err(e)) => self.set_status_message(StatusMessage::Error(format!(
+                "Failed to read the jobs file: {}",
+                e
+            ))),
+            AppMessage::Budgets(budgets) => {
+                let depleting = budgets
+                    .iter()
+                    .filter(|b| {
+                        let previous = self
+                            .budgets
+                            .iter()
+                            .find(|p| p.budget.account == b.budget.account)
+                            .and_then(|p| p.reached());
+                        b.reached().is_some_and(|r| previous.is_none_or(|p| r > p))
+                    })
+                    .map(|b| {
+                        format!(
+                            "{} {:.0}% ({} of {} core-hours since {})",
+                            b.budget.account,
+                            b.percent().unwrap_or_default(),
+                            format_core_hours(*b.used.as_ref().unwrap_or(&0.0)),
+                            format_core_hours(b.budget.core_hours),
+                            b.since.format("%Y-%m-%d")
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let failed = budgets
+                    .iter()
+                    .filter_map(|b| b.used.as_ref().err())
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !failed.is_empty() && self.budgets.iter().all(|b| b.used.is_ok()) {
+                    self.set_status_message(StatusMessage::Error(format!(
+                        "Failed to get the usage of the budgets: {}",
+                        failed.join(", ")
+                    )));
+                }
+                if !depleting.is_empty() {
+                    self.set_status_message(StatusMessage::Error(format!(
+                        "Allocation budget used: {}",
+                        depleting.join(", ")
+                    )));
+                }
+                self.budgets = budgets;
+            }
+            AppMessage::Preemption(preemptible) => self.preemptible = preemptible,
+            AppMessage::Allocation(job_id, allocation) => {
+                self.allocations.insert(job_id, Some(allocation));
+            }
+            AppMessage::Idle(idle) => {
+                let new = idle
+                    .keys()
+                    .filter(|id| !self.idle.contains_key(*id))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !new.is_empty() {
+                    self.set_status_message(StatusMessage::Error(format!(
+                        "No output and no CPU use for {} minutes, {} may hang (H: processes, c: cancel): {}",
+                        self.config.idle.minutes,
+                        if new.len() == 1 { "the job" } else { "the jobs" },
+                        new.join(", ")
+                    )));
+                }
+                self.idle = idle;
+            }
+            AppMessage::Duplicates(duplicates) => {
+                let new = duplicates
+                    .iter()
+                    .filter(|ids| !self.duplicates.contains(ids))
+                    .map(|ids| ids.join(", "))
+                    .collect::<Vec<_>>();
+                if !new.is_empty() {
+                    self.set_status_message(StatusMessage::Error(format!(
+                        "Submitted twice with the same script and arguments: {}",
+                        new.join("; ")
+                    )));
+                }
+                self.duplicates = duplicates;
+            }
+            AppMessage::LogSearch(query, result) => {
+                if let Some(Dialog::LogSearch(q, current @ None, state)) = &mut self.dialog {
+                    if *q == query {
+                        if !result.hits.is_empty() {
+                            state.select(Some(0));
+                        }
+                        *current = Some(result);
+                    }
+                }
+            }
+            AppMessage::WhatIf(job_id, estimates) => {
+                if let Some(Dialog::WhatIf(id, current @ None, state)) = &mut self.dialog {
+                    if *id == job_id {
+                        if estimates.as_ref().is_ok_and(|e| !e.is_empty()) {
+                            state.select(Some(0));
+                        }
+                        *current = Some(estimates);
+                    }
+                }
+            }
+            AppMessage::Steps(job_id, steps) => {
+                if let Some(Dialog::Steps(id, current @ None, state)) = &mut self.dialog {
+                    if *id == job_id {
+                        let selected = match (&self.log_step, &steps) {
+                            (Some((id, step)), Ok(steps)) if *id == job_id => steps
+                                .iter()
+                                .position(|s| s.id == step.id)
+                                .map_or(0, |i| i + 1),
+                            _ => 0,
+                        };
+                        state.select(Some(selected));
+                        *current = Some(steps);
+                    }
+                }
+            }
+            AppMessage::Associations(associations) => {
+                if let Some(Dialog::Associations(current @ None, state)) = &mut self.dialog {
+                    if associations.as_ref().is_ok_and(|a| !a.is_empty()) {
+                        state.select(Some(0));
+                    }
+                    *current = Some(associations);
+                }
+            }
+            AppMessage::Efficiency(jobs) => {
+                if let Some(Dialog::Efficiency(current @ None, state)) = &mut self.dialog {
+                    if jobs.as_ref().is_ok_and(|j| !j.is_empty()) {
+                        state.select(Some(0));
+                    }
+                    *current = Some(jobs);
+                }
+            }
+            AppMessage::Manifest(jobs, result) => self.set_status_message(match result {
+                Ok(path) => StatusMessage::Info(format!(
+                    "Saved the manifest of {} {} to {}",
+                    jobs,
+                    if jobs == 1 { "job" } else { "jobs" },
+                    path.display()
+                )),
+                Err(e) => StatusMessage::Error(format!("Failed to save the manifest: {}", e)),
+            }),
+            AppMessage::Nodes(nodes, selected) => {
+                if let Some(Dialog::Nodes(current, state)) = &mut self.dialog {
+                    state.select(match &nodes {
+                        Ok(nodes) if !nodes.is_empty() => Some(
+                            selected
+                                .and_then(|s| nodes.iter().position(|n| n.name == s))
+                                .unwrap_or(0),
+                        ),
+                        _ => None,
+                    });
+                    *current = Some(nodes);
+                }
+            }
+            AppMessage::UsageReport(period, report) => {
+                if let Some(Dialog::UsageReport(p, current @ None, state)) = &mut self.dialog {
+                    if *p == period {
+                        if report.as_ref().is_ok_and(|r| !r.is_empty()) {
+                            state.select(Some(0));
+                        }
+                        *current = Some(report);
+                    }
+                }
+            }
+            AppMessage::EmailFailed(message) => {
+                self.set_status_message(StatusMessage::Error(message))
+            }
+            AppMessage::Archived(Ok(archived)) => {
+                self.state.archived.retain(|a| a.job_id != archived.job_id);
+                self.state.archived.insert(0, archived);
+                self.state.archived.truncate(archive::MAX_ARCHIVED);
+                self.save_state();
+            }
+            AppMessage::Archived(Err(message)) => {
+                self.set_status_message(StatusMessage::Error(message))
+            }
+            AppMessage::Ipc(request) => {
+                let result = self.handle_ipc(&request.method, &request.params);
+                let _ = request.reply.send(result);
+            }
+            AppMessage::Processes(job_id, processes) => {
+                if let Some((shown, current)) = &mut self.processes {
+                    if *shown == job_id {
+                        *current = Some(processes);
+                    }
+                }
             }
-            AppMessage::JobOutput(content) => self.job_output = content,
             AppMessage::Key(key) => {
-                if let Some(dialog) = &self.dialog {
+                if let Some(dialog) = &mut self.dialog {
                     match dialog {
-                        Dialog::ConfirmCancelJob(id) => match key.code {
+                        Dialog::Confirm(_) => match key.code {
                             KeyCode::Enter | KeyCode::Char('y') => {
-                                Command::new("scancel")
-                                    .arg(id)
-                                    .stdout(Stdio::null())
-                                    .stderr(Stdio::null())
-                                    .spawn()
-                                    .expect("failed to execute scancel");
+                                if let Some(Dialog::Confirm(action)) = self.dialog.take() {
+                                    self.run_action(action);
+                                }
+                            }
+                            KeyCode::Esc => {
+                                if let Some(Dialog::Confirm(action)) = self.dialog.take() {
+                                    self.leave_action(&action);
+                                }
+                            }
+                            _ => {}
+                        },
+                        Dialog::ConfirmTyped(action, value) => match key.code {
+                            KeyCode::Enter if action.confirmation_text() == Some(value.trim()) => {
+                                if let Some(Dialog::ConfirmTyped(action, _)) = self.dialog.take() {
+                                    self.run_action(action);
+                                }
+                            }
+                            KeyCode::Enter => {}
+                            KeyCode::Esc => {
+                                if let Some(Dialog::ConfirmTyped(action, _)) = self.dialog.take() {
+                                    self.leave_action(&action);
+                                }
+                            }
+                            KeyCode::Backspace => {
+                                value.pop();
+                            }
+                            KeyCode::Char(c) => value.push(c),
+                            _ => {}
+                        },
+                        Dialog::Input(_, value) => match key.code {
+                            KeyCode::Enter => {
+                                if let Some(Dialog::Input(target, value)) = self.dialog.take() {
+                                    self.submit_input(target, value);
+                                }
+                            }
+                            KeyCode::Esc => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Backspace => {
+                                value.pop();
+                            }
+                            KeyCode::Char(c) => value.push(c),
+                            _ => {}
+                        },
+                        Dialog::Associations(associations, state) => match key.code {
+                            KeyCode::Enter => {
+                                let account = state.selected().and_then(|i| {
+                                    associations
+                                        .as_ref()
+                                        .and_then(|a| a.as_ref().ok())
+                                        .and_then(|a| a.get(i))
+                                        .map(|a| a.account.clone())
+                                });
                                 self.dialog = None;
+                                if let Some(account) = account {
+                                    self.squeue_args.set_account(Some(account));
+                                    self.update_scope();
+                                }
                             }
                             KeyCode::Esc => {
                                 self.dialog = None;
                             }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = match associations {
+                                    Some(Ok(associations)) => associations.len(),
+                                    _ => 0,
+                                };
+                                state.select(
+                                    state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                );
+                            }
                             _ => {}
                         },
-                    };
-                } else {
-                    match key.code {
-                        KeyCode::Char('h') | KeyCode::Left => self.focus_previous_panel(),
-                        KeyCode::Char('l') | KeyCode::Right => self.focus_next_panel(),
-                        KeyCode::Char('k') | KeyCode::Up => match self.focus {
-                            Focus::Jobs => self.select_previous_job(),
-                            Focus::Stdout => self.scroll_output_up(1),
+                        Dialog::Settings(state) => match key.code {
+                            KeyCode::Enter | KeyCode::Char(' ') => {
+                                let i = state.selected().unwrap_or(0);
+                                let setting = Setting::all()[i];
+                                if setting.is_number() {
+                                    self.dialog = Some(Dialog::Input(
+                                        InputTarget::Setting(i),
+                                        setting.value(&self.config),
+                                    ));
+                                } else if let Some((key, value)) = setting.toggled(&self.config) {
+                                    self.write_setting(key, value);
+                                }
+                            }
+                            KeyCode::Esc | KeyCode::Char('s') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = Setting::all().len();
+                                state.select(state.selected().map(|i| min(i + 1, len - 1)));
+                            }
+                            _ => {}
                         },
-                        KeyCode::Char('j') | KeyCode::Down => match self.focus {
-                            Focus::Jobs => self.select_next_job(),
-                            Focus::Stdout => self.scroll_output_down(1),
+                        Dialog::Users(state) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('U') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = leaderboard::by_user(&self.all_jobs).len();
+                                state.select(
+                                    state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                );
+                            }
+                            _ => {}
                         },
-                        KeyCode::PageDown => {
-                            let delta = if key.modifiers.intersects(
-                                crossterm::event::KeyModifiers::SHIFT
-                                    | crossterm::event::KeyModifiers::CONTROL
-                                    | crossterm::event::KeyModifiers::ALT,
-                            ) {
-                                50
-                            } else {
-                                1
-                            };
-                            self.scroll_output_down(delta);
-                        }
-                        KeyCode::PageUp => {
-                            let delta = if key.modifiers.intersects(
-                                crossterm::event::KeyModifiers::SHIFT
-                                    | crossterm::event::KeyModifiers::CONTROL
-                                    | crossterm::event::KeyModifiers::ALT,
-                            ) {
-                                50
-                            } else {
-                                1
-                            };
-                            self.scroll_output_up(delta);
-                        }
-                        KeyCode::Home | KeyCode::Char('g') => match self.focus {
-                            Focus::Jobs => self.select_first_job(),
-                            Focus::Stdout => {
-                                self.job_output_offset = 0;
-                                self.job_output_anchor = ScrollAnchor::Top;
+                        Dialog::Nodes(nodes, state) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('N') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
                             }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = match nodes {
+                                    Some(Ok(nodes)) => nodes.len(),
+                                    _ => 0,
+                                };
+                                state.select(
+                                    state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                );
+                            }
+                            KeyCode::Char(c @ ('d' | 'r')) => {
+                                let node = state.selected().and_then(|i| {
+                                    nodes
+                                        .as_ref()
+                                        .and_then(|n| n.as_ref().ok())
+                                        .and_then(|n| n.get(i))
+                                        .map(|n| n.name.clone())
+                                });
+                                match node {
+                                    Some(_) if !self.admin => {
+                                        self.set_status_message(StatusMessage::Error(
+                                            "Node actions require --admin".to_string(),
+                                        ));
+                                    }
+                                    Some(node) if c == 'd' => {
+                                        self.dialog = Some(Dialog::Input(
+                                            InputTarget::DrainReason(node),
+                                            String::new(),
+                                        ));
+                                    }
+                                    Some(node) => self.perform(Action::Node {
+                                        node,
+                                        action: NodeAction::Resume,
+                                    }),
+                                    None => {}
+                                }
+                            }
+                            _ => {}
                         },
-                        KeyCode::End | KeyCode::Char('G') => match self.focus {
-                            Focus::Jobs => self.select_last_job(),
-                            Focus::Stdout => {
-                                self.job_output_offset = 0;
-                                self.job_output_anchor = ScrollAnchor::Bottom;
+                        Dialog::JobNodes(_, nodes, state) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('b') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                state.select(
+                                    state
+                                        .selected()
+                                        .map(|i| min(i + 1, nodes.len().saturating_sub(1))),
+                                );
+                            }
+                            KeyCode::Enter => {
+                                if let Some(node) = state.selected().and_then(|i| nodes.get(i)) {
+                                    self.pending_command = Some(job_commands::ssh_to_node(node));
+                                }
                             }
+                            KeyCode::Char('i') => {
+                                if let Some(node) =
+                                    state.selected().and_then(|i| nodes.get(i)).cloned()
+                                {
+                                    self.open_nodes(Some(&node));
+                                }
+                            }
+                            KeyCode::Char('p') => {
+                                if let Some(node) =
+                                    state.selected().and_then(|i| nodes.get(i)).cloned()
+                                {
+                                    self.pinger.ping(&node);
+                                    self.set_status_message(StatusMessage::Info(format!(
+                                        "Pinging {}",
+                                        node
+                                    )));
+                                }
+                            }
+                            _ => {}
                         },
-                        KeyCode::Char('c') => {
-                            if let Some(id) = self
-                                .job_list_state
-                                .selected()
-                                .and_then(|i| self.jobs.get(i).map(|j| j.id()))
-                            {
-                                self.dialog = Some(Dialog::ConfirmCancelJob(id));
+                        Dialog::Escalation(_, offset) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('w') => {
+                                self.dialog = None;
                             }
-                        }
-                        KeyCode::Char('o') => {
-                            self.output_file_view = match self.output_file_view {
-                                OutputFileView::Stdout => OutputFileView::Stderr,
-                                OutputFileView::Stderr => OutputFileView::Stdout,
-                            };
-                        }
-                        _ => {}
-                    }
-                }
-            }
-        }
-
-        // update
-        self.job_output_watcher
-            .set_file_path(self.job_list_state.selected().and_then(|i| {
-                self.jobs.get(i).and_then(|j| match self.output_file_view {
-                    OutputFileView::Stdout => j.stdout.clone(),
-                    OutputFileView::Stderr => j.stderr.clone(),
-                })
-            }));
-    }
-
-    fn update_jobs_and_selection(&mut self, new_jobs: Vec<Job>) {
-        if let Some(selected_id) = &self.selected_job_id {
-            // Find the index of the currently selected job in the new job list
-            let new_index = new_jobs.iter().position(|job| job.id() == *selected_id);
-
-            if let Some(index) = new_index {
-                // Update the job list state if the job is still present
-                self.job_list_state.select(Some(index));
-            } else if !new_jobs.is_empty() {
-                // Reset selection if the job is no longer in the list
-                self.select_first_job();
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                *offset = offset.saturating_sub(1);
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                *offset = offset.saturating_add(1);
+                            }
+                            _ => {}
+                        },
+                        Dialog::Plugins(offset) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('P') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                *offset = offset.saturating_sub(1);
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                *offset = offset.saturating_add(1);
+                            }
+                            _ => {}
+                        },
+                        Dialog::Activity(entries, state) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('L') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = entries.as_ref().map_or(0, |e| e.len());
+                                state.select(
+                                    state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                );
+                            }
+                            _ => {}
+                        },
+                        Dialog::Commands(state) => match key.code {
+                            KeyCode::Esc | KeyCode::Char(':') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = self.config.job_commands.len();
+                                state.select(
+                                    state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                );
+                            }
+                            KeyCode::Enter => {
+                                let command = state
+                                    .selected()
+                                    .and_then(|i| self.config.job_commands.get(i));
+                                match (command, self.selected_job()) {
+                                    (Some(command), Some(job)) => {
+                                        self.pending_command = Some(command.expand(job));
+                                        self.dialog = None;
+                                    }
+                                    (Some(_), None) => self.set_status_message(
+                                        StatusMessage::Error("No job selected".to_string()),
+                                    ),
+                                    (None, _) => {}
+                                }
+                            }
+                            _ => {}
+                        },
+                        Dialog::Notifications(state) => {
+                            let selected = state.selected().unwrap_or(0);
+                            // the first row is the quiet hours
+                            let rule = selected.checked_sub(1);
+                            match key.code {
+                                KeyCode::Esc | KeyCode::Char('Z') => {
+                                    self.dialog = None;
+                                }
+                                KeyCode::Char('k') | KeyCode::Up => {
+                                    state.select(Some(selected.saturating_sub(1)));
+                                }
+                                KeyCode::Char('j') | KeyCode::Down => {
+                                    let len = self.state.notifications.rules.len();
+                                    state.select(Some(min(selected + 1, len)));
+                                }
+                                KeyCode::Enter => match rule {
+                                    Some(i) => {
+                                        let states =
+                                            self.state.notifications.rules[i].states.join(", ");
+                                        self.dialog = Some(Dialog::Input(
+                                            InputTarget::NotificationStates(i),
+                                            states,
+                                        ));
+                                    }
+                                    None => {
+                                        let quiet_hours = self
+                                            .state
+                                            .notifications
+                                            .quiet_hours
+                                            .map(|q| q.to_string())
+                                            .unwrap_or_default();
+                                        self.dialog = Some(Dialog::Input(
+                                            InputTarget::QuietHours,
+                                            quiet_hours,
+                                        ));
+                                    }
+                                },
+                                KeyCode::Char('a') => {
+                                    if let Some(id) = self.selected_job().map(|j| j.id()) {
+                                        let i = self.state.notifications.add(RuleTarget::Job(id));
+                                        self.save_state();
+                                        self.open_notifications(i + 1);
+                                    }
+                                }
+                                KeyCode::Char('f') => {
+                                    self.dialog = Some(Dialog::Input(
+                                        InputTarget::NotificationFilter,
+                                        self.search.clone().unwrap_or_default(),
+                                    ));
+                                }
+                                KeyCode::Char('z') => {
+                                    if let Some(i) = rule {
+                                        self.dialog = Some(Dialog::Input(
+                                            InputTarget::Snooze(i),
+                                            String::new(),
+                                        ));
+                                    }
+                                }
+                                KeyCode::Char('d') => {
+                                    match rule {
+                                        Some(i) => {
+                                            self.state.notifications.rules.remove(i);
+                                        }
+                                        None => self.state.notifications.quiet_hours = None,
+                                    }
+                                    self.save_state();
+                                    self.open_notifications(selected.saturating_sub(1));
+                                }
+                                _ => {}
+                            }
+                        }
+                        Dialog::History(state) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('I') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = self.notification_history.len();
+                                state.select(
+                                    state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                );
+                            }
+                            _ => {}
+                        },
+                        Dialog::Alert(job) => match key.code {
+                            KeyCode::Esc | KeyCode::Enter => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('l') => {
+                                let id = job.id();
+                                match self.select_job_by_id(&id) {
+                                    Ok(()) => {
+                                        self.focus = Focus::Stdout;
+                                        self.job_output_offset = 0;
+                                        self.job_output_anchor = ScrollAnchor::Bottom;
+                                    }
+                                    Err(e) => self.set_status_message(StatusMessage::Error(
+                                        format!("Cannot show the log: {}", e),
+                                    )),
+                                }
+                            }
+                            KeyCode::Char('r') => {
+                                let action = Action::Requeue {
+                                    id: job.id(),
+                                    user: job.user.to_string(),
+                                };
+                                self.dialog = None;
+                                self.perform(action);
+                            }
+                            KeyCode::Char('s') if job.array_step.is_some() => {
+                                self.set_status_message(StatusMessage::Error(
+                                    "Array tasks cannot be resubmitted on their own, requeue them instead".to_string(),
+                                ));
+                            }
+                            KeyCode::Char('s') => {
+                                let id = job.id();
+                                let work_dir = job.work_dir.clone();
+                                let sacct = self.capabilities.sacct;
+                                self.dialog = None;
+                                self.resubmit(id.clone(), move || {
+                                    Resubmission::capture(&id, &work_dir?, sacct)
+                                });
+                            }
+                            _ => {}
+                        },
+                        Dialog::Triage(array_id, triage, state) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('X') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = triage.as_ref().map_or(0, |t| t.buckets.len());
+                                state.select(
+                                    state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                );
+                            }
+                            KeyCode::Enter => {
+                                // the stderr of the first task with the error
+                                let task = state.selected().and_then(|i| {
+                                    triage.as_ref()?.buckets.get(i)?.tasks.first().cloned()
+                                });
+                                if let Some(task) = task {
+                                    match self.select_job_by_id(&task) {
+                                        Ok(()) => {
+                                            self.output_file_view = OutputFileView::Stderr;
+                                            self.focus = Focus::Stdout;
+                                            self.job_output_offset = 0;
+                                            self.job_output_anchor = ScrollAnchor::Bottom;
+                                        }
+                                        Err(e) => self.set_status_message(StatusMessage::Error(
+                                            format!("Cannot show task {}: {}", task, e),
+                                        )),
+                                    }
+                                }
+                            }
+                            KeyCode::Char('s') => {
+                                let array_id = array_id.clone();
+                                self.resubmit_failed_tasks(&array_id);
+                            }
+                            _ => {}
+                        },
+                        Dialog::Matrix(array_id, cursor, columns) => {
+                            let len = matrix::array_tasks(&self.all_jobs, array_id).len();
+                            let last = len.saturating_sub(1);
+                            match key.code {
+                                KeyCode::Esc | KeyCode::Char('V') => {
+                                    self.dialog = None;
+                                }
+                                KeyCode::Char('h') | KeyCode::Left => {
+                                    *cursor = cursor.saturating_sub(1);
+                                }
+                                KeyCode::Char('l') | KeyCode::Right => {
+                                    *cursor = min(*cursor + 1, last);
+                                }
+                                KeyCode::Char('k') | KeyCode::Up => {
+                                    *cursor = cursor.saturating_sub(*columns);
+                                }
+                                KeyCode::Char('j') | KeyCode::Down => {
+                                    *cursor = min(*cursor + *columns, last);
+                                }
+                                KeyCode::Home | KeyCode::Char('g') => *cursor = 0,
+                                KeyCode::End | KeyCode::Char('G') => *cursor = last,
+                                KeyCode::Enter => {
+                                    let task = matrix::array_tasks(&self.all_jobs, array_id)
+                                        .get(*cursor)
+                                        .map(|t| t.id());
+                                    if let Some(task) = task {
+                                        match self.select_job_by_id(&task) {
+                                            Ok(()) => {
+                                                self.focus = Focus::Stdout;
+                                                self.job_output_offset = 0;
+                                                self.job_output_anchor = ScrollAnchor::Bottom;
+                                            }
+                                            Err(e) => {
+                                                self.set_status_message(StatusMessage::Error(
+                                                    format!("Cannot show task {}: {}", task, e),
+                                                ))
+                                            }
+                                        }
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                        Dialog::Efficiency(jobs, state) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('C') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = match jobs {
+                                    Some(Ok(jobs)) => jobs.len(),
+                                    _ => 0,
+                                };
+                                state.select(
+                                    state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                );
+                            }
+                            _ => {}
+                        },
+                        Dialog::Pipelines(state) => {
+                            let pipelines = self.config.pipelines.len();
+                            let len = pipelines + self.state.pipeline_runs.len();
+                            match key.code {
+                                KeyCode::Esc | KeyCode::Char('B') => {
+                                    self.dialog = None;
+                                }
+                                KeyCode::Char('k') | KeyCode::Up => {
+                                    state.select(state.selected().map(|i| i.saturating_sub(1)));
+                                }
+                                KeyCode::Char('j') | KeyCode::Down => {
+                                    state.select(
+                                        state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                    );
+                                }
+                                KeyCode::Enter => match state.selected() {
+                                    Some(i) if i < pipelines => {
+                                        self.dialog = None;
+                                        self.submit_pipeline(i);
+                                    }
+                                    Some(i) => {
+                                        // the first step that has not completed yet
+                                        let run = &self.state.pipeline_runs[i - pipelines];
+                                        let step = run
+                                            .steps
+                                            .iter()
+                                            .find(|s| {
+                                                !self.all_jobs.iter().any(|j| {
+                                                    j.id() == s.job_id && j.state == "COMPLETED"
+                                                })
+                                            })
+                                            .or(run.steps.last());
+                                        if let Some(job_id) = step.map(|s| s.job_id.clone()) {
+                                            match self.select_job_by_id(&job_id) {
+                                                Ok(()) => self.dialog = None,
+                                                Err(e) => self.set_status_message(
+                                                    StatusMessage::Error(format!(
+                                                        "Cannot show job {}: {}",
+                                                        job_id, e
+                                                    )),
+                                                ),
+                                            }
+                                        }
+                                    }
+                                    None => {}
+                                },
+                                KeyCode::Char('d') => {
+                                    if let Some(i) = state.selected().filter(|&i| i >= pipelines) {
+                                        state.select((len > 1).then(|| min(i, len - 2)));
+                                        self.state.pipeline_runs.remove(i - pipelines);
+                                        self.save_state();
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                        Dialog::LogSearch(query, result, state) => match key.code {
+                            KeyCode::Esc => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('K') => {
+                                self.dialog =
+                                    Some(Dialog::Input(InputTarget::LogSearch, query.clone()));
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = result.as_ref().map_or(0, |r| r.hits.len());
+                                state.select(
+                                    state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                );
+                            }
+                            KeyCode::Enter => {
+                                let hit = result
+                                    .as_ref()
+                                    .zip(state.selected())
+                                    .and_then(|(r, i)| r.hits.get(i))
+                                    .map(|hit| {
+                                        let location =
+                                            format!("{}:{}", hit.path.display(), hit.line_number);
+                                        (hit.job_id.clone(), location)
+                                    });
+                                if let Some((id, location)) = hit {
+                                    // past jobs are usually no longer in the list
+                                    if self.select_job_by_id(&id).is_err() {
+                                        self.set_status_message(StatusMessage::Info(format!(
+                                            "Job {} is not in the list, the hit is in {}",
+                                            id, location
+                                        )));
+                                    }
+                                }
+                            }
+                            _ => {}
+                        },
+                        Dialog::UsageReport(period, report, state) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('Y') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('h') | KeyCode::Left => {
+                                *period = period.cycle(-1);
+                                *report = None;
+                                *state = ListState::default();
+                                self.usage_report
+                                    .request(*period, self.capabilities.sacctmgr);
+                            }
+                            KeyCode::Char('l') | KeyCode::Right => {
+                                *period = period.cycle(1);
+                                *report = None;
+                                *state = ListState::default();
+                                self.usage_report
+                                    .request(*period, self.capabilities.sacctmgr);
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = match report {
+                                    Some(Ok(rows)) => rows.len(),
+                                    _ => 0,
+                                };
+                                state.select(
+                                    state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                );
+                            }
+                            _ => {}
+                        },
+                        Dialog::Steps(job_id, steps, state) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('.') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = match steps {
+                                    Some(Ok(steps)) => steps.len() + 1,
+                                    _ => 0,
+                                };
+                                state.select(
+                                    state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                );
+                            }
+                            KeyCode::Enter => {
+                                if let (Some(Ok(steps)), Some(i)) = (steps, state.selected()) {
+                                    let step = i.checked_sub(1).and_then(|i| steps.get(i));
+                                    match step {
+                                        Some(step) if step.stdout.is_none() => {
+                                            let message = format!(
+                                                "Step {} writes to the output of the job",
+                                                step.id
+                                            );
+                                            self.log_step = None;
+                                            self.set_status_message(StatusMessage::Info(message));
+                                        }
+                                        step => {
+                                            self.log_step =
+                                                step.map(|s| (job_id.clone(), s.clone()));
+                                        }
+                                    }
+                                    self.job_output_offset = 0;
+                                    self.dialog = None;
+                                }
+                            }
+                            _ => {}
+                        },
+                        Dialog::WhatIf(_, estimates, state) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('Q') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                state.select(state.selected().map(|i| i.saturating_sub(1)));
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                let len = match estimates {
+                                    Some(Ok(estimates)) => estimates.len(),
+                                    _ => 0,
+                                };
+                                state.select(
+                                    state.selected().map(|i| min(i + 1, len.saturating_sub(1))),
+                                );
+                            }
+                            _ => {}
+                        },
+                        Dialog::Timeline(offset) => match key.code {
+                            KeyCode::Esc | KeyCode::Char('T') => {
+                                self.dialog = None;
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                *offset = offset.saturating_sub(1);
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                *offset = offset.saturating_add(1);
+                            }
+                            _ => {}
+                        },
+                    };
+                } else {
+                    match key.code {
+                        KeyCode::Char('h') | KeyCode::Left => self.focus_previous_panel(),
+                        KeyCode::Char('l') | KeyCode::Right => self.focus_next_panel(),
+                        KeyCode::Char('k') | KeyCode::Up => match self.focus {
+                            Focus::Jobs => self.select_previous_job(),
+                            Focus::Stdout => self.scroll_output_up(1),
+                        },
+                        KeyCode::Char('j') | KeyCode::Down => match self.focus {
+                            Focus::Jobs => self.select_next_job(),
+                            Focus::Stdout => self.scroll_output_down(1),
+                        },
+                        KeyCode::PageDown => {
+                            let delta = if key.modifiers.intersects(
+                                crossterm::event::KeyModifiers::SHIFT
+                                    | crossterm::event::KeyModifiers::CONTROL
+                                    | crossterm::event::KeyModifiers::ALT,
+                            ) {
+                                50
+                            } else {
+                                1
+                            };
+                            self.scroll_output_down(delta);
+                        }
+                        KeyCode::PageUp => {
+                            let delta = if key.modifiers.intersects(
+                                crossterm::event::KeyModifiers::SHIFT
+                                    | crossterm::event::KeyModifiers::CONTROL
+                                    | crossterm::event::KeyModifiers::ALT,
+                            ) {
+                                50
+                            } else {
+                                1
+                            };
+                            self.scroll_output_up(delta);
+                        }
+                        KeyCode::Home | KeyCode::Char('g') => match self.focus {
+                            Focus::Jobs => self.select_first_job(),
+                            Focus::Stdout => {
+                                self.job_output_offset = 0;
+                                self.job_output_anchor = ScrollAnchor::Top;
+                            }
+                        },
+                        KeyCode::End | KeyCode::Char('G') => match self.focus {
+                            Focus::Jobs => self.select_last_job(),
+                            Focus::Stdout => {
+                                self.job_output_offset = 0;
+                                self.job_output_anchor = ScrollAnchor::Bottom;
+                            }
+                        },
+                        KeyCode::Enter | KeyCode::Char(' ') => {
+                            if let Some(JobListRow::Group(g)) = self.selected_row() {
+                                let key = g.key.clone();
+                                if !self.collapsed_groups.remove(&key) {
+                                    self.collapsed_groups.insert(key);
+                                }
+                                self.refresh_rows();
+                            }
+                        }
+                        KeyCode::Char('c') => match self.selected_row() {
+                            Some(JobListRow::Job(i)) => {
+                                let job = &self.jobs[*i];
+                                self.perform(Action::Cancel {
+                                    id: job.id(),
+                                    user: job.user.to_string(),
+                                });
+                            }
+                            Some(JobListRow::Group(group)) => {
+                                let action = Action::CancelGroup {
+                                    key: group.key.clone(),
+                                    jobs: group
+                                        .jobs
+                                        .iter()
+                                        .map(|&i| {
+                                            (self.jobs[i].id(), self.jobs[i].user.to_string())
+                                        })
+                                        .collect(),
+                                };
+                                self.perform(action);
+                            }
+                            None => {}
+                        },
+                        KeyCode::Char('m') => {
+                            self.squeue_args.toggle_me();
+                            self.update_scope();
+                            self.refresh_rows();
+                        }
+                        KeyCode::Char('v') if self.config.team.is_empty() => {
+                            self.set_status_message(StatusMessage::Error(
+                                "No team configured: add the usernames as `team` to the config"
+                                    .to_string(),
+                            ));
+                        }
+                        KeyCode::Char('v') => {
+                            let team = match self.squeue_args.team() {
+                                Some(_) => None,
+                                None => Some(self.config.team.clone()),
+                            };
+                            self.squeue_args.set_team(team);
+                            self.update_scope();
+                            self.refresh_rows();
+                        }
+                        KeyCode::Char('p') => {
+                            self.dialog = Some(Dialog::Input(
+                                InputTarget::Scope(ScopeField::Partition),
+                                self.squeue_args.partition().unwrap_or_default().to_string(),
+                            ));
+                        }
+                        KeyCode::Char('a') => {
+                            self.dialog = Some(Dialog::Input(
+                                InputTarget::Scope(ScopeField::Account),
+                                self.squeue_args.account().unwrap_or_default().to_string(),
+                            ));
+                        }
+                        KeyCode::Char('t') => {
+                            self.dialog = Some(Dialog::Input(
+                                InputTarget::Scope(ScopeField::States),
+                                self.squeue_args.states().unwrap_or_default().to_string(),
+                            ));
+                        }
+                        KeyCode::Char('r') => {
+                            if let Some(j) = self.selected_job().filter(|j| {
+                                matches!(j.state.as_str(), "PENDING" | "RUNNING" | "SUSPENDED")
+                            }) {
+                                self.dialog = Some(Dialog::Input(
+                                    InputTarget::RenameJob(j.id()),
+                                    j.name.clone(),
+                                ));
+                            }
+                        }
+                        KeyCode::Char('e') => {
+                            if let Some(j) = self.selected_job().filter(|j| j.state == "PENDING") {
+                                self.dialog = Some(Dialog::Input(
+                                    InputTarget::Dependency(j.id()),
+                                    j.dependency
+                                        .as_deref()
+                                        .map(dependency::without_status)
+                                        .unwrap_or_default(),
+                                ));
+                            }
+                        }
+                        KeyCode::Char('#') => {
+                            if let Some(id) = self.selected_job().map(|j| j.id()) {
+                                let tags = self
+                                    .state
+                                    .annotations(&id)
+                                    .map(|a| a.tags.iter().cloned().collect::<Vec<_>>().join(", "))
+                                    .unwrap_or_default();
+                                self.dialog = Some(Dialog::Input(InputTarget::Tags(id), tags));
+                            }
+                        }
+                        KeyCode::Char('/') => {
+                            self.dialog = Some(Dialog::Input(
+                                InputTarget::Search,
+                                self.search.clone().unwrap_or_default(),
+                            ));
+                        }
+                        KeyCode::Char('F') => {
+                            self.show_finished = !self.show_finished;
+                            self.refresh_jobs();
+                        }
+                        KeyCode::Char('.') => {
+                            if let Some(job) = self.selected_job() {
+                                let job_id = job.id();
+                                self.step_watcher.request(job.clone());
+                                self.dialog =
+                                    Some(Dialog::Steps(job_id, None, ListState::default()));
+                            }
+                        }
+                        KeyCode::Char('@') => {
+                            self.dialog = Some(Dialog::Input(
+                                InputTarget::LogRank,
+                                self.log_rank.map(|r| r.to_string()).unwrap_or_default(),
+                            ));
+                        }
+                        KeyCode::Char('*') => {
+                            if let Some(id) = self.selected_job().map(|j| j.id()) {
+                                self.state.toggle_pinned(&id);
+                                self.save_state();
+                                self.job_watcher
+                                    .set_pinned(self.state.pinned.iter().cloned().collect());
+                                self.refresh_jobs();
+                            }
+                        }
+                        KeyCode::Char('n') => {
+                            if let Some(id) = self.selected_job().map(|j| j.id()) {
+                                let note = self
+                                    .state
+                                    .annotations(&id)
+                                    .map(|a| a.note.clone())
+                                    .unwrap_or_default();
+                                self.dialog = Some(Dialog::Input(InputTarget::Note(id), note));
+                            }
+                        }
+                        KeyCode::Char('f') => {
+                            self.dialog = Some(Dialog::Input(
+                                InputTarget::TagFilter,
+                                self.tag_filter.clone().unwrap_or_default(),
+                            ));
+                        }
+                        KeyCode::Char('T') => {
+                            self.dialog = Some(Dialog::Timeline(0));
+                        }
+                        KeyCode::Char('U') => {
+                            let mut state = ListState::default();
+                            state.select(Some(0));
+                            self.dialog = Some(Dialog::Users(state));
+                        }
+                        KeyCode::Char('s') => {
+                            let mut state = ListState::default();
+                            state.select(Some(0));
+                            self.dialog = Some(Dialog::Settings(state));
+                        }
+                        KeyCode::Char('A') if !self.capabilities.sacctmgr => {
+                            self.set_status_message(StatusMessage::Error(
+                                "Associations are not available: sacctmgr cannot be used on this cluster".to_string(),
+                            ));
+                        }
+                        KeyCode::Char('A') => {
+                            self.association_lister.request();
+                            self.dialog = Some(Dialog::Associations(None, ListState::default()));
+                        }
+                        KeyCode::Char('Y') => {
+                            let period = ReportPeriod::ThisMonth;
+                            self.usage_report
+                                .request(period, self.capabilities.sacctmgr);
+                            self.dialog =
+                                Some(Dialog::UsageReport(period, None, ListState::default()));
+                        }
+                        KeyCode::Char('N') => self.open_nodes(None),
+                        KeyCode::Char('P') => self.dialog = Some(Dialog::Plugins(0)),
+                        KeyCode::Char('b') => match self.selected_job() {
+                            Some(job) if !job.nodelist.is_empty() => {
+                                let nodes = hostlist::expand(&job.nodelist);
+                                let mut state = ListState::default();
+                                state.select(Some(0));
+                                self.dialog = Some(Dialog::JobNodes(job.id(), nodes, state));
+                            }
+                            Some(job) => self.set_status_message(StatusMessage::Error(format!(
+                                "Job {} has no nodes",
+                                job.id()
+                            ))),
+                            None => {}
+                        },
+                        KeyCode::Char('w') => {
+                            let now = clock::now();
+                            match self
+                                .selected_job()
+                                .map(|j| (j, self.completing.since(&j.id())))
+                            {
+                                Some((job, Some(since))) => {
+                                    let message = completing::escalation_message(
+                                        &self.config.completing,
+                                        job,
+                                        since,
+                                        now,
+                                    );
+                                    self.dialog = Some(Dialog::Escalation(message, 0));
+                                }
+                                Some((job, None)) => self.set_status_message(StatusMessage::Error(
+                                    format!("Job {} is not completing", job.id()),
+                                )),
+                                None => {}
+                            }
+                        }
+                        KeyCode::Char('M') => {
+                            self.show_metrics = !self.show_metrics;
+                            self.update_metrics();
+                        }
+                        KeyCode::Char('O') => match self.run_links.last() {
+                            Some(link) => {
+                                if let Err(e) = links::open(link) {
+                                    self.set_status_message(StatusMessage::Error(format!(
+                                        "Failed to open {}: {}",
+                                        link, e
+                                    )));
+                                }
+                            }
+                            None => self.set_status_message(StatusMessage::Error(
+                                "No run link in the log of this job".to_string(),
+                            )),
+                        },
+                        KeyCode::Char('H') if self.processes.is_some() => self.processes = None,
+                        KeyCode::Char('H' | 'R') => match self.selected_job() {
+                            Some(job) if job.state == "RUNNING" => {
+                                let job_id = job.job_id.clone();
+                                self.process_watcher.refresh(&job_id);
+                                self.processes = Some((job_id, None));
+                            }
+                            Some(_) => self.set_status_message(StatusMessage::Error(
+                                "Processes can only be listed for running jobs".to_string(),
+                            )),
+                            None => {}
+                        },
+                        KeyCode::Char(':') => {
+                            let mut state = ListState::default();
+                            if !self.config.job_commands.is_empty() {
+                                state.select(Some(0));
+                            }
+                            self.dialog = Some(Dialog::Commands(state));
+                        }
+                        KeyCode::Char('E') => self.snapshot_requested = true,
+                        KeyCode::Char('i') => {
+                            self.anonymizer = match self.anonymizer.take() {
+                                Some(_) => {
+                                    self.set_status_message(StatusMessage::Info(
+                                        "Showing real users, job names and paths".to_string(),
+                                    ));
+                                    None
+                                }
+                                None => {
+                                    let mut anonymizer = Anonymizer::default();
+                                    anonymizer.learn(&self.all_jobs, remote::current_user());
+                                    self.set_status_message(StatusMessage::Info(
+                                        "Anonymized: users, job names and paths are replaced by pseudonyms".to_string(),
+                                    ));
+                                    Some(anonymizer)
+                                }
+                            };
+                        }
+                        KeyCode::Char('S') => {
+                            let jobs: Vec<Job> = match self.selected_row() {
+                                Some(JobListRow::Group(group)) => {
+                                    group.jobs.iter().map(|&i| self.jobs[i].clone()).collect()
+                                }
+                                Some(JobListRow::Job(i)) => vec![self.jobs[*i].clone()],
+                                _ => Vec::new(),
+                            };
+                            if !jobs.is_empty() {
+                                self.manifest_exporter.export(jobs);
+                            }
+                        }
+                        KeyCode::Char('Z') => self.open_notifications(0),
+                        KeyCode::Char('C') if !self.capabilities.sacct => {
+                            self.set_status_message(StatusMessage::Error(
+                                "Comparing requests and usage requires sacct".to_string(),
+                            ));
+                        }
+                        KeyCode::Char('C') => {
+                            let ids = self.compared_jobs();
+                            if ids.is_empty() {
+                                self.set_status_message(StatusMessage::Error(
+                                    "No finished jobs to compare".to_string(),
+                                ));
+                            } else {
+                                self.efficiency_querier.query(ids);
+                                self.dialog = Some(Dialog::Efficiency(None, ListState::default()));
+                            }
+                        }
+                        KeyCode::Char('V') => match self.selected_job() {
+                            Some(job) if job.array_step.is_some() => {
+                                let array_id = job.array_id.clone();
+                                let cursor = matrix::array_tasks(&self.all_jobs, &array_id)
+                                    .iter()
+                                    .position(|t| t.id() == job.id())
+                                    .unwrap_or(0);
+                                self.dialog = Some(Dialog::Matrix(array_id, cursor, 1));
+                            }
+                            Some(_) => self.set_status_message(StatusMessage::Error(
+                                "The task grid is for array jobs".to_string(),
+                            )),
+                            None => {}
+                        },
+                        KeyCode::Char('X') => match self.selected_job() {
+                            Some(job) if job.array_step.is_some() => {
+                                let array_id = job.array_id.clone();
+                                if self.triage.request(&self.all_jobs, &array_id) == 0 {
+                                    self.set_status_message(StatusMessage::Error(format!(
+                                        "No failed tasks in array job {}",
+                                        array_id
+                                    )));
+                                } else {
+                                    self.dialog =
+                                        Some(Dialog::Triage(array_id, None, ListState::default()));
+                                }
+                            }
+                            Some(_) => self.set_status_message(StatusMessage::Error(
+                                "Failure triage is for array jobs".to_string(),
+                            )),
+                            None => {}
+                        },
+                        KeyCode::Char('Q') => match self.selected_job() {
+                            Some(job)
+                                if job.state == "PENDING" && job.user == remote::current_user() =>
+                            {
+                                let job_id = job.id();
+                                match job.work_dir.clone() {
+                                    Some(work_dir) => {
+                                        self.action_runner.capture(
+                                            job_id.clone(),
+                                            work_dir,
+                                            self.capabilities.sacct,
+                                        );
+                                        self.dialog = Some(Dialog::WhatIf(
+                                            job_id,
+                                            None,
+                                            ListState::default(),
+                                        ));
+                                    }
+                                    None => self.set_status_message(StatusMessage::Error(format!(
+                                        "The submit line of job {} is not known",
+                                        job_id
+                                    ))),
+                                }
+                            }
+                            Some(_) => self.set_status_message(StatusMessage::Error(
+                                "Other queues can only be compared for my pending jobs".to_string(),
+                            )),
+                            None => {}
+                        },
+                        KeyCode::Char('K') => {
+                            self.dialog =
+                                Some(Dialog::Input(InputTarget::LogSearch, String::new()));
+                        }
+                        KeyCode::Char('J') => {
+                            let path = self
+                                .state
+                                .jobs_file
+                                .as_ref()
+                                .map(|f| f.path.to_string_lossy().into_owned())
+                                .unwrap_or_default();
+                            self.dialog = Some(Dialog::Input(InputTarget::JobsFile, path));
+                        }
+                        KeyCode::Char('B') => {
+                            if self.config.pipelines.is_empty()
+                                && self.state.pipeline_runs.is_empty()
+                            {
+                                self.set_status_message(StatusMessage::Error(
+                                    "No pipelines configured".to_string(),
+                                ));
+                            } else {
+                                let mut state = ListState::default();
+                                state.select(Some(0));
+                                self.dialog = Some(Dialog::Pipelines(state));
+                            }
+                        }
+                        KeyCode::Char('I') => {
+                            let mut state = ListState::default();
+                            if !self.notification_history.is_empty() {
+                                state.select(Some(0));
+                            }
+                            self.unseen_notifications = 0;
+                            self.dialog = Some(Dialog::History(state));
+                        }
+                        KeyCode::Char('W') => {
+                            if let Some(job) = self.selected_job() {
+                                let result = job_commands::open_in_tmux_pane(
+                                    job,
+                                    self.config.tmux_pane_command.as_deref(),
+                                    self.socket(),
+                                );
+                                self.set_status_message(match result {
+                                    Ok(message) => StatusMessage::Info(message),
+                                    Err(message) => StatusMessage::Error(message),
+                                });
+                            }
+                        }
+                        KeyCode::Char('D') => {
+                            self.show_heatmap = !self.show_heatmap;
+                            self.node_watcher.set_enabled(self.show_heatmap);
+                        }
+                        KeyCode::Char('L') => {
+                            let entries = audit::recent();
+                            let mut state = ListState::default();
+                            if entries.as_ref().is_ok_and(|e| !e.is_empty()) {
+                                state.select(Some(0));
+                            }
+                            self.dialog = Some(Dialog::Activity(entries, state));
+                        }
+                        KeyCode::Char('u') => match self.undo.take() {
+                            Some((resubmission, at)) if at.elapsed() < UNDO_WINDOW => {
+                                self.resubmit(resubmission.id.clone(), || Some(resubmission));
+                            }
+                            _ => self.set_status_message(StatusMessage::Error(
+                                "Nothing to undo".to_string(),
+                            )),
+                        },
+                        KeyCode::Char('x') => {
+                            if let Some(announcement) = self.visible_announcement() {
+                                self.state.dismissed_announcement = Some(announcement.to_string());
+                                self.save_state();
+                            }
+                        }
+                        KeyCode::Char('o') => {
+                            self.output_file_view = match self.output_file_view {
+                                OutputFileView::Stdout => OutputFileView::Stderr,
+                                OutputFileView::Stderr => OutputFileView::Stdout,
+                            };
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        // update
+        if self.dialog.is_none() {
+            if let Some(job) = self.alerts.pop_front() {
+                self.dialog = Some(Dialog::Alert(Box::new(job)));
+            }
+        }
+        let step = self.selected_step();
+        self.job_output_watcher.set_file_path(
+            self.selected_job().and_then(|j| {
+                let (stdout, stderr) = match step {
+                    Some(step) => (&step.stdout, &step.stderr),
+                    None => (&j.stdout, &j.stderr),
+                };
+                match self.output_file_view {
+                    OutputFileView::Stdout => stdout.clone(),
+                    OutputFileView::Stderr => stderr.clone(),
+                }
+            }),
+            self.selected_job().and_then(JobNode::of),
+        );
+        self.usage_watcher.set_job(
+            self.selected_job()
+                .filter(|j| self.capabilities.sstat && j.state == "RUNNING")
+                .map(|j| j.job_id.clone()),
+        );
+        self.node_load_watcher.set_nodes(
+            self.selected_job()
+                .filter(|j| j.state == "RUNNING")
+                .map(|j| hostlist::expand(&j.nodelist))
+                .unwrap_or_default(),
+        );
+        if let Some(job_id) = self
+            .selected_job()
+            .filter(|j| j.state == "RUNNING" && !self.allocations.contains_key(&j.job_id))
+            .map(|j| j.job_id.clone())
+        {
+            self.allocations.insert(job_id.clone(), None);
+            self.allocation_watcher.request(job_id);
+        }
+        if self.capabilities.sacct {
+            // pending jobs have neither steps nor an exit code yet
+            self.prefetcher.set_selection(
+                self.selected_job()
+                    .filter(|j| j.state != "PENDING")
+                    .map(|j| j.id()),
+                self.nearby_jobs()
+                    .filter(|j| j.state != "PENDING")
+                    .map(|j| j.id())
+                    .collect(),
+            );
+        }
+        if let Some(job) = self
+            .selected_job()
+            .filter(|j| self.capabilities.sacct && j.user == remote::current_user())
+        {
+            let pattern = suggest::name_pattern(&job.name);
+            if !self.suggestions.contains_key(&pattern) {
+                self.suggester.request(pattern.clone());
+                self.suggestions.insert(pattern, None);
+            }
+        }
+        self.backfill_watcher
+            .set_job(self.selected_job().and_then(|j| self.backfill_query(j)));
+        // only nodes that run jobs are checked
+        self.node_health_watcher.set_nodes(match &self.dialog {
+            Some(Dialog::Nodes(Some(Ok(nodes)), _)) if self.config.node_health.is_some() => nodes
+                .iter()
+                .filter(|n| {
+                    ["allocated", "mixed", "completing"]
+                        .iter()
+                        .any(|s| n.state.starts_with(s))
+                })
+                .map(|n| n.name.clone())
+                .collect(),
+            _ => Vec::new(),
+        });
+    }
+
+    fn is_editing_text(&self) -> bool {
+        matches!(
+            self.dialog,
+            Some(Dialog::Input(..) | Dialog::ConfirmTyped(..))
+        )
+    }
+
+    fn submit_input(&mut self, target: InputTarget, value: String) {
+        let value = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        match target {
+            InputTarget::Scope(field) => {
+                match field {
+                    ScopeField::Partition => self.squeue_args.set_partition(value),
+                    ScopeField::Account => self.squeue_args.set_account(value),
+                    ScopeField::States => self.squeue_args.set_states(value),
+                }
+                self.update_scope();
+            }
+            InputTarget::RenameJob(id) => {
+                if let Some(name) = value {
+                    let user = self
+                        .all_jobs
+                        .iter()
+                        .find(|j| j.id() == id)
+                        .map(|j| j.user.to_string())
+                        .unwrap_or_default();
+                    self.perform(Action::Rename { id, user, name });
+                }
+            }
+            InputTarget::Dependency(id) => {
+                let user = self
+                    .all_jobs
+                    .iter()
+                    .find(|j| j.id() == id)
+                    .map(|j| j.user.to_string())
+                    .unwrap_or_default();
+                self.perform(Action::SetDependency {
+                    id,
+                    user,
+                    dependency: value.unwrap_or_default(),
+                });
+            }
+            InputTarget::Tags(id) => {
+                let tags = value
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect();
+                self.state.set_tags(&id, tags);
+                self.save_state();
+                self.refresh_jobs();
+            }
+            InputTarget::Note(id) => {
+                self.state.set_note(&id, value.unwrap_or_default());
+                self.save_state();
+            }
+            InputTarget::TagFilter => {
+                self.tag_filter = value;
+                self.refresh_jobs();
+            }
+            InputTarget::JobsFile => match value {
+                Some(path) => {
+                    let path = match path.strip_prefix("~/").zip(dirs::home_dir()) {
+                        Some((path, home)) => home.join(path),
+                        None => PathBuf::from(path),
+                    };
+                    self.import_jobs_file(path);
+                }
+                None => {
+                    self.jobs_file_watcher = None;
+                    self.set_jobs_file(None);
+                }
+            },
+            InputTarget::Search => {
+                self.search = value;
+                self.refresh_jobs();
+            }
+            InputTarget::LogRank => match value.as_deref().map(str::parse).transpose() {
+                Ok(rank) => {
+                    self.log_rank = rank;
+                    self.job_output_offset = 0;
+                }
+                Err(_) => self.set_status_message(StatusMessage::Error(format!(
+                    "Not a rank: {}",
+                    value.unwrap_or_default()
+                ))),
+            },
+            InputTarget::LogSearch => {
+                if let Some(query) = value {
+                    self.log_search.search(
+                        query.clone(),
+                        self.state.archived.clone(),
+                        self.config.history.clone(),
+                        self.config.log_index,
+                    );
+                    self.dialog = Some(Dialog::LogSearch(query, None, ListState::default()));
+                }
+            }
+            InputTarget::Setting(i) => {
+                match Setting::all()[i].parse(value.as_deref().unwrap_or_default()) {
+                    Ok((key, value)) => self.write_setting(key, value),
+                    Err(e) => self.set_status_message(StatusMessage::Error(e)),
+                }
+                let mut state = ListState::default();
+                state.select(Some(i));
+                self.dialog = Some(Dialog::Settings(state));
+            }
+            InputTarget::QuietHours => {
+                match value.as_deref().map(QuietHours::parse).transpose() {
+                    Ok(quiet_hours) => {
+                        self.state.notifications.quiet_hours = quiet_hours;
+                        self.save_state();
+                    }
+                    Err(e) => self.set_status_message(StatusMessage::Error(e)),
+                }
+                self.open_notifications(0);
+            }
+            InputTarget::NotificationFilter => match value {
+                Some(query) => {
+                    let i = self.state.notifications.add(RuleTarget::Filter(query));
+                    self.save_state();
+                    self.open_notifications(i + 1);
+                }
+                None => self.open_notifications(0),
+            },
+            InputTarget::NotificationStates(i) => {
+                self.state.notifications.rules[i].states = value
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|s| s.trim().to_uppercase())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                self.save_state();
+                self.open_notifications(i + 1);
+            }
+            InputTarget::Snooze(i) => {
+                let now = clock::now();
+                match value.as_deref().map(|v| parse_snooze(v, now)).transpose() {
+                    Ok(until) => {
+                        self.state.notifications.rules[i].snoozed_until = until;
+                        self.save_state();
+                    }
+                    Err(e) => self.set_status_message(StatusMessage::Error(e)),
+                }
+                self.open_notifications(i + 1);
+            }
+            InputTarget::DrainReason(node) => match value {
+                Some(reason) => {
+                    self.perform(Action::Node {
+                        node,
+                        action: NodeAction::Drain(reason),
+                    });
+                }
+                None => {
+                    self.set_status_message(StatusMessage::Error(
+                        "Draining a node requires a reason".to_string(),
+                    ));
+                    self.open_nodes(Some(&node));
+                }
+            },
+        }
+    }
+
+    /// Answers a request from an IPC client, see [`crate::ipc::METHODS`].
+    fn handle_ipc(
+        &mut self,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let id = || {
+            params
+                .get("id")
+                .and_then(|id| id.as_str())
+                .ok_or_else(|| "missing job id".to_string())
+        };
+        match method {
+            "jobs" => Ok(self.jobs.iter().map(Job::to_json).collect()),
+            "selected" => Ok(self.selected_job().map(|j| j.id()).into()),
+            "info" => Ok(serde_json::json!({
+                "pid": std::process::id(),
+                "tmux_pane": std::env::var("TMUX_PANE").ok(),
+            })),
+            "select" => {
+                self.select_job_by_id(id()?)?;
+                Ok(serde_json::Value::Bool(true))
+            }
+            "cancel" => {
+                let id = id()?;
+                let job = self
+                    .all_jobs
+                    .iter()
+                    .find(|j| j.id() == id)
+                    .ok_or_else(|| format!("job {} not found", id))?;
+                let action = Action::Cancel {
+                    id: job.id(),
+                    user: job.user.to_string(),
+                };
+                let confirm = action.needs_confirmation(&self.config.confirm);
+                self.perform(action);
+                // the result is reported in the status bar once scancel is done
+                Ok(if confirm {
+                    "awaiting confirmation"
+                } else {
+                    "queued"
+                }
+                .into())
+            }
+            _ => Err(format!("unknown method {}", method)),
+        }
+    }
+
+    /// Selects the job with the given id (or, for array tasks, raw job id), expanding its group
+    /// if it is collapsed.
+    fn select_job_by_id(&mut self, id: &str) -> Result<(), String> {
+        let index = self
+            .jobs
+            .iter()
+            .position(|j| j.id() == id || j.job_id == id)
+            .ok_or_else(|| format!("job {} is not in the job list", id))?;
+        let collapsed = self.rows.iter().find_map(|row| match row {
+            JobListRow::Group(g) if g.collapsed && g.jobs.contains(&index) => Some(g.key.clone()),
+            _ => None,
+        });
+        if let Some(key) = collapsed {
+            self.collapsed_groups.remove(&key);
+            self.refresh_rows();
+        }
+        let row = self
+            .rows
+            .iter()
+            .position(|row| matches!(row, JobListRow::Job(i) if *i == index));
+        self.select_job(row);
+        self.focus = Focus::Jobs;
+        self.dialog = None;
+        Ok(())
+    }
+
+    /// Runs the action, after asking for confirmation if the config requires it.
+    fn perform(&mut self, action: Action) {
+        if !action.needs_confirmation(&self.config.confirm) {
+            self.run_action(action);
+        } else if action.confirmation_text().is_some() {
+            self.dialog = Some(Dialog::ConfirmTyped(action, String::new()));
+        } else {
+            self.dialog = Some(Dialog::Confirm(action));
+        }
+    }
+
+    fn run_action(&mut self, action: Action) {
+        // my pending jobs can be resubmitted for a while, unless they are part of an array
+        let undoable = match &action {
+            Action::Cancel { id, .. } => self
+                .all_jobs
+                .iter()
+                .find(|j| j.id() == *id && j.state == "PENDING" && j.array_step.is_none())
+                .filter(|j| j.user == remote::current_user())
+                .and_then(|j| Some((id.clone(), j.work_dir.clone()?))),
+            _ => None,
+        };
+        let node = match &action {
+            Action::Node { node, .. } => Some(node.clone()),
+            _ => None,
+        };
+        self.leave_action(&action);
+        let (name, target) = (action.name(), action.target());
+        let sacct = self.capabilities.sacct;
+        self.action_runner.run(
+            name,
+            &target,
+            self.hook_env(name, &target),
+            &self.config.hooks,
+            node,
+            move || {
+                // captured before cancelling, while the job is still known
+                let undo = undoable.and_then(|(id, dir)| Resubmission::capture(&id, &dir, sacct));
+                (action.run(), undo.map(FollowUp::Undo))
+            },
+        );
+    }
+
+    /// Resubmits a job in the background, like an action. The submit line is captured there
+    /// too, since that asks `sacct` or `scontrol`.
+    fn resubmit(
+        &mut self,
+        id: String,
+        resubmission: impl FnOnce() -> Option<Resubmission> + Send + 'static,
+    ) {
+        let env = self.hook_env("resubmit", &id);
+        self.action_runner.run(
+            "resubmit",
+            &id.clone(),
+            env,
+            &self.config.hooks,
+            None,
+            move || match resubmission() {
+                Some(resubmission) => (resubmission.run(), None),
+                None => (
+                    Err(format!("The submit line of job {} is not known", id)),
+                    None,
+                ),
+            },
+        );
+    }
+
+    /// The IPC socket, which hooks and job commands get in `TURM_SOCKET` to talk back to this
+    /// instance.
+    fn socket(&self) -> Option<&Path> {
+        self.ipc_server.as_ref().map(|server| server.path())
+    }
+
+    /// Environment variables describing an action for its hooks.
+    fn hook_env(&self, action: &str, target: &str) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("TURM_ACTION", action.to_string()),
+            ("TURM_TARGET", target.to_string()),
+            ("TURM_USER", remote::current_user().to_string()),
+        ];
+        if let Some(socket) = self.socket() {
+            env.push(("TURM_SOCKET", socket.to_string_lossy().into_owned()));
+        }
+        if let Some(job) = self.all_jobs.iter().find(|j| j.id() == target) {
+            env.extend([
+                ("TURM_JOB_ID", job.id()),
+                ("TURM_JOB_NAME", job.name.clone()),
+                ("TURM_JOB_USER", job.user.to_string()),
+                ("TURM_JOB_PARTITION", job.partition.to_string()),
+                ("TURM_JOB_STATE", job.state.to_string()),
+            ]);
+        }
+        env
+    }
+
+    /// Shows the result of an action and records it in the audit log.
+    fn report(&mut self, action: &str, target: &str, result: Result<String, String>) {
+        if let Err(e) = audit::record(action, target, &result) {
+            self.set_status_message(StatusMessage::Error(format!(
+                "Failed to write the audit log: {}",
+                e
+            )));
+            return;
+        }
+        self.set_status_message(match result {
+            Ok(message) => StatusMessage::Info(message),
+            Err(message) => StatusMessage::Error(message),
+        });
+    }
+
+    /// Returns to where the action was started from once it ran or was aborted.
+    fn leave_action(&mut self, action: &Action) {
+        self.dialog = None;
+        if let Action::Node { node, .. } = action {
+            self.open_nodes(Some(node));
+        }
+    }
+
+    fn update_metrics(&mut self) {
+        self.metrics = match &self.job_output {
+            Ok(output) if self.show_metrics => metrics::extract(
+                &output.content,
+                &self.config.metrics,
+                self.config.metric_step.as_deref(),
+            ),
+            _ => Vec::new(),
+        };
+    }
+
+    /// The announcement, unless it was dismissed.
+    fn visible_announcement(&self) -> Option<&str> {
+        self.announcement
+            .as_deref()
+            .filter(|a| self.state.dismissed_announcement.as_deref() != Some(*a))
+    }
+
+    /// Opens the node view, with `selected` selected if given.
+    fn open_nodes(&mut self, selected: Option<&str>) {
+        self.node_lister.list(selected);
+        // the list that is already shown stays until the new one arrives
+        if !matches!(self.dialog, Some(Dialog::Nodes(..))) {
+            self.dialog = Some(Dialog::Nodes(None, ListState::default()));
+        }
+    }
+
+    /// Writes a setting to the config file and applies the updated config.
+    fn write_setting(&mut self, key: &str, value: toml_edit::Value) {
+        let Some(path) = self.config_path.clone().or_else(Config::default_path) else {
+            self.set_status_message(StatusMessage::Error(
+                "No config directory found".to_string(),
+            ));
+            return;
+        };
+        match Config::write_value(&path, key, value) {
+            Ok(()) => self.handle(AppMessage::Config(Config::load(Some(&path)).map(Box::new))),
+            Err(e) => self.set_status_message(StatusMessage::Error(format!(
+                "Failed to write {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    /// The finished jobs whose requests and usage are compared: those of the selected group or
+    /// array job, or else all shown finished jobs.
+    fn compared_jobs(&self) -> Vec<String> {
+        let jobs: Vec<&Job> = match self.selected_row() {
+            Some(JobListRow::Group(group)) => group.jobs.iter().map(|&i| &self.jobs[i]).collect(),
+            Some(JobListRow::Job(i)) if self.jobs[*i].array_step.is_some() => {
+                matrix::array_tasks(&self.all_jobs, &self.jobs[*i].array_id)
+            }
+            _ => self.jobs.iter().collect(),
+        };
+        jobs.into_iter()
+            .filter(|j| j.is_finished())
+            .map(|j| j.id())
+            .take(efficiency::MAX_JOBS)
+            .collect()
+    }
+
+    /// What the backfill watcher needs for my job if it waits for resources that are likely
+    /// held for the next pending job of its partition.
+    fn backfill_query(&self, job: &Job) -> Option<BackfillQuery> {
+        if job.state != "PENDING"
+            || job.user != remote::current_user()
+            || !matches!(job.reason.as_deref(), Some("Priority" | "Resources"))
+        {
+            return None;
+        }
+        let now = clock::now();
+        let window_end = self
+            .all_jobs
+            .iter()
+            .filter(|j| j.state == "PENDING" && j.partition == job.partition && j.id() != job.id())
+            .filter_map(|j| j.start_time)
+            .filter(|&t| t > now)
+            .min();
+        Some(BackfillQuery {
+            job_id: job.id(),
+            partition: job.partition.to_string(),
+            cpus: Tres::parse(&job.tres).cpus(),
+            time_limit: job.time_limit?,
+            window_end,
+        })
+    }
+
+    /// Restricts the view to the jobs in the file and watches it for more.
+    fn import_jobs_file(&mut self, path: PathBuf) {
+        match JobsFile::read(&path) {
+            Ok(jobs_file) => {
+                self.jobs_file_watcher = Some(JobsFileWatcherHandle::new(
+                    self.sender.clone(),
+                    jobs_file.path.clone(),
+                ));
+                self.set_jobs_file(Some(jobs_file));
+            }
+            Err(e) => self.set_status_message(StatusMessage::Error(format!(
+                "Failed to read {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    fn set_jobs_file(&mut self, jobs_file: Option<JobsFile>) {
+        // squeue only gets the ids if there are any, the view is restricted either way
+        self.squeue_args.set_job(
+            jobs_file
+                .as_ref()
+                .map(|f| f.ids())
+                .filter(|ids| !ids.is_empty()),
+        );
+        self.state.jobs_file = jobs_file;
+        self.save_state();
+        self.update_scope();
+        self.refresh_jobs();
+    }
+
+    /// Submits all steps of the configured pipeline and remembers their jobs.
+    fn submit_pipeline(&mut self, i: usize) {
+        let Some(pipeline) = self.config.pipelines.get(i).cloned() else {
+            return;
+        };
+        let name = pipeline.name.clone();
+        self.action_runner.run(
+            "submit",
+            &name,
+            self.hook_env("submit", &name),
+            &self.config.hooks,
+            None,
+            move || match pipeline.submit() {
+                Ok(run) => {
+                    let message = format!(
+                        "Submitted pipeline {} as jobs {}",
+                        run.name,
+                        run.steps
+                            .iter()
+                            .map(|s| s.job_id.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                    (Ok(message), Some(FollowUp::Pipeline(run)))
+                }
+                Err(e) => (Err(e), None),
+            },
+        );
+    }
+
+    /// Submits the failed tasks of an array job again with the original submit line.
+    fn resubmit_failed_tasks(&mut self, array_id: &str) {
+        let failed = triage::failed_tasks(&self.all_jobs, array_id);
+        let Some(first) = failed.first() else {
+            return;
+        };
+        let indices = failed
+            .iter()
+            .filter_map(|t| triage::task_index(t))
+            .collect::<Vec<_>>();
+        match first.work_dir.clone() {
+            Some(work_dir) => self.perform(Action::ResubmitTasks {
+                array_id: array_id.to_string(),
+                work_dir,
+                sacct: self.capabilities.sacct,
+                user: first.user.to_string(),
+                tasks: triage::compress_indices(&indices),
+            }),
+            None => self.set_status_message(StatusMessage::Error(format!(
+                "The submit line of job {} is not known",
+                array_id
+            ))),
+        }
+    }
+
+    /// Opens the notification settings with the given row selected.
+    fn open_notifications(&mut self, selected: usize) {
+        let mut state = ListState::default();
+        state.select(Some(selected));
+        self.dialog = Some(Dialog::Notifications(state));
+    }
+
+    fn save_state(&mut self) {
+        if let Err(e) = self.state.save() {
+            self.set_status_message(StatusMessage::Error(format!("Failed to save state: {}", e)));
+        }
+    }
+
+    fn set_status_message(&mut self, message: StatusMessage) {
+        self.status_message = Some((message, Instant::now()));
+    }
+
+    /// Hands the current squeue/sacct arguments to the job watcher, which re-polls immediately.
+    fn update_scope(&mut self) {
+        self.job_watcher
+            .set_args(self.squeue_args.to_vec(), self.squeue_args.to_sacct_vec());
+    }
+
+    /// Has the script columns and filter evaluated for all jobs in the background.
+    fn evaluate_scripts(&mut self) {
+        if self.config.script_columns.is_empty() && self.config.script_filter.is_none() {
+            self.script_values.clear();
+            return;
+        }
+        self.script_evaluator.evaluate(self.all_jobs.clone());
+    }
+
+    /// Applies the filters to all known jobs and maintains the selection.
+    fn refresh_jobs(&mut self) {
+        let mut jobs: Vec<Job> = self
+            .all_jobs
+            .iter()
+            .filter(|j| self.is_visible(j))
+            .cloned()
+            .collect();
+        if clock::is_frozen() {
+            // squeue orders by priority, which changes between polls
+            jobs.sort_by_key(|j| (j.job_id.parse::<u64>().unwrap_or(u64::MAX), j.id()));
+        }
+        self.update_jobs_and_selection(jobs);
+    }
+
+    /// Reports state changes of pinned jobs in the status bar and emails about my jobs, as far
+    /// as the notification rules allow.
+    fn notify(&mut self, transitions: &[Transition]) {
+        // archiving does not depend on the notification rules
+        if let Some(config) = &self.config.archive {
+            for t in transitions {
+                if t.job.user == remote::current_user() {
+                    self.archiver.archive(config, &t.job);
+                }
+            }
+        }
+        let now = clock::now();
+        let transitions = transitions
+            .iter()
+            .filter(|t| {
+                let notified = self.state.notifications.allows(t, now);
+                if self.notification_history.len() == HISTORY_LEN {
+                    self.notification_history.pop_front();
+                }
+                self.notification_history.push_back(HistoryEntry {
+                    time: now,
+                    job_id: t.job.id(),
+                    name: t.job.name.clone(),
+                    from: t.from.clone(),
+                    to: t.to.clone(),
+                    notified,
+                });
+                self.unseen_notifications += notified as usize;
+                notified
+            })
+            .collect::<Vec<_>>();
+        for t in &transitions {
+            let mine = t.job.user == remote::current_user();
+            let pinned = self.state.is_pinned(&t.job.id());
+            // the status bar is missed while the terminal is not in view
+            if let Some(config) = self.config.email.as_ref().filter(|_| mine || pinned) {
+                self.mailer.notify(config, &t.job, &t.from, pinned);
+            }
+            if mine && CRITICAL_STATES.contains(&t.to.as_str()) {
+                self.alerts.push_back(t.job.clone());
+            }
+        }
+        let pinned = transitions
+            .iter()
+            .filter(|t| self.state.is_pinned(&t.job.id()))
+            .map(|t| format!("Job {} ({}): {} → {}", t.job.id(), t.job.name, t.from, t.to))
+            .collect::<Vec<_>>();
+        if !pinned.is_empty() {
+            self.set_status_message(StatusMessage::Info(pinned.join(", ")));
+        }
+    }
+
+    fn is_visible(&self, job: &Job) -> bool {
+        if self.state.is_pinned(&job.id()) {
+            return true;
+        }
+        if job.is_finished() {
+            let retention =
+                chrono::Duration::from_std(self.config.finished_retention()).unwrap_or_default();
+            let now = clock::now();
+            if !self.show_finished || job.end_time.is_some_and(|t| t + retention < now) {
+                return false;
+            }
+        }
+        if self
+            .script_values
+            .get(&job.id())
+            .is_some_and(|(_, visible)| !visible)
+        {
+            return false;
+        }
+        if let Some(query) = &self.search {
+            if !job.matches(query) {
+                return false;
+            }
+        }
+        if let Some(jobs_file) = &self.state.jobs_file {
+            if !jobs_file.contains(job) {
+                return false;
+            }
+        }
+        match &self.tag_filter {
+            Some(tag) => self.state.has_tag(&job.id(), tag),
+            None => true,
+        }
+    }
+
+    fn update_jobs_and_selection(&mut self, new_jobs: Vec<Job>) {
+        // Update the jobs list
+        self.jobs = new_jobs;
+        self.refresh_rows();
+    }
+
+    /// Rebuilds the rows of the job list (groups) and maintains the selection.
+    fn refresh_rows(&mut self) {
+        let had_rows = !self.rows.is_empty();
+        self.rows = build_rows(
+            &self.jobs,
+            &self.config.groups,
+            &self.collapsed_groups,
+            self.squeue_args.team().is_some(),
+            |j| self.state.is_pinned(&j.id()),
+        );
+
+        if let Some(selected_row) = self.selected_row.clone() {
+            // Find the index of the currently selected row in the new job list
+            let new_index = self
+                .rows
+                .iter()
+                .position(|row| row.id(&self.jobs) == selected_row);
+
+            if let Some(index) = new_index {
+                // Update the job list state if the row is still present
+                self.job_list_state.select(Some(index));
+            } else if !self.rows.is_empty() {
+                // Reset selection if the row is no longer in the list
+                self.select_first_job();
             } else {
                 self.select_job(None);
             }
-        } else if self.jobs.is_empty() && !new_jobs.is_empty() {
+        } else if !had_rows && !self.rows.is_empty() {
             // If there were no jobs before and now there are, select the first one
             self.select_first_job();
         }
+    }
+
+    fn selected_row(&self) -> Option<&JobListRow> {
+        self.job_list_state
+            .selected()
+            .and_then(|i| self.rows.get(i))
+    }
+
+    /// The jobs in the rows around the selection, closest first.
+    fn nearby_jobs(&self) -> impl Iterator<Item = &Job> {
+        const NEARBY: usize = 10;
+        let selected = self.job_list_state.selected();
+        (1..=NEARBY)
+            .flat_map(move |d| {
+                [
+                    selected.and_then(|i| i.checked_sub(d)),
+                    selected.map(|i| i + d),
+                ]
+            })
+            .flatten()
+            .filter_map(|i| self.rows.get(i)?.job(&self.jobs))
+    }
+
+    /// The selected job, `None` if nothing or a group is selected.
+    fn selected_job(&self) -> Option<&Job> {
+        self.selected_row()?.job(&self.jobs)
+    }
+
+    /// Checks why the log cannot be opened, once per path, to show what was tried instead of
+    /// only the error.
+    fn diagnose_log(&mut self) {
+        let query = match (&self.job_output, self.job_output_watcher.file_path()) {
+            (Err(_), Some(path)) => {
+                let job = self.selected_job();
+                let pattern = match (self.selected_step(), job) {
+                    (None, Some(job)) => match self.output_file_view {
+                        OutputFileView::Stdout => job.stdout_pattern.clone(),
+                        OutputFileView::Stderr => job.stderr_pattern.clone(),
+                    },
+                    _ => None,
+                };
+                LogQuery {
+                    path: path.to_path_buf(),
+                    pattern,
+                    node_local: is_node_local(path, &self.config.node_local_prefixes),
+                    node: job.and_then(JobNode::of).map(|n| n.node),
+                }
+            }
+            _ => {
+                self.log_diagnosis = None;
+                return;
+            }
+        };
+        if self.log_diagnosis.as_ref().map(|(q, _)| q) != Some(&query) {
+            self.log_diagnostics.request(query.clone());
+            self.log_diagnosis = Some((query, None));
+        }
+    }
 
-        // Update the jobs list
-        self.jobs = new_jobs;
+    /// Switches the log to the new path when `squeue` reports another output file for the
+    /// selected job, e.g. after `scontrol update` or when it was requeued to other nodes and
+    /// the file name contains `%N`.
+    fn follow_moved_output(&mut self, jobs: &[Job]) {
+        if self.selected_step().is_some() {
+            return;
+        }
+        let Some(old) = self.selected_job() else {
+            return;
+        };
+        let Some(new) = jobs.iter().find(|j| j.id() == old.id()) else {
+            return;
+        };
+        let (from, to) = match self.output_file_view {
+            OutputFileView::Stdout => (&old.stdout, &new.stdout),
+            OutputFileView::Stderr => (&old.stderr, &new.stderr),
+        };
+        let (Some(from), Some(to)) = (from, to) else {
+            return;
+        };
+        if from == to {
+            return;
+        }
+        let message = format!("The output of job {} moved to {}", new.id(), to.display());
+        let output = match &self.job_output {
+            Ok(output) if !output.binary => output.content.clone(),
+            _ => String::new(),
+        };
+        self.job_output_watcher
+            .move_file(to.clone(), JobNode::of(new), output);
+        self.set_status_message(StatusMessage::Info(message));
+    }
+
+    /// The step of the selected job whose output the log shows, if one was picked with `.`.
+    fn selected_step(&self) -> Option<&StepOutput> {
+        let (job_id, step) = self.log_step.as_ref()?;
+        (self.selected_job()?.id() == *job_id).then_some(step)
     }
 
     fn select_job(&mut self, index: Option<usize>) {
         self.job_list_state.select(index);
-        self.selected_job_id = index.and_then(|i| self.jobs.get(i).map(|job| job.id()));
+        self.selected_row = index.and_then(|i| self.rows.get(i).map(|row| row.id(&self.jobs)));
     }
 
     fn select_next_job(&mut self) {
         if let Some(i) = self.job_list_state.selected() {
-            if i < self.jobs.len() - 1 {
+            if i + 1 < self.rows.len() {
                 self.select_job(Some(i + 1));
             }
-        } else if !self.jobs.is_empty() {
+        } else if !self.rows.is_empty() {
             self.select_job(Some(0));
         }
     }
@@ -310,43 +3434,111 @@ impl App {
             if i > 0 {
                 self.select_job(Some(i - 1));
             }
-        } else if !self.jobs.is_empty() {
-            self.select_job(Some(self.jobs.len() - 1));
+        } else if !self.rows.is_empty() {
+            self.select_job(Some(self.rows.len() - 1));
         }
     }
 
     fn select_first_job(&mut self) {
-        if !self.jobs.is_empty() {
+        if !self.rows.is_empty() {
             self.select_job(Some(0));
         }
     }
 
     fn select_last_job(&mut self) {
-        if !self.jobs.is_empty() {
-            self.select_job(Some(self.jobs.len() - 1));
+        if !self.rows.is_empty() {
+            self.select_job(Some(self.rows.len() - 1));
+        }
+    }
+
+    /// One line summary of the job list, e.g. `3 R / 12 PD | 96 CPUs, 8 GPUs allocated`.
+    fn summary(&self) -> Line<'static> {
+        let mut histories = vec![("mine", self.queue_history.mine().iter().copied().collect())];
+        if self.config.sparkline_all_jobs {
+            histories.push(("all", self.queue_history.all().iter().copied().collect()));
         }
+        summary_line(&SummaryView {
+            jobs: &self.jobs,
+            now: clock::now(),
+            budgets: &self.budgets,
+            histories,
+        })
     }
 
     fn ui(&mut self, f: &mut Frame) {
         // Layout
 
+        let announcement = self.visible_announcement().map(|a| {
+            let mut lines = a.lines().map(str::to_string).collect::<Vec<_>>();
+            if lines.len() > MAX_ANNOUNCEMENT_LINES {
+                lines.truncate(MAX_ANNOUNCEMENT_LINES);
+                lines[MAX_ANNOUNCEMENT_LINES - 1].push_str(" …");
+            }
+            lines
+        });
+        let heatmap = match &self.heatmap {
+            Some(Ok(nodes)) if self.show_heatmap => heatmap_lines(nodes, f.size().width),
+            Some(Err(e)) if self.show_heatmap => vec![Line::styled(
+                format!("Failed to list nodes: {}", e),
+                Style::default().fg(Color::Red),
+            )],
+            _ => Vec::new(),
+        };
         let content_help = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(3), Constraint::Length(1)].as_ref())
+            .constraints(
+                [
+                    Constraint::Length(announcement.as_ref().map_or(0, |a| a.len() as u16)),
+                    Constraint::Length(1),
+                    Constraint::Length(heatmap.len() as u16),
+                    Constraint::Min(3),
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
             .split(f.size());
+        let (announcement_area, summary_area, heatmap_area, content_help) = (
+            content_help[0],
+            content_help[1],
+            content_help[2],
+            &content_help[3..],
+        );
 
         let master_detail = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Min(50), Constraint::Percentage(70)].as_ref())
             .split(content_help[0]);
 
-        let job_detail_log = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Length(7), Constraint::Min(3)].as_ref())
-            .split(master_detail[1]);
+        // Announcement
+        if let Some(lines) = announcement {
+            let style = Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD);
+            let lines = lines
+                .into_iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    let mut spans = vec![Span::raw(line)];
+                    if i == 0 {
+                        spans.push(Span::styled(
+                            "  (x: dismiss)",
+                            Style::default().remove_modifier(Modifier::BOLD),
+                        ));
+                    }
+                    Line::from(spans)
+                })
+                .collect::<Vec<_>>();
+            f.render_widget(Paragraph::new(lines).style(style), announcement_area);
+        }
+
+        // Summary
+        f.render_widget(Paragraph::new(self.summary()), summary_area);
+        f.render_widget(Paragraph::new(heatmap), heatmap_area);
 
         // Help
-        let help_options = vec![
+        let help_options = [
             ("q", "quit"),
             ("⏶/⏷", "navigate"),
             ("pgup/pgdown", "scroll"),
@@ -355,6 +3547,41 @@ impl App {
             ("enter", "confirm"),
             ("c", "cancel job"),
             ("o", "toggle stdout/stderr"),
+            ("m/v", "toggle --me/team view"),
+            ("p/a/t", "partitions/accounts/states"),
+            ("A", "associations"),
+            ("r/e", "rename job/edit dependency"),
+            ("#/n", "tags/note"),
+            ("/", "search"),
+            ("f", "filter by tag"),
+            ("*", "pin job"),
+            ("@", "filter log by rank"),
+            (".", "output of step"),
+            ("F", "toggle finished"),
+            ("s", "settings"),
+            ("T", "timeline"),
+            ("U", "users"),
+            ("N/b", "nodes/of job"),
+            ("D", "node heatmap"),
+            ("L", "activity"),
+            ("P", "plugins"),
+            ("w", "message admins about stuck job"),
+            ("O", "open run link"),
+            ("M", "metrics"),
+            ("H/R", "processes/refresh"),
+            (":", "job commands"),
+            ("W", "tail in tmux pane"),
+            ("E/S", "export HTML/manifest"),
+            ("i", "anonymize"),
+            ("Z/I", "notification rules/history"),
+            ("X/V", "array failure triage/grid"),
+            ("C", "requested vs used"),
+            ("Q", "start in other queues"),
+            ("B", "pipelines"),
+            ("J", "jobs file"),
+            ("K", "search past logs"),
+            ("Y", "usage report"),
+            ("space", "collapse group"),
         ];
         let blue_style = Style::default().fg(Color::Blue);
         let light_blue_style = Style::default().fg(Color::LightBlue);
@@ -373,185 +3600,592 @@ impl App {
         ));
 
         let help = Paragraph::new(help);
-        f.render_widget(help, content_help[1]);
+        f.render_widget(help, content_help[2]);
+
+        // Status
+        let scope = self.squeue_args.to_vec();
+        let mut status = vec![
+            Span::styled("Scope: ", blue_style),
+            if scope.is_empty() {
+                Span::styled("all jobs", Style::default().add_modifier(Modifier::DIM))
+            } else {
+                Span::styled(scope.join(" "), light_blue_style)
+            },
+        ];
+        if !self.show_finished {
+            status.push(Span::styled(
+                " (finished hidden)",
+                Style::default().add_modifier(Modifier::DIM),
+            ));
+        }
+        let missing = self.capabilities.missing();
+        if !missing.is_empty() {
+            status.push(Span::styled(
+                format!(" (unavailable: {})", missing.join(", ")),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::DIM),
+            ));
+        }
+        if let Some(query) = &self.search {
+            status.push(Span::styled(" Search: ", blue_style));
+            status.push(Span::styled(query.as_str(), light_blue_style));
+        }
+        if let Some(tag) = &self.tag_filter {
+            status.push(Span::styled(" Tag: ", blue_style));
+            status.push(Span::styled(tag.as_str(), light_blue_style));
+        }
+        if let Some(jobs_file) = &self.state.jobs_file {
+            status.push(Span::styled(" Jobs file: ", blue_style));
+            status.push(Span::styled(
+                format!(
+                    "{} ({})",
+                    jobs_file
+                        .path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy(),
+                    jobs_file.labels.len()
+                ),
+                light_blue_style,
+            ));
+        }
+        if self.unseen_notifications > 0 {
+            status.push(Span::styled(
+                format!(
+                    " ({} new {}, I: history)",
+                    self.unseen_notifications,
+                    if self.unseen_notifications == 1 {
+                        "event"
+                    } else {
+                        "events"
+                    }
+                ),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        match &self.status_message {
+            Some((message, at)) if clock::elapsed(*at) < STATUS_MESSAGE_TIMEOUT => {
+                status.push(Span::raw(" | "));
+                status.push(match message {
+                    StatusMessage::Info(m) => {
+                        Span::styled(m.as_str(), Style::default().fg(Color::Green))
+                    }
+                    StatusMessage::Error(m) => {
+                        Span::styled(m.as_str(), Style::default().fg(Color::Red))
+                    }
+                });
+            }
+            _ => {}
+        }
+        if let Some((resubmission, at)) = &self.undo {
+            if let Some(left) = UNDO_WINDOW.checked_sub(clock::elapsed(*at)) {
+                status.push(Span::styled(
+                    format!(
+                        " | u: undo cancelling job {} ({}s)",
+                        resubmission.id,
+                        left.as_secs() + 1
+                    ),
+                    Style::default().fg(Color::Yellow),
+                ));
+            }
+        }
+        let status = Line::from(status);
+        f.render_widget(Paragraph::new(status), content_help[1]);
 
         // Jobs
-        let max_id_len = self.jobs.iter().map(|j| j.id().len()).max().unwrap_or(0);
-        let max_user_len = self.jobs.iter().map(|j| j.user.len()).max().unwrap_or(0);
-        let max_qos_len = self.jobs.iter().map(|j| j.qos.len()).max().unwrap_or(0);
-        let max_time_len = self.jobs.iter().map(|j| j.time.len()).max().unwrap_or(0);
-        let max_state_compact_len = self
-            .jobs
-            .iter()
-            .map(|j| j.state_compact.len())
-            .max()
-            .unwrap_or(0);
-        let jobs: Vec<ListItem> = self
-            .jobs
-            .iter()
-            .map(|j| {
-                ListItem::new(Line::from(vec![
-                    Span::styled(
-                        format!(
-                            "{:<max$.max$}",
-                            j.state_compact,
-                            max = max_state_compact_len
-                        ),
-                        Style::default(),
+        let since_poll = clock::elapsed(self.jobs_updated_at);
+        let job_list = JobListView {
+            columns: &self.config.columns,
+            jobs: self
+                .jobs
+                .iter()
+                .map(|j| JobRow {
+                    id: j.id(),
+                    state: j.state_compact.to_string(),
+                    qos: j.qos.to_string(),
+                    user: j.user.to_string(),
+                    time_used: j.time_used_display(since_poll),
+                    time_left: j.time_left(since_poll),
+                    preemption: self.preemptible.get(&j.id()).map(|p| p.outcome),
+                    alert: self.dead_dependencies.contains(&j.id())
+                        || self.flapping.contains(&j.id())
+                        || self.stuck.contains(&j.id()),
+                    script_values: self
+                        .script_values
+                        .get(&j.id())
+                        .map(|(values, _)| values.clone())
+                        .unwrap_or_default(),
+                    progress: self
+                        .progress
+                        .get(&j.id())
+                        .map(|p| progress_bar(p, j.time_used)),
+                    name: self.config.display_name(&j.name).into_owned(),
+                    tags: self
+                        .state
+                        .annotations(&j.id())
+                        .map(|a| a.tags.iter().cloned().collect())
+                        .unwrap_or_default(),
+                    pinned: self.state.is_pinned(&j.id()),
+                })
+                .collect(),
+            rows: &self.rows,
+            me: remote::current_user(),
+            team: self.squeue_args.team().is_some(),
+            user_colors: self.config.user_colors,
+            focused: self.dialog.is_none() && matches!(self.focus, Focus::Jobs),
+        };
+        render_job_list(
+            &job_list,
+            master_detail[0],
+            f.buffer_mut(),
+            &mut self.job_list_state,
+        );
+
+        // Job details
+        let job_detail = self.selected_job();
+        let mut fields = Vec::new();
+        if let Some(j) = job_detail {
+            let now = clock::now();
+            let size = self.job_output.as_ref().ok().and_then(|o| o.size);
+            fields = job_fields(j, now, &self.output_file_view, size);
+            if self.flapping.contains(&j.id()) {
+                fields[0].value.push(Span::styled(
+                    format!(
+                        ", {} in the last {} minutes",
+                        self.restarts
+                            .recent(&self.config.requeue_storm, &j.id(), now),
+                        self.config.requeue_storm.minutes
                     ),
-                    Span::raw(" "),
-                    Span::styled(
-                        format!("{:<max$.max$}", j.id(), max = max_id_len),
+                    Style::default().fg(Color::Red),
+                ));
+            }
+            fields.extend(events_field(&self.timelines.events(j, now), now));
+            match self.allocations.get(&j.job_id) {
+                Some(Some(Ok(nodes))) if !nodes.is_empty() => fields.push(Field::new(
+                    "Alloc",
+                    vec![Span::raw(
+                        nodes
+                            .iter()
+                            .map(NodeAllocation::summary)
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    )],
+                )),
+                Some(Some(Err(e))) => fields.push(Field::new(
+                    "Alloc",
+                    vec![Span::styled(e.clone(), Style::default().fg(Color::Red))],
+                )),
+                _ => {}
+            }
+            let loads = match j.state == "RUNNING" {
+                true => hostlist::expand(&j.nodelist)
+                    .into_iter()
+                    .filter_map(|node| Some((self.node_loads.get(&node)?, node)))
+                    .collect::<Vec<_>>(),
+                false => Vec::new(),
+            };
+            if !loads.is_empty() {
+                let mut value = Vec::new();
+                for (i, (load, node)) in loads.iter().enumerate() {
+                    if i > 0 {
+                        value.push(Span::raw("; "));
+                    }
+                    let mut text = format!(
+                        "{}: load {}",
+                        node,
+                        load.load.map_or("?".to_string(), |l| format!("{:.1}", l))
+                    );
+                    if let Some(cpus) = load.cpus {
+                        text += &format!("/{}", cpus);
+                    }
+                    match (load.free_bytes, load.total_bytes) {
+                        (Some(free), Some(total)) => {
+                            text += &format!(
+                                ", {} of {} free",
+                                format_bytes(free as f64),
+                                format_bytes(total as f64)
+                            )
+                        }
+                        (Some(free), None) => {
+                            text += &format!(", {} free", format_bytes(free as f64))
+                        }
+                        _ => {}
+                    }
+                    value.push(match load.overloaded() {
+                        true => Span::styled(text, Style::default().fg(Color::Red)),
+                        false => Span::raw(text),
+                    });
+                }
+                if node_load::imbalanced(&loads.iter().map(|(l, _)| *l).collect::<Vec<_>>()) {
+                    value.push(Span::styled(
+                        " (imbalanced)",
                         Style::default().fg(Color::Yellow),
-                    ),
-                    Span::raw(" "),
-                    Span::styled(
-                        format!("{:<max$.max$}", j.qos, max = max_qos_len),
-                        Style::default().fg(Color::Blue),
-                    ),
-                    Span::raw(" "),
-                    Span::styled(
-                        format!("{:<max$.max$}", j.user, max = max_user_len),
-                        Style::default().fg(Color::Green),
-                    ),
-                    Span::raw(" "),
-                    Span::styled(
-                        format!("{:>max$.max$}", j.time, max = max_time_len),
+                    ));
+                }
+                fields.push(Field::new("Load", value));
+            }
+            if let Some(p) = self.preemptible.get(&j.id()) {
+                let requeue = match p.requeue {
+                    Some(true) => ", --requeue set",
+                    Some(false) => ", --no-requeue set",
+                    None => "",
+                };
+                fields.push(Field::new(
+                    "Preempt",
+                    vec![Span::styled(
+                        format!(
+                            "{} when preempted by {}{}",
+                            p.outcome.label(),
+                            p.by,
+                            requeue
+                        ),
+                        Style::default().fg(Color::LightRed),
+                    )],
+                ));
+            }
+            if let Some(since) = self.completing.since(&j.id()) {
+                let stuck = self
+                    .completing
+                    .is_stuck(&self.config.completing, &j.id(), now);
+                fields.push(Field::new(
+                    "Completing",
+                    vec![Span::styled(
+                        format!(
+                            "since {} ({}){}",
+                            since.format("%H:%M"),
+                            format_duration(now - since),
+                            match stuck {
+                                true => ", stuck: likely a hung epilog or filesystem, w: message for the admins",
+                                false => "",
+                            }
+                        ),
+                        match stuck {
+                            true => Style::default().fg(Color::Red),
+                            false => Style::default(),
+                        },
+                    )],
+                ));
+            }
+            if let Some(since) = self.idle.get(&j.id()) {
+                fields.push(Field::new(
+                    "Idle",
+                    vec![Span::styled(
+                        format!(
+                            "no output and no CPU use since {} ({}), H: processes, c: cancel",
+                            since.format("%H:%M"),
+                            format_duration(now - *since)
+                        ),
                         Style::default().fg(Color::Red),
-                    ),
-                    Span::raw(" "),
-                    Span::raw(&j.name),
-                ]))
-            })
-            .collect();
-        let job_list = List::new(jobs)
-            .block(
-                Block::default()
-                    .title(format!("Jobs ({})", self.jobs.len()))
-                    .borders(Borders::ALL)
-                    .border_style(if self.dialog.is_some() {
+                    )],
+                ));
+            }
+            if let Some(ids) = self.duplicates.iter().find(|ids| ids.contains(&j.array_id)) {
+                let others = ids
+                    .iter()
+                    .filter(|id| **id != j.array_id)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                fields.push(Field::new(
+                    "Duplicate",
+                    vec![Span::styled(
+                        format!("same script and arguments as {}", others.join(", ")),
+                        Style::default().fg(Color::Red),
+                    )],
+                ));
+            }
+            if let Some(label) = self.state.jobs_file.as_ref().and_then(|f| f.label(j)) {
+                fields.push(Field::new("Label", vec![Span::raw(label)]));
+            }
+            if let Some(dependency) = &j.dependency {
+                let dead = self.dead_dependencies.contains(&j.id());
+                fields.push(Field::new(
+                    "Depends",
+                    vec![
+                        Span::raw(dependency.as_str()),
+                        if dead {
+                            Span::styled(
+                                " never satisfied, e: fix, c: cancel",
+                                Style::default().fg(Color::Red),
+                            )
+                        } else {
+                            Span::raw("")
+                        },
+                    ],
+                ));
+            }
+            if let Some(archived) = self.state.archived.iter().find(|a| a.job_id == j.id()) {
+                fields.push(Field::new(
+                    "Archived",
+                    vec![Span::raw(archived.dir.to_string_lossy())],
+                ));
+            }
+            if let Some(energy) = j.energy {
+                fields.push(Field::new(
+                    "Energy",
+                    vec![Span::raw(format!("{:.2} kWh", leaderboard::kwh(energy)))],
+                ));
+            }
+            if let Some((_, usage)) = self
+                .usage
+                .as_ref()
+                .filter(|(id, _)| j.state == "RUNNING" && *id == j.job_id)
+            {
+                fields.push(Field::new(
+                    "Usage",
+                    vec![match usage {
+                        Ok(usage) => Span::raw(usage.to_string()),
+                        Err(e) => Span::styled(e.as_str(), Style::default().fg(Color::Red)),
+                    }],
+                ));
+            }
+            if let Some(details) = self.details.get(&j.id()) {
+                if details.finished {
+                    let style = match details.exit_code.as_str() {
+                        "0:0" => Style::default(),
+                        _ => Style::default().fg(Color::Red),
+                    };
+                    fields.push(Field::new(
+                        "Exit",
+                        vec![Span::styled(format_exit_code(&details.exit_code), style)],
+                    ));
+                }
+                if !details.steps.is_empty() {
+                    fields.push(Field::new(
+                        "Steps",
+                        vec![Span::raw(
+                            details
+                                .steps
+                                .iter()
+                                .map(Step::summary)
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        )],
+                    ));
+                }
+            }
+            if let Some(Some(suggestion)) = self.suggestions.get(&suggest::name_pattern(&j.name)) {
+                fields.push(Field::new(
+                    "Suggest",
+                    vec![
+                        Span::raw(suggestion.options()),
+                        Span::styled(
+                            format!(
+                                " (95th percentile of {} similar completed jobs + 20%)",
+                                suggestion.samples
+                            ),
+                            Style::default().add_modifier(Modifier::DIM),
+                        ),
+                    ],
+                ));
+            }
+            if let Some((_, Some(hint))) = self
+                .backfill
+                .as_ref()
+                .filter(|(id, _)| j.state == "PENDING" && *id == j.id())
+            {
+                fields.push(Field::new("Backfill", vec![Span::raw(hint.as_str())]));
+            }
+            for link in &self.run_links {
+                let mut field = Field::new(
+                    "Run",
+                    vec![Span::styled(
+                        link.as_str(),
                         Style::default()
-                    } else {
-                        match self.focus {
-                            Focus::Jobs => Style::default().fg(Color::Green),
-                            _ => Style::default(),
-                        }
-                    }),
+                            .fg(Color::Blue)
+                            .add_modifier(Modifier::UNDERLINED),
+                    )],
+                );
+                field.link = Some(link.clone());
+                fields.push(field);
+            }
+            for output in &self.plugin_outputs {
+                if let Some(annotation) = output.annotations.get(&j.id()) {
+                    fields.push(Field::new(
+                        output.name.as_str(),
+                        vec![Span::raw(annotation.as_str())],
+                    ));
+                }
+            }
+            if let Some(a) = self.state.annotations(&j.id()) {
+                if !a.tags.is_empty() {
+                    fields.push(Field::new(
+                        "Tags",
+                        vec![Span::styled(
+                            a.tags.iter().cloned().collect::<Vec<_>>().join(", "),
+                            Style::default().fg(Color::Cyan),
+                        )],
+                    ));
+                }
+                if !a.note.is_empty() {
+                    fields.push(Field::new("Note", vec![Span::raw(a.note.as_str())]));
+                }
+            }
+        }
+        let job_detail_log = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(details_height(&fields)),
+                    Constraint::Min(3),
+                ]
+                .as_ref(),
+            )
+            .split(master_detail[1]);
+        self.hyperlinks = render_details(&fields, job_detail_log[0], f.buffer_mut());
+
+        // Log
+        let panes = [self.show_metrics, self.processes.is_some()];
+        let pane_count = panes.iter().filter(|&&p| p).count() as u16;
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                std::iter::once(Constraint::Min(3))
+                    .chain((0..pane_count).map(|_| Constraint::Percentage(50 / pane_count.max(1))))
+                    .collect::<Vec<_>>(),
             )
-            .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
-        f.render_stateful_widget(job_list, master_detail[0], &mut self.job_list_state);
+            .split(job_detail_log[1]);
+        let log_area = areas[0];
+        let mut pane_areas = areas.iter().skip(1).copied();
+        let metrics_area = panes[0].then(|| pane_areas.next()).flatten();
+        let processes_area = panes[1].then(|| pane_areas.next()).flatten();
+        let log = LogView {
+            stream: &self.output_file_view,
+            anchor: self.job_output_anchor,
+            offset: self.job_output_offset as usize,
+            output: self.job_output.as_ref().map_err(|e| e.to_string()),
+            focused: self.dialog.is_none() && matches!(self.focus, Focus::Stdout),
+            rank: self.log_rank,
+            step: self.selected_step().map(|s| s.id.as_str()),
+            diagnosis: self
+                .log_diagnosis
+                .as_ref()
+                .and_then(|(_, diagnosis)| diagnosis.as_ref()),
+        };
+        render_log(&log, log_area, f.buffer_mut());
 
-        // Job details
-
-        let job_detail = self
-            .job_list_state
-            .selected()
-            .and_then(|i| self.jobs.get(i));
-
-        let job_detail = job_detail.map(|j| {
-            let state = Line::from(vec![
-                Span::styled("State    ", Style::default().fg(Color::Yellow)),
-                Span::raw(" "),
-                Span::raw(&j.state),
-                if let Some(s) = j.reason.as_deref() {
-                    Span::styled(
-                        format!(" ({s})"),
-                        Style::default().add_modifier(Modifier::DIM),
-                    )
+        // Metrics
+        if let Some(area) = metrics_area {
+            if self.metrics.is_empty() {
+                let message = if self.config.metrics.is_empty() {
+                    "No metrics configured".to_string()
                 } else {
-                    Span::raw("")
-                },
-            ]);
-
-            let command = Line::from(vec![
-                Span::styled("Command  ", Style::default().fg(Color::Yellow)),
-                Span::raw(" "),
-                Span::raw(&j.command),
-            ]);
-            let nodes = Line::from(vec![
-                Span::styled("Nodes    ", Style::default().fg(Color::Yellow)),
-                Span::raw(" "),
-                Span::raw(&j.nodelist),
-            ]);
-            let tres = Line::from(vec![
-                Span::styled("TRES     ", Style::default().fg(Color::Yellow)),
-                Span::raw(" "),
-                Span::raw(&j.tres),
-            ]);
-            let partition = Line::from(vec![
-                Span::styled("Partition", Style::default().fg(Color::Yellow)),
-                Span::raw(" "),
-                Span::raw(&j.partition),
-            ]);
-            let ui_stdout_text = match self.output_file_view {
-                OutputFileView::Stdout => "stdout   ",
-                OutputFileView::Stderr => "stderr   ",
-            };
-            let stdout = Line::from(vec![
-                Span::styled(ui_stdout_text, Style::default().fg(Color::Yellow)),
-                Span::raw(" "),
-                Span::raw(
-                    match self.output_file_view {
-                        OutputFileView::Stdout => &j.stdout,
-                        OutputFileView::Stderr => &j.stderr,
-                    }
-                    .as_ref()
-                    .map(|p| p.to_str().unwrap_or_default())
-                    .unwrap_or_default(),
-                ),
-            ]);
-
-            Text::from(vec![state, command, nodes, tres, partition, stdout])
-        });
-        let job_detail = Paragraph::new(job_detail.unwrap_or_default())
-            .block(Block::default().title("Details").borders(Borders::ALL));
-        f.render_widget(job_detail, job_detail_log[0]);
+                    format!("No {} found in the log", self.config.metrics.join(", "))
+                };
+                f.render_widget(
+                    Paragraph::new(message)
+                        .block(Block::default().title("Metrics").borders(Borders::ALL)),
+                    area,
+                );
+            }
+            let areas = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(
+                    self.metrics
+                        .iter()
+                        .map(|_| Constraint::Ratio(1, self.metrics.len() as u32))
+                        .collect::<Vec<_>>(),
+                )
+                .split(area);
+            let colors = [Color::Cyan, Color::Magenta, Color::Yellow, Color::Green];
+            for ((series, area), color) in self
+                .metrics
+                .iter()
+                .zip(areas.iter())
+                .zip(colors.iter().cycle())
+            {
+                let bounds = |values: &mut dyn Iterator<Item = f64>| {
+                    values.fold([f64::INFINITY, f64::NEG_INFINITY], |[lo, hi], v| {
+                        [lo.min(v), hi.max(v)]
+                    })
+                };
+                let [x_min, x_max] = bounds(&mut series.points.iter().map(|p| p.0));
+                let [y_min, y_max] = bounds(&mut series.points.iter().map(|p| p.1));
+                // at most 4 decimals, without trailing zeros
+                let label = |v: f64| {
+                    let s = format!("{:.4}", v);
+                    s.trim_end_matches('0').trim_end_matches('.').to_string()
+                };
+                let last = series.points.last().map_or(0.0, |p| p.1);
+                let chart = Chart::new(vec![Dataset::default()
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(*color))
+                    .data(&series.points)])
+                .block(
+                    Block::default()
+                        .title(format!("{} (last {})", series.name, label(last)))
+                        .borders(Borders::ALL),
+                )
+                .x_axis(
+                    Axis::default()
+                        .bounds([x_min, x_max.max(x_min + 1.0)])
+                        .labels(vec![label(x_min).into(), label(x_max).into()]),
+                )
+                .y_axis(
+                    Axis::default()
+                        .bounds([y_min, y_max.max(y_min + f64::EPSILON)])
+                        .labels(vec![label(y_min).into(), label(y_max).into()]),
+                );
+                f.render_widget(chart, *area);
+            }
+        }
 
-        // Log
-        let log_area = job_detail_log[1];
-        let log_title = Line::from(vec![
-            Span::raw(match self.output_file_view {
-                OutputFileView::Stdout => "stdout",
-                OutputFileView::Stderr => "stderr",
-            }),
-            Span::styled(
-                match self.job_output_anchor {
-                    ScrollAnchor::Top if self.job_output_offset == 0 => "[T]".to_string(),
-                    ScrollAnchor::Top => format!("[T+{}]", self.job_output_offset),
-                    ScrollAnchor::Bottom if self.job_output_offset == 0 => "".to_string(),
-                    ScrollAnchor::Bottom => format!("[B-{}]", self.job_output_offset),
-                },
-                Style::default().add_modifier(Modifier::DIM),
-            ),
-        ]);
-        let log_block = Block::default()
-            .title(log_title)
-            .borders(Borders::ALL)
-            .border_style(if self.dialog.is_some() {
-                Style::default()
-            } else {
-                match self.focus {
-                    Focus::Stdout => Style::default().fg(Color::Green),
-                    _ => Style::default(),
+        // Processes
+        if let (Some(area), Some((job_id, processes))) = (processes_area, &self.processes) {
+            let block = Block::default()
+                .title(format!("Processes of job {} (R: refresh)", job_id))
+                .borders(Borders::ALL);
+            let paragraph = match processes {
+                None => Paragraph::new("Listing processes...")
+                    .style(Style::default().add_modifier(Modifier::DIM)),
+                Some(Err(e)) => Paragraph::new(e.as_str())
+                    .style(Style::default().fg(Color::Red))
+                    .wrap(Wrap { trim: true }),
+                Some(Ok(processes)) => {
+                    let node_width = processes.iter().map(|p| p.node.len()).max().unwrap_or(0);
+                    let header = Line::styled(
+                        format!(
+                            "{:<nw$} {:>8} {:>6} {:>6} {:>9} {:>11} COMMAND",
+                            "NODE",
+                            "PID",
+                            "%CPU",
+                            "%MEM",
+                            "RSS",
+                            "ELAPSED",
+                            nw = node_width
+                        ),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    );
+                    let lines = processes.iter().map(|p| {
+                        Line::styled(
+                            format!(
+                                "{:<nw$} {:>8} {:>6.1} {:>6.1} {:>5} MiB {:>11} {}",
+                                p.node,
+                                p.pid,
+                                p.cpu,
+                                p.mem,
+                                p.rss_kib / 1024,
+                                p.elapsed,
+                                p.command,
+                                nw = node_width
+                            ),
+                            // mostly idle processes stand out less
+                            if p.cpu < 1.0 {
+                                Style::default().add_modifier(Modifier::DIM)
+                            } else {
+                                Style::default()
+                            },
+                        )
+                    });
+                    Paragraph::new(std::iter::once(header).chain(lines).collect::<Vec<_>>())
                 }
-            });
-
-        let log = match self.job_output.as_deref() {
-            Ok(s) => Paragraph::new(string_for_paragraph(
-                s,
-                log_block.inner(log_area).height as usize,
-                self.job_output_anchor,
-                self.job_output_offset as usize,
-            )),
-            Err(e) => Paragraph::new(e.to_string())
-                .style(Style::default().fg(Color::Red))
-                .wrap(Wrap { trim: true }),
+            };
+            f.render_widget(paragraph.block(block), area);
         }
-        .block(log_block);
 
-        f.render_widget(log, log_area);
-
-        if let Some(dialog) = &self.dialog {
+        let selected_id = self.selected_job().map(|j| j.id());
+        if let Some(dialog) = &mut self.dialog {
             fn centered_lines(percent_x: u16, lines: u16, r: Rect) -> Rect {
                 let dy = r.height.saturating_sub(lines) / 2;
                 let r = Rect::new(r.x, r.y + dy, r.width, min(lines, r.height - dy));
@@ -570,17 +4204,1406 @@ impl App {
             }
 
             match dialog {
-                Dialog::ConfirmCancelJob(id) => {
+                Dialog::Confirm(action) => {
+                    let dialog = Paragraph::new(action.question())
+                        .style(Style::default().fg(Color::White))
+                        .wrap(Wrap { trim: true })
+                        .block(
+                            Block::default()
+                                .title("Confirm")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        );
+
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::ConfirmTyped(action, value) => {
+                    let dialog = Paragraph::new(Line::from(vec![
+                        Span::raw(value.as_str()),
+                        Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+                    ]))
+                    .style(Style::default().fg(Color::White))
+                    .block(
+                        Block::default()
+                            .title(format!(
+                                "{} Type {} to confirm",
+                                action.question(),
+                                action.confirmation_text().unwrap_or_default()
+                            ))
+                            .borders(Borders::ALL)
+                            .style(Style::default().fg(Color::Red)),
+                    );
+
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Associations(None, _) => {
+                    let dialog = Paragraph::new("Asking sacctmgr...")
+                        .block(
+                            Block::default()
+                                .title("Associations")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White));
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Associations(Some(Err(e)), _) => {
+                    let dialog = Paragraph::new(e.to_string())
+                        .style(Style::default().fg(Color::Red))
+                        .wrap(Wrap { trim: true })
+                        .block(
+                            Block::default()
+                                .title("Associations")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        );
+
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Associations(Some(Ok(associations)), state) => {
+                    let header = [
+                        "Cluster",
+                        "Account",
+                        "Partition",
+                        "QOS",
+                        "GrpTRES",
+                        "MaxTRES",
+                    ];
+                    let rows = associations
+                        .iter()
+                        .map(|a| {
+                            let qos = if a.default_qos.is_empty() {
+                                a.qos.clone()
+                            } else {
+                                format!("{} (default {})", a.qos, a.default_qos)
+                            };
+                            [
+                                a.cluster.clone(),
+                                a.account.clone(),
+                                a.partition.clone(),
+                                qos,
+                                a.grp_tres.clone(),
+                                a.max_tres.clone(),
+                            ]
+                        })
+                        .collect::<Vec<_>>();
+                    let widths = header.map(|h| h.len()).to_vec();
+                    let widths = rows.iter().fold(widths, |mut widths, row| {
+                        for (w, c) in widths.iter_mut().zip(row.iter()) {
+                            *w = (*w).max(c.len());
+                        }
+                        widths
+                    });
+                    let format_row = |row: &[String]| {
+                        row.iter()
+                            .zip(widths.iter())
+                            .map(|(c, w)| format!("{:<w$}", c, w = w))
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    };
+                    let items = rows
+                        .iter()
+                        .map(|row| ListItem::new(format_row(row)))
+                        .collect::<Vec<_>>();
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(format_row(&header.map(String::from)))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+
+                    let area = centered_lines(90, rows.len() as u16 + 2, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::Settings(state) => {
+                    let settings = Setting::all();
+                    let labels = settings.iter().map(|s| s.label()).collect::<Vec<_>>();
+                    let width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+                    let items = settings
+                        .iter()
+                        .zip(labels.iter())
+                        .map(|(s, label)| {
+                            ListItem::new(Line::from(vec![
+                                Span::raw(format!("{:<w$}  ", label, w = width)),
+                                Span::styled(
+                                    s.value(&self.config),
+                                    Style::default().add_modifier(Modifier::BOLD),
+                                ),
+                            ]))
+                        })
+                        .collect::<Vec<_>>();
+                    let title = match self.config_path.clone().or_else(Config::default_path) {
+                        Some(path) => format!("Settings ({})", path.display()),
+                        None => "Settings".to_string(),
+                    };
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(title)
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+
+                    let area = centered_lines(75, settings.len() as u16 + 2, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::Users(state) => {
+                    let usage = leaderboard::by_user(&self.all_jobs);
+                    let user_width = usage.iter().map(|u| u.user.len()).max().unwrap_or(0).max(5);
+                    // energy of finished jobs, on clusters with energy accounting
+                    let total_energy = usage.iter().map(|u| u.energy).sum::<u64>();
+                    let energy = |joules: u64| {
+                        if total_energy > 0 {
+                            format!("  {:>8.2}", leaderboard::kwh(joules))
+                        } else {
+                            String::new()
+                        }
+                    };
+                    let header = format!(
+                        "{:<w$}  {:>7}  {:>7}  {:>6}  {:>6}{}",
+                        "User",
+                        "Running",
+                        "Pending",
+                        "CPUs",
+                        "GPUs",
+                        if total_energy > 0 { "  kWh done" } else { "" },
+                        w = user_width
+                    );
+                    let mut items = usage
+                        .iter()
+                        .map(|u| {
+                            ListItem::new(format!(
+                                "{:<w$}  {:>7}  {:>7}  {:>6}  {:>6}{}",
+                                u.user,
+                                u.running,
+                                u.pending,
+                                u.cpus,
+                                u.gpus,
+                                energy(u.energy),
+                                w = user_width
+                            ))
+                        })
+                        .collect::<Vec<_>>();
+                    if total_energy > 0 {
+                        items.push(
+                            ListItem::new(format!(
+                                "{:<w$}  {:>7}  {:>7}  {:>6}  {:>6}{}",
+                                "total",
+                                "",
+                                "",
+                                "",
+                                "",
+                                energy(total_energy),
+                                w = user_width
+                            ))
+                            .style(Style::default().add_modifier(Modifier::BOLD)),
+                        );
+                    }
+                    let height = items.len().max(1) as u16 + 2;
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(Span::styled(
+                                    header,
+                                    Style::default().add_modifier(Modifier::BOLD),
+                                ))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(60, height, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::JobNodes(id, nodes, state) => {
+                    let allocation = self
+                        .jobs
+                        .iter()
+                        .find(|j| j.id() == *id)
+                        .and_then(|j| self.allocations.get(&j.job_id))
+                        .and_then(|a| a.as_ref()?.as_ref().ok());
+                    let items = nodes
+                        .iter()
+                        .map(|node| {
+                            ListItem::new(
+                                allocation
+                                    .and_then(|a| a.iter().find(|a| a.node == *node))
+                                    .map_or(node.clone(), NodeAllocation::summary),
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    let height = items.len() as u16 + 2;
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(format!(
+                                    "Nodes of job {} (enter: ssh, i: details, p: ping)",
+                                    id
+                                ))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(70, height, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::Escalation(message, offset) => {
+                    let area = centered_lines(
+                        80,
+                        (message.lines().count() as u16 + 4).min(f.size().height.saturating_sub(4)),
+                        f.size(),
+                    );
+                    let dialog = Paragraph::new(message.as_str())
+                        .wrap(Wrap { trim: false })
+                        .scroll((*offset, 0))
+                        .block(
+                            Block::default()
+                                .title("Message for the admins (select to copy, esc: close)")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White));
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Plugins(offset) => {
+                    let mut lines = Vec::new();
+                    for output in &self.plugin_outputs {
+                        let title = output
+                            .panel
+                            .as_ref()
+                            .map(|p| p.title.as_str())
+                            .filter(|t| !t.is_empty())
+                            .unwrap_or(output.name.as_str());
+                        lines.push(Line::from(Span::styled(
+                            title.to_string(),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        )));
+                        if let Some(error) = &output.error {
+                            lines.push(Line::from(Span::styled(
+                                format!("{} failed: {}", output.name, error),
+                                Style::default().fg(Color::Red),
+                            )));
+                        }
+                        if let Some(panel) = &output.panel {
+                            lines.extend(panel.lines.iter().map(|l| Line::from(l.clone())));
+                        }
+                        lines.push(Line::default());
+                    }
+                    if lines.is_empty() {
+                        lines.push(Line::from(if self.config.plugins.is_empty() {
+                            "No plugins configured"
+                        } else {
+                            "Waiting for the plugins to answer"
+                        }));
+                    }
+                    let area = centered_lines(
+                        90,
+                        (lines.len() as u16 + 2).min(f.size().height.saturating_sub(4)),
+                        f.size(),
+                    );
+                    *offset = (*offset).min(lines.len().saturating_sub(1) as u16);
+                    let dialog = Paragraph::new(lines)
+                        .scroll((*offset, 0))
+                        .block(
+                            Block::default()
+                                .title("Plugins")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White));
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Commands(state) => {
+                    let items = self
+                        .config
+                        .job_commands
+                        .iter()
+                        .map(|c| ListItem::new(c.name.as_str()))
+                        .collect::<Vec<_>>();
+                    let title = match &selected_id {
+                        _ if items.is_empty() => {
+                            "Commands (none configured, see `job_commands`)".to_string()
+                        }
+                        Some(id) => format!("Commands for job {}", id),
+                        None => "Commands (no job selected)".to_string(),
+                    };
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(title)
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(
+                        50,
+                        self.config.job_commands.len().max(1) as u16 + 2,
+                        f.size(),
+                    );
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::Notifications(state) => {
+                    let now = clock::now();
+                    let rules = &self.state.notifications.rules;
+                    let targets = rules
+                        .iter()
+                        .map(|r| r.target.to_string())
+                        .collect::<Vec<_>>();
+                    let width = targets
+                        .iter()
+                        .map(|t| t.chars().count())
+                        .max()
+                        .unwrap_or(0)
+                        .max("Quiet hours".len());
+                    let bold = Style::default().add_modifier(Modifier::BOLD);
+                    let quiet_hours = match self.state.notifications.quiet_hours {
+                        Some(q) => q.to_string(),
+                        None => "none".to_string(),
+                    };
+                    let mut items = vec![ListItem::new(Line::from(vec![
+                        Span::raw(format!("{:<w$}  ", "Quiet hours", w = width)),
+                        Span::styled(quiet_hours, bold),
+                    ]))];
+                    items.extend(rules.iter().zip(targets).map(|(rule, target)| {
+                        let states = if rule.states.is_empty() {
+                            "all states".to_string()
+                        } else {
+                            rule.states.join(", ")
+                        };
+                        let mut spans = vec![
+                            Span::raw(format!("{:<w$}  ", target, w = width)),
+                            Span::styled(states, bold),
+                        ];
+                        if let Some(until) = rule.snoozed_until.filter(|_| rule.is_snoozed(now)) {
+                            spans.push(Span::styled(
+                                format!("  snoozed until {}", until.format("%a %H:%M")),
+                                Style::default().fg(Color::Yellow),
+                            ));
+                        }
+                        ListItem::new(Line::from(spans))
+                    }));
+                    let height = items.len() as u16 + 2;
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title("Notifications (a: rule for job, f: rule for filter, z: snooze, d: delete)")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(80, height, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::History(state) => {
+                    let today = clock::now().date();
+                    let id_width = self
+                        .notification_history
+                        .iter()
+                        .map(|e| e.job_id.len())
+                        .max()
+                        .unwrap_or(0);
+                    let items = self
+                        .notification_history
+                        .iter()
+                        .rev()
+                        .map(|e| {
+                            let time = if e.time.date() == today {
+                                e.time.format("%H:%M:%S")
+                            } else {
+                                e.time.format("%m-%d %H:%M")
+                            };
+                            let line = format!(
+                                "{:>11}  {:<w$}  {} → {}  {}",
+                                time.to_string(),
+                                e.job_id,
+                                e.from,
+                                e.to,
+                                e.name,
+                                w = id_width
+                            );
+                            let style = if e.notified {
+                                Style::default()
+                            } else {
+                                Style::default().add_modifier(Modifier::DIM)
+                            };
+                            ListItem::new(line).style(style)
+                        })
+                        .collect::<Vec<_>>();
+                    let title = if items.is_empty() {
+                        "Notification history (no state changes yet)"
+                    } else {
+                        "Notification history (newest first, dimmed: suppressed by the rules)"
+                    };
+                    let height =
+                        (items.len().max(1) as u16 + 2).min(f.size().height.saturating_sub(4));
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(title)
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(80, height, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::Triage(array_id, None, _) => {
+                    let dialog = Paragraph::new("Reading the stderr of the failed tasks...")
+                        .block(
+                            Block::default()
+                                .title(format!("Failures of array job {}", array_id))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White));
+                    let area = centered_lines(90, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Triage(array_id, Some(triage), state) => {
+                    let count_width = triage
+                        .buckets
+                        .iter()
+                        .map(|b| b.tasks.len().to_string().len())
+                        .max()
+                        .unwrap_or(0);
+                    let items = triage
+                        .buckets
+                        .iter()
+                        .map(|b| {
+                            let tasks = if b.tasks.len() == 1 { "task " } else { "tasks" };
+                            ListItem::new(Line::from(vec![
+                                Span::styled(
+                                    format!("{:>w$} {}  ", b.tasks.len(), tasks, w = count_width),
+                                    Style::default().add_modifier(Modifier::BOLD),
+                                ),
+                                Span::raw(b.error.as_str()),
+                                Span::styled(
+                                    format!("  [{}]", triage::compress_indices(&b.indices)),
+                                    Style::default().add_modifier(Modifier::DIM),
+                                ),
+                            ]))
+                        })
+                        .collect::<Vec<_>>();
+                    let sampled = if triage.sampled < triage.failed {
+                        format!(", sampled {} of {}", triage.sampled, triage.failed)
+                    } else {
+                        String::new()
+                    };
+                    let title = format!(
+                        "Failures of array job {} ({} failed tasks{}, enter: show stderr, s: resubmit failed)",
+                        array_id, triage.failed, sampled
+                    );
+                    let height = (items.len() as u16 + 2).min(f.size().height.saturating_sub(4));
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(title)
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(90, height, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::Efficiency(None, _) => {
+                    let dialog = Paragraph::new("Asking sacct...")
+                        .block(
+                            Block::default()
+                                .title("Requested vs used")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White));
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Efficiency(Some(Err(e)), _) => {
+                    let dialog = Paragraph::new(e.as_str())
+                        .style(Style::default().fg(Color::Red))
+                        .wrap(Wrap { trim: true })
+                        .block(
+                            Block::default()
+                                .title("Requested vs used")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        );
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Efficiency(Some(Ok(jobs)), state) => {
+                    let id_width = jobs.iter().map(|j| j.id.len()).max().unwrap_or(0).max(3);
+                    let name_width = jobs
+                        .iter()
+                        .map(|j| j.name.chars().count())
+                        .max()
+                        .unwrap_or(0)
+                        .clamp(4, 24);
+                    let percent = |f: Option<f64>| match f {
+                        Some(f) => format!("{:.0}%", f * 100.0),
+                        None => "-".to_string(),
+                    };
+                    // less than half of the request was used
+                    let wasted = |f: Option<f64>| {
+                        if f.is_some_and(|f| f < 0.5) {
+                            Style::default().fg(Color::Yellow)
+                        } else {
+                            Style::default()
+                        }
+                    };
+                    let bytes = |b: Option<u64>| b.map_or("-".to_string(), efficiency::format_size);
+                    let header = format!(
+                        "{:<iw$}  {:<nw$}  {:>9}  {:>9}  {:>4}  {:>4}  {:>4}  {:>4}",
+                        "Job",
+                        "Name",
+                        "Mem req",
+                        "Max RSS",
+                        "Mem",
+                        "CPUs",
+                        "CPU",
+                        "Time",
+                        iw = id_width,
+                        nw = name_width
+                    );
+                    let mut items = jobs
+                        .iter()
+                        .map(|j| {
+                            ListItem::new(Line::from(vec![
+                                Span::raw(format!(
+                                    "{:<iw$}  {:<nw$.nw$}  {:>9}  {:>9}  ",
+                                    j.id,
+                                    j.name,
+                                    bytes(j.req_mem),
+                                    bytes(j.max_rss),
+                                    iw = id_width,
+                                    nw = name_width
+                                )),
+                                Span::styled(
+                                    format!("{:>4}", percent(j.mem_used())),
+                                    wasted(j.mem_used()),
+                                ),
+                                Span::raw(format!("  {:>4}  ", j.cpus)),
+                                Span::styled(
+                                    format!("{:>4}", percent(j.cpu_used())),
+                                    wasted(j.cpu_used()),
+                                ),
+                                Span::raw("  "),
+                                Span::styled(
+                                    format!("{:>4}", percent(j.time_used())),
+                                    wasted(j.time_used()),
+                                ),
+                            ]))
+                        })
+                        .collect::<Vec<_>>();
+                    let medians = [
+                        (
+                            "memory",
+                            efficiency::median(jobs.iter().map(|j| j.mem_used())),
+                        ),
+                        ("CPU", efficiency::median(jobs.iter().map(|j| j.cpu_used()))),
+                        (
+                            "time",
+                            efficiency::median(jobs.iter().map(|j| j.time_used())),
+                        ),
+                    ];
+                    let over = medians
+                        .iter()
+                        .filter(|(_, m)| m.is_some_and(|m| m < 0.5))
+                        .map(|(label, _)| *label)
+                        .collect::<Vec<_>>();
+                    let mut summary = format!(
+                        "Median use of the request: {}",
+                        medians
+                            .iter()
+                            .map(|(label, m)| format!("{} {}", label, percent(*m)))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                    if !over.is_empty() {
+                        summary.push_str(&format!(
+                            " ({} systematically over-requested)",
+                            over.join(", ")
+                        ));
+                    }
+                    items.push(
+                        ListItem::new(summary).style(Style::default().add_modifier(Modifier::BOLD)),
+                    );
+                    let height = (items.len() as u16 + 2).min(f.size().height.saturating_sub(4));
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(Span::styled(
+                                    header,
+                                    Style::default().add_modifier(Modifier::BOLD),
+                                ))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(90, height, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::Pipelines(state) => {
+                    let now = clock::now();
+                    let mut items = self
+                        .config
+                        .pipelines
+                        .iter()
+                        .map(|p| {
+                            let steps = p
+                                .steps
+                                .iter()
+                                .map(|s| match s.after.is_empty() {
+                                    true => s.name.clone(),
+                                    false => format!(
+                                        "{} ({} {})",
+                                        s.name,
+                                        s.dependency,
+                                        s.after.join(", ")
+                                    ),
+                                })
+                                .collect::<Vec<_>>()
+                                .join(", ");
+                            ListItem::new(Line::from(vec![
+                                Span::styled(
+                                    format!("{}  ", p.name),
+                                    Style::default().add_modifier(Modifier::BOLD),
+                                ),
+                                Span::raw(steps),
+                            ]))
+                        })
+                        .collect::<Vec<_>>();
+                    for run in &self.state.pipeline_runs {
+                        let mut spans = vec![
+                            Span::raw(format!("  {} ", run.name)),
+                            Span::styled(
+                                format!("({})  ", format_relative(run.submitted, now)),
+                                Style::default().add_modifier(Modifier::DIM),
+                            ),
+                        ];
+                        for (i, step) in run.steps.iter().enumerate() {
+                            if i > 0 {
+                                spans.push(Span::raw(if step.after.is_empty() {
+                                    "  "
+                                } else {
+                                    " → "
+                                }));
+                            }
+                            // steps that finished before the retention are not known anymore
+                            match self.all_jobs.iter().find(|j| j.id() == step.job_id) {
+                                Some(job) => spans.push(Span::styled(
+                                    format!(
+                                        "{} {}",
+                                        step.name,
+                                        job.state.split_whitespace().next().unwrap_or_default()
+                                    ),
+                                    Style::default().fg(matrix::task_color(job)),
+                                )),
+                                None => spans.push(Span::styled(
+                                    format!("{} {}", step.name, step.job_id),
+                                    Style::default().add_modifier(Modifier::DIM),
+                                )),
+                            }
+                        }
+                        items.push(ListItem::new(Line::from(spans)));
+                    }
+                    let height = (items.len() as u16 + 2).min(f.size().height.saturating_sub(4));
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title("Pipelines (enter: submit, or show the current step of a submitted one; d: forget)")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(90, height, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::LogSearch(query, None, _) => {
+                    let dialog = Paragraph::new("Searching the logs of past jobs...")
+                        .block(
+                            Block::default()
+                                .title(format!("Logs mentioning \"{}\"", query))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White));
+                    let area = centered_lines(90, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::LogSearch(query, Some(result), state) => {
+                    let id_width = result
+                        .hits
+                        .iter()
+                        .map(|h| h.job_id.len())
+                        .max()
+                        .unwrap_or(0);
+                    let lowercase_query = query.to_lowercase();
+                    let items = result
+                        .hits
+                        .iter()
+                        .map(|hit| {
+                            let mut spans = vec![
+                                Span::styled(
+                                    format!("{:<w$}", hit.job_id, w = id_width),
+                                    Style::default().add_modifier(Modifier::BOLD),
+                                ),
+                                Span::raw(format!(" {} ", hit.name)),
+                                Span::styled(
+                                    format!(
+                                        "{}:{} ",
+                                        hit.path.file_name().unwrap_or_default().to_string_lossy(),
+                                        hit.line_number
+                                    ),
+                                    Style::default().add_modifier(Modifier::DIM),
+                                ),
+                            ];
+                            // highlight the first match, unless lowercasing moved it
+                            match hit.line.to_lowercase().find(&lowercase_query) {
+                                Some(start)
+                                    if hit.line.is_char_boundary(start)
+                                        && hit.line.is_char_boundary(start + query.len())
+                                        && hit.line.to_lowercase().len() == hit.line.len() =>
+                                {
+                                    let end = start + query.len();
+                                    spans.push(Span::raw(hit.line[..start].to_string()));
+                                    spans.push(Span::styled(
+                                        hit.line[start..end].to_string(),
+                                        Style::default().fg(Color::Yellow),
+                                    ));
+                                    spans.push(Span::raw(hit.line[end..].to_string()));
+                                }
+                                _ => spans.push(Span::raw(hit.line.clone())),
+                            }
+                            ListItem::new(Line::from(spans))
+                        })
+                        .collect::<Vec<_>>();
+                    let title = match result.hits.len() {
+                        0 => format!(
+                            "No log of a past job mentions \"{}\" ({} searched)",
+                            query, result.searched
+                        ),
+                        n => format!(
+                            "{}{} {} mentioning \"{}\" in {} {}{} (enter: select job, K: search again)",
+                            if n >= log_search::MAX_HITS { "First " } else { "" },
+                            n,
+                            if n == 1 { "line" } else { "lines" },
+                            query,
+                            result.searched,
+                            if result.searched == 1 { "log" } else { "logs" },
+                            if result.skipped > 0 {
+                                format!(", {} skipped by the index", result.skipped)
+                            } else {
+                                String::new()
+                            }
+                        ),
+                    };
+                    let height = (items.len() as u16 + 2)
+                        .max(3)
+                        .min(f.size().height.saturating_sub(4));
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(title)
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(90, height, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::UsageReport(period, None, _) => {
+                    let dialog = Paragraph::new("Asking sreport...")
+                        .block(
+                            Block::default()
+                                .title(format!("Usage {}", period.label()))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White));
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::UsageReport(period, Some(Err(e)), _) => {
+                    let dialog = Paragraph::new(e.as_str())
+                        .style(Style::default().fg(Color::Red))
+                        .wrap(Wrap { trim: true })
+                        .block(
+                            Block::default()
+                                .title(format!("Usage {}", period.label()))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        );
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::UsageReport(period, Some(Ok(rows)), state) => {
+                    const BAR_WIDTH: usize = 30;
+                    let label = |r: &Utilization| match &r.user {
+                        Some(user) => format!("  {}", user),
+                        None => r.account.clone(),
+                    };
+                    let label_width = rows.iter().map(|r| label(r).len()).max().unwrap_or(0);
+                    let name_width = rows.iter().map(|r| r.name.len()).max().unwrap_or(0);
+                    let max_account = rows
+                        .iter()
+                        .filter(|r| r.user.is_none())
+                        .map(|r| r.core_hours)
+                        .fold(0.0, f64::max);
+                    let items = rows
+                        .iter()
+                        .map(|r| {
+                            // accounts relative to the largest one, users to their account
+                            let total = match r.user {
+                                Some(_) => rows
+                                    .iter()
+                                    .find(|a| a.user.is_none() && a.account == r.account)
+                                    .map_or(0.0, |a| a.core_hours),
+                                None => max_account,
+                            };
+                            let fraction = if total > 0.0 {
+                                (r.core_hours / total).min(1.0)
+                            } else {
+                                0.0
+                            };
+                            let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+                            let style = match r.user {
+                                Some(_) => Style::default(),
+                                None => Style::default().add_modifier(Modifier::BOLD),
+                            };
+                            ListItem::new(Line::from(vec![
+                                Span::styled(
+                                    format!(
+                                        "{:<lw$}  {:<nw$}  {:>7}  ",
+                                        label(r),
+                                        r.name,
+                                        format_core_hours(r.core_hours),
+                                        lw = label_width,
+                                        nw = name_width
+                                    ),
+                                    style,
+                                ),
+                                Span::styled(
+                                    "█".repeat(filled),
+                                    Style::default().fg(match r.user {
+                                        Some(_) => Color::Cyan,
+                                        None => Color::Blue,
+                                    }),
+                                ),
+                                Span::styled(
+                                    format!(" {:.0}%", fraction * 100.0),
+                                    Style::default().add_modifier(Modifier::DIM),
+                                ),
+                            ]))
+                        })
+                        .collect::<Vec<_>>();
+                    let height = if items.is_empty() {
+                        3
+                    } else {
+                        (items.len() as u16 + 2).min(f.size().height.saturating_sub(4))
+                    };
+                    let title = format!("Usage {} in core-hours (h/l: period)", period.label());
+                    let dialog = if items.is_empty() {
+                        List::new(vec![ListItem::new("No usage in this period")])
+                    } else {
+                        List::new(items)
+                    }
+                    .block(
+                        Block::default()
+                            .title(title)
+                            .borders(Borders::ALL)
+                            .style(Style::default().fg(Color::Green)),
+                    )
+                    .style(Style::default().fg(Color::White))
+                    .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(90, height, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::Steps(job_id, None, _) => {
+                    let dialog = Paragraph::new("Looking up the steps...")
+                        .block(
+                            Block::default()
+                                .title(format!("Steps of job {}", job_id))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White));
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Steps(job_id, Some(Err(e)), _) => {
+                    let dialog = Paragraph::new(e.as_str())
+                        .style(Style::default().fg(Color::Red))
+                        .wrap(Wrap { trim: true })
+                        .block(
+                            Block::default()
+                                .title(format!("Steps of job {}", job_id))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        );
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Steps(job_id, Some(Ok(steps)), state) => {
+                    let dim = Style::default().add_modifier(Modifier::DIM);
+                    let items = std::iter::once(ListItem::new("Output of the job"))
+                        .chain(steps.iter().map(|step| {
+                            let path = match &step.stdout {
+                                Some(path) => Span::raw(path.to_string_lossy().into_owned()),
+                                None => Span::styled("in the output of the job", dim),
+                            };
+                            ListItem::new(Line::from(vec![
+                                Span::raw(format!("Step {} ", step.id)),
+                                Span::styled(format!("({}): ", step.name), dim),
+                                path,
+                            ]))
+                        }))
+                        .collect::<Vec<_>>();
+                    let height = items.len() as u16 + 2;
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(format!("Steps of job {} (enter: show output)", job_id))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(90, height, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::WhatIf(job_id, None, _) => {
+                    let dialog = Paragraph::new("Asking sbatch --test-only...")
+                        .block(
+                            Block::default()
+                                .title(format!("Job {} in other queues", job_id))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White));
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::WhatIf(job_id, Some(Err(e)), _) => {
+                    let dialog = Paragraph::new(e.as_str())
+                        .style(Style::default().fg(Color::Red))
+                        .wrap(Wrap { trim: true })
+                        .block(
+                            Block::default()
+                                .title(format!("Job {} in other queues", job_id))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        );
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::WhatIf(job_id, Some(Ok(estimates)), state) => {
+                    let now = clock::now();
+                    let partition_width = estimates
+                        .iter()
+                        .map(|e| e.partition.len())
+                        .max()
+                        .unwrap_or(0)
+                        .max(9);
+                    let qos_width = estimates
+                        .iter()
+                        .map(|e| e.qos.len())
+                        .max()
+                        .unwrap_or(0)
+                        .max(3);
+                    let header = format!(
+                        "{:<pw$}  {:<qw$}  Estimated start",
+                        "Partition",
+                        "QOS",
+                        pw = partition_width,
+                        qw = qos_width
+                    );
+                    let items = estimates
+                        .iter()
+                        .map(|e| {
+                            let mut spans = vec![Span::raw(format!(
+                                "{:<pw$}  {:<qw$}  ",
+                                e.partition,
+                                e.qos,
+                                pw = partition_width,
+                                qw = qos_width
+                            ))];
+                            match &e.start {
+                                Ok(Some(start)) => {
+                                    spans.push(Span::raw(
+                                        start.format("%Y-%m-%d %H:%M").to_string(),
+                                    ));
+                                    spans.push(Span::styled(
+                                        format!(" ({})", format_relative(*start, now)),
+                                        Style::default().add_modifier(Modifier::DIM),
+                                    ));
+                                }
+                                Ok(None) => spans.push(Span::raw("unknown")),
+                                Err(e) => spans.push(Span::styled(
+                                    e.as_str(),
+                                    Style::default().fg(Color::Red),
+                                )),
+                            }
+                            if e.current {
+                                spans.push(Span::styled(
+                                    "  (current)",
+                                    Style::default().add_modifier(Modifier::BOLD),
+                                ));
+                            }
+                            ListItem::new(Line::from(spans))
+                        })
+                        .collect::<Vec<_>>();
+                    let height = (items.len() as u16 + 2).min(f.size().height.saturating_sub(4));
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(vec![
+                                    Span::styled(
+                                        header,
+                                        Style::default().add_modifier(Modifier::BOLD),
+                                    ),
+                                    Span::raw(format!(" (job {})", job_id)),
+                                ])
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(75, height, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::Matrix(array_id, cursor, columns) => {
+                    let tasks = matrix::array_tasks(&self.all_jobs, array_id);
+                    *cursor = min(*cursor, tasks.len().saturating_sub(1));
+                    *columns = centered_lines(90, 3, f.size())
+                        .width
+                        .saturating_sub(2)
+                        .max(1) as usize;
+                    // task under the cursor, legend and borders
+                    let max_rows = (f.size().height.saturating_sub(8) as usize).max(1);
+                    let rows = min(tasks.len().div_ceil(*columns), max_rows);
+                    let mut lines = matrix::matrix_lines(&tasks, *cursor, *columns, rows);
+                    lines.insert(
+                        0,
+                        match tasks.get(*cursor) {
+                            Some(task) => Line::from(vec![
+                                Span::styled(
+                                    format!(
+                                        "Task {} ",
+                                        task.array_step.as_deref().unwrap_or_default()
+                                    ),
+                                    Style::default().add_modifier(Modifier::BOLD),
+                                ),
+                                Span::raw(format!(
+                                    "{}  {}  {}",
+                                    task.state, task.time, task.nodelist
+                                )),
+                            ]),
+                            None => Line::from("No tasks"),
+                        },
+                    );
+                    let area = centered_lines(90, lines.len() as u16 + 2, f.size());
+                    let dialog = Paragraph::new(lines).block(
+                        Block::default()
+                            .title(format!(
+                                "Tasks of array job {} ({}, enter: show log)",
+                                array_id,
+                                tasks.len()
+                            ))
+                            .borders(Borders::ALL)
+                            .style(Style::default().fg(Color::Green)),
+                    );
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Alert(job) => {
+                    let state = job.state.split_whitespace().next().unwrap_or_default();
+                    let what = match state {
+                        "NODE_FAIL" => "lost a node",
+                        "OUT_OF_MEMORY" => "ran out of memory",
+                        "PREEMPTED" => "was preempted",
+                        _ => "failed",
+                    };
+                    let mut lines = vec![Line::from(format!(
+                        "Job {} ({}) {} ({}).",
+                        job.id(),
+                        job.name,
+                        what,
+                        state
+                    ))];
+                    if !job.nodelist.is_empty() {
+                        lines.push(Line::from(format!("Nodes: {}", job.nodelist)));
+                    }
+                    lines.push(Line::from(""));
+                    lines.push(Line::styled(
+                        "l: view log end | r: requeue | s: resubmit | esc: dismiss",
+                        Style::default().add_modifier(Modifier::DIM),
+                    ));
+                    let area = centered_lines(70, lines.len() as u16 + 2, f.size());
+                    let dialog = Paragraph::new(lines)
+                        .style(Style::default().fg(Color::White))
+                        .wrap(Wrap { trim: true })
+                        .block(
+                            Block::default()
+                                .title("Critical event")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Red)),
+                        );
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Activity(Err(e), _) => {
+                    let dialog = Paragraph::new(e.to_string())
+                        .style(Style::default().fg(Color::Red))
+                        .wrap(Wrap { trim: true })
+                        .block(
+                            Block::default()
+                                .title("Activity")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        );
+
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Activity(Ok(entries), state) => {
+                    let action_width = entries.iter().map(|e| e.action.len()).max().unwrap_or(0);
+                    let target_width = entries
+                        .iter()
+                        .map(|e| e.target.chars().count().min(30))
+                        .max()
+                        .unwrap_or(0);
+                    let items = entries
+                        .iter()
+                        .map(|e| {
+                            let target = e.target.chars().take(30).collect::<String>();
+                            ListItem::new(format!(
+                                "{}  {:<aw$}  {:<tw$}  {}",
+                                e.time,
+                                e.action,
+                                target,
+                                e.message,
+                                aw = action_width,
+                                tw = target_width
+                            ))
+                            .style(if e.ok {
+                                Style::default()
+                            } else {
+                                Style::default().fg(Color::Red)
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    let title = if entries.is_empty() {
+                        "Activity (nothing done through turm yet)"
+                    } else {
+                        "Activity (newest first)"
+                    };
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(title)
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+                    let area = centered_lines(
+                        90,
+                        (entries.len() as u16 + 2).min(f.size().height.saturating_sub(4)),
+                        f.size(),
+                    );
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::Nodes(None, _) => {
+                    let dialog = Paragraph::new("Asking sinfo...")
+                        .block(
+                            Block::default()
+                                .title("Nodes")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White));
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Nodes(Some(Err(e)), _) => {
+                    let dialog = Paragraph::new(e.to_string())
+                        .style(Style::default().fg(Color::Red))
+                        .wrap(Wrap { trim: true })
+                        .block(
+                            Block::default()
+                                .title("Nodes")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        );
+
+                    let area = centered_lines(75, 3, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Nodes(Some(Ok(nodes)), state) => {
+                    let header = [
+                        "Node",
+                        "State",
+                        "CPUs (A/I/O/T)",
+                        "Partitions",
+                        "Health",
+                        "Reason",
+                    ];
+                    let rows = nodes
+                        .iter()
+                        .map(|n| {
+                            [
+                                n.name.clone(),
+                                n.state.clone(),
+                                n.cpus.clone(),
+                                n.partitions.join(","),
+                                self.node_health
+                                    .get(&n.name)
+                                    .map(|h| h.text.clone())
+                                    .unwrap_or_default(),
+                                n.reason.clone(),
+                            ]
+                        })
+                        .collect::<Vec<_>>();
+                    let widths = header.map(|h| h.len()).to_vec();
+                    let widths = rows.iter().fold(widths, |mut widths, row| {
+                        for (w, c) in widths.iter_mut().zip(row.iter()) {
+                            *w = (*w).max(c.len());
+                        }
+                        widths
+                    });
+                    let format_row = |row: &[String]| {
+                        row.iter()
+                            .zip(widths.iter())
+                            .map(|(c, w)| format!("{:<w$}", c, w = w))
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    };
+                    let items = rows
+                        .iter()
+                        .map(|row| {
+                            let style = match row[1].as_str() {
+                                s if s.starts_with("down")
+                                    || s.starts_with("drain")
+                                    || s.starts_with("fail") =>
+                                {
+                                    Style::default().fg(Color::Red)
+                                }
+                                _ => match self.node_health.get(&row[0]).map(|h| h.level) {
+                                    Some(HealthLevel::Critical) => {
+                                        Style::default().fg(Color::LightRed)
+                                    }
+                                    Some(HealthLevel::Warn) => Style::default().fg(Color::Yellow),
+                                    _ => Style::default(),
+                                },
+                            };
+                            ListItem::new(format_row(row)).style(style)
+                        })
+                        .collect::<Vec<_>>();
+                    let hint = if self.admin {
+                        " (d: drain, r: resume)"
+                    } else {
+                        ""
+                    };
+                    let dialog = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(format!("{}{}", format_row(&header.map(String::from)), hint))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White))
+                        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+
+                    let area = centered_lines(
+                        90,
+                        (rows.len() as u16 + 2).min(f.size().height.saturating_sub(4)),
+                        f.size(),
+                    );
+                    f.render_widget(Clear, area);
+                    f.render_stateful_widget(dialog, area, state);
+                }
+                Dialog::Timeline(offset) => {
+                    let area = centered_lines(90, f.size().height.saturating_sub(4), f.size());
+                    let user = remote::current_user();
+                    let jobs = self
+                        .all_jobs
+                        .iter()
+                        .filter(|j| j.user == user)
+                        .collect::<Vec<_>>();
+                    let lines = timeline_lines(&jobs, clock::now(), area.width.saturating_sub(2));
+                    *offset = (*offset).min(lines.len().saturating_sub(1) as u16);
+                    let dialog = Paragraph::new(lines)
+                        .scroll((*offset, 0))
+                        .block(
+                            Block::default()
+                                .title(format!("Timeline of my jobs ({})", user))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Green)),
+                        )
+                        .style(Style::default().fg(Color::White));
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Input(target, value) => {
                     let dialog = Paragraph::new(Line::from(vec![
-                        Span::raw("Cancel job "),
-                        Span::styled(id, Style::default().add_modifier(Modifier::BOLD)),
-                        Span::raw("?"),
+                        Span::raw(value.as_str()),
+                        Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
                     ]))
                     .style(Style::default().fg(Color::White))
-                    .wrap(Wrap { trim: true })
                     .block(
                         Block::default()
-                            .title("Confirm")
+                            .title(target.title())
                             .borders(Borders::ALL)
                             .style(Style::default().fg(Color::Green)),
                     );
@@ -591,6 +5614,12 @@ impl App {
                 }
             }
         }
+
+        if let Some(anonymizer) = &self.anonymizer {
+            // the hyperlinks would write the real paths over the masked frame
+            self.hyperlinks.clear();
+            anonymizer.apply(f.buffer_mut());
+        }
     }
 }
 
@@ -604,6 +5633,9 @@ pub fn process_terminal_output(input: &str) -> Vec<String> {
                     result.pop();
                 } else if char == '\r' {
                     result.clear();
+                } else if char.is_control() && char != '\t' {
+                    // would garble the terminal, e.g. escape sequences
+                    result.push(char::REPLACEMENT_CHARACTER);
                 } else {
                     result.push(char);
                 }
@@ -613,32 +5645,6 @@ pub fn process_terminal_output(input: &str) -> Vec<String> {
         .collect()
 }
 
-fn string_for_paragraph(s: &str, lines: usize, anchor: ScrollAnchor, offset: usize) -> String {
-    // skip everything after last line delimiter
-    let s = s.rsplit_once(&['\r', '\n']).map_or(s, |(p, _)| p);
-
-    let l = process_terminal_output(s);
-    let l = match anchor {
-        ScrollAnchor::Top => l
-            .iter()
-            .skip(offset)
-            .take(lines)
-            .map(|l| l.chars().collect::<String>())
-            .collect::<Vec<_>>(),
-        ScrollAnchor::Bottom => l
-            .iter()
-            .rev()
-            .skip(offset)
-            .take(lines)
-            .map(|l| l.chars().collect::<String>())
-            .collect::<Vec<_>>()
-            .into_iter()
-            .rev()
-            .collect::<Vec<_>>(),
-    };
-    l.join("\n")
-}
-
 impl App {
     fn focus_next_panel(&mut self) {
         match self.focus {
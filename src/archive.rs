@@ -0,0 +1,212 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    thread,
+};
+
+use chrono::{Local, NaiveDateTime};
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::app::{AppMessage, Job};
+use crate::log_source::{log_source, LogSource, Throttle};
+use crate::states;
+
+/// Archived jobs that are kept in the state file.
+pub const MAX_ARCHIVED: usize = 1000;
+
+/// Copies the logs of my jobs into an archive directory when they finish, before they are
+/// cleaned up or overwritten by the next run.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ArchiveConfig {
+    pub dir: PathBuf,
+    /// Subdirectory per job, a template with `{job_id}`, `{name}`, `{user}`, `{partition}`,
+    /// `{state}` and `{date}` (of the end).
+    #[serde(default = "default_layout")]
+    pub layout: String,
+    /// States that trigger the archiving when a job enters them, or all final states if empty.
+    #[serde(default)]
+    pub states: Vec<String>,
+    /// Whether the files are compressed with gzip.
+    #[serde(default = "default_compress")]
+    pub compress: bool,
+    /// Whether the batch script is archived, too.
+    #[serde(default)]
+    pub script: bool,
+}
+
+fn default_layout() -> String {
+    "{name}/{job_id}".to_string()
+}
+
+fn default_compress() -> bool {
+    true
+}
+
+/// Where the logs of a job were archived.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ArchivedJob {
+    pub job_id: String,
+    pub time: NaiveDateTime,
+    pub dir: PathBuf,
+    pub files: Vec<PathBuf>,
+}
+
+/// Keeps a template value from adding or escaping directories.
+fn path_component(value: &str) -> String {
+    let value = value.replace(['/', '\0'], "_");
+    match value.as_str() {
+        "" | "." | ".." => "_".to_string(),
+        _ => value,
+    }
+}
+
+fn archive_dir(config: &ArchiveConfig, job: &Job) -> PathBuf {
+    let dir = match config.dir.strip_prefix("~").ok().zip(dirs::home_dir()) {
+        Some((dir, home)) => home.join(dir),
+        None => config.dir.clone(),
+    };
+    let date = job
+        .end_time
+        .unwrap_or_else(|| Local::now().naive_local())
+        .format("%Y-%m-%d")
+        .to_string();
+    let state = job.state.split_whitespace().next().unwrap_or_default();
+    let layout = config
+        .layout
+        .split('/')
+        .map(|part| {
+            part.replace("{job_id}", &path_component(&job.id()))
+                .replace("{name}", &path_component(&job.name))
+                .replace("{user}", &path_component(&job.user))
+                .replace("{partition}", &path_component(&job.partition))
+                .replace("{state}", state)
+                .replace("{date}", &date)
+        })
+        .collect::<PathBuf>();
+    dir.join(layout)
+}
+
+struct Request {
+    config: ArchiveConfig,
+    job: Job,
+}
+
+/// Copies the files in the background, also from the cluster in remote mode, and reports
+/// the archived jobs to the app.
+struct Archiver {
+    app: Sender<AppMessage>,
+    receiver: Receiver<Request>,
+    source: Box<dyn LogSource>,
+}
+
+impl Archiver {
+    fn copy(&mut self, source: &Path, dir: &Path, compress: bool) -> io::Result<PathBuf> {
+        let content = self.source.read_all(source)?;
+        let mut name = source.file_name().unwrap_or_default().to_os_string();
+        // logs that are already compressed are read decompressed
+        let compressed = ["gz", "zst"]
+            .iter()
+            .any(|e| source.extension().is_some_and(|ext| ext == *e));
+        if compressed {
+            name = source.file_stem().unwrap_or_default().to_os_string();
+        }
+        if compress {
+            name.push(".gz");
+        }
+        let target = dir.join(name);
+        let mut file = fs::File::create(&target)?;
+        if compress {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            encoder.write_all(&content)?;
+            encoder.finish()?;
+        } else {
+            file.write_all(&content)?;
+        }
+        Ok(target)
+    }
+
+    fn archive(&mut self, request: &Request) -> Result<ArchivedJob, String> {
+        let Request { config, job } = request;
+        let dir = archive_dir(config, job);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let mut sources = Vec::new();
+        for path in [&job.stdout, &job.stderr].into_iter().flatten() {
+            if !sources.contains(path) {
+                sources.push(path.clone());
+            }
+        }
+        let mut files = sources
+            .iter()
+            .map(|source| {
+                self.copy(source, &dir, config.compress)
+                    .map_err(|e| format!("{}: {}", source.display(), e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // the command of a batch job is its script, which may have been removed since
+        if config.script && Path::new(&job.command).is_absolute() {
+            match self.copy(Path::new(&job.command), &dir, config.compress) {
+                Ok(file) => files.push(file),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("{}: {}", job.command, e)),
+            }
+        }
+        Ok(ArchivedJob {
+            job_id: job.id(),
+            time: Local::now().naive_local(),
+            dir,
+            files,
+        })
+    }
+
+    fn run(&mut self) {
+        while let Ok(request) = self.receiver.recv() {
+            let archived = self.archive(&request).map_err(|e| {
+                format!(
+                    "Failed to archive the logs of job {}: {}",
+                    request.job.id(),
+                    e
+                )
+            });
+            if self.app.send(AppMessage::Archived(archived)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+pub struct ArchiverHandle {
+    sender: Sender<Request>,
+}
+
+impl ArchiverHandle {
+    pub fn new(app: Sender<AppMessage>, throttle: Throttle) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = Archiver {
+            app,
+            receiver,
+            source: log_source(throttle),
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Archives the logs of the job if it entered one of the configured states.
+    pub fn archive(&self, config: &ArchiveConfig, job: &Job) {
+        let state = job.state.split_whitespace().next().unwrap_or_default();
+        let triggers = match config.states.is_empty() {
+            true => states::is_final(state),
+            false => config.states.iter().any(|s| s == state),
+        };
+        if triggers {
+            let _ = self.sender.send(Request {
+                config: config.clone(),
+                job: job.clone(),
+            });
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use chrono::Local;
+
+/// Number of entries shown in the activity panel.
+const RECENT_ENTRIES: usize = 500;
+
+/// Every action taken through turm, one per line: time, action, target, `ok` or `error`,
+/// and the message shown in the status line, tab-separated.
+pub fn path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|d| d.join("turm").join("audit.log"))
+}
+
+pub struct Entry {
+    pub time: String,
+    pub action: String,
+    pub target: String,
+    pub ok: bool,
+    pub message: String,
+}
+
+/// Appends an action and its result to the audit log.
+pub fn record(action: &str, target: &str, result: &Result<String, String>) -> io::Result<()> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let (status, message) = match result {
+        Ok(m) => ("ok", m),
+        Err(m) => ("error", m),
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        file,
+        "{}\t{}\t{}\t{}\t{}",
+        Local::now().format("%Y-%m-%dT%H:%M:%S"),
+        action,
+        target,
+        status,
+        message.replace(['\t', '\n'], " ")
+    )
+}
+
+/// The most recent entries, newest first.
+pub fn recent() -> io::Result<Vec<Entry>> {
+    let content = match path().map(fs::read_to_string) {
+        Some(Ok(content)) => content,
+        Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => String::new(),
+    };
+    Ok(content
+        .lines()
+        .rev()
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, '\t');
+            Some(Entry {
+                time: fields.next()?.replace('T', " "),
+                action: fields.next()?.to_string(),
+                target: fields.next()?.to_string(),
+                ok: fields.next()? == "ok",
+                message: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .take(RECENT_ENTRIES)
+        .collect())
+}
@@ -0,0 +1,138 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{Local, NaiveDateTime};
+use crossbeam::{
+    channel::{unbounded, Receiver, Sender},
+    select,
+};
+
+use crate::app::AppMessage;
+use crate::remote::slurm_command;
+use crate::slurm_time::{format_duration, format_slurm_duration};
+
+/// How often `sinfo` is run for the selected pending job.
+const INTERVAL: Duration = Duration::from_secs(60);
+/// Time limits are suggested in these steps, leaving some slack before the window closes.
+const STEP_MINUTES: i64 = 5;
+
+/// What is needed to tell whether a pending job could backfill.
+#[derive(Clone, PartialEq)]
+pub struct BackfillQuery {
+    pub job_id: String,
+    pub partition: String,
+    pub cpus: u64,
+    pub time_limit: chrono::Duration,
+    /// When the next pending job of the partition is expected to start, which the idle
+    /// resources are probably held for.
+    pub window_end: Option<NaiveDateTime>,
+}
+
+/// Idle CPUs of the partitions (comma separated, as in squeue), from `sinfo`.
+fn idle_cpus(partition: &str) -> Result<u64, String> {
+    let output = slurm_command("sinfo")
+        .arg(format!("--partition={}", partition))
+        .args(["--noheader", "--format=%C"])
+        .output()
+        .map_err(|e| format!("Failed to execute sinfo: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "sinfo failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    // allocated/idle/other/total
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| l.trim().split('/').nth(1)?.parse::<u64>().ok())
+        .sum())
+}
+
+/// A hint if the job would fit into the idle resources with a shorter time limit.
+fn hint(query: &BackfillQuery, idle: u64, now: NaiveDateTime) -> Option<String> {
+    let window = query.window_end? - now;
+    if idle < query.cpus.max(1) || query.time_limit <= window {
+        return None;
+    }
+    let limit = chrono::Duration::minutes(window.num_minutes() / STEP_MINUTES * STEP_MINUTES);
+    if limit < chrono::Duration::minutes(STEP_MINUTES) {
+        return None;
+    }
+    Some(format!(
+        "{} idle CPUs in {} for ~{}, with TimeLimit={} it could start now (scontrol update JobId={} TimeLimit={})",
+        idle,
+        query.partition,
+        format_duration(window),
+        format_slurm_duration(limit),
+        query.job_id,
+        format_slurm_duration(limit)
+    ))
+}
+
+/// Checks whether the selected pending job could backfill with a shorter time limit.
+struct BackfillWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<Option<BackfillQuery>>,
+    query: Option<BackfillQuery>,
+    polled_at: Option<Instant>,
+}
+
+impl BackfillWatcher {
+    fn poll(&mut self) {
+        self.polled_at = Some(Instant::now());
+        let Some(query) = &self.query else {
+            return;
+        };
+        // without sinfo, there is simply no hint
+        let hint = idle_cpus(&query.partition)
+            .ok()
+            .and_then(|idle| hint(query, idle, Local::now().naive_local()));
+        let _ = self
+            .app
+            .send(AppMessage::Backfill(query.job_id.clone(), hint));
+    }
+
+    fn run(&mut self) {
+        loop {
+            let due = self
+                .polled_at
+                .map_or(Duration::ZERO, |t| INTERVAL.saturating_sub(t.elapsed()));
+            select! {
+                recv(self.receiver) -> msg => match msg {
+                    Ok(query) => {
+                        if query != self.query {
+                            self.query = query;
+                            self.poll();
+                        }
+                    }
+                    Err(_) => return,
+                },
+                default(due) => self.poll(),
+            }
+        }
+    }
+}
+
+pub struct BackfillWatcherHandle {
+    sender: Sender<Option<BackfillQuery>>,
+}
+
+impl BackfillWatcherHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = BackfillWatcher {
+            app,
+            receiver,
+            query: None,
+            polled_at: None,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    pub fn set_job(&self, query: Option<BackfillQuery>) {
+        let _ = self.sender.send(query);
+    }
+}
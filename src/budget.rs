@@ -0,0 +1,225 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{Datelike, Local, NaiveDate};
+use crossbeam::{
+    channel::{unbounded, Receiver, Sender},
+    select,
+};
+use serde::Deserialize;
+
+use crate::app::AppMessage;
+use crate::remote::slurm_command;
+
+/// How often the consumption is refreshed, usage is only rolled up hourly by Slurm anyway.
+const INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Allocation budget of an account in core-hours per period, e.g. 100k per quarter.
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Budget {
+    pub account: String,
+    pub core_hours: f64,
+    #[serde(default)]
+    pub period: Period,
+    /// Percentages of the budget at which to warn once they are reached.
+    #[serde(default = "default_warn_at")]
+    pub warn_at: Vec<f64>,
+}
+
+fn default_warn_at() -> Vec<f64> {
+    vec![75.0, 90.0, 100.0]
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Month,
+    #[default]
+    Quarter,
+    Year,
+}
+
+impl Period {
+    /// The first day of the period that contains `today`.
+    pub fn start(self, today: NaiveDate) -> NaiveDate {
+        let month = match self {
+            Period::Month => today.month(),
+            Period::Quarter => (today.month() - 1) / 3 * 3 + 1,
+            Period::Year => 1,
+        };
+        NaiveDate::from_ymd_opt(today.year(), month, 1).unwrap_or(today)
+    }
+}
+
+/// Consumption of a budget in the current period.
+pub struct BudgetStatus {
+    pub budget: Budget,
+    pub since: NaiveDate,
+    /// Core-hours used so far.
+    pub used: Result<f64, String>,
+}
+
+impl BudgetStatus {
+    pub fn percent(&self) -> Option<f64> {
+        let used = self.used.as_ref().ok()?;
+        Some(100.0 * used / self.budget.core_hours)
+    }
+
+    /// The highest warning threshold that was reached.
+    pub fn reached(&self) -> Option<f64> {
+        let percent = self.percent()?;
+        self.budget
+            .warn_at
+            .iter()
+            .copied()
+            .filter(|t| percent >= *t)
+            .max_by(f64::total_cmp)
+    }
+}
+
+/// Core-hours with a `k` or `M` suffix, e.g. `12.5k`.
+pub fn format_core_hours(hours: f64) -> String {
+    match hours {
+        h if h >= 1e6 => format!("{:.1}M", h / 1e6),
+        h if h >= 1e4 => format!("{:.0}k", h / 1e3),
+        h if h >= 1e3 => format!("{:.1}k", h / 1e3),
+        h => format!("{:.0}", h),
+    }
+}
+
+/// Core-hours of the account since the given day, from the hourly rollups of `sreport`.
+fn sreport(account: &str, since: NaiveDate) -> Result<f64, String> {
+    let output = slurm_command("sreport")
+        .args([
+            "--noheader",
+            "--parsable2",
+            "cluster",
+            "AccountUtilizationByUser",
+        ])
+        .arg(format!("Accounts={}", account))
+        .arg(format!("Start={}", since.format("%Y-%m-%d")))
+        .arg("End=now")
+        .args(["--tres=cpu", "-t", "Hours", "Format=Accounts,Login,Used"])
+        .output()
+        .map_err(|e| format!("Failed to execute sreport: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "sreport failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    // the account itself is the line without a user, summed up over the clusters
+    let used = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| match l.split('|').collect::<Vec<_>>()[..] {
+            [a, "", used] if a == account => used.trim().parse::<f64>().ok(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    match used.is_empty() {
+        true => Err(format!("sreport knows no usage of account {}", account)),
+        false => Ok(used.iter().sum()),
+    }
+}
+
+/// Core-hours of the jobs of the account that ran since the given day, from `sacct`.
+/// Jobs that started before count fully.
+fn sacct(account: &str, since: NaiveDate) -> Result<f64, String> {
+    let output = slurm_command("sacct")
+        .args(["--allusers", "--allocations", "--noheader", "--parsable2"])
+        .arg(format!("--accounts={}", account))
+        .arg(format!("--starttime={}", since.format("%Y-%m-%d")))
+        .arg("--endtime=now")
+        .arg("--format=CPUTimeRAW")
+        .output()
+        .map_err(|e| format!("Failed to execute sacct: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "sacct failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let seconds = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| l.trim().parse::<u64>().ok())
+        .sum::<u64>();
+    Ok(seconds as f64 / 3600.0)
+}
+
+/// Polls the consumption of the configured budgets.
+struct BudgetWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<Vec<Budget>>,
+    budgets: Vec<Budget>,
+    polled_at: Option<Instant>,
+}
+
+impl BudgetWatcher {
+    fn poll(&mut self) {
+        self.polled_at = Some(Instant::now());
+        let today = Local::now().date_naive();
+        let statuses = self
+            .budgets
+            .iter()
+            .map(|budget| {
+                let since = budget.period.start(today);
+                let used = sreport(&budget.account, since).or_else(|e| {
+                    // sreport is not available everywhere, e.g. without slurmdbd rollups
+                    sacct(&budget.account, since).map_err(|_| e)
+                });
+                BudgetStatus {
+                    budget: budget.clone(),
+                    since,
+                    used,
+                }
+            })
+            .collect();
+        let _ = self.app.send(AppMessage::Budgets(statuses));
+    }
+
+    fn run(&mut self) {
+        loop {
+            let due = self
+                .polled_at
+                .map_or(Duration::ZERO, |t| INTERVAL.saturating_sub(t.elapsed()));
+            select! {
+                recv(self.receiver) -> msg => match msg {
+                    Ok(budgets) => {
+                        if budgets != self.budgets {
+                            self.budgets = budgets;
+                            self.poll();
+                        }
+                    }
+                    Err(_) => return,
+                },
+                default(due) => self.poll(),
+            }
+        }
+    }
+}
+
+pub struct BudgetWatcherHandle {
+    sender: Sender<Vec<Budget>>,
+}
+
+impl BudgetWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, budgets: Vec<Budget>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = BudgetWatcher {
+            app,
+            receiver,
+            budgets,
+            polled_at: None,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Watches the budgets, answered with [`AppMessage::Budgets`] after every poll.
+    pub fn set_budgets(&self, budgets: Vec<Budget>) {
+        let _ = self.sender.send(budgets);
+    }
+}
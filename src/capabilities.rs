@@ -0,0 +1,91 @@
+use std::{process::Stdio, thread};
+
+use crate::remote::slurm_command;
+
+/// Slurm commands and flags beyond `squeue` that turm can make use of.
+/// Restricted clusters may lack some of them (e.g. `sacct` is admin-only or accounting is
+/// disabled), so they are probed once at startup and the dependent features are disabled.
+#[derive(Clone, Copy)]
+pub struct Capabilities {
+    /// Recently finished jobs.
+    pub sacct: bool,
+    /// The associations dialog.
+    pub sacctmgr: bool,
+    pub squeue_json: bool,
+    pub sstat: bool,
+    pub sprio: bool,
+}
+
+fn succeeds(program: &str, args: &[&str]) -> bool {
+    slurm_command(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+fn help_mentions(program: &str, flag: &str) -> bool {
+    slurm_command(program)
+        .arg("--help")
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).contains(flag))
+}
+
+impl Capabilities {
+    /// Runs the probes in parallel.
+    pub fn detect() -> Capabilities {
+        thread::scope(|s| {
+            let sacct = s.spawn(|| {
+                succeeds(
+                    "sacct",
+                    &[
+                        "--noheader",
+                        "-X",
+                        "--starttime=now",
+                        "--endtime=now",
+                        "--format=jobid",
+                    ],
+                )
+            });
+            let sacctmgr = s.spawn(|| {
+                succeeds(
+                    "sacctmgr",
+                    &[
+                        "--noheader",
+                        "--parsable2",
+                        "show",
+                        "cluster",
+                        "format=cluster",
+                    ],
+                )
+            });
+            let squeue_json = s.spawn(|| help_mentions("squeue", "--json"));
+            let sstat = s.spawn(|| succeeds("sstat", &["--usage"]));
+            let sprio = s.spawn(|| succeeds("sprio", &["--noheader", "--format=%i"]));
+            Capabilities {
+                sacct: sacct.join().unwrap_or(false),
+                sacctmgr: sacctmgr.join().unwrap_or(false),
+                squeue_json: squeue_json.join().unwrap_or(false),
+                sstat: sstat.join().unwrap_or(false),
+                sprio: sprio.join().unwrap_or(false),
+            }
+        })
+    }
+
+    /// Names of the unavailable commands and flags.
+    pub fn missing(&self) -> Vec<&'static str> {
+        [
+            (self.sacct, "sacct"),
+            (self.sacctmgr, "sacctmgr"),
+            (self.squeue_json, "squeue --json"),
+            (self.sstat, "sstat"),
+            (self.sprio, "sprio"),
+        ]
+        .into_iter()
+        .filter(|(available, _)| !available)
+        .map(|(_, name)| name)
+        .collect()
+    }
+}
@@ -0,0 +1,461 @@
+use crate::{
+    app, capabilities, clock, commands, completion, config, dispatcher, file_watcher, history,
+    job_commands, log_source, remote, snapshot, squeue_args, state, states, sweep, whose,
+};
+
+use app::App;
+use capabilities::Capabilities;
+use chrono::NaiveDateTime;
+use clap::CommandFactory;
+use clap::Parser;
+use clap::Subcommand;
+use clap_complete::Shell;
+use commands::StatusFormat;
+use completion::{print_candidates, print_completion, Candidates};
+use config::Config;
+use crossbeam::channel::{unbounded, Sender};
+use crossterm::{
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use file_watcher::LogSettings;
+use log_source::Throttle;
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    Terminal,
+};
+use snapshot::FrameDumper;
+use squeue_args::SqueueArgs;
+use state::State;
+use std::{io, path::PathBuf, sync::atomic::Ordering, thread, time::Duration};
+use sweep::Parameter;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Refresh rate for the job watcher [default: 2, or `slurm_refresh_seconds` from the config]
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        global = true,
+        help_heading = "Global Options"
+    )]
+    slurm_refresh: Option<u64>,
+
+    /// Refresh rate for the file watcher.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 2,
+        global = true,
+        help_heading = "Global Options"
+    )]
+    file_refresh: u64,
+
+    /// Path to the config file [default: $XDG_CONFIG_HOME/turm/config.toml]
+    #[arg(
+        long,
+        value_name = "FILE",
+        global = true,
+        help_heading = "Global Options"
+    )]
+    config: Option<PathBuf>,
+
+    /// Run Slurm commands and read log files on this host via ssh, e.g. `user@login.cluster`.
+    /// Requires key-based authentication.
+    #[arg(
+        long,
+        value_name = "HOST",
+        global = true,
+        help_heading = "Global Options"
+    )]
+    ssh: Option<String>,
+
+    /// Allow node actions such as drain and resume from the node view (requires Slurm operator
+    /// rights). Every action is logged to `$XDG_STATE_HOME/turm/audit.log`.
+    #[arg(long, global = true, help_heading = "Global Options")]
+    admin: bool,
+
+    /// Only show the jobs whose ids are listed in this file, one per line (optionally followed
+    /// by a label), e.g. written by a sweep launcher. The file is watched for new ids, and the
+    /// restriction is kept across restarts until it is cleared with `J`.
+    #[arg(
+        long,
+        value_name = "FILE",
+        global = true,
+        help_heading = "Global Options"
+    )]
+    jobs_file: Option<PathBuf>,
+
+    /// Start with usernames, job names and paths replaced by stable pseudonyms, e.g. for
+    /// sharing screenshots or recordings. Toggled with `i`.
+    #[arg(long, global = true, help_heading = "Global Options")]
+    anonymize: bool,
+
+    /// Render reproducible frames for recorded demos and golden-file tests: the clock is frozen
+    /// at TIME [default: 2024-01-01T12:00:00], jobs are ordered by id, and the screen is only
+    /// redrawn on updates and keys instead of every second.
+    #[arg(
+        long,
+        value_name = "TIME",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "2024-01-01T12:00:00",
+        global = true,
+        help_heading = "Global Options"
+    )]
+    deterministic_ui: Option<NaiveDateTime>,
+
+    /// Write every rendered frame that differs from the previous one as plain text to
+    /// `DIR/frame-<n>.txt`, e.g. for snapshot tests of the renderer.
+    #[arg(
+        long,
+        value_name = "DIR",
+        global = true,
+        help_heading = "Global Options"
+    )]
+    dump_frames: Option<PathBuf>,
+
+    /// squeue arguments
+    #[command(flatten, next_help_heading = "Squeue Options")]
+    squeue_args: SqueueArgs,
+
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Start the interactive UI (the default).
+    Ui,
+    /// Print the jobs as tab-separated lines: id, state, user, partition, time, name.
+    List {
+        /// Also print jobs that finished recently, as configured by `finished_retention_minutes`.
+        #[arg(long)]
+        finished: bool,
+    },
+    /// Print the output of a job.
+    Tail {
+        /// The job id, e.g. `1234` or `1234_5` for an array task.
+        #[arg(value_name = "JOB")]
+        id: String,
+
+        /// Print stderr instead of stdout.
+        #[arg(long)]
+        stderr: bool,
+
+        /// Number of lines to print.
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        lines: usize,
+
+        /// Keep printing new output as it is written.
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Cancel jobs.
+    Cancel {
+        /// The job ids to cancel.
+        #[arg(required = true)]
+        jobs: Vec<String>,
+    },
+    /// Submit a batch script via sbatch and print the new job id.
+    Submit {
+        /// Only check the submission with `sbatch --test-only`: print the options of the
+        /// `#SBATCH` lines and when and where the job would start, without submitting it.
+        #[arg(long, conflicts_with = "sweep")]
+        validate: bool,
+
+        /// Submit once per combination of the parameters, e.g. `--sweep 'lr={1e-3,1e-4}'
+        /// --sweep seed=0..4`, replacing `{{lr}}` and `{{seed}}` in the script and options.
+        #[arg(long, value_name = "NAME=VALUES")]
+        sweep: Vec<Parameter>,
+
+        /// Submit the sweep as one array job with a task per combination, the parameters are
+        /// exported as environment variables.
+        #[arg(long, requires = "sweep")]
+        as_array: bool,
+
+        /// Print the sbatch calls and scripts of the sweep instead of submitting them.
+        #[arg(long, requires = "sweep")]
+        dry_run: bool,
+
+        /// Submit without checking my active jobs for the same script and arguments, which
+        /// otherwise asks for confirmation.
+        #[arg(long)]
+        allow_duplicate: bool,
+
+        /// Ask which of my accounts to charge, listing their partitions, QOS and limits, unless
+        /// `--account`, `#SBATCH --account` or `$SBATCH_ACCOUNT` chooses one.
+        #[arg(long)]
+        pick_account: bool,
+
+        /// Arguments passed on to sbatch, e.g. `-- --time=1:00:00 job.sh`.
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        sbatch_args: Vec<String>,
+    },
+    /// Select a job in the running turm instance (`$TURM_SOCKET`, or else the most recently
+    /// started one) and switch to its tmux pane, e.g. from a tmux key binding or editor plugin.
+    Jump {
+        /// The job id, e.g. `1234` or `1234_5` for an array task.
+        #[arg(value_name = "JOB")]
+        id: String,
+    },
+    /// Print a one-line summary of the jobs (`--me` for mine) for desktop status bars: counts
+    /// of running, pending and recently failed jobs and the most recent event.
+    Status {
+        #[arg(long, value_enum, default_value_t = StatusFormat::Text)]
+        format: StatusFormat,
+    },
+    /// Print the man page to stdout.
+    Man,
+    /// Print shell completion script to stdout.
+    #[command(alias = "completions")]
+    Completion {
+        /// The shell to generate completion for.
+        shell: Shell,
+    },
+    /// Print the finished jobs in the local history as tab-separated lines: id, end, state,
+    /// user, partition, time, name. Unlike `list --finished`, this includes jobs `sacct` no
+    /// longer knows.
+    History {
+        /// Only jobs matching this, as in the search of the job list.
+        query: Option<String>,
+
+        /// Only jobs of this user.
+        #[arg(long)]
+        user: Option<String>,
+
+        /// Only jobs that ended within this many days.
+        #[arg(long, value_name = "DAYS")]
+        days: Option<u32>,
+
+        /// Print the number of jobs by state and their elapsed time instead.
+        #[arg(long)]
+        stats: bool,
+    },
+    /// Print the job(s) that produced the given file, e.g. a log file.
+    Whose {
+        /// The file to look up.
+        path: PathBuf,
+
+        /// How far back to look for finished jobs.
+        #[arg(long, value_name = "HOURS", default_value_t = 24 * 7)]
+        since: u64,
+    },
+    /// Print dynamic completion candidates (used by the completion scripts).
+    #[command(name = "complete-values", hide = true)]
+    Complete { kind: Candidates },
+}
+
+pub fn main() -> Result<(), io::Error> {
+    let args = Cli::parse();
+    if let Some(host) = &args.ssh {
+        remote::set_host(host.clone());
+    }
+    if let Some(at) = args.deterministic_ui {
+        clock::freeze(at);
+    }
+    match args.command {
+        Some(CliCommand::Ui) | None => {}
+        Some(CliCommand::List { finished }) => {
+            let finished = finished
+                .then(|| load_config(&args).map(|c| c.finished_retention()))
+                .transpose()?;
+            commands::list(&args.squeue_args, finished);
+            return Ok(());
+        }
+        Some(CliCommand::Tail {
+            ref id,
+            stderr,
+            lines,
+            follow,
+        }) => {
+            let interval = Duration::from_secs(args.file_refresh);
+            let config = load_config(&args)?;
+            let settings = LogSettings {
+                // the initial read needs the whole file to find the last lines
+                throttle: Throttle {
+                    bytes_per_second: None,
+                    ..config.log_throttle()
+                },
+                encoding: config.log_encoding,
+                node_local_prefixes: config.node_local_prefixes.clone(),
+            };
+            if let Err(e) = commands::tail(id, stderr, lines, follow, interval, settings) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(CliCommand::Cancel { ref jobs }) => {
+            std::process::exit(commands::cancel(jobs)?.code().unwrap_or(1));
+        }
+        Some(CliCommand::Submit {
+            validate,
+            ref sweep,
+            as_array,
+            dry_run,
+            allow_duplicate,
+            pick_account,
+            ref sbatch_args,
+        }) => {
+            let mut sbatch_args = sbatch_args.clone();
+            if pick_account {
+                if let Some(account) = commands::pick_account(&sbatch_args)? {
+                    sbatch_args.insert(0, format!("--account={}", account));
+                }
+            }
+            let sbatch_args = &sbatch_args;
+            let status = if validate {
+                commands::validate(sbatch_args)?
+            } else if !sweep.is_empty() {
+                match commands::submit_sweep(sweep, sbatch_args, as_array, dry_run) {
+                    Ok(status) => status,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                if !allow_duplicate {
+                    let duplicates = commands::duplicates(sbatch_args).unwrap_or_default();
+                    if !duplicates.is_empty() && !commands::confirm_duplicate(&duplicates)? {
+                        std::process::exit(1);
+                    }
+                }
+                commands::submit(sbatch_args)?
+            };
+            std::process::exit(status.code().unwrap_or(1));
+        }
+        Some(CliCommand::Status { format }) => {
+            let finished = load_config(&args)?.finished_retention();
+            commands::status(&args.squeue_args, finished, format);
+            return Ok(());
+        }
+        Some(CliCommand::Jump { ref id }) => {
+            if let Err(e) = commands::jump(id) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(CliCommand::Man) => {
+            return clap_mangen::Man::new(Cli::command()).render(&mut io::stdout());
+        }
+        Some(CliCommand::Completion { shell }) => {
+            print_completion(shell, &mut Cli::command());
+            return Ok(());
+        }
+        Some(CliCommand::Complete { kind }) => return print_candidates(kind),
+        Some(CliCommand::History {
+            ref query,
+            ref user,
+            days,
+            stats,
+        }) => {
+            let config = load_config(&args)?;
+            history::print(
+                &config.history,
+                query.as_deref(),
+                user.as_deref(),
+                days,
+                stats,
+            )?;
+            return Ok(());
+        }
+        Some(CliCommand::Whose { path, since }) => {
+            if !whose::whose(&path, &args.squeue_args, Duration::from_secs(since * 3600)) {
+                eprintln!("No job found for {}", path.display());
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+    }
+
+    let config = load_config(&args)?;
+    dispatcher::configure(config.slurm_max_concurrent, config.slurm_min_interval());
+    states::configure(&config.states);
+    let state = State::load()?;
+    if let Some(host) = remote::host() {
+        remote::check_connection(host)?;
+    }
+    let capabilities = Capabilities::detect();
+    let frame_dumper = args
+        .dump_frames
+        .as_deref()
+        .map(FrameDumper::new)
+        .transpose()?;
+
+    // setup terminal
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+    run_app(
+        &mut terminal,
+        args,
+        config,
+        state,
+        capabilities,
+        frame_dumper,
+    )?;
+
+    // restore terminal
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+
+    Ok(())
+}
+
+fn load_config(args: &Cli) -> io::Result<Config> {
+    Config::load(args.config.as_deref())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+fn input_loop(tx: Sender<std::io::Result<Event>>) {
+    loop {
+        if job_commands::SUSPENDED.load(Ordering::SeqCst) {
+            thread::sleep(job_commands::INPUT_POLL);
+            continue;
+        }
+        match event::poll(job_commands::INPUT_POLL) {
+            Ok(false) => {}
+            Ok(true) => tx.send(event::read()).unwrap(),
+            Err(e) => tx.send(Err(e)).unwrap(),
+        }
+    }
+}
+
+fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    args: Cli,
+    config: Config,
+    state: State,
+    capabilities: Capabilities,
+    frame_dumper: Option<FrameDumper>,
+) -> io::Result<()> {
+    let (input_tx, input_rx) = unbounded();
+    let mut app = App::new(
+        input_rx,
+        args.slurm_refresh,
+        args.file_refresh,
+        args.squeue_args,
+        args.config,
+        config,
+        state,
+        capabilities,
+        args.admin,
+        args.jobs_file,
+        args.anonymize,
+        frame_dumper,
+    );
+    thread::spawn(move || input_loop(input_tx));
+    app.run(terminal)
+}
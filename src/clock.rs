@@ -0,0 +1,35 @@
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use chrono::{Local, NaiveDateTime};
+
+/// The time shown by the UI in deterministic mode (`--deterministic-ui`).
+static FROZEN: OnceLock<NaiveDateTime> = OnceLock::new();
+
+/// Freezes the clock of the UI, so that relative times, countdowns and ticking time columns
+/// render the same on every run, e.g. for recorded demos and golden-file tests.
+pub fn freeze(at: NaiveDateTime) {
+    let _ = FROZEN.set(at);
+}
+
+pub fn is_frozen() -> bool {
+    FROZEN.get().is_some()
+}
+
+/// The current local time, or the frozen time in deterministic mode.
+pub fn now() -> NaiveDateTime {
+    FROZEN
+        .get()
+        .copied()
+        .unwrap_or_else(|| Local::now().naive_local())
+}
+
+/// The time passed since the instant, which is always zero in deterministic mode.
+pub fn elapsed(since: Instant) -> Duration {
+    match is_frozen() {
+        true => Duration::ZERO,
+        false => since.elapsed(),
+    }
+}
@@ -0,0 +1,506 @@
+use std::{
+    env, fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    thread,
+    time::Duration,
+};
+
+use chrono::Local;
+use clap::ValueEnum;
+
+use crate::app::Job;
+use crate::duplicates;
+use crate::file_watcher::LogSettings;
+use crate::ipc;
+use crate::job_watcher::{list_jobs, list_running_jobs};
+use crate::log_source::{job_log_source, JobNode};
+use crate::remote::{self, slurm_command};
+use crate::sacctmgr;
+use crate::slurm_time::format_relative;
+use crate::squeue_args::SqueueArgs;
+use crate::sweep::{self, Parameter};
+
+/// How far back to look for a finished job when it is referenced by id.
+const FINISHED_LOOKBACK: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// Prints the jobs matching `squeue_args` as tab-separated lines,
+/// including jobs that finished within `finished` if given.
+pub fn list(squeue_args: &SqueueArgs, finished: Option<Duration>) {
+    let jobs = match finished {
+        Some(since) => list_jobs(&squeue_args.to_vec(), &squeue_args.to_sacct_vec(), since),
+        None => list_running_jobs(&squeue_args.to_vec()),
+    };
+    for job in jobs {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            job.id(),
+            job.state,
+            job.user,
+            job.partition,
+            job.time,
+            job.name
+        );
+    }
+}
+
+fn find_job(id: &str) -> Option<Job> {
+    let args = [format!("--jobs={}", id)];
+    list_jobs(&args, &args, FINISHED_LOOKBACK)
+        .into_iter()
+        .find(|j| j.id() == id || j.job_id == id)
+}
+
+/// Prints the last `lines` lines of the job's stdout (or stderr) and, if `follow` is set,
+/// keeps printing whatever is appended, checking every `interval`.
+pub fn tail(
+    id: &str,
+    stderr: bool,
+    lines: usize,
+    follow: bool,
+    interval: Duration,
+    settings: LogSettings,
+) -> io::Result<()> {
+    let job = find_job(id)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("job {} not found", id)))?;
+    let node = JobNode::of(&job);
+    let path: PathBuf = if stderr { job.stderr } else { job.stdout }.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("job {} has no output file", id),
+        )
+    })?;
+
+    let mut source = job_log_source(settings.throttle, &settings.node_local_prefixes, node);
+    let content = source.read_from(&path, 0)?;
+    let mut pos = content.len() as u64;
+    let mut decoder = settings
+        .encoding
+        .detect(&content)
+        .new_decoder_without_bom_handling();
+    let mut decode = |bytes: &[u8]| {
+        let mut text = String::with_capacity(decoder.max_utf8_buffer_length(bytes.len()).unwrap());
+        let _ = decoder.decode_to_string(bytes, &mut text, false);
+        text
+    };
+    let content = decode(&content);
+    let last_lines = content.lines().collect::<Vec<_>>();
+    let mut stdout = io::stdout();
+    for line in &last_lines[last_lines.len().saturating_sub(lines)..] {
+        writeln!(stdout, "{}", line)?;
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    loop {
+        thread::sleep(interval);
+        let new = source.read_from(&path, pos)?;
+        pos += new.len() as u64;
+        stdout.write_all(decode(&new).as_bytes())?;
+        stdout.flush()?;
+    }
+}
+
+/// Selects the job in a running instance and, if both run in tmux, switches to its pane.
+pub fn jump(id: &str) -> io::Result<()> {
+    ipc::call("select", serde_json::json!({ "id": id }))?;
+    if std::env::var_os("TMUX").is_none() {
+        return Ok(());
+    }
+    let info = ipc::call("info", serde_json::Value::Null)?;
+    if let Some(pane) = info["tmux_pane"].as_str() {
+        for command in ["select-window", "select-pane"] {
+            Command::new("tmux").args([command, "-t", pane]).status()?;
+        }
+    }
+    Ok(())
+}
+
+/// Output formats of `turm status`.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StatusFormat {
+    /// One line of text.
+    Text,
+    /// JSON for a waybar custom module.
+    Waybar,
+    /// Full text, short text and color lines for i3blocks.
+    I3blocks,
+}
+
+/// The most recent submit, start or end of one of the jobs, e.g. `1234 train failed 5m ago`.
+fn most_recent_event(jobs: &[Job], now: chrono::NaiveDateTime) -> Option<String> {
+    jobs.iter()
+        .flat_map(|j| {
+            let end = j.end_time.filter(|_| j.is_finished()).map(|t| {
+                let state = j.state.split_whitespace().next().unwrap_or_default();
+                (t, state.to_lowercase())
+            });
+            [
+                j.submit_time.map(|t| (t, "submitted".to_string())),
+                j.start_time.map(|t| (t, "started".to_string())),
+                end,
+            ]
+            .into_iter()
+            .flatten()
+            .filter(|(t, _)| *t <= now)
+            .map(move |(t, label)| (t, j, label))
+        })
+        .max_by_key(|(t, _, _)| *t)
+        .map(|(t, j, label)| {
+            format!(
+                "{} {} {} {}",
+                j.id(),
+                j.name,
+                label,
+                format_relative(t, now)
+            )
+        })
+}
+
+/// Prints a one-line summary of the jobs for desktop status bars: the number of running,
+/// pending and (within `finished`) failed jobs, and the most recent event.
+pub fn status(squeue_args: &SqueueArgs, finished: Duration, format: StatusFormat) {
+    let jobs = list_jobs(&squeue_args.to_vec(), &squeue_args.to_sacct_vec(), finished);
+    let count = |states: &[&str]| {
+        jobs.iter()
+            .filter(|j| states.contains(&j.state.split_whitespace().next().unwrap_or_default()))
+            .count()
+    };
+    let running = count(&["RUNNING", "COMPLETING"]);
+    let pending = count(&["PENDING"]);
+    let failed = count(&["FAILED", "TIMEOUT", "OUT_OF_MEMORY", "NODE_FAIL"]);
+    let text = [("R", running), ("PD", pending), ("F", failed)]
+        .iter()
+        .filter(|(_, n)| *n > 0)
+        .map(|(label, n)| format!("{} {}", label, n))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let text = if text.is_empty() {
+        "no jobs".to_string()
+    } else {
+        text
+    };
+    let event = most_recent_event(&jobs, Local::now().naive_local()).unwrap_or_default();
+    let class = if failed > 0 {
+        "failed"
+    } else if running > 0 {
+        "running"
+    } else if pending > 0 {
+        "pending"
+    } else {
+        "idle"
+    };
+    match format {
+        StatusFormat::Text if event.is_empty() => println!("{}", text),
+        StatusFormat::Text => println!("{} | {}", text, event),
+        StatusFormat::Waybar => println!(
+            "{}",
+            serde_json::json!({ "text": text, "tooltip": event, "class": class, "alt": class })
+        ),
+        StatusFormat::I3blocks => {
+            println!("{}", text);
+            println!("{}", text);
+            match class {
+                "failed" => println!("#ff5555"),
+                "running" => println!("#50fa7b"),
+                "pending" => println!("#f1fa8c"),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Cancels the given jobs with `scancel`.
+pub fn cancel(ids: &[String]) -> io::Result<ExitStatus> {
+    slurm_command("scancel").args(ids).status()
+}
+
+/// Submits a batch script with `sbatch` and prints the id of the new job.
+pub fn submit(sbatch_args: &[String]) -> io::Result<ExitStatus> {
+    let output = slurm_command("sbatch")
+        .arg("--parsable")
+        .args(sbatch_args)
+        .output()?;
+    io::stderr().write_all(&output.stderr)?;
+    if output.status.success() {
+        // `--parsable` prints `jobid[;cluster]`
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let id = stdout.trim().split(';').next().unwrap_or_default();
+        println!("{}", id);
+    }
+    Ok(output.status)
+}
+
+/// My active jobs that were submitted with the same batch script, arguments and working
+/// directory as the submission would be.
+pub fn duplicates(sbatch_args: &[String]) -> io::Result<Vec<String>> {
+    let Some(i) = script_position(sbatch_args) else {
+        return Ok(Vec::new());
+    };
+    let script_path = fs::canonicalize(&sbatch_args[i])?;
+    let script = fs::read_to_string(&script_path)?;
+    let work_dir = env::current_dir()?;
+    let submission = duplicates::fingerprint(
+        &format!("sbatch {}", sbatch_args.join(" ")),
+        &work_dir,
+        &script,
+    );
+    let mut ids = list_running_jobs(&[format!("--user={}", remote::current_user())])
+        .into_iter()
+        .filter(|j| j.state == "PENDING" || j.state == "RUNNING")
+        .filter(|j| Path::new(&j.command) == script_path)
+        .filter(|j| j.work_dir.as_deref() == Some(work_dir.as_path()))
+        .map(|j| j.array_id)
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    ids.retain(|id| duplicates::job_fingerprint(id, &work_dir, true) == Some(submission));
+    Ok(ids)
+}
+
+/// Asks whether to submit although the jobs look like the same submission. Without a
+/// terminal to ask on, the submission goes ahead with a warning.
+pub fn confirm_duplicate(ids: &[String]) -> io::Result<bool> {
+    eprint!(
+        "Already submitted with the same script and arguments: {}",
+        ids.join(", ")
+    );
+    if !io::stdin().is_terminal() {
+        eprintln!();
+        return Ok(true);
+    }
+    eprint!(". Submit anyway? [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// The options of the `#SBATCH` lines of a batch script. Like sbatch, reading stops at the
+/// first line that is neither blank nor a comment.
+fn sbatch_directives(script: &str) -> Vec<String> {
+    let mut options = Vec::new();
+    for line in script.lines().map(str::trim) {
+        if let Some(directive) = line.strip_prefix("#SBATCH") {
+            // `#SBATCH --time=1:00:00 # a comment`
+            let directive = directive.split(" #").next().unwrap_or_default();
+            options.extend(directive.split_whitespace().map(str::to_string));
+        } else if !line.is_empty() && !line.starts_with('#') {
+            break;
+        }
+    }
+    options
+}
+
+/// Whether the sbatch arguments or the `#SBATCH` lines of the script choose an account.
+fn has_account(sbatch_args: &[String]) -> bool {
+    let script = script_position(sbatch_args);
+    let options = sbatch_args[..script.unwrap_or(sbatch_args.len())]
+        .iter()
+        .cloned()
+        .chain(
+            script
+                .and_then(|i| fs::read_to_string(&sbatch_args[i]).ok())
+                .map(|s| sbatch_directives(&s))
+                .unwrap_or_default(),
+        );
+    options
+        .into_iter()
+        .any(|o| o.starts_with("-A") || o == "--account" || o.starts_with("--account="))
+}
+
+/// Asks which of my accounts to charge the job to, if I have several and neither the submission
+/// nor `$SBATCH_ACCOUNT` chooses one. Returns the chosen account, or `None` for the default
+/// one. Without a terminal to ask on, or if the associations are not known, the default
+/// account is used.
+pub fn pick_account(sbatch_args: &[String]) -> io::Result<Option<String>> {
+    if !io::stdin().is_terminal()
+        || std::env::var_os("SBATCH_ACCOUNT").is_some_and(|a| !a.is_empty())
+        || has_account(sbatch_args)
+    {
+        return Ok(None);
+    }
+    let Ok(associations) = sacctmgr::user_associations() else {
+        return Ok(None);
+    };
+    let mut accounts: Vec<(&str, Vec<&sacctmgr::Association>)> = Vec::new();
+    for association in &associations {
+        match accounts.iter_mut().find(|(a, _)| *a == association.account) {
+            Some((_, list)) => list.push(association),
+            None => accounts.push((&association.account, vec![association])),
+        }
+    }
+    if accounts.len() < 2 {
+        return Ok(None);
+    }
+    let default = sacctmgr::default_account().unwrap_or_default();
+    eprintln!("Accounts:");
+    for (i, (account, list)) in accounts.iter().enumerate() {
+        let mut partitions = list
+            .iter()
+            .map(|a| {
+                Some(a.partition.as_str())
+                    .filter(|p| !p.is_empty())
+                    .unwrap_or("all")
+            })
+            .collect::<Vec<_>>();
+        partitions.dedup();
+        let qos = list.first().map_or("", |a| a.qos.as_str());
+        let limits = list
+            .iter()
+            .map(|a| a.grp_tres.as_str())
+            .find(|t| !t.is_empty())
+            .unwrap_or("none");
+        eprintln!(
+            "{:>3}) {}{}  partitions {}  QOS {}  limits {}",
+            i + 1,
+            account,
+            if *account == default {
+                " (default)"
+            } else {
+                ""
+            },
+            partitions.join(","),
+            qos,
+            limits
+        );
+    }
+    loop {
+        eprint!("Account [1-{}, enter for the default]: ", accounts.len());
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(None);
+        }
+        let chosen = match answer.parse::<usize>() {
+            Ok(i) => accounts.get(i.wrapping_sub(1)).map(|(a, _)| *a),
+            Err(_) => accounts.iter().map(|(a, _)| *a).find(|a| *a == answer),
+        };
+        match chosen {
+            Some(account) => return Ok(Some(account.to_string())),
+            None => eprintln!("No such account: {}", answer),
+        }
+    }
+}
+
+/// The batch script among the sbatch arguments: the first one that is a file starting with
+/// `#!`, options are not checked for values.
+fn script_position(sbatch_args: &[String]) -> Option<usize> {
+    sbatch_args
+        .iter()
+        .position(|a| fs::read_to_string(a).is_ok_and(|s| s.starts_with("#!")))
+}
+
+/// Checks a submission with `sbatch --test-only` without submitting it: prints the options
+/// of the batch script and of the command line, and sbatch's estimate of when and where the
+/// job would start. Errors, e.g. in `#SBATCH` lines, are printed by sbatch.
+pub fn validate(sbatch_args: &[String]) -> io::Result<ExitStatus> {
+    if let Some(i) = script_position(sbatch_args) {
+        let directives = sbatch_directives(&fs::read_to_string(&sbatch_args[i])?);
+        println!("#SBATCH      {}", directives.join(" "));
+        println!("Command line {}", sbatch_args[..i].join(" "));
+    }
+    let output = slurm_command("sbatch")
+        .arg("--test-only")
+        .args(sbatch_args)
+        .output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for line in stderr.lines() {
+        match line.strip_prefix("sbatch: ") {
+            Some(estimate) if output.status.success() && estimate.contains(" to start at ") => {
+                println!("Estimate     {}", estimate)
+            }
+            _ => eprintln!("{}", line),
+        }
+    }
+    Ok(output.status)
+}
+
+/// Submits the batch script once per combination of the parameters, or as one array job
+/// with a task per combination, with the `{{name}}` placeholders in the script (and the
+/// sbatch options) replaced. With `dry_run`, prints the sbatch calls and scripts instead.
+pub fn submit_sweep(
+    parameters: &[Parameter],
+    sbatch_args: &[String],
+    as_array: bool,
+    dry_run: bool,
+) -> io::Result<ExitStatus> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let i = script_position(sbatch_args)
+        .ok_or_else(|| invalid("No batch script (a file starting with #!) given".to_string()))?;
+    if i + 1 < sbatch_args.len() {
+        return Err(invalid(
+            "Arguments of the batch script are not supported in sweeps, use placeholders in the script instead".to_string(),
+        ));
+    }
+    let script = fs::read_to_string(&sbatch_args[i])?;
+    let options = &sbatch_args[..i];
+    let unknown =
+        sweep::unknown_placeholders(&format!("{} {}", script, options.join(" ")), parameters);
+    if !unknown.is_empty() {
+        return Err(invalid(format!(
+            "No values for the placeholders {}",
+            unknown.into_iter().collect::<Vec<_>>().join(", ")
+        )));
+    }
+
+    let combinations = sweep::combinations(parameters);
+    let submissions = if as_array {
+        if !sweep::unknown_placeholders(&options.join(" "), &[]).is_empty() {
+            return Err(invalid(
+                "Placeholders in sbatch options need one job per combination".to_string(),
+            ));
+        }
+        let mut options = options.to_vec();
+        options.push(format!("--array=0-{}", combinations.len() - 1));
+        vec![(
+            options,
+            sweep::array_script(&script, parameters).map_err(invalid)?,
+        )]
+    } else {
+        combinations
+            .iter()
+            .map(|c| {
+                let options = options.iter().map(|o| sweep::render(o, c)).collect();
+                (options, sweep::render(&script, c))
+            })
+            .collect()
+    };
+
+    if dry_run {
+        for (options, script) in &submissions {
+            println!("$ sbatch {} <<'EOF'", options.join(" "));
+            print!("{}", script);
+            if !script.ends_with('\n') {
+                println!();
+            }
+            println!("EOF");
+        }
+        return Ok(ExitStatus::default());
+    }
+    let mut status = ExitStatus::default();
+    for (options, script) in &submissions {
+        // sbatch reads the script from stdin if none is given
+        let mut child = slurm_command("sbatch")
+            .arg("--parsable")
+            .args(options)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(script.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            println!("{}", stdout.trim().split(';').next().unwrap_or_default());
+        } else {
+            status = output.status;
+        }
+    }
+    Ok(status)
+}
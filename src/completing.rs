@@ -0,0 +1,107 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+use crate::app::Job;
+use crate::hostlist;
+use crate::slurm_time::format_duration;
+
+/// Flags jobs that stay in COMPLETING for long, which usually means a hung epilog or an
+/// unresponsive filesystem on one of their nodes that only an admin can fix.
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompletingConfig {
+    pub enabled: bool,
+    /// How long a job may be completing before it counts as stuck.
+    pub minutes: u64,
+    /// Message for the admins, with `{job_id}`, `{name}`, `{user}`, `{partition}`, `{nodes}`,
+    /// `{since}` and `{duration}`.
+    pub template: String,
+}
+
+impl Default for CompletingConfig {
+    fn default() -> Self {
+        CompletingConfig {
+            enabled: true,
+            minutes: 15,
+            template: "Subject: Job {job_id} stuck in COMPLETING on {nodes}\n\n\
+                Hi,\n\n\
+                job {job_id} ({name}) of {user} in partition {partition} has been completing \
+                for {duration}, since {since}, on {nodes}. This usually means a hung epilog or \
+                an unresponsive filesystem on one of these nodes. Could you have a look, and \
+                drain the nodes if needed?\n\n\
+                Thanks"
+                .to_string(),
+        }
+    }
+}
+
+/// Since when the jobs are completing. Slurm reports the time a job ended as its end time,
+/// so this survives restarts of turm; jobs without it count from when they were first seen.
+#[derive(Default)]
+pub struct CompletingTracker {
+    since: HashMap<String, NaiveDateTime>,
+}
+
+fn is_completing(job: &Job) -> bool {
+    job.state.split_whitespace().next() == Some("COMPLETING")
+}
+
+impl CompletingTracker {
+    /// Records the jobs that started completing and forgets the others.
+    pub fn observe(&mut self, jobs: &[Job], now: NaiveDateTime) {
+        for job in jobs.iter().filter(|j| is_completing(j)) {
+            self.since
+                .entry(job.id())
+                .or_insert(job.end_time.filter(|t| *t <= now).unwrap_or(now));
+        }
+        let ids: HashSet<_> = jobs
+            .iter()
+            .filter(|j| is_completing(j))
+            .map(|j| j.id())
+            .collect();
+        self.since.retain(|id, _| ids.contains(id));
+    }
+
+    pub fn since(&self, id: &str) -> Option<NaiveDateTime> {
+        self.since.get(id).copied()
+    }
+
+    pub fn is_stuck(&self, config: &CompletingConfig, id: &str, now: NaiveDateTime) -> bool {
+        config.enabled
+            && self
+                .since(id)
+                .is_some_and(|t| now - t > chrono::Duration::minutes(config.minutes as i64))
+    }
+
+    /// The jobs that are completing for longer than allowed.
+    pub fn stuck(&self, config: &CompletingConfig, now: NaiveDateTime) -> Vec<String> {
+        let mut ids = self
+            .since
+            .keys()
+            .filter(|id| self.is_stuck(config, id, now))
+            .cloned()
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+}
+
+/// The message for the admins about a stuck job, from the configured template.
+pub fn escalation_message(
+    config: &CompletingConfig,
+    job: &Job,
+    since: NaiveDateTime,
+    now: NaiveDateTime,
+) -> String {
+    config
+        .template
+        .replace("{job_id}", &job.id())
+        .replace("{name}", &job.name)
+        .replace("{user}", &job.user)
+        .replace("{partition}", &job.partition)
+        .replace("{nodes}", &hostlist::expand(&job.nodelist).join(","))
+        .replace("{since}", &since.format("%Y-%m-%d %H:%M").to_string())
+        .replace("{duration}", &format_duration(now - since))
+}
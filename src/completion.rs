@@ -0,0 +1,94 @@
+use std::{collections::BTreeSet, io};
+
+use clap::ValueEnum;
+use clap_complete::{generate, Shell};
+
+use crate::remote::slurm_command;
+
+/// Values that are completed dynamically by calling `turm complete-values <kind>`.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Candidates {
+    /// Partition names, via `sinfo`.
+    Partitions,
+    /// Ids of my active jobs, via `squeue`.
+    Jobs,
+}
+
+/// Options whose values are completed dynamically.
+const DYNAMIC_OPTIONS: [(&str, &str, &str); 2] =
+    [("-p", "--partition", "partitions"), ("-j", "--job", "jobs")];
+
+/// Writes the completion script for `shell` to stdout, with dynamic completion
+/// of partition names and job ids added for bash, zsh and fish.
+pub fn print_completion(shell: Shell, cmd: &mut clap::Command) {
+    let name = cmd.get_name().to_string();
+    generate(shell, cmd, &name, &mut io::stdout());
+
+    match shell {
+        Shell::Bash => {
+            println!("_{name}_dynamic() {{");
+            println!("    local prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"");
+            println!("    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"");
+            println!("    case \"$prev\" in");
+            for (short, long, kind) in DYNAMIC_OPTIONS {
+                println!("        {short}|{long})");
+                println!(
+                    "            COMPREPLY=($(compgen -W \"$({name} complete-values {kind} 2>/dev/null)\" -- \"$cur\"))"
+                );
+                println!("            return 0 ;;");
+            }
+            println!("    esac");
+            println!("    _{name} \"$@\"");
+            println!("}}");
+            println!("complete -F _{name}_dynamic -o nosort -o bashdefault -o default {name}");
+        }
+        Shell::Zsh => {
+            println!("_{name}_dynamic() {{");
+            println!("    case \"${{words[CURRENT-1]}}\" in");
+            for (short, long, kind) in DYNAMIC_OPTIONS {
+                println!(
+                    "        {short}|{long}) compadd -- ${{(f)\"$({name} complete-values {kind} 2>/dev/null)\"}} ;;"
+                );
+            }
+            println!("        *) _{name} \"$@\" ;;");
+            println!("    esac");
+            println!("}}");
+            println!("compdef _{name}_dynamic {name}");
+        }
+        Shell::Fish => {
+            for (short, long, kind) in DYNAMIC_OPTIONS {
+                println!(
+                    "complete -c {name} -s {} -l {} -x -a \"({name} complete-values {kind} 2>/dev/null)\"",
+                    &short[1..],
+                    &long[2..]
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Prints the completion candidates of the given kind, one per line.
+pub fn print_candidates(kind: Candidates) -> io::Result<()> {
+    let output = match kind {
+        Candidates::Partitions => slurm_command("sinfo")
+            .arg("--noheader")
+            .arg("--format=%R")
+            .output()?,
+        Candidates::Jobs => slurm_command("squeue")
+            .arg("--me")
+            .arg("--array")
+            .arg("--noheader")
+            .arg("--format=%i")
+            .output()?,
+    };
+    let candidates: BTreeSet<_> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect();
+    for c in candidates {
+        println!("{}", c);
+    }
+    Ok(())
+}
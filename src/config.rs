@@ -0,0 +1,449 @@
+use std::{
+    borrow::Cow,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use encoding_rs::Encoding;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+use crate::archive::ArchiveConfig;
+use crate::budget::Budget;
+use crate::completing::CompletingConfig;
+use crate::email::EmailConfig;
+use crate::file_watcher::LogSettings;
+use crate::history::HistoryConfig;
+use crate::hooks::Hook;
+use crate::idle::IdleConfig;
+use crate::job_commands::JobCommand;
+use crate::links;
+use crate::log_source::Throttle;
+use crate::node_health::NodeHealthConfig;
+use crate::pipelines::Pipeline;
+use crate::plugins::PluginConfig;
+use crate::requeue::RequeueStormConfig;
+use crate::scripting::{deserialize_optional_script, Script, ScriptColumn};
+use crate::states::StateConfig;
+
+/// User configuration, read from `$XDG_CONFIG_HOME/turm/config.toml` (or `--config`).
+/// Every field is optional.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Transforms applied, in order, to job names in the job list.
+    pub name_transforms: Vec<NameTransform>,
+    /// Rules that group related jobs by their name, independent of Slurm arrays.
+    pub groups: Vec<GroupRule>,
+    /// How long finished jobs stay in the job list after they ended.
+    pub finished_retention_minutes: u64,
+    /// Refresh rate for the job watcher, unless overridden by `--slurm-refresh`.
+    pub slurm_refresh_seconds: u64,
+    /// How many Slurm queries may run at the same time.
+    pub slurm_max_concurrent: usize,
+    /// Minimum time between the starts of Slurm queries, plus up to as much again of jitter.
+    pub slurm_min_interval_ms: u64,
+    /// Columns of the job list, in order. The job name is always shown.
+    pub columns: Vec<Column>,
+    /// Only show my jobs on startup, like `--me`.
+    pub only_mine: bool,
+    /// Hide finished jobs on startup.
+    pub hide_finished: bool,
+    /// Color the users and job names by user when the job list shows several users.
+    pub user_colors: bool,
+    /// Show the queue depth sparklines of all jobs in the scope, not only of mine.
+    pub sparkline_all_jobs: bool,
+    /// Size of the chunks log files are read in over SFTP in remote mode.
+    pub log_chunk_kib: u32,
+    /// Caps the bandwidth used for reading log files in remote mode. Unlimited by default.
+    pub log_max_kib_per_second: Option<u64>,
+    /// Encoding of the log files: `"auto"` (UTF-8, or Latin-1 if that fails) or any label
+    /// from the WHATWG Encoding Standard, e.g. `"latin1"` or `"shift_jis"`.
+    #[serde(deserialize_with = "deserialize_encoding")]
+    pub log_encoding: LogEncoding,
+    /// Directories on the nodes' local storage. Log files there that are missing on the login
+    /// node are read on the first node of the running job with `srun --overlap`.
+    pub node_local_prefixes: Vec<String>,
+    /// File with a cluster-wide announcement, e.g. about upcoming maintenance, shown as a
+    /// banner while it is not empty. Read from the cluster in remote mode.
+    pub announcement_file: Option<PathBuf>,
+    /// When actions such as cancelling a job have to be confirmed.
+    pub confirm: ConfirmPolicies,
+    /// Shell commands run before or after actions.
+    pub hooks: Vec<Hook>,
+    /// Commands for the selected job in the command palette.
+    pub job_commands: Vec<JobCommand>,
+    /// Command for following the output of the selected job in a new tmux pane, like a job
+    /// command.
+    pub tmux_pane_command: Option<String>,
+    /// Email notifications about state changes of my jobs.
+    pub email: Option<EmailConfig>,
+    /// Columns of the job list computed by Rhai scripts, after the built-in ones.
+    pub script_columns: Vec<ScriptColumn>,
+    /// Rhai script that decides per job whether it is shown.
+    #[serde(deserialize_with = "deserialize_optional_script")]
+    pub script_filter: Option<Script>,
+    /// External programs that contribute job annotations and panels.
+    pub plugins: Vec<PluginConfig>,
+    /// Links to experiment tracker runs found in job logs, by default those of Weights & Biases
+    /// and MLflow.
+    #[serde(deserialize_with = "deserialize_regexes")]
+    pub run_link_patterns: Vec<Regex>,
+    /// Metrics logged as `name=value` or `name: value` that are plotted for the selected job.
+    pub metrics: Vec<String>,
+    /// Metric the other metrics are plotted over, e.g. the training step.
+    pub metric_step: Option<String>,
+    /// Regex capturing the current and total progress of a job in its log, e.g.
+    /// `epoch (\d+)/(\d+)`, for progress bars in the job list.
+    #[serde(deserialize_with = "deserialize_optional_regex")]
+    pub progress_pattern: Option<Regex>,
+    /// Whether the usage of the selected job includes the Lustre and NFS client counters of its
+    /// nodes, read in an overlapping job step.
+    pub fs_client_stats: bool,
+    /// Command reporting the health of allocated nodes in the node view.
+    pub node_health: Option<NodeHealthConfig>,
+    /// Shell command with `{node}` replaced by the node name, run locally, that prints the load
+    /// average and free memory of a node (e.g. `3.5 12G`), instead of `scontrol show node`.
+    pub node_load_command: Option<String>,
+    /// Whether the node heatmap is shown on startup (toggled with `D`).
+    pub node_heatmap: bool,
+    /// Named sets of batch scripts submitted together with dependencies between them.
+    pub pipelines: Vec<Pipeline>,
+    /// Copies the logs of my jobs into an archive directory when they finish.
+    pub archive: Option<ArchiveConfig>,
+    /// The local history of finished jobs.
+    pub history: HistoryConfig,
+    /// Whether the logs searched with `K` are indexed by their trigrams, which makes searches
+    /// faster at the cost of an index file in the state directory.
+    pub log_index: bool,
+    /// When my running jobs are flagged as likely hung.
+    pub idle: IdleConfig,
+    /// When restarting jobs count as flapping.
+    pub requeue_storm: RequeueStormConfig,
+    /// When jobs count as stuck in COMPLETING, and the message for the admins about them.
+    pub completing: CompletingConfig,
+    /// Allocation budgets of accounts, shown with their consumption in the summary line.
+    pub budgets: Vec<Budget>,
+    /// Usernames of my teammates, whose jobs the team view shows grouped by user.
+    pub team: Vec<String>,
+    /// Compact forms, sort order and colors of job states, added to or overriding the
+    /// built-in ones.
+    pub states: Vec<StateConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            name_transforms: Vec::new(),
+            groups: Vec::new(),
+            finished_retention_minutes: 60,
+            slurm_refresh_seconds: 2,
+            slurm_max_concurrent: 2,
+            slurm_min_interval_ms: 250,
+            columns: Column::ALL.to_vec(),
+            only_mine: false,
+            hide_finished: false,
+            user_colors: true,
+            sparkline_all_jobs: false,
+            log_chunk_kib: 32,
+            log_max_kib_per_second: None,
+            log_encoding: LogEncoding::Fixed(encoding_rs::UTF_8),
+            node_local_prefixes: ["/tmp", "/dev/shm", "/scratch-local", "/local"]
+                .map(String::from)
+                .to_vec(),
+            announcement_file: None,
+            confirm: ConfirmPolicies::default(),
+            hooks: Vec::new(),
+            job_commands: Vec::new(),
+            tmux_pane_command: None,
+            email: None,
+            script_columns: Vec::new(),
+            script_filter: None,
+            plugins: Vec::new(),
+            run_link_patterns: links::default_patterns(),
+            metrics: vec!["loss".to_string()],
+            metric_step: Some("step".to_string()),
+            progress_pattern: None,
+            fs_client_stats: false,
+            node_health: None,
+            node_load_command: None,
+            node_heatmap: false,
+            pipelines: Vec::new(),
+            archive: None,
+            history: HistoryConfig::default(),
+            log_index: false,
+            idle: IdleConfig::default(),
+            requeue_storm: RequeueStormConfig::default(),
+            completing: CompletingConfig::default(),
+            budgets: Vec::new(),
+            team: Vec::new(),
+            states: Vec::new(),
+        }
+    }
+}
+
+/// Whether an action has to be confirmed.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmPolicy {
+    Always,
+    Never,
+    /// Only if the action affects other users' jobs, e.g. as an account coordinator.
+    Others,
+}
+
+/// Confirmation policies per class of actions.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfirmPolicies {
+    /// Cancelling jobs.
+    pub cancel: ConfirmPolicy,
+    /// Changing jobs, e.g. renaming them.
+    pub update: ConfirmPolicy,
+    /// Admin actions on nodes, which always affect others.
+    pub node: ConfirmPolicy,
+}
+
+impl Default for ConfirmPolicies {
+    fn default() -> Self {
+        ConfirmPolicies {
+            cancel: ConfirmPolicy::Always,
+            update: ConfirmPolicy::Never,
+            node: ConfirmPolicy::Always,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    State,
+    Id,
+    Qos,
+    User,
+    TimeUsed,
+    TimeLeft,
+    /// What happens to running jobs that may be preempted.
+    Preempt,
+}
+
+impl Column {
+    pub const ALL: [Column; 7] = [
+        Column::State,
+        Column::Id,
+        Column::Qos,
+        Column::User,
+        Column::TimeUsed,
+        Column::TimeLeft,
+        Column::Preempt,
+    ];
+
+    /// The name used in the config file.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Column::State => "state",
+            Column::Id => "id",
+            Column::Qos => "qos",
+            Column::User => "user",
+            Column::TimeUsed => "time_used",
+            Column::TimeLeft => "time_left",
+            Column::Preempt => "preempt",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum LogEncoding {
+    Auto,
+    Fixed(&'static Encoding),
+}
+
+impl LogEncoding {
+    /// The encoding to decode a file with, given its first bytes.
+    pub fn detect(&self, sample: &[u8]) -> &'static Encoding {
+        match self {
+            LogEncoding::Fixed(encoding) => encoding,
+            // an incomplete character at the end is fine, it is completed by the next read
+            LogEncoding::Auto => match std::str::from_utf8(sample) {
+                Err(e) if e.error_len().is_some() => encoding_rs::WINDOWS_1252,
+                _ => encoding_rs::UTF_8,
+            },
+        }
+    }
+}
+
+/// Groups all jobs whose name matches `regex`, e.g. `{ regex = '^(sweep\d+)-' }`.
+/// The group is named by expanding `name` with the captures, which defaults to the first
+/// capture group (or the whole match if there are none).
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupRule {
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub regex: Regex,
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum NameTransform {
+    /// Remove a fixed prefix, e.g. `{ strip_prefix = "sweep-" }`.
+    StripPrefix { strip_prefix: String },
+    /// Replace regex matches, e.g. `{ regex = ".*exp(\\d+).*", replace = "exp$1" }`.
+    /// `replace` defaults to `$1`, i.e. extracting the first capture group.
+    Regex {
+        #[serde(deserialize_with = "deserialize_regex")]
+        regex: Regex,
+        #[serde(default = "default_replace")]
+        replace: String,
+    },
+}
+
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read(p, e) => write!(f, "Cannot read {}: {}", p.display(), e),
+            ConfigError::Parse(p, e) => write!(f, "Invalid config {}: {}", p.display(), e),
+        }
+    }
+}
+
+impl ConfigError {
+    /// A single-line description of the error, for the status bar.
+    pub fn summary(&self) -> String {
+        match self {
+            ConfigError::Parse(p, e) => {
+                format!("Invalid config {}: {}", p.display(), e.message().trim())
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("turm").join("config.toml"))
+    }
+
+    /// Loads the config from `path`. A missing file is only an error if it was given explicitly.
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        let (path, explicit) = match path {
+            Some(p) => (p.to_path_buf(), true),
+            None => match Self::default_path() {
+                Some(p) => (p, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !explicit => {
+                return Ok(Config::default())
+            }
+            Err(e) => return Err(ConfigError::Read(path, e)),
+        };
+        toml::from_str(&content).map_err(|e| ConfigError::Parse(path, e))
+    }
+
+    /// Sets `key` to `value` in the config file at `path`, keeping everything else
+    /// (including comments) as it is.
+    pub fn write_value(path: &Path, key: &str, value: toml_edit::Value) -> io::Result<()> {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let mut doc = content
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        doc[key] = toml_edit::Item::Value(value);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, doc.to_string())
+    }
+
+    pub fn slurm_min_interval(&self) -> Duration {
+        Duration::from_millis(self.slurm_min_interval_ms)
+    }
+
+    pub fn finished_retention(&self) -> Duration {
+        Duration::from_secs(self.finished_retention_minutes * 60)
+    }
+
+    pub fn slurm_refresh(&self) -> Duration {
+        Duration::from_secs(self.slurm_refresh_seconds)
+    }
+
+    pub fn log_throttle(&self) -> Throttle {
+        Throttle {
+            chunk_size: self.log_chunk_kib.saturating_mul(1024),
+            bytes_per_second: self.log_max_kib_per_second.map(|k| k.saturating_mul(1024)),
+        }
+    }
+
+    pub fn log_settings(&self) -> LogSettings {
+        LogSettings {
+            throttle: self.log_throttle(),
+            encoding: self.log_encoding,
+            node_local_prefixes: self.node_local_prefixes.clone(),
+        }
+    }
+
+    pub fn display_name<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        self.name_transforms
+            .iter()
+            .fold(Cow::Borrowed(name), |name, t| match t {
+                NameTransform::StripPrefix { strip_prefix } => {
+                    match name.strip_prefix(strip_prefix.as_str()) {
+                        Some(stripped) => Cow::Owned(stripped.to_owned()),
+                        None => name,
+                    }
+                }
+                NameTransform::Regex { regex, replace } => {
+                    match regex.replace_all(&name, replace.as_str()) {
+                        Cow::Borrowed(_) => name,
+                        Cow::Owned(replaced) => Cow::Owned(replaced),
+                    }
+                }
+            })
+    }
+}
+
+fn deserialize_encoding<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<LogEncoding, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    if s.eq_ignore_ascii_case("auto") {
+        return Ok(LogEncoding::Auto);
+    }
+    Encoding::for_label(s.as_bytes())
+        .map(LogEncoding::Fixed)
+        .ok_or_else(|| serde::de::Error::custom(format!("unknown encoding {}", s)))
+}
+
+fn default_replace() -> String {
+    "$1".to_string()
+}
+
+fn deserialize_optional_regex<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Regex>, D::Error> {
+    deserialize_regex(deserializer).map(Some)
+}
+
+fn deserialize_regexes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Regex>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|s| Regex::new(s).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+pub fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    Regex::new(&s).map_err(serde::de::Error::custom)
+}
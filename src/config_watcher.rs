@@ -0,0 +1,54 @@
+use std::{fs, path::PathBuf};
+
+use crossbeam::channel::Sender;
+use notify::{RecursiveMode, Watcher};
+
+use crate::app::AppMessage;
+use crate::config::Config;
+
+/// Reloads the config whenever the file changes and hands it to the app.
+/// The watch stops when the handle is dropped.
+pub struct ConfigWatcherHandle {
+    _watcher: Option<notify::RecommendedWatcher>,
+}
+
+impl ConfigWatcherHandle {
+    /// `cli_path` is the path given via `--config`, if any.
+    pub fn new(app: Sender<AppMessage>, cli_path: Option<PathBuf>) -> Self {
+        let Some(path) = cli_path.clone().or_else(Config::default_path) else {
+            return Self { _watcher: None };
+        };
+        let mut last_content = fs::read_to_string(&path).ok();
+        let file_name = path.file_name().map(|n| n.to_owned());
+        let dir = path.parent().unwrap_or(&path).to_path_buf();
+
+        // watch the directory, since editors often replace the file instead of writing to it
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+            if event.kind.is_access()
+                || !event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == file_name.as_deref())
+            {
+                return;
+            }
+            // editors emit several events per save, only reload when the content changed
+            let content = fs::read_to_string(&path).ok();
+            if content == last_content {
+                return;
+            }
+            last_content = content;
+            let _ = app.send(AppMessage::Config(
+                Config::load(cli_path.as_deref()).map(Box::new),
+            ));
+        })
+        .and_then(|mut w| w.watch(&dir, RecursiveMode::NonRecursive).map(|_| w));
+
+        Self {
+            _watcher: watcher.ok(),
+        }
+    }
+}
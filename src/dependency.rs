@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use crate::app::Job;
+
+/// The jobs by array id, to look up the jobs a dependency waits for without scanning all jobs
+/// for every dependency.
+pub struct JobIndex<'a>(HashMap<&'a str, Vec<&'a Job>>);
+
+impl<'a> JobIndex<'a> {
+    pub fn new(jobs: &'a [Job]) -> Self {
+        let mut index: HashMap<&str, Vec<&Job>> = HashMap::new();
+        for job in jobs {
+            index.entry(job.array_id.as_str()).or_default().push(job);
+        }
+        JobIndex(index)
+    }
+
+    /// The jobs an id in a dependency refers to: a job, all tasks of an array job or a single
+    /// task (`1234_5`).
+    fn get<'s>(&'s self, id: &'s str) -> impl Iterator<Item = &'a Job> + 's {
+        let (array_id, task) = match id.split_once('_') {
+            Some((array_id, task)) => (array_id, Some(task)),
+            None => (id, None),
+        };
+        self.0
+            .get(array_id)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(move |j| task.is_none_or(|t| j.array_step.as_deref() == Some(t)))
+    }
+}
+
+/// One condition of a dependency, e.g. `afterok:1234_*(failed)`.
+struct Condition<'a> {
+    kind: &'a str,
+    /// Ids of the jobs it waits for, without the `+time` of `after` or the `_*` of arrays.
+    ids: Vec<&'a str>,
+    /// The status Slurm reports in parentheses, e.g. `unfulfilled` or `failed`.
+    status: Option<&'a str>,
+}
+
+impl Condition<'_> {
+    /// Whether the condition can no longer be satisfied, going by the status Slurm reports or
+    /// by the final states of the jobs it waits for.
+    fn failed(&self, jobs: &JobIndex) -> bool {
+        if let Some(status) = self.status {
+            return status == "failed";
+        }
+        self.ids.iter().any(|id| {
+            // the tasks of an array job count individually
+            jobs.get(id).filter(|j| j.is_finished()).any(|j| {
+                let completed = j.state == "COMPLETED";
+                match self.kind {
+                    "afterok" | "aftercorr" => !completed,
+                    "afternotok" => completed,
+                    _ => false,
+                }
+            })
+        })
+    }
+}
+
+/// The conditions of a dependency and whether all of them (`,`) or any of them (`?`) have to
+/// be satisfied.
+fn parse(dependency: &str) -> (Vec<Condition<'_>>, bool) {
+    let any = dependency.contains('?');
+    let conditions = dependency
+        .split([',', '?'])
+        .filter(|c| !c.is_empty())
+        .map(|condition| {
+            let (condition, status) = match condition.split_once('(') {
+                Some((condition, status)) => (condition, Some(status.trim_end_matches(')'))),
+                None => (condition, None),
+            };
+            let (kind, ids) = condition.split_once(':').unwrap_or((condition, ""));
+            Condition {
+                kind,
+                ids: ids
+                    .split(':')
+                    .filter(|id| !id.is_empty())
+                    .map(|id| id.split('+').next().unwrap_or_default())
+                    .map(|id| id.strip_suffix("_*").unwrap_or(id))
+                    .collect(),
+                status,
+            }
+        })
+        .collect();
+    (conditions, any)
+}
+
+/// Whether the job is pending on a dependency that can never be satisfied, so that it would
+/// wait forever: Slurm says so, or a job it depends on ended the wrong way.
+pub fn never_satisfied(job: &Job, jobs: &JobIndex) -> bool {
+    if job.state != "PENDING" {
+        return false;
+    }
+    if job.reason.as_deref() == Some("DependencyNeverSatisfied") {
+        return true;
+    }
+    let Some(dependency) = &job.dependency else {
+        return false;
+    };
+    let (conditions, any) = parse(dependency);
+    match any {
+        true => !conditions.is_empty() && conditions.iter().all(|c| c.failed(jobs)),
+        false => conditions.iter().any(|c| c.failed(jobs)),
+    }
+}
+
+/// The dependency without the statuses Slurm adds, as it is passed to `scontrol update`.
+pub fn without_status(dependency: &str) -> String {
+    let mut result = String::new();
+    let mut in_status = false;
+    for c in dependency.chars() {
+        match c {
+            '(' => in_status = true,
+            ')' => in_status = false,
+            c if !in_status => result.push(c),
+            _ => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(id: &str, dependency: &str) -> Job {
+        let mut job = Job::fixture(id, "eval", "PENDING", "alice");
+        job.dependency = Some(dependency.to_string());
+        job.reason = Some("Dependency".to_string());
+        job
+    }
+
+    fn finished(id: &str, state: &str) -> Job {
+        Job::fixture(id, "train", state, "alice")
+    }
+
+    fn task(array_id: &str, step: &str, state: &str) -> Job {
+        let mut job = finished(&format!("{}{}", array_id, step), state);
+        job.array_id = array_id.to_string();
+        job.array_step = Some(step.to_string());
+        job
+    }
+
+    #[test]
+    fn conditions() {
+        let (conditions, any) = parse("afterok:1234_*(unfulfilled),afterany:99+10:100");
+        assert!(!any);
+        assert_eq!(conditions.len(), 2);
+        assert_eq!(
+            (
+                conditions[0].kind,
+                &conditions[0].ids[..],
+                conditions[0].status
+            ),
+            ("afterok", &["1234"][..], Some("unfulfilled"))
+        );
+        assert_eq!(
+            (
+                conditions[1].kind,
+                &conditions[1].ids[..],
+                conditions[1].status
+            ),
+            ("afterany", &["99", "100"][..], None)
+        );
+
+        let (conditions, any) = parse("afterok:1(failed)?afternotok:2");
+        assert!(any);
+        assert_eq!(conditions[0].status, Some("failed"));
+        assert_eq!(conditions[1].ids, ["2"]);
+
+        let (conditions, _) = parse("singleton");
+        assert_eq!(
+            (conditions[0].kind, conditions[0].ids.len()),
+            ("singleton", 0)
+        );
+        assert!(parse("").0.is_empty());
+    }
+
+    #[test]
+    fn statuses() {
+        assert_eq!(
+            without_status("afterok:1(failed),afterany:2_*(unfulfilled)"),
+            "afterok:1,afterany:2_*"
+        );
+        let jobs = [pending("2", "afterok:1(failed)")];
+        assert!(never_satisfied(&jobs[0], &JobIndex::new(&jobs)));
+        let jobs = [pending("2", "afterok:1(unfulfilled)")];
+        assert!(!never_satisfied(&jobs[0], &JobIndex::new(&jobs)));
+        let mut job = pending("2", "afterok:1");
+        job.reason = Some("DependencyNeverSatisfied".to_string());
+        assert!(never_satisfied(&job, &JobIndex::new(&[])));
+    }
+
+    #[test]
+    fn finished_dependencies() {
+        let jobs = [
+            finished("1", "COMPLETED"),
+            finished("2", "FAILED"),
+            finished("3", "RUNNING"),
+            pending("10", "afterok:1"),
+            pending("11", "afterok:2"),
+            pending("12", "afternotok:1"),
+            pending("13", "afternotok:2"),
+            pending("14", "afterany:2"),
+            pending("15", "afterok:3"),
+            pending("16", "afterok:1:2"),
+            pending("17", "afterok:2?afterok:1"),
+            pending("18", "afterok:2?afternotok:1"),
+            pending("19", "afterok:404"),
+        ];
+        let index = JobIndex::new(&jobs);
+        let dead = jobs
+            .iter()
+            .filter(|j| never_satisfied(j, &index))
+            .map(|j| j.job_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(dead, ["11", "12", "16", "18"]);
+
+        // a running job with a dependency is past it
+        let mut running = pending("20", "afterok:2");
+        running.state = "RUNNING".into();
+        assert!(!never_satisfied(&running, &index));
+    }
+
+    #[test]
+    fn array_dependencies() {
+        let jobs = [
+            task("100", "0", "COMPLETED"),
+            task("100", "1", "FAILED"),
+            task("200", "0", "COMPLETED"),
+            pending("10", "afterok:100_*"),
+            pending("11", "afterok:100_0"),
+            pending("12", "afterok:100_1"),
+            pending("13", "afterok:200"),
+        ];
+        let index = JobIndex::new(&jobs);
+        assert_eq!(index.get("100").count(), 2);
+        assert_eq!(index.get("100_1").count(), 1);
+        assert_eq!(index.get("100_7").count(), 0);
+        let dead = jobs
+            .iter()
+            .filter(|j| never_satisfied(j, &index))
+            .map(|j| j.job_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(dead, ["10", "12"]);
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    ffi::OsString,
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Slurm commands that query slurmctld or slurmdbd (`scontrol` also for its updates). Only a
+/// few of them run at a time and their starts are spaced out, while actions such as `scancel`
+/// and `sbatch` and job steps (`srun`) run right away.
+const QUERIES: [&str; 9] = [
+    "squeue", "sacct", "sstat", "scontrol", "sinfo", "sprio", "sacctmgr", "sreport", "sshare",
+];
+
+struct Dispatcher {
+    max_concurrent: usize,
+    min_interval: Duration,
+    running: usize,
+    next_start: Option<Instant>,
+}
+
+static DISPATCHER: Mutex<Dispatcher> = Mutex::new(Dispatcher {
+    max_concurrent: 2,
+    min_interval: Duration::from_millis(250),
+    running: 0,
+    next_start: None,
+});
+static CHANGED: Condvar = Condvar::new();
+
+/// Limits the Slurm queries that run at the same time and the rate they are started at, so
+/// that bursts of queries (e.g. prefetching details, triaging a large array) do not hammer
+/// slurmctld on shared login nodes.
+pub fn configure(max_concurrent: usize, min_interval: Duration) {
+    let mut dispatcher = DISPATCHER.lock().unwrap();
+    dispatcher.max_concurrent = max_concurrent.max(1);
+    dispatcher.min_interval = min_interval;
+    CHANGED.notify_all();
+}
+
+/// Up to as much again as the interval, so that the queries of several turm instances (or of
+/// several features) do not line up.
+fn jitter(interval: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    // spread the low-entropy clock over the whole range
+    let fraction = nanos.wrapping_mul(2654435761) as f64 / u32::MAX as f64;
+    interval.mul_f64(fraction)
+}
+
+/// A running Slurm query, which frees its slot when dropped.
+pub struct Permit(());
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        DISPATCHER.lock().unwrap().running -= 1;
+        CHANGED.notify_all();
+    }
+}
+
+/// Waits until the command may run, if it is a Slurm query. Asking for the usage or version
+/// does not contact Slurm and runs right away.
+pub fn acquire(program: &str, args: &[OsString]) -> Option<Permit> {
+    if !QUERIES.contains(&program)
+        || args
+            .iter()
+            .any(|a| a == "--help" || a == "--usage" || a == "--version")
+    {
+        return None;
+    }
+    let mut dispatcher = DISPATCHER.lock().unwrap();
+    loop {
+        let wait = dispatcher.next_start.map_or(Duration::ZERO, |t| {
+            t.saturating_duration_since(Instant::now())
+        });
+        if dispatcher.running < dispatcher.max_concurrent && wait.is_zero() {
+            break;
+        }
+        dispatcher = match wait.is_zero() {
+            true => CHANGED.wait(dispatcher).unwrap(),
+            false => CHANGED.wait_timeout(dispatcher, wait).unwrap().0,
+        };
+    }
+    dispatcher.running += 1;
+    let interval = dispatcher.min_interval;
+    dispatcher.next_start = Some(Instant::now() + interval + jitter(interval));
+    Some(Permit(()))
+}
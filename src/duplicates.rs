@@ -0,0 +1,155 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    thread,
+};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use crate::actions::Resubmission;
+use crate::app::{AppMessage, Job};
+use crate::remote::slurm_command;
+
+/// The batch script Slurm stored for an active job.
+pub fn batch_script(id: &str) -> Option<String> {
+    let output = slurm_command("scontrol")
+        .args(["write", "batch_script", id, "-"])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Identifies a submission by its arguments, working directory and batch script. The sbatch
+/// binary itself may be given with its path and is left out, as is the `--parsable` that
+/// `turm submit` adds.
+pub fn fingerprint(submit_line: &str, work_dir: &Path, script: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    submit_line
+        .split_whitespace()
+        .skip(1)
+        .filter(|arg| *arg != "--parsable")
+        .for_each(|arg| arg.hash(&mut hasher));
+    work_dir.hash(&mut hasher);
+    script.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The fingerprint of an active job, from its submit line and stored batch script.
+pub fn job_fingerprint(id: &str, work_dir: &Path, sacct: bool) -> Option<u64> {
+    let resubmission = Resubmission::capture(id, work_dir, sacct)?;
+    let script = batch_script(id)?;
+    Some(fingerprint(resubmission.submit_line(), work_dir, &script))
+}
+
+/// A submission that may be a duplicate: one of my active jobs, array jobs counted once.
+#[derive(Clone, PartialEq)]
+pub struct Candidate {
+    pub id: String,
+    pub command: String,
+    pub work_dir: PathBuf,
+}
+
+impl Candidate {
+    pub fn of(job: &Job) -> Option<Candidate> {
+        Some(Candidate {
+            id: job.array_id.clone(),
+            command: job.command.clone(),
+            work_dir: job.work_dir.clone()?,
+        })
+    }
+}
+
+/// Looks for my active jobs that were submitted with the same script and arguments in the
+/// background. Only jobs with the same command and working directory are compared, since the
+/// submit lines and scripts have to be fetched for each job.
+struct DuplicateWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<Vec<Candidate>>,
+    sacct: bool,
+    candidates: Vec<Candidate>,
+    /// Fingerprints by job id, which do not change while a job is active.
+    fingerprints: HashMap<String, Option<u64>>,
+}
+
+impl DuplicateWatcher {
+    fn duplicates(&mut self) -> Vec<Vec<String>> {
+        let mut by_submission = HashMap::<(&str, &Path), Vec<&str>>::new();
+        for candidate in &self.candidates {
+            let ids = by_submission
+                .entry((&candidate.command, &candidate.work_dir))
+                .or_default();
+            if !ids.contains(&candidate.id.as_str()) {
+                ids.push(&candidate.id);
+            }
+        }
+        let mut by_fingerprint = HashMap::<u64, Vec<String>>::new();
+        for ((_, work_dir), ids) in by_submission.into_iter().filter(|(_, ids)| ids.len() > 1) {
+            for id in ids {
+                let fingerprint = *self
+                    .fingerprints
+                    .entry(id.to_string())
+                    .or_insert_with(|| job_fingerprint(id, work_dir, self.sacct));
+                if let Some(fingerprint) = fingerprint {
+                    by_fingerprint
+                        .entry(fingerprint)
+                        .or_default()
+                        .push(id.to_string());
+                }
+            }
+        }
+        let mut duplicates = by_fingerprint
+            .into_values()
+            .filter(|ids| ids.len() > 1)
+            .collect::<Vec<_>>();
+        duplicates.iter_mut().for_each(|ids| ids.sort());
+        duplicates.sort();
+        duplicates
+    }
+
+    fn run(&mut self) {
+        while let Ok(mut candidates) = self.receiver.recv() {
+            // only the latest jobs matter
+            while let Ok(newer) = self.receiver.try_recv() {
+                candidates = newer;
+            }
+            if candidates == self.candidates {
+                continue;
+            }
+            self.fingerprints
+                .retain(|id, _| candidates.iter().any(|c| c.id == *id));
+            self.candidates = candidates;
+            let duplicates = self.duplicates();
+            if self.app.send(AppMessage::Duplicates(duplicates)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+pub struct DuplicateWatcherHandle {
+    sender: Sender<Vec<Candidate>>,
+}
+
+impl DuplicateWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, sacct: bool) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = DuplicateWatcher {
+            app,
+            receiver,
+            sacct,
+            candidates: Vec::new(),
+            fingerprints: HashMap::new(),
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Compares my active jobs, answered with [`AppMessage::Duplicates`] if they changed.
+    pub fn set_jobs(&self, candidates: Vec<Candidate>) {
+        let _ = self.sender.send(candidates);
+    }
+}
@@ -0,0 +1,173 @@
+use std::{collections::HashMap, thread};
+
+use chrono::Duration;
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use crate::app::AppMessage;
+use crate::remote::slurm_command;
+use crate::slurm_time::parse_duration;
+use crate::usage::parse_size;
+
+/// Jobs are passed to `sacct` on the command line, so there is a limit.
+pub const MAX_JOBS: usize = 500;
+
+/// What a finished job requested and what it used, as `seff` reports it.
+pub struct Efficiency {
+    pub id: String,
+    pub name: String,
+    /// Requested memory of the whole job in bytes.
+    pub req_mem: Option<u64>,
+    /// Largest resident set of any step in bytes.
+    pub max_rss: Option<u64>,
+    pub cpus: u32,
+    pub total_cpu: Option<Duration>,
+    pub elapsed: Option<Duration>,
+    pub time_limit: Option<Duration>,
+}
+
+impl Efficiency {
+    /// Fraction of the requested memory that was used.
+    pub fn mem_used(&self) -> Option<f64> {
+        let req_mem = self.req_mem.filter(|&m| m > 0)?;
+        Some(self.max_rss? as f64 / req_mem as f64)
+    }
+
+    /// Fraction of the allocated CPU time that was used.
+    pub fn cpu_used(&self) -> Option<f64> {
+        let available = self.elapsed?.num_seconds() * self.cpus as i64;
+        let used = self.total_cpu?.num_seconds();
+        (available > 0).then(|| used as f64 / available as f64)
+    }
+
+    /// Fraction of the time limit that was used.
+    pub fn time_used(&self) -> Option<f64> {
+        let limit = self.time_limit?.num_seconds();
+        let elapsed = self.elapsed?.num_seconds();
+        (limit > 0).then(|| elapsed as f64 / limit as f64)
+    }
+}
+
+/// Parses `ReqMem`, which is per CPU (`c`) or per node (`n`) on old Slurm versions.
+fn parse_req_mem(s: &str, cpus: u32, nodes: u32) -> Option<u64> {
+    match s.chars().last()? {
+        'c' => Some(parse_size(&s[..s.len() - 1])? * cpus as u64),
+        'n' => Some(parse_size(&s[..s.len() - 1])? * nodes.max(1) as u64),
+        _ => parse_size(s),
+    }
+}
+
+/// Formats a size the way Slurm options take it, e.g. `16G` or `3.4G` (powers of 1024).
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["K", "M", "G", "T", "P"];
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value.fract() < 0.05 || value >= 100.0 {
+        format!("{:.0}{}", value, UNITS[unit])
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
+
+/// Parses `TotalCPU`, which has milliseconds, e.g. `01:02.345`.
+fn parse_cpu_time(s: &str) -> Option<Duration> {
+    parse_duration(s.split('.').next()?)
+}
+
+/// The median of the values, ignoring `None`.
+pub fn median(values: impl Iterator<Item = Option<f64>>) -> Option<f64> {
+    let mut values = values.flatten().collect::<Vec<_>>();
+    values.sort_by(f64::total_cmp);
+    values.get(values.len() / 2).copied()
+}
+
+/// Requests and usage of the jobs from `sacct`.
+pub fn query(ids: &[String]) -> Result<Vec<Efficiency>, String> {
+    const SEPARATOR: &str = "###turm###";
+    let output = slurm_command("sacct")
+        .arg(format!("--jobs={}", ids.join(",")))
+        .args(["--noheader", "--parsable2", "--delimiter", SEPARATOR])
+        .arg("--format=JobID,JobName,ReqMem,MaxRSS,AllocCPUS,NNodes,TotalCPU,Elapsed,Timelimit")
+        .output()
+        .map_err(|e| format!("Failed to execute sacct: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "sacct failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let mut jobs: Vec<Efficiency> = Vec::new();
+    // steps (`1234.batch`, `1234_5.0`) only report MaxRSS
+    let mut max_rss: HashMap<String, u64> = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let parts = line.split(SEPARATOR).collect::<Vec<_>>();
+        let [id, name, req_mem, rss, cpus, nodes, total_cpu, elapsed, time_limit] = parts[..]
+        else {
+            continue;
+        };
+        if let Some((job, _step)) = id.split_once('.') {
+            if let Some(rss) = parse_size(rss) {
+                let max = max_rss.entry(job.to_string()).or_default();
+                *max = (*max).max(rss);
+            }
+            continue;
+        }
+        let cpus = cpus.parse().unwrap_or(0);
+        jobs.push(Efficiency {
+            id: id.to_string(),
+            name: name.to_string(),
+            req_mem: parse_req_mem(req_mem, cpus, nodes.parse().unwrap_or(1)),
+            max_rss: None,
+            cpus,
+            total_cpu: parse_cpu_time(total_cpu),
+            elapsed: parse_duration(elapsed),
+            time_limit: parse_duration(time_limit),
+        });
+    }
+    for job in &mut jobs {
+        job.max_rss = max_rss.get(&job.id).copied();
+    }
+    Ok(jobs)
+}
+
+/// Queries `sacct` in the background, which takes a while for many jobs.
+struct EfficiencyQuerier {
+    app: Sender<AppMessage>,
+    receiver: Receiver<Vec<String>>,
+}
+
+impl EfficiencyQuerier {
+    fn run(&mut self) {
+        while let Ok(mut ids) = self.receiver.recv() {
+            // only the last comparison is shown
+            while let Ok(newer) = self.receiver.try_recv() {
+                ids = newer;
+            }
+            if self.app.send(AppMessage::Efficiency(query(&ids))).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+pub struct EfficiencyQuerierHandle {
+    sender: Sender<Vec<String>>,
+}
+
+impl EfficiencyQuerierHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = EfficiencyQuerier { app, receiver };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Queries requests and usage of the jobs, answered with [`AppMessage::Efficiency`].
+    pub fn query(&self, ids: Vec<String>) {
+        let _ = self.sender.send(ids);
+    }
+}
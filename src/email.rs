@@ -0,0 +1,253 @@
+use std::{process::Command, thread};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, Message, SendmailTransport,
+    SmtpTransport, Transport,
+};
+use serde::Deserialize;
+
+use crate::app::{AppMessage, Job};
+use crate::hostlist;
+use crate::log_source::{log_source, LogSource, Throttle};
+use crate::states;
+
+/// Bytes read from the end of stderr for the failure excerpt.
+const STDERR_TAIL_BYTES: u64 = 64 * 1024;
+
+/// Email notifications about state changes of my jobs, for clusters where Slurm's own
+/// `--mail-type` is disabled.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    pub to: String,
+    /// Defaults to `to`.
+    pub from: Option<String>,
+    /// States that trigger an email when a job enters them, or all final states if empty.
+    #[serde(default)]
+    pub states: Vec<String>,
+    /// Number of stderr lines included for failed jobs.
+    #[serde(default = "default_stderr_lines")]
+    pub stderr_lines: usize,
+    /// Sent via this SMTP server, or else via `sendmail`.
+    pub smtp: Option<SmtpConfig>,
+    /// The sendmail binary.
+    #[serde(default = "default_sendmail")]
+    pub sendmail: String,
+    /// Templates with `{job_id}`, `{name}`, `{state}`, `{old_state}`, `{partition}`, `{nodes}`,
+    /// `{stdout}` and, in the body, `{stderr_tail}`.
+    #[serde(default = "default_subject")]
+    pub subject: String,
+    #[serde(default = "default_body")]
+    pub body: String,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Upgrade the connection with STARTTLS.
+    #[serde(default = "default_starttls")]
+    pub starttls: bool,
+    pub username: Option<String>,
+    /// Command printing the password, e.g. `pass show smtp`, so that it is not in the config.
+    pub password_command: Option<String>,
+}
+
+fn default_stderr_lines() -> usize {
+    20
+}
+
+fn default_sendmail() -> String {
+    "sendmail".to_string()
+}
+
+fn default_subject() -> String {
+    "[turm] job {job_id} ({name}): {state}".to_string()
+}
+
+fn default_body() -> String {
+    "Job {job_id} ({name}) on {partition} went from {old_state} to {state}.\n\nNodes: {nodes}\nstdout: {stdout}\n{stderr_tail}".to_string()
+}
+
+fn default_port() -> u16 {
+    587
+}
+
+fn default_starttls() -> bool {
+    true
+}
+
+fn is_failure(state: &str) -> bool {
+    matches!(state, "FAILED" | "TIMEOUT" | "OUT_OF_MEMORY" | "NODE_FAIL")
+}
+
+struct Notification {
+    config: EmailConfig,
+    job: Job,
+    old_state: String,
+}
+
+/// Sends the emails in the background and reports failures to the app.
+struct Mailer {
+    app: Sender<AppMessage>,
+    receiver: Receiver<Notification>,
+    source: Box<dyn LogSource>,
+}
+
+impl Mailer {
+    fn stderr_tail(&mut self, job: &Job, lines: usize) -> String {
+        let Some(path) = job.stderr.as_ref().or(job.stdout.as_ref()) else {
+            return String::new();
+        };
+        match self.source.read_tail(path, STDERR_TAIL_BYTES) {
+            Ok(tail) => {
+                let tail = String::from_utf8_lossy(&tail);
+                let tail = tail.lines().collect::<Vec<_>>();
+                format!(
+                    "\nLast lines of {}:\n\n{}\n",
+                    path.display(),
+                    tail[tail.len().saturating_sub(lines)..].join("\n")
+                )
+            }
+            Err(e) => format!("\nFailed to read {}: {}\n", path.display(), e),
+        }
+    }
+
+    fn send(&mut self, notification: Notification) -> Result<(), String> {
+        let Notification {
+            config,
+            job,
+            old_state,
+        } = notification;
+        let state = job.state.split_whitespace().next().unwrap_or_default();
+        let stderr_tail = if is_failure(state) {
+            self.stderr_tail(&job, config.stderr_lines)
+        } else {
+            String::new()
+        };
+        let render = |template: &str| {
+            template
+                .replace("{job_id}", &job.id())
+                .replace("{name}", &job.name)
+                .replace("{state}", state)
+                .replace("{old_state}", &old_state)
+                .replace("{partition}", &job.partition)
+                .replace("{nodes}", &hostlist::expand(&job.nodelist).join(","))
+                .replace(
+                    "{stdout}",
+                    &job.stdout
+                        .as_ref()
+                        .map(|p| p.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                )
+                .replace("{stderr_tail}", &stderr_tail)
+        };
+
+        let to = config
+            .to
+            .parse::<Mailbox>()
+            .map_err(|e| format!("Invalid email address {}: {}", config.to, e))?;
+        let from = match &config.from {
+            Some(from) => from
+                .parse::<Mailbox>()
+                .map_err(|e| format!("Invalid email address {}: {}", from, e))?,
+            None => to.clone(),
+        };
+        let message = Message::builder()
+            .from(from)
+            .to(to)
+            .subject(render(&config.subject))
+            .body(render(&config.body))
+            .map_err(|e| e.to_string())?;
+
+        match &config.smtp {
+            Some(smtp) => {
+                let builder = if smtp.starttls {
+                    SmtpTransport::starttls_relay(&smtp.host).map_err(|e| e.to_string())?
+                } else {
+                    SmtpTransport::builder_dangerous(&smtp.host)
+                };
+                let mut builder = builder.port(smtp.port);
+                if let Some(username) = &smtp.username {
+                    let password = match &smtp.password_command {
+                        Some(command) => password(command)?,
+                        None => String::new(),
+                    };
+                    builder = builder.credentials(Credentials::new(username.clone(), password));
+                }
+                builder
+                    .build()
+                    .send(&message)
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+            None => SendmailTransport::new_with_command(&config.sendmail)
+                .send(&message)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    fn run(&mut self) {
+        while let Ok(notification) = self.receiver.recv() {
+            let id = notification.job.id();
+            if let Err(e) = self.send(notification) {
+                let message = format!("Failed to email about job {}: {}", id, e);
+                if self.app.send(AppMessage::EmailFailed(message)).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn password(command: &str) -> Result<String, String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .map_err(|e| format!("Failed to run `{}`: {}", command, e))?;
+    if !output.status.success() {
+        return Err(format!("`{}` failed ({})", command, output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+pub struct MailerHandle {
+    sender: Sender<Notification>,
+}
+
+impl MailerHandle {
+    pub fn new(app: Sender<AppMessage>, throttle: Throttle) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = Mailer {
+            app,
+            receiver,
+            source: log_source(throttle),
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Emails about the job if it entered one of the configured states, or about any state
+    /// change of a pinned job.
+    pub fn notify(&self, config: &EmailConfig, job: &Job, old_state: &str, pinned: bool) {
+        let state = job.state.split_whitespace().next().unwrap_or_default();
+        let triggers = pinned
+            || match config.states.is_empty() {
+                true => states::is_final(state),
+                false => config.states.iter().any(|s| s == state),
+            };
+        if triggers {
+            let _ = self.sender.send(Notification {
+                config: config.clone(),
+                job: job.clone(),
+                old_state: old_state.to_string(),
+            });
+        }
+    }
+}
@@ -1,28 +1,158 @@
 use std::{
-    fmt,
-    fs::File,
-    io::{self, Read, Seek},
+    collections::{HashMap, VecDeque},
+    fmt, io,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crossbeam::{
     channel::{unbounded, Receiver, RecvError, SendError, Sender},
     select,
 };
+use encoding_rs::Decoder;
 use notify::{event::ModifyKind, RecursiveMode, Watcher};
 use tempfile::NamedTempFile;
 
 use crate::app::AppMessage;
+use crate::config::LogEncoding;
+use crate::log_source::{is_node_local, job_log_source, JobNode, LogSource, Throttle};
+use crate::remote;
+
+/// How far back the growth rate of a file is averaged.
+const GROWTH_WINDOW: Duration = Duration::from_secs(30);
+
+/// Bytes at the end of a binary file that are shown as a hex dump.
+pub const HEX_PREVIEW_BYTES: usize = 4096;
+
+/// What is shown of the watched file.
+#[derive(Default)]
+pub struct JobOutput {
+    /// The text, or a hex dump of the end of the file if it is binary.
+    pub content: String,
+    /// None if there is no file.
+    pub size: Option<OutputSize>,
+    pub binary: bool,
+}
+
+/// Size of the watched file and how fast it grows.
+#[derive(Clone, Copy)]
+pub struct OutputSize {
+    pub bytes: u64,
+    /// Bytes per second, averaged over [`GROWTH_WINDOW`].
+    pub rate: f64,
+}
+
+pub fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", value as u64)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+impl fmt::Display for OutputSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format_bytes(self.bytes as f64))?;
+        if self.rate > 0.0 {
+            write!(f, ", +{}/s", format_bytes(self.rate))?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether newly read `bytes`, decoded as `text`, look like binary data rather than text:
+/// NUL bytes, or more than 10% undecodable bytes and control characters that have no
+/// meaning in logs.
+fn looks_binary(bytes: &[u8], text: &str) -> bool {
+    if bytes[..bytes.len().min(8192)].contains(&0) {
+        return true;
+    }
+    let (total, garbage) = text.chars().take(8192).fold((0, 0), |(total, garbage), c| {
+        let is_garbage = c == char::REPLACEMENT_CHARACTER
+            || (c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x08' | '\x0c' | '\x1b'));
+        (total + 1, garbage + is_garbage as usize)
+    });
+    // a few stray bytes don't make a log binary
+    garbage >= 4 && garbage * 10 > total
+}
+
+fn hex_dump(bytes: &[u8], offset: u64) -> String {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex = chunk
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+            format!("{:08x}  {:<47}  |{}|\n", offset + i as u64 * 16, hex, ascii)
+        })
+        .collect()
+}
+
+/// Shown between the output of a job before and after it was requeued.
+const REQUEUE_SEPARATOR: &str =
+    "──────── file truncated (job requeued?), earlier output above ────────\n";
+
+/// Shown between the output of a job from before and after its log file moved.
+fn moved_separator(from: &Path) -> String {
+    format!(
+        "──────── output moved, earlier output above is from {} ────────\n",
+        from.display()
+    )
+}
+
+/// Output of previous runs of requeued jobs by log file, kept while turm is running.
+type PreviousRuns = Arc<Mutex<HashMap<PathBuf, String>>>;
 
 struct FileReader {
-    content_sender: Sender<io::Result<String>>,
+    content_sender: Sender<io::Result<JobOutput>>,
+    source: Box<dyn LogSource>,
     receiver: Receiver<()>,
     file_path: PathBuf,
     interval: Duration,
     content: String,
     pos: u64,
+    /// File sizes over the last [`GROWTH_WINDOW`], oldest first.
+    sizes: VecDeque<(Instant, u64)>,
+    /// Once binary data was seen, only a hex dump of the end of the file is shown.
+    binary: bool,
+    /// The last [`HEX_PREVIEW_BYTES`] bytes read.
+    tail: Vec<u8>,
+    encoding: LogEncoding,
+    /// Chosen when the first bytes are read.
+    decoder: Option<Decoder>,
+    previous_runs: PreviousRuns,
+    /// Output of previous runs of the job, shown before the current one.
+    previous: String,
+}
+
+/// How log files are read, from the config.
+#[derive(Clone)]
+pub struct LogSettings {
+    pub throttle: Throttle,
+    pub encoding: LogEncoding,
+    pub node_local_prefixes: Vec<String>,
 }
 
 struct FileWatcher {
@@ -30,14 +160,23 @@ struct FileWatcher {
     receiver: Receiver<FileWatcherMessage>,
     file_path: Option<PathBuf>,
     interval: Duration,
+    settings: LogSettings,
+    previous_runs: PreviousRuns,
 }
 pub enum FileWatcherMessage {
-    FilePath(Option<PathBuf>),
+    /// The file to watch, with the node of the running job it belongs to.
+    FilePath(Option<PathBuf>, Option<JobNode>),
+    /// The watched file moved to another path, with what was read of it so far, which is
+    /// kept above the output of the new file.
+    Moved(PathBuf, String),
+    /// Applies to files opened from now on.
+    Settings(LogSettings),
 }
 
 pub struct FileWatcherHandle {
     sender: Sender<FileWatcherMessage>,
     file_path: Option<PathBuf>,
+    job: Option<JobNode>,
 }
 
 pub enum FileWatcherError {
@@ -59,12 +198,15 @@ impl FileWatcher {
         app: Sender<AppMessage>,
         receiver: Receiver<FileWatcherMessage>,
         interval: Duration,
+        settings: LogSettings,
     ) -> Self {
         FileWatcher {
-            app: app,
-            receiver: receiver,
+            app,
+            receiver,
             file_path: None,
-            interval: interval,
+            interval,
+            settings,
+            previous_runs: PreviousRuns::default(),
         }
     }
 
@@ -75,11 +217,8 @@ impl FileWatcher {
                 Ok(e) => e,
                 Err(_) => return,
             };
-            match event.kind {
-                notify::EventKind::Modify(ModifyKind::Data(_)) => {
-                    watch_sender.send(event.paths).unwrap();
-                }
-                _ => {}
+            if let notify::EventKind::Modify(ModifyKind::Data(_)) = event.kind {
+                watch_sender.send(event.paths).unwrap();
             };
         })
         .unwrap();
@@ -113,11 +252,8 @@ impl FileWatcher {
                         Ok(e) => e,
                         Err(_) => return,
                     };
-                    match event.kind {
-                        notify::EventKind::Modify(ModifyKind::Data(_)) => {
-                            let _ = watch_sender.send(event.paths);
-                        }
-                        _ => {}
+                    if let notify::EventKind::Modify(ModifyKind::Data(_)) = event.kind {
+                        let _ = watch_sender.send(event.paths);
                     };
                 },
                 config,
@@ -127,13 +263,13 @@ impl FileWatcher {
             Box::new(watcher) as Box<dyn Watcher>
         };
 
-        let (mut _content_sender, mut _content_receiver) = unbounded::<io::Result<String>>();
+        let (mut _content_sender, mut _content_receiver) = unbounded::<io::Result<JobOutput>>();
         let (mut _watch_sender, mut _watch_receiver) = unbounded::<()>();
         loop {
             select! {
                 recv(self.receiver) -> msg => {
                     match msg? {
-                        FileWatcherMessage::FilePath(file_path) => {
+                        FileWatcherMessage::FilePath(file_path, job) => {
                             (_content_sender, _content_receiver) = unbounded();
                             (_watch_sender, _watch_receiver) = unbounded::<()>();
 
@@ -143,24 +279,45 @@ impl FileWatcher {
                             }
 
                             if let Some(p) = file_path {
-                                let res = watcher.watch(Path::new(&p), RecursiveMode::NonRecursive);
+                                // remote files cannot be watched, they are only polled, and
+                                // neither can files on the local storage of a node
+                                let node_local = job.is_some() && is_node_local(&p, &self.settings.node_local_prefixes);
+                                let res = match remote::host() {
+                                    Some(_) => Ok(()),
+                                    None => watcher.watch(Path::new(&p), RecursiveMode::NonRecursive).or_else(|e| match node_local {
+                                        true => Ok(()),
+                                        false => Err(e),
+                                    }),
+                                };
                                 match res {
                                     Ok(_) => {
                                         self.file_path = Some(p.clone());
-                                        let i = self.interval.clone();
-                                        thread::spawn(move || FileReader::new(_content_sender, _watch_receiver, p, i).run());
+                                        let i = self.interval;
+                                        let s = self.settings.clone();
+                                        let r = self.previous_runs.clone();
+                                        thread::spawn(move || FileReader::new(_content_sender, _watch_receiver, p, i, s, r, job).run());
                                     },
                                     Err(e) => self.app.send(AppMessage::JobOutput(Err(FileWatcherError::Watcher(e)))).unwrap()
                                 };
                             } else {
-                                _content_sender.send(Ok("".to_string())).unwrap();
+                                _content_sender.send(Ok(JobOutput::default())).unwrap();
+                            }
+                        }
+                        FileWatcherMessage::Moved(to, mut output) => {
+                            if let Some(from) = self.file_path.as_ref().filter(|_| !output.is_empty()) {
+                                if !output.ends_with('\n') {
+                                    output.push('\n');
+                                }
+                                output.push_str(&moved_separator(from));
+                                self.previous_runs.lock().unwrap().entry(to).or_insert(output);
                             }
                         }
+                        FileWatcherMessage::Settings(settings) => self.settings = settings,
                     }
                 }
                 recv(watch_receiver) -> _ => { _watch_sender.send(()).unwrap(); }
                 recv(_content_receiver) -> msg => {
-                    self.app.send(AppMessage::JobOutput(msg.unwrap().map_err(|e| FileWatcherError::File(e)))).unwrap();
+                    self.app.send(AppMessage::JobOutput(msg.unwrap().map_err(FileWatcherError::File))).unwrap();
                 }
             }
         }
@@ -169,18 +326,35 @@ impl FileWatcher {
 
 impl FileReader {
     fn new(
-        content_sender: Sender<io::Result<String>>,
+        content_sender: Sender<io::Result<JobOutput>>,
         receiver: Receiver<()>,
         file_path: PathBuf,
         interval: Duration,
+        settings: LogSettings,
+        previous_runs: PreviousRuns,
+        job: Option<JobNode>,
     ) -> Self {
+        let previous = previous_runs
+            .lock()
+            .unwrap()
+            .get(&file_path)
+            .cloned()
+            .unwrap_or_default();
         FileReader {
-            content_sender: content_sender,
-            receiver: receiver,
-            file_path: file_path,
-            interval: interval,
+            content_sender,
+            source: job_log_source(settings.throttle, &settings.node_local_prefixes, job),
+            receiver,
+            file_path,
+            interval,
             content: "".to_string(),
             pos: 0,
+            sizes: VecDeque::new(),
+            binary: false,
+            tail: Vec::new(),
+            encoding: settings.encoding,
+            decoder: None,
+            previous_runs,
+            previous,
         }
     }
 
@@ -197,34 +371,155 @@ impl FileReader {
         }
     }
 
-    fn update(&mut self) -> Result<(), SendError<io::Result<String>>> {
-        let s = File::open(&self.file_path).and_then(|mut f| {
-            // avoid reading the whole file every time
-            self.pos = f.seek(io::SeekFrom::Start(self.pos))?;
-            self.pos += f.read_to_string(&mut self.content)? as u64;
-            Ok(self.content.clone())
-        });
+    fn update(&mut self) -> Result<(), SendError<io::Result<JobOutput>>> {
+        // avoid reading the whole file every time
+        let s = loop {
+            match self.source.read_from(&self.file_path, self.pos) {
+                Ok(_) if self.source.size() < self.pos => self.archive_run(),
+                Ok(new) if !self.append(&new) => continue,
+                result => {
+                    break result.map(|_| JobOutput {
+                        content: if self.binary || self.previous.is_empty() {
+                            self.content.clone()
+                        } else {
+                            format!("{}{}", self.previous, self.content)
+                        },
+                        size: Some(self.output_size()),
+                        binary: self.binary,
+                    })
+                }
+            }
+        };
         // let s = fs::read_to_string(&self.file_path); // alternative: always read the whole file
         self.content_sender.send(s)
     }
+
+    /// Keeps the output read so far when the file is truncated, which is what Slurm does when
+    /// it requeues a job (unless it was submitted with `--open-mode=append`), and starts over.
+    fn archive_run(&mut self) {
+        if !self.binary && !self.content.is_empty() {
+            self.previous.push_str(&self.content);
+            if !self.content.ends_with('\n') {
+                self.previous.push('\n');
+            }
+            self.previous.push_str(REQUEUE_SEPARATOR);
+            self.previous_runs
+                .lock()
+                .unwrap()
+                .insert(self.file_path.clone(), self.previous.clone());
+        }
+        self.content.clear();
+        self.pos = 0;
+        self.tail.clear();
+        self.binary = false;
+        self.decoder = None;
+    }
+
+    /// Decodes and adds newly read bytes. Returns false if the file has to be read again
+    /// from the start.
+    fn append(&mut self, new: &[u8]) -> bool {
+        let encoding = self.encoding;
+        let decoder = self
+            .decoder
+            .get_or_insert_with(|| encoding.detect(new).new_decoder_without_bom_handling());
+        let mut text = String::with_capacity(decoder.max_utf8_buffer_length(new.len()).unwrap());
+        let (_, _, had_errors) = decoder.decode_to_string(new, &mut text, false);
+        if had_errors
+            && matches!(encoding, LogEncoding::Auto)
+            && decoder.encoding() == encoding_rs::UTF_8
+        {
+            // the beginning looked like UTF-8, but the file is not
+            self.decoder = Some(encoding_rs::WINDOWS_1252.new_decoder_without_bom_handling());
+            self.pos = 0;
+            self.content.clear();
+            self.tail.clear();
+            return false;
+        }
+
+        self.pos += new.len() as u64;
+        self.tail.extend_from_slice(new);
+        let excess = self.tail.len().saturating_sub(HEX_PREVIEW_BYTES);
+        self.tail.drain(..excess);
+        if !self.binary && looks_binary(new, &text) {
+            self.binary = true;
+        }
+        if self.binary {
+            self.content = hex_dump(&self.tail, self.pos - self.tail.len() as u64);
+        } else {
+            self.content.push_str(&text);
+        }
+        true
+    }
+
+    /// Records the current size of the file and returns it with the growth rate.
+    fn output_size(&mut self) -> OutputSize {
+        let now = Instant::now();
+        let bytes = self.source.size();
+        if self.sizes.back().is_some_and(|&(_, b)| b > bytes) {
+            // truncated or replaced
+            self.sizes.clear();
+        }
+        self.sizes.push_back((now, bytes));
+        while self
+            .sizes
+            .front()
+            .is_some_and(|&(t, _)| now - t > GROWTH_WINDOW)
+        {
+            self.sizes.pop_front();
+        }
+        let (since, initial) = self.sizes[0];
+        let elapsed = (now - since).as_secs_f64();
+        OutputSize {
+            bytes,
+            rate: if elapsed > 0.0 {
+                (bytes - initial) as f64 / elapsed
+            } else {
+                0.0
+            },
+        }
+    }
 }
 
 impl FileWatcherHandle {
-    pub fn new(app: Sender<AppMessage>, interval: Duration) -> Self {
+    pub fn new(app: Sender<AppMessage>, interval: Duration, settings: LogSettings) -> Self {
         let (sender, receiver) = unbounded();
-        let mut actor = FileWatcher::new(app, receiver, interval);
+        let mut actor = FileWatcher::new(app, receiver, interval, settings);
         thread::spawn(move || actor.run());
 
         Self {
             sender,
             file_path: None,
+            job: None,
         }
     }
 
-    pub fn set_file_path(&mut self, file_path: Option<PathBuf>) {
-        if self.file_path != file_path {
+    pub fn file_path(&self) -> Option<&Path> {
+        self.file_path.as_deref()
+    }
+
+    /// Watches the file, which is read on the node of the job if it is on its local storage.
+    pub fn set_file_path(&mut self, file_path: Option<PathBuf>, job: Option<JobNode>) {
+        if self.file_path != file_path || self.job != job {
             self.file_path = file_path.clone();
-            let _ = self.sender.send(FileWatcherMessage::FilePath(file_path));
+            self.job = job.clone();
+            let _ = self
+                .sender
+                .send(FileWatcherMessage::FilePath(file_path, job));
         }
     }
+
+    /// Switches to the new path of the watched file, keeping the `output` read from the old
+    /// one above it.
+    pub fn move_file(&mut self, file_path: PathBuf, job: Option<JobNode>, output: String) {
+        if self.file_path.is_some() && self.file_path.as_ref() != Some(&file_path) {
+            let _ = self
+                .sender
+                .send(FileWatcherMessage::Moved(file_path.clone(), output));
+        }
+        self.set_file_path(Some(file_path), job);
+    }
+
+    pub fn set_settings(&self, settings: LogSettings) {
+        let _ = self.sender.send(FileWatcherMessage::Settings(settings));
+    }
 }
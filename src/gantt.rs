@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDateTime};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+
+use crate::app::Job;
+use crate::states;
+
+/// Width of the job labels in front of the bars.
+const LABEL_WIDTH: usize = 24;
+/// How far back the timeline goes at most.
+const MAX_SPAN: Duration = Duration::days(7);
+
+/// Renders the jobs as horizontal bars from submit over start to end, grouped by partition:
+/// `░` while the job is waiting in the queue and `█` while it runs.
+pub fn timeline_lines(jobs: &[&Job], now: NaiveDateTime, width: u16) -> Vec<Line<'static>> {
+    let bar_width = (width as usize).saturating_sub(LABEL_WIDTH + 1).max(10);
+    let Some(first) = jobs.iter().filter_map(|j| j.submit_time).min() else {
+        return vec![Line::from("No jobs with a submit time")];
+    };
+    let from = first.max(now - MAX_SPAN);
+    let span = (now - from).num_seconds().max(1) as f64;
+    let column = |t: NaiveDateTime| {
+        let x = (t - from).num_seconds() as f64 / span * bar_width as f64;
+        (x.max(0.0) as usize).min(bar_width)
+    };
+
+    let mut partitions = BTreeMap::<&str, Vec<&Job>>::new();
+    for job in jobs {
+        partitions.entry(&job.partition).or_default().push(job);
+    }
+
+    let mut lines = Vec::new();
+    for (partition, mut jobs) in partitions {
+        jobs.sort_by_key(|j| j.submit_time);
+        lines.push(Line::from(Span::styled(
+            partition.to_string(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )));
+        for job in jobs {
+            let Some(submit) = job.submit_time else {
+                continue;
+            };
+            let start = job.started_at(now).map(|t| column(t.max(from)));
+            let end = job.end_time.filter(|&t| t <= now).map_or(bar_width, column);
+            let submit = column(submit.max(from));
+            let (wait, run) = match start {
+                Some(start) => (start.saturating_sub(submit), end.max(start) - start),
+                None => (end.max(submit) - submit, 0),
+            };
+            let label = format!("{} {}", job.id(), job.name)
+                .chars()
+                .take(LABEL_WIDTH)
+                .collect::<String>();
+            let color = states::color(&job.state);
+            lines.push(Line::from(vec![
+                Span::raw(format!("{:<w$} ", label, w = LABEL_WIDTH)),
+                Span::raw(" ".repeat(submit)),
+                Span::styled("░".repeat(wait), Style::default().fg(color)),
+                // keep jobs that ran for less than a column visible
+                Span::styled(
+                    "█".repeat(if start.is_some() { run.max(1) } else { 0 }),
+                    Style::default().fg(color),
+                ),
+            ]));
+        }
+    }
+
+    let format = if from.date() == now.date() {
+        "%H:%M"
+    } else {
+        "%m-%d %H:%M"
+    };
+    let from_label = from.format(format).to_string();
+    lines.push(Line::from(Span::styled(
+        format!(
+            "{:w$}{}{:>r$}",
+            "",
+            from_label,
+            "now",
+            w = LABEL_WIDTH + 1,
+            r = bar_width.saturating_sub(from_label.len()),
+        ),
+        Style::default().add_modifier(Modifier::DIM),
+    )));
+    lines
+}
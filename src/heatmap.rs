@@ -0,0 +1,153 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::{
+    channel::{unbounded, Receiver, Sender},
+    select,
+};
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+
+use crate::app::AppMessage;
+use crate::nodes::{list_nodes, Node};
+
+/// How often `sinfo` is run while the heatmap is shown.
+const INTERVAL: Duration = Duration::from_secs(60);
+/// The heatmap never takes more lines than this; further nodes are cut off.
+pub const MAX_LINES: usize = 4;
+
+/// Color of a node by its state and the fraction of its CPUs that are allocated.
+fn node_color(node: &Node) -> Color {
+    let state = node.state.as_str();
+    if ["down", "drain", "fail", "not_responding", "inval"]
+        .iter()
+        .any(|s| state.starts_with(s))
+    {
+        return Color::DarkGray;
+    }
+    if ["reserved", "maint", "planned"]
+        .iter()
+        .any(|s| state.starts_with(s))
+    {
+        return Color::Blue;
+    }
+    // Allocated/idle/other/total
+    let cpus = node
+        .cpus
+        .split('/')
+        .filter_map(|c| c.parse::<f64>().ok())
+        .collect::<Vec<_>>();
+    let allocated = match cpus[..] {
+        [allocated, _, _, total] if total > 0.0 => allocated / total,
+        _ if state.starts_with("allocated") => 1.0,
+        _ if state.starts_with("mixed") => 0.5,
+        _ => 0.0,
+    };
+    match allocated {
+        a if a >= 1.0 => Color::Red,
+        a if a >= 0.5 => Color::LightRed,
+        a if a > 0.0 => Color::Yellow,
+        _ => Color::Green,
+    }
+}
+
+/// One cell per node, wrapped at `width`, followed by a legend.
+pub fn heatmap_lines(nodes: &[Node], width: u16) -> Vec<Line<'static>> {
+    let width = (width as usize).max(1);
+    let mut lines = nodes
+        .chunks(width)
+        .take(MAX_LINES)
+        .map(|row| {
+            Line::from(
+                row.iter()
+                    .map(|n| Span::styled("■", Style::default().fg(node_color(n))))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut legend = vec![Span::raw(format!("{} nodes: ", nodes.len()))];
+    for (label, color) in [
+        ("idle", Color::Green),
+        ("<50%", Color::Yellow),
+        ("≥50%", Color::LightRed),
+        ("full", Color::Red),
+        ("reserved", Color::Blue),
+        ("down/drained", Color::DarkGray),
+    ] {
+        legend.push(Span::styled("■", Style::default().fg(color)));
+        legend.push(Span::raw(format!(" {}  ", label)));
+    }
+    if nodes.len() > width * MAX_LINES {
+        legend.push(Span::raw(format!(
+            "({} not shown)",
+            nodes.len() - width * MAX_LINES
+        )));
+    }
+    lines.push(Line::from(legend));
+    lines
+}
+
+/// Polls `sinfo` for the heatmap while it is shown.
+struct NodeWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<bool>,
+    enabled: bool,
+    polled_at: Option<Instant>,
+}
+
+impl NodeWatcher {
+    fn poll(&mut self) {
+        self.polled_at = Some(Instant::now());
+        if self.enabled {
+            let nodes = list_nodes().map_err(|e| e.to_string());
+            let _ = self.app.send(AppMessage::NodeHeatmap(nodes));
+        }
+    }
+
+    fn run(&mut self) {
+        loop {
+            let due = self
+                .polled_at
+                .map_or(Duration::ZERO, |t| INTERVAL.saturating_sub(t.elapsed()));
+            select! {
+                recv(self.receiver) -> msg => match msg {
+                    Ok(enabled) => {
+                        if enabled && !self.enabled {
+                            self.enabled = true;
+                            self.poll();
+                        }
+                        self.enabled = enabled;
+                    }
+                    Err(_) => return,
+                },
+                default(due) => self.poll(),
+            }
+        }
+    }
+}
+
+pub struct NodeWatcherHandle {
+    sender: Sender<bool>,
+}
+
+impl NodeWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, enabled: bool) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = NodeWatcher {
+            app,
+            receiver,
+            enabled,
+            polled_at: None,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        let _ = self.sender.send(enabled);
+    }
+}
@@ -0,0 +1,519 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use chrono::{Local, NaiveDateTime};
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use rusqlite::{params, Connection, Row};
+use serde::Deserialize;
+
+use crate::app::Job;
+use crate::intern::Interned;
+use crate::slurm_time::{format_duration, format_slurm_duration};
+use crate::state::state_dir;
+use crate::states;
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS jobs (
+    id TEXT PRIMARY KEY,
+    job_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    user TEXT NOT NULL,
+    state TEXT NOT NULL,
+    partition TEXT NOT NULL,
+    qos TEXT NOT NULL,
+    nodes TEXT NOT NULL,
+    tres TEXT NOT NULL,
+    command TEXT NOT NULL,
+    work_dir TEXT,
+    stdout TEXT,
+    stderr TEXT,
+    submit_time TEXT,
+    start_time TEXT,
+    end_time TEXT,
+    elapsed INTEGER,
+    time_limit INTEGER,
+    restarts INTEGER NOT NULL,
+    energy INTEGER
+);
+CREATE INDEX IF NOT EXISTS jobs_end_time ON jobs (end_time, id);";
+
+const COLUMNS: &str = "id, job_id, name, user, state, partition, qos, nodes, tres, command, \
+    work_dir, stdout, stderr, submit_time, start_time, end_time, elapsed, time_limit, restarts, \
+    energy";
+
+/// Keeps every finished job turm sees in a local SQLite database, so that it can still be
+/// looked up after `sacct` forgot it or on clusters without accounting.
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    /// Jobs that ended longer ago are pruned.
+    pub max_age_days: u32,
+    /// The oldest jobs beyond this number are pruned.
+    pub max_jobs: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            enabled: true,
+            max_age_days: 365,
+            max_jobs: 100_000,
+        }
+    }
+}
+
+/// A finished job as recorded in the history, the final record of `sacct` if it was seen.
+#[derive(Clone, PartialEq)]
+pub struct Record {
+    /// The id as shown, e.g. `123_4` for a task of an array.
+    pub id: String,
+    /// The raw job id, which differs from `id` for tasks of arrays.
+    pub job_id: String,
+    pub name: String,
+    pub user: String,
+    pub state: String,
+    pub partition: String,
+    pub qos: String,
+    pub nodes: String,
+    pub tres: String,
+    pub command: String,
+    pub work_dir: Option<PathBuf>,
+    pub stdout: Option<PathBuf>,
+    pub stderr: Option<PathBuf>,
+    pub submit_time: Option<NaiveDateTime>,
+    pub start_time: Option<NaiveDateTime>,
+    pub end_time: Option<NaiveDateTime>,
+    /// In seconds.
+    pub elapsed: Option<i64>,
+    /// In seconds.
+    pub time_limit: Option<i64>,
+    pub restarts: u32,
+    pub energy: Option<u64>,
+}
+
+impl From<&Job> for Record {
+    fn from(job: &Job) -> Self {
+        Record {
+            id: job.id(),
+            job_id: job.job_id.clone(),
+            name: job.name.clone(),
+            user: job.user.to_string(),
+            state: job.state.to_string(),
+            partition: job.partition.to_string(),
+            qos: job.qos.to_string(),
+            nodes: job.nodelist.clone(),
+            tres: job.tres.clone(),
+            command: job.command.clone(),
+            work_dir: job.work_dir.clone(),
+            stdout: job.stdout.clone(),
+            stderr: job.stderr.clone(),
+            submit_time: job.submit_time,
+            start_time: job.start_time,
+            end_time: job.end_time,
+            elapsed: job.time_used.map(|d| d.num_seconds()),
+            time_limit: job.time_limit.map(|d| d.num_seconds()),
+            restarts: job.restarts,
+            energy: job.energy,
+        }
+    }
+}
+
+impl Record {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let path = |i| {
+            row.get::<_, Option<String>>(i)
+                .map(|p| p.map(PathBuf::from))
+        };
+        Ok(Record {
+            id: row.get(0)?,
+            job_id: row.get(1)?,
+            name: row.get(2)?,
+            user: row.get(3)?,
+            state: row.get(4)?,
+            partition: row.get(5)?,
+            qos: row.get(6)?,
+            nodes: row.get(7)?,
+            tres: row.get(8)?,
+            command: row.get(9)?,
+            work_dir: path(10)?,
+            stdout: path(11)?,
+            stderr: path(12)?,
+            submit_time: row.get(13)?,
+            start_time: row.get(14)?,
+            end_time: row.get(15)?,
+            elapsed: row.get(16)?,
+            time_limit: row.get(17)?,
+            restarts: row.get(18)?,
+            energy: row.get::<_, Option<i64>>(19)?.map(|e| e as u64),
+        })
+    }
+
+    fn insert(&self, connection: &Connection) -> rusqlite::Result<()> {
+        let path = |p: &Option<PathBuf>| p.as_ref().map(|p| p.to_string_lossy().into_owned());
+        connection
+            .prepare_cached(&format!(
+                "INSERT OR REPLACE INTO jobs ({}) VALUES \
+                    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+                COLUMNS
+            ))?
+            .execute(params![
+                self.id,
+                self.job_id,
+                self.name,
+                self.user,
+                self.state,
+                self.partition,
+                self.qos,
+                self.nodes,
+                self.tres,
+                self.command,
+                path(&self.work_dir),
+                path(&self.stdout),
+                path(&self.stderr),
+                self.submit_time,
+                self.start_time,
+                self.end_time,
+                self.elapsed,
+                self.time_limit,
+                self.restarts,
+                self.energy.map(|e| e as i64),
+            ])?;
+        Ok(())
+    }
+
+    pub fn to_job(&self) -> Job {
+        let (array_id, array_step) = match self.id.split_once('_') {
+            Some((array_id, step)) => (array_id.to_string(), Some(step.into())),
+            None => (self.id.clone(), None),
+        };
+        let time_used = self.elapsed.map(chrono::Duration::seconds);
+        Job {
+            job_id: self.job_id.clone(),
+            array_id,
+            array_step,
+            name: self.name.clone(),
+            state: Interned::new(&self.state),
+            state_compact: Interned::new(&states::compact(&self.state)),
+            reason: None,
+            dependency: None,
+            user: Interned::new(&self.user),
+            time: time_used.map(format_slurm_duration).unwrap_or_default(),
+            tres: self.tres.clone(),
+            partition: Interned::new(&self.partition),
+            nodelist: self.nodes.clone(),
+            stdout: self.stdout.clone(),
+            stderr: self.stderr.clone(),
+            stdout_pattern: None,
+            stderr_pattern: None,
+            command: self.command.clone(),
+            qos: Interned::new(&self.qos),
+            submit_time: self.submit_time,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            time_used,
+            time_limit: self.time_limit.map(chrono::Duration::seconds),
+            work_dir: self.work_dir.clone(),
+            restarts: self.restarts,
+            energy: self.energy,
+        }
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    state_dir().map(|d| d.join("history.sqlite"))
+}
+
+fn open(path: &Path) -> rusqlite::Result<Connection> {
+    let connection = Connection::open(path)?;
+    // several turm instances may record at the same time
+    connection.busy_timeout(std::time::Duration::from_secs(5))?;
+    connection.execute_batch(SCHEMA)?;
+    Ok(connection)
+}
+
+/// Removes the jobs that ended more than `max_age_days` ago, and the oldest beyond `max_jobs`.
+fn prune(connection: &Connection, config: &HistoryConfig) -> rusqlite::Result<()> {
+    let cutoff = Local::now().naive_local() - chrono::Duration::days(config.max_age_days as i64);
+    connection.execute("DELETE FROM jobs WHERE end_time < ?1", [cutoff])?;
+    connection.execute(
+        "DELETE FROM jobs WHERE id IN (SELECT id FROM jobs ORDER BY end_time, id \
+            LIMIT max(0, (SELECT count(*) FROM jobs) - ?1))",
+        [config.max_jobs as i64],
+    )?;
+    Ok(())
+}
+
+/// The records in the database, oldest first.
+fn read(connection: &Connection) -> rusqlite::Result<Vec<Record>> {
+    connection
+        .prepare(&format!(
+            "SELECT {} FROM jobs ORDER BY end_time, id",
+            COLUMNS
+        ))?
+        .query_map([], Record::from_row)?
+        .collect()
+}
+
+/// The history, pruned, oldest first.
+pub fn load(config: &HistoryConfig) -> io::Result<Vec<Record>> {
+    let Some(path) = history_path().filter(|p| p.exists()) else {
+        return Ok(Vec::new());
+    };
+    let connection = open(&path).map_err(io::Error::other)?;
+    let records = read(&connection).map_err(io::Error::other)?;
+    let cutoff = Local::now().naive_local() - chrono::Duration::days(config.max_age_days as i64);
+    let mut records = records
+        .into_iter()
+        .filter(|r| r.end_time.is_none_or(|t| t >= cutoff))
+        .collect::<Vec<_>>();
+    let excess = records.len().saturating_sub(config.max_jobs);
+    records.drain(..excess);
+    Ok(records)
+}
+
+enum HistoryCommand {
+    Record(Vec<Job>),
+    SetConfig(HistoryConfig),
+}
+
+/// Records the finished jobs of each refresh in the history database in the background.
+struct HistoryRecorder {
+    receiver: Receiver<HistoryCommand>,
+    config: HistoryConfig,
+    path: Option<PathBuf>,
+    connection: Option<Connection>,
+    /// The recorded jobs, to only write the ones that changed.
+    records: HashMap<String, Record>,
+}
+
+impl HistoryRecorder {
+    /// Opens the database and applies the pruning policies.
+    fn load(&mut self) -> rusqlite::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if self.connection.is_none() {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            self.connection = Some(open(path)?);
+        }
+        let connection = self.connection.as_ref().unwrap();
+        prune(connection, &self.config)?;
+        self.records = read(connection)?
+            .into_iter()
+            .map(|r| (r.id.clone(), r))
+            .collect();
+        Ok(())
+    }
+
+    fn record(&mut self, jobs: Vec<Job>) -> rusqlite::Result<()> {
+        let Some(connection) = &mut self.connection else {
+            return Ok(());
+        };
+        let changed = jobs
+            .iter()
+            .map(Record::from)
+            .filter(|r| self.records.get(&r.id) != Some(r))
+            .collect::<Vec<_>>();
+        if changed.is_empty() {
+            return Ok(());
+        }
+        let transaction = connection.transaction()?;
+        for record in &changed {
+            record.insert(&transaction)?;
+        }
+        transaction.commit()?;
+        self.records
+            .extend(changed.into_iter().map(|r| (r.id.clone(), r)));
+        if self.records.len() > self.config.max_jobs {
+            self.load()?;
+        }
+        Ok(())
+    }
+
+    fn run(&mut self) {
+        // the history is best effort, it must not get in the way of the UI
+        let _ = self.load();
+        while let Ok(command) = self.receiver.recv() {
+            match command {
+                HistoryCommand::Record(jobs) => {
+                    if self.config.enabled {
+                        let _ = self.record(jobs);
+                    }
+                }
+                HistoryCommand::SetConfig(config) => {
+                    self.config = config;
+                    let _ = self.load();
+                }
+            }
+        }
+    }
+}
+
+pub struct HistoryRecorderHandle {
+    sender: Sender<HistoryCommand>,
+}
+
+impl HistoryRecorderHandle {
+    pub fn new(config: HistoryConfig) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = HistoryRecorder {
+            receiver,
+            config,
+            path: history_path(),
+            connection: None,
+            records: HashMap::new(),
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Records the finished ones of the jobs, unless they are recorded already.
+    pub fn record(&self, jobs: &[Job]) {
+        let finished = jobs
+            .iter()
+            .filter(|j| j.is_finished())
+            .cloned()
+            .collect::<Vec<_>>();
+        if !finished.is_empty() {
+            let _ = self.sender.send(HistoryCommand::Record(finished));
+        }
+    }
+
+    pub fn set_config(&self, config: HistoryConfig) {
+        let _ = self.sender.send(HistoryCommand::SetConfig(config));
+    }
+}
+
+/// Prints the jobs in the history that match the query (as in the job list search) and ended
+/// within `days`, oldest first, or statistics about them.
+pub fn print(
+    config: &HistoryConfig,
+    query: Option<&str>,
+    user: Option<&str>,
+    days: Option<u32>,
+    stats: bool,
+) -> io::Result<()> {
+    let cutoff = days.map(|d| Local::now().naive_local() - chrono::Duration::days(d as i64));
+    let jobs = load(config)?
+        .iter()
+        .map(Record::to_job)
+        .filter(|j| query.is_none_or(|q| j.matches(q)))
+        .filter(|j| user.is_none_or(|u| j.user == u))
+        .filter(|j| cutoff.is_none_or(|c| j.end_time.is_some_and(|t| t >= c)))
+        .collect::<Vec<_>>();
+    if !stats {
+        for job in jobs {
+            println!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                job.id(),
+                job.end_time
+                    .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default(),
+                job.state,
+                job.user,
+                job.partition,
+                job.time,
+                job.name
+            );
+        }
+        return Ok(());
+    }
+
+    let mut states = BTreeMap::<&str, usize>::new();
+    for job in &jobs {
+        *states
+            .entry(job.state.split_whitespace().next().unwrap_or_default())
+            .or_default() += 1;
+    }
+    let mut elapsed = jobs.iter().filter_map(|j| j.time_used).collect::<Vec<_>>();
+    elapsed.sort();
+    println!("Jobs      {}", jobs.len());
+    for (state, count) in states {
+        println!(
+            "  {:<14} {:>6} ({:.0}%)",
+            state,
+            count,
+            100.0 * count as f64 / jobs.len() as f64
+        );
+    }
+    if !elapsed.is_empty() {
+        let total = elapsed.iter().copied().sum::<chrono::Duration>();
+        println!("Elapsed   {} in total", format_duration(total));
+        println!("  median  {}", format_duration(elapsed[elapsed.len() / 2]));
+        println!("  longest {}", format_duration(elapsed[elapsed.len() - 1]));
+    }
+    if let Some(first) = jobs.iter().filter_map(|j| j.end_time).min() {
+        println!("Since     {}", first.format("%Y-%m-%d"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    fn record(id: &str, job_id: &str, days_ago: i64) -> Record {
+        Record {
+            id: id.to_string(),
+            job_id: job_id.to_string(),
+            name: "train".to_string(),
+            user: "alice".to_string(),
+            state: "COMPLETED".to_string(),
+            partition: "gpu".to_string(),
+            qos: "normal".to_string(),
+            nodes: "gpu-[16-18]".to_string(),
+            tres: String::new(),
+            command: String::new(),
+            work_dir: Some(PathBuf::from("/home/alice")),
+            stdout: None,
+            stderr: None,
+            submit_time: None,
+            start_time: None,
+            end_time: Some(
+                (Local::now() - chrono::Duration::days(days_ago))
+                    .naive_local()
+                    .with_nanosecond(0)
+                    .unwrap(),
+            ),
+            elapsed: Some(60),
+            time_limit: None,
+            restarts: 0,
+            energy: Some(42),
+        }
+    }
+
+    #[test]
+    fn prune_by_age_and_count() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(SCHEMA).unwrap();
+        let records = [
+            record("1", "1", 400),
+            record("2_0", "3", 30),
+            record("2_1", "4", 20),
+            record("5", "5", 10),
+        ];
+        for r in &records {
+            r.insert(&connection).unwrap();
+        }
+        let config = HistoryConfig {
+            enabled: true,
+            max_age_days: 365,
+            max_jobs: 2,
+        };
+        prune(&connection, &config).unwrap();
+        let read = read(&connection).unwrap();
+        assert!(read == records[2..]);
+        let job = read[0].to_job();
+        assert_eq!(
+            (job.job_id.as_str(), job.array_id.as_str(), job.id()),
+            ("4", "2", "2_1".to_string())
+        );
+    }
+}
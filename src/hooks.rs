@@ -0,0 +1,99 @@
+use std::{
+    process::{Command, Stdio},
+    thread,
+};
+
+use serde::Deserialize;
+
+/// A shell command run before or after an action, e.g. to notify a lab channel whenever
+/// someone cancels a shared pipeline job. Details of the action are passed in `TURM_*`
+/// environment variables.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    /// `cancel`, `rename`, `resubmit`, `drain` or `resume`.
+    pub action: String,
+    #[serde(default)]
+    pub when: HookTime,
+    pub command: String,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HookTime {
+    /// Before the action. The action is aborted if the command fails.
+    Pre,
+    #[default]
+    Post,
+}
+
+/// Hooks always run locally, also in remote mode.
+fn command(hook: &Hook, env: &[(&str, String)]) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C");
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c");
+        c
+    };
+    command
+        .arg(&hook.command)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    command
+}
+
+fn matching<'a>(
+    hooks: &'a [Hook],
+    action: &'a str,
+    when: HookTime,
+) -> impl Iterator<Item = &'a Hook> {
+    hooks
+        .iter()
+        .filter(move |h| h.action == action && h.when == when)
+}
+
+/// Runs the pre-action hooks, stopping at the first one that fails.
+pub fn run_pre(hooks: &[Hook], action: &str, env: &[(&str, String)]) -> Result<(), String> {
+    for hook in matching(hooks, action, HookTime::Pre) {
+        match command(hook, env).output() {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => {
+                return Err(format!(
+                    "{} hook failed ({}): {}",
+                    action,
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ))
+            }
+            Err(e) => return Err(format!("Failed to run hook `{}`: {}", hook.command, e)),
+        }
+    }
+    Ok(())
+}
+
+/// Starts the post-action hooks in the background, with the result in `TURM_RESULT`
+/// (`ok` or `error`) and `TURM_MESSAGE`.
+pub fn run_post(
+    hooks: &[Hook],
+    action: &str,
+    env: &[(&str, String)],
+    result: &Result<String, String>,
+) {
+    let (status, message) = match result {
+        Ok(m) => ("ok", m),
+        Err(m) => ("error", m),
+    };
+    for hook in matching(hooks, action, HookTime::Post) {
+        let mut command = command(hook, env);
+        command
+            .env("TURM_RESULT", status)
+            .env("TURM_MESSAGE", message);
+        // wait in the background, so that the UI does not block and the process is reaped
+        thread::spawn(move || command.output());
+    }
+}
@@ -0,0 +1,111 @@
+/// The hosts of a Slurm host list such as `gpu-[01-04,07],cpu-1`, in order. Zero-padded
+/// ranges keep their width, and several bracket groups in one name (`r[1-2]n[1-2]`) expand to
+/// every combination. Malformed parts are kept as they are.
+pub fn expand(hostlist: &str) -> Vec<String> {
+    split_top_level(hostlist)
+        .into_iter()
+        .filter(|h| !h.is_empty())
+        .flat_map(expand_host)
+        .collect()
+}
+
+/// Splits at the commas outside of brackets.
+fn split_top_level(hostlist: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in hostlist.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(hostlist[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(hostlist[start..].trim());
+    parts
+}
+
+fn expand_host(host: &str) -> Vec<String> {
+    let Some((prefix, rest)) = host.split_once('[') else {
+        return vec![host.to_string()];
+    };
+    let Some((ranges, suffix)) = rest.split_once(']') else {
+        return vec![host.to_string()];
+    };
+    let Some(values) = ranges
+        .split(',')
+        .map(expand_range)
+        .collect::<Option<Vec<_>>>()
+    else {
+        return vec![host.to_string()];
+    };
+    let suffixes = expand_host(suffix);
+    values
+        .iter()
+        .flatten()
+        .flat_map(|v| {
+            suffixes
+                .iter()
+                .map(move |s| format!("{}{}{}", prefix, v, s))
+        })
+        .collect()
+}
+
+/// The numbers of a range like `01-04` or a single number like `7`.
+fn expand_range(range: &str) -> Option<Vec<String>> {
+    let range = range.trim();
+    let (first, last) = range.split_once('-').unwrap_or((range, range));
+    let (start, end) = (first.parse::<u64>().ok()?, last.parse::<u64>().ok()?);
+    if end < start {
+        return None;
+    }
+    let width = first.len();
+    Some(
+        (start..=end)
+            .map(|n| format!("{:0width$}", n, width = width))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_hosts() {
+        assert_eq!(expand("gpu-17"), ["gpu-17"]);
+        assert_eq!(expand("a,b, c"), ["a", "b", "c"]);
+        assert!(expand("").is_empty());
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(
+            expand("node[01-03,07]"),
+            ["node01", "node02", "node03", "node07"]
+        );
+        assert_eq!(expand("n[9-11]"), ["n9", "n10", "n11"]);
+        assert_eq!(
+            expand("gpu-[1-2]-ib,cpu1"),
+            ["gpu-1-ib", "gpu-2-ib", "cpu1"]
+        );
+    }
+
+    #[test]
+    fn several_groups() {
+        assert_eq!(
+            expand("r[1-2]n[08-09]"),
+            ["r1n08", "r1n09", "r2n08", "r2n09"]
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(expand("node[01-"), ["node[01-"]);
+        assert_eq!(expand("node[5-3]"), ["node[5-3]"]);
+        assert_eq!(expand("node[a-b],x"), ["node[a-b]", "x"]);
+    }
+}
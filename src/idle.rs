@@ -0,0 +1,206 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{Local, NaiveDateTime};
+use crossbeam::{
+    channel::{unbounded, Receiver, Sender},
+    select,
+};
+use serde::Deserialize;
+
+use crate::app::AppMessage;
+use crate::log_source::{log_source, LogSource, Throttle};
+use crate::remote::slurm_command;
+use crate::slurm_time::parse_duration;
+
+/// How often the logs and CPU times are sampled.
+const INTERVAL: Duration = Duration::from_secs(60);
+
+/// Flags my running jobs that neither write output nor use CPU time for a while, as they are
+/// likely hung, e.g. in a deadlock or waiting for a dead data loader.
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdleConfig {
+    pub enabled: bool,
+    /// How long a job has to be idle before it is flagged.
+    pub minutes: u64,
+    /// CPU time per second below which a job counts as idle, i.e. the number of busy cores.
+    pub cpu_threshold: f64,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        IdleConfig {
+            enabled: true,
+            minutes: 30,
+            cpu_threshold: 0.05,
+        }
+    }
+}
+
+/// A running job to watch: its id and logs.
+#[derive(PartialEq)]
+pub struct IdleQuery {
+    pub id: String,
+    pub logs: Vec<PathBuf>,
+}
+
+struct Activity {
+    log_size: u64,
+    cpu: Option<chrono::Duration>,
+    sampled_at: Instant,
+    /// When the job last wrote output or used CPU time.
+    active_at: NaiveDateTime,
+}
+
+/// CPU time of each job summed over its steps, from one `sstat` call for all of them.
+fn cpu_times(ids: &[&str]) -> HashMap<String, chrono::Duration> {
+    let Ok(output) = slurm_command("sstat")
+        .args(["--noheader", "--parsable2", "--allsteps"])
+        .arg(format!("--jobs={}", ids.join(",")))
+        .arg("--format=JobID,AveCPU")
+        .output()
+    else {
+        return HashMap::new();
+    };
+    let mut cpu = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((step, time)) = line.split_once('|') else {
+            continue;
+        };
+        // steps are reported as e.g. `1234.batch` or `1234_5.0`
+        let id = step.split('.').next().unwrap_or_default();
+        if let Some(time) = parse_duration(time.split('.').next().unwrap_or_default()) {
+            *cpu.entry(id.to_string())
+                .or_insert_with(chrono::Duration::zero) += time;
+        }
+    }
+    cpu
+}
+
+/// Samples the size of the logs and the CPU time of my running jobs in the background.
+struct IdleWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<(IdleConfig, Vec<IdleQuery>)>,
+    source: Box<dyn LogSource>,
+    config: IdleConfig,
+    jobs: Vec<IdleQuery>,
+    activity: HashMap<String, Activity>,
+    sampled_at: Option<Instant>,
+}
+
+impl IdleWatcher {
+    fn log_size(&mut self, job: &IdleQuery) -> u64 {
+        job.logs
+            .iter()
+            .filter_map(|path| {
+                // reading nothing from the end gives the size
+                self.source.read_tail(path, 0).ok()?;
+                Some(self.source.size())
+            })
+            .sum()
+    }
+
+    fn sample(&mut self) {
+        self.sampled_at = Some(Instant::now());
+        if !self.config.enabled || self.jobs.is_empty() {
+            self.activity.clear();
+            let _ = self.app.send(AppMessage::Idle(HashMap::new()));
+            return;
+        }
+        let ids = self.jobs.iter().map(|j| j.id.as_str()).collect::<Vec<_>>();
+        let cpu_times = cpu_times(&ids);
+        let now = Local::now().naive_local();
+        let jobs = std::mem::take(&mut self.jobs);
+        let mut activity = HashMap::new();
+        for job in &jobs {
+            let log_size = self.log_size(job);
+            let cpu = cpu_times.get(&job.id).copied();
+            let active_at = match self.activity.remove(&job.id) {
+                Some(previous) => {
+                    let seconds = previous.sampled_at.elapsed().as_secs_f64();
+                    let cpu_rate = cpu.zip(previous.cpu).map(|(cpu, previous)| {
+                        (cpu - previous).num_milliseconds() as f64 / 1000.0 / seconds
+                    });
+                    // without sstat, the logs decide alone
+                    let busy = cpu_rate.is_some_and(|r| r >= self.config.cpu_threshold);
+                    if log_size != previous.log_size || busy {
+                        now
+                    } else {
+                        previous.active_at
+                    }
+                }
+                None => now,
+            };
+            activity.insert(
+                job.id.clone(),
+                Activity {
+                    log_size,
+                    cpu,
+                    sampled_at: Instant::now(),
+                    active_at,
+                },
+            );
+        }
+        self.jobs = jobs;
+        self.activity = activity;
+        let threshold = chrono::Duration::minutes(self.config.minutes as i64);
+        let idle = self
+            .activity
+            .iter()
+            .filter(|(_, a)| now - a.active_at >= threshold)
+            .map(|(id, a)| (id.clone(), a.active_at))
+            .collect();
+        let _ = self.app.send(AppMessage::Idle(idle));
+    }
+
+    fn run(&mut self) {
+        loop {
+            let due = self
+                .sampled_at
+                .map_or(Duration::ZERO, |t| INTERVAL.saturating_sub(t.elapsed()));
+            select! {
+                recv(self.receiver) -> msg => match msg {
+                    Ok((config, jobs)) => {
+                        // new jobs are first sampled with the others
+                        self.config = config;
+                        self.jobs = jobs;
+                    }
+                    Err(_) => return,
+                },
+                default(due) => self.sample(),
+            }
+        }
+    }
+}
+
+pub struct IdleWatcherHandle {
+    sender: Sender<(IdleConfig, Vec<IdleQuery>)>,
+}
+
+impl IdleWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, throttle: Throttle) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = IdleWatcher {
+            app,
+            receiver,
+            source: log_source(throttle),
+            config: IdleConfig::default(),
+            jobs: Vec::new(),
+            activity: HashMap::new(),
+            sampled_at: None,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Watches the jobs, answered with [`AppMessage::Idle`] after each sample: the idle jobs
+    /// and since when they are idle.
+    pub fn set_jobs(&self, config: IdleConfig, jobs: Vec<IdleQuery>) {
+        let _ = self.sender.send((config, jobs));
+    }
+}
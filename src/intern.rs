@@ -0,0 +1,121 @@
+use std::{cmp::Ordering, collections::HashMap, fmt, ops::Deref, sync::RwLock};
+
+use serde::{Serialize, Serializer};
+
+#[derive(Default)]
+struct Interner {
+    ids: HashMap<&'static str, u32>,
+    strings: Vec<&'static str>,
+}
+
+lazy_static::lazy_static! {
+    static ref INTERNER: RwLock<Interner> = RwLock::new(Interner::default());
+}
+
+/// A string of a job field whose values repeat across the jobs of a large shared queue
+/// (users, partitions, states, QOS). Each distinct value is stored once for the
+/// whole process and the field is just its id, so jobs are small and cheap to clone, and
+/// comparing two fields does not compare strings. Interned values are never freed, which is
+/// fine since these fields only take a limited number of distinct values.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interned(u32);
+
+impl Interned {
+    pub fn new(s: &str) -> Self {
+        if let Some(&id) = INTERNER.read().unwrap().ids.get(s) {
+            return Self(id);
+        }
+        let mut interner = INTERNER.write().unwrap();
+        // another thread may have interned it in the meantime
+        if let Some(&id) = interner.ids.get(s) {
+            return Self(id);
+        }
+        let s: &'static str = Box::leak(s.into());
+        let id = interner.strings.len() as u32;
+        interner.strings.push(s);
+        interner.ids.insert(s, id);
+        Self(id)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        INTERNER.read().unwrap().strings[self.0 as usize]
+    }
+}
+
+impl Default for Interned {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl Deref for Interned {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Interned {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for Interned {
+    fn from(s: &str) -> Self {
+        Self::new(s)
+    }
+}
+
+impl PartialEq<str> for Interned {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Interned {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Interned {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+/// Ordered by the strings, like the fields were before being interned.
+impl Ord for Interned {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self == other {
+            true => Ordering::Equal,
+            false => self.as_str().cmp(other.as_str()),
+        }
+    }
+}
+
+impl PartialOrd for Interned {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Serialize for Interned {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
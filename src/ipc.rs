@@ -0,0 +1,186 @@
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use crossbeam::channel::{bounded, Sender};
+use serde_json::{json, Value};
+
+use crate::app::AppMessage;
+
+/// How long a request waits for the app to answer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Methods of the JSON-RPC interface:
+/// - `jobs`: the jobs in the job list,
+/// - `selected`: the id of the selected job, or `null`,
+/// - `select` (`{"id": ...}`): selects the job, expanding its group if needed,
+/// - `cancel` (`{"id": ...}`): cancels the job in the background, asking for confirmation as
+///   configured, and answers `queued` or `awaiting confirmation`,
+/// - `info`: the `pid` of the instance and its `tmux_pane`, if it runs in tmux.
+pub const METHODS: [&str; 5] = ["jobs", "selected", "select", "cancel", "info"];
+
+/// A request from an IPC client, answered by the app.
+pub struct IpcRequest {
+    pub method: String,
+    pub params: Value,
+    pub reply: Sender<Result<Value, String>>,
+}
+
+/// Where the sockets of running instances are, one per process.
+fn socket_dir() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .or_else(dirs::state_dir)
+        .map(|dir| dir.join("turm"))
+}
+
+fn error(id: &Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Answers one line-delimited JSON-RPC 2.0 request.
+fn answer(app: &Sender<AppMessage>, line: &str) -> Value {
+    let request = match serde_json::from_str::<Value>(line) {
+        Ok(request) => request,
+        Err(e) => return error(&Value::Null, -32700, &e.to_string()),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return error(&id, -32600, "missing method");
+    };
+    if !METHODS.contains(&method) {
+        return error(&id, -32601, &format!("unknown method {}", method));
+    }
+    let (reply, response) = bounded(1);
+    let request = IpcRequest {
+        method: method.to_string(),
+        params: request.get("params").cloned().unwrap_or(Value::Null),
+        reply,
+    };
+    if app.send(AppMessage::Ipc(request)).is_err() {
+        return error(&id, -32000, "turm is shutting down");
+    }
+    match response.recv_timeout(TIMEOUT) {
+        Ok(Ok(result)) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Ok(Err(message)) => error(&id, -32000, &message),
+        Err(_) => error(&id, -32000, "turm did not answer in time"),
+    }
+}
+
+/// Listens on a Unix domain socket through which scripts and editor integrations can query and
+/// control this instance. The socket is removed when the handle is dropped.
+pub struct IpcServerHandle {
+    path: PathBuf,
+}
+
+impl IpcServerHandle {
+    #[cfg(unix)]
+    pub fn new(app: Sender<AppMessage>) -> io::Result<Self> {
+        use std::os::unix::{fs::DirBuilderExt, net::UnixListener};
+
+        let dir = socket_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no runtime directory"))?;
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)?;
+        let path = dir.join(format!("turm-{}.sock", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let app = app.clone();
+                thread::spawn(move || {
+                    let Ok(mut writer) = stream.try_clone() else {
+                        return;
+                    };
+                    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        let response = answer(&app, &line);
+                        if writeln!(writer, "{}", response).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        Ok(Self { path })
+    }
+
+    #[cfg(not(unix))]
+    pub fn new(_app: Sender<AppMessage>) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "IPC sockets are only supported on Unix",
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Connects to `$TURM_SOCKET` or else to the most recently started instance.
+#[cfg(unix)]
+fn connect() -> io::Result<std::os::unix::net::UnixStream> {
+    use std::os::unix::net::UnixStream;
+
+    if let Some(path) = std::env::var_os("TURM_SOCKET") {
+        return UnixStream::connect(path);
+    }
+    let dir = socket_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no runtime directory"))?;
+    let mut sockets = fs::read_dir(&dir)?
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().ends_with(".sock"))
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .collect::<Vec<_>>();
+    sockets.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    // sockets of crashed instances are left behind
+    sockets
+        .into_iter()
+        .find_map(|(_, path)| UnixStream::connect(path).ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no running turm instance"))
+}
+
+/// Calls a method of a running instance and returns its result.
+#[cfg(unix)]
+pub fn call(method: &str, params: Value) -> io::Result<Value> {
+    let stream = connect()?;
+    stream.set_read_timeout(Some(TIMEOUT * 2))?;
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    writeln!(&stream, "{}", request)?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let mut response = serde_json::from_str::<Value>(&line)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    match response.get("error") {
+        Some(error) => Err(io::Error::other(
+            error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error")
+                .to_string(),
+        )),
+        None => Ok(response["result"].take()),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn call(_method: &str, _params: Value) -> io::Result<Value> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "IPC sockets are only supported on Unix",
+    ))
+}
+
+impl Drop for IpcServerHandle {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
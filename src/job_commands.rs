@@ -0,0 +1,152 @@
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use serde::Deserialize;
+
+use crate::app::Job;
+use crate::hostlist;
+use crate::remote;
+
+/// Set while a command has the terminal, so that the input loop does not steal its keystrokes.
+pub static SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+/// How often the input loop checks whether it is suspended. Suspending waits a little longer
+/// than this so that the input loop is not polling the terminal anymore.
+pub const INPUT_POLL: Duration = Duration::from_millis(100);
+
+/// A command for the selected job, e.g. `ssh -t {node} htop`, run from the command palette (`:`).
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobCommand {
+    pub name: String,
+    /// Shell command with `{job_id}`, `{stdout}` and `{node}` (the first node of the job)
+    /// replaced by shell-quoted values.
+    pub command: String,
+}
+
+impl JobCommand {
+    pub fn expand(&self, job: &Job) -> String {
+        let stdout = job
+            .stdout
+            .as_ref()
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut command = self
+            .command
+            .replace("{job_id}", &quote(&job.id()))
+            .replace("{stdout}", &quote(&stdout));
+        if command.contains("{node}") {
+            command = command.replace("{node}", &quote(&first_node(&job.nodelist)));
+        }
+        command
+    }
+}
+
+/// The command logging into a node, through the remote host in remote mode.
+pub fn ssh_to_node(node: &str) -> String {
+    match remote::host() {
+        Some(host) => format!("ssh -t {} ssh {}", quote(host), quote(node)),
+        None => format!("ssh {}", quote(node)),
+    }
+}
+
+/// Opens the output of the job in a new tmux pane next to turm, running `command` (`tail -F
+/// {stdout}` by default, over ssh in remote mode).
+pub fn open_in_tmux_pane(
+    job: &Job,
+    command: Option<&str>,
+    socket: Option<&Path>,
+) -> Result<String, String> {
+    if std::env::var_os("TMUX").is_none() {
+        return Err("Not running inside tmux".to_string());
+    }
+    if job.stdout.is_none() {
+        return Err(format!("Job {} has no output file", job.id()));
+    }
+    let command = match (command, remote::host()) {
+        (Some(command), _) => command.to_string(),
+        (None, Some(host)) => format!("ssh -t {} tail -F {{stdout}}", quote(host)),
+        (None, None) => "tail -F {stdout}".to_string(),
+    };
+    let command = JobCommand {
+        name: String::new(),
+        command,
+    }
+    .expand(job);
+    let mut tmux = Command::new("tmux");
+    tmux.args(["split-window", "-h"]);
+    // the pane gets the environment of the tmux server, not ours
+    if let Some(socket) = socket {
+        tmux.arg("-e")
+            .arg(format!("TURM_SOCKET={}", socket.display()));
+    }
+    let status = tmux
+        .arg(&command)
+        .status()
+        .map_err(|e| format!("Failed to run tmux: {}", e))?;
+    if status.success() {
+        Ok(format!("Opened `{}` in a new tmux pane", command))
+    } else {
+        Err(format!("tmux split-window failed ({})", status))
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// The first host of a Slurm host list like `gpu-[17-18]`.
+fn first_node(nodelist: &str) -> String {
+    hostlist::expand(nodelist)
+        .into_iter()
+        .next()
+        .unwrap_or_default()
+}
+
+/// Leaves the TUI, runs the command (locally, like hooks) in the terminal and waits for enter
+/// before the TUI is restored, so that output like a `py-spy dump` can be read.
+pub fn run_suspended(command: &str, socket: Option<&Path>) -> io::Result<Result<String, String>> {
+    SUSPENDED.store(true, Ordering::SeqCst);
+    thread::sleep(INPUT_POLL * 2);
+    let mut stdout = io::stdout();
+    disable_raw_mode()?;
+    execute!(stdout, LeaveAlternateScreen, DisableMouseCapture, Show)?;
+
+    let mut shell = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C");
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c");
+        c
+    };
+    if let Some(socket) = socket {
+        shell.env("TURM_SOCKET", socket);
+    }
+    let result = match shell.arg(command).status() {
+        Ok(status) if status.success() => Ok(format!("`{}` finished", command)),
+        Ok(status) => Err(format!("`{}` failed ({})", command, status)),
+        Err(e) => Err(format!("Failed to run `{}`: {}", command, e)),
+    };
+    print!("\n[turm] press enter to return");
+    stdout.flush()?;
+    io::stdin().lock().read_line(&mut String::new())?;
+
+    enable_raw_mode()?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    SUSPENDED.store(false, Ordering::SeqCst);
+    Ok(result)
+}
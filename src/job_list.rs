@@ -0,0 +1,200 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::app::Job;
+use crate::config::GroupRule;
+
+/// A row of the job list: either a job or the header of a group of related jobs.
+pub enum JobListRow {
+    /// Index into the job list.
+    Job(usize),
+    Group(JobGroup),
+}
+
+pub struct JobGroup {
+    pub key: String,
+    /// Indices of the member jobs, in job list order.
+    pub jobs: Vec<usize>,
+    pub collapsed: bool,
+}
+
+/// Identifies a row across job list updates.
+#[derive(Clone, PartialEq)]
+pub enum RowId {
+    Job(String),
+    Group(String),
+}
+
+impl JobListRow {
+    pub fn id(&self, jobs: &[Job]) -> RowId {
+        match self {
+            JobListRow::Job(i) => RowId::Job(jobs[*i].id()),
+            JobListRow::Group(g) => RowId::Group(g.key.clone()),
+        }
+    }
+
+    /// The job of the row, `None` for a group header.
+    pub fn job<'a>(&self, jobs: &'a [Job]) -> Option<&'a Job> {
+        match self {
+            JobListRow::Job(i) => jobs.get(*i),
+            JobListRow::Group(_) => None,
+        }
+    }
+}
+
+/// Returns the key of the group `name` belongs to, according to the first matching rule.
+fn group_key(rules: &[GroupRule], name: &str) -> Option<String> {
+    rules.iter().find_map(|rule| {
+        let caps = rule.regex.captures(name)?;
+        let template = match &rule.name {
+            Some(name) => name.as_str(),
+            None if caps.len() > 1 => "$1",
+            None => "$0",
+        };
+        let mut key = String::new();
+        caps.expand(template, &mut key);
+        Some(key).filter(|k| !k.is_empty())
+    })
+}
+
+/// Builds the rows of the job list. Pinned jobs come first and are never grouped.
+/// Grouped jobs are moved right after their group header, which takes the position of the
+/// first member. Groups need at least two members, except with `by_user`, where jobs are
+/// grouped by their user instead of by the rules.
+pub fn build_rows(
+    jobs: &[Job],
+    rules: &[GroupRule],
+    collapsed: &HashSet<String>,
+    by_user: bool,
+    is_pinned: impl Fn(&Job) -> bool,
+) -> Vec<JobListRow> {
+    let pinned: Vec<_> = jobs.iter().map(is_pinned).collect();
+    let keys: Vec<_> = jobs
+        .iter()
+        .zip(pinned.iter())
+        .map(|(j, &pinned)| {
+            if pinned {
+                None
+            } else if by_user {
+                Some(j.user.to_string())
+            } else {
+                group_key(rules, &j.name)
+            }
+        })
+        .collect();
+    let mut members: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        if let Some(key) = key {
+            members.entry(key).or_default().push(i);
+        }
+    }
+
+    let mut rows: Vec<_> = (0..jobs.len())
+        .filter(|&i| pinned[i])
+        .map(JobListRow::Job)
+        .collect();
+    for (i, key) in keys.iter().enumerate() {
+        if pinned[i] {
+            continue;
+        }
+        match key.as_deref().and_then(|k| members.get(k).map(|m| (k, m))) {
+            Some((key, group)) if group.len() > 1 || by_user => {
+                if group[0] != i {
+                    continue; // already added with the group
+                }
+                let is_collapsed = collapsed.contains(key);
+                rows.push(JobListRow::Group(JobGroup {
+                    key: key.to_owned(),
+                    jobs: group.clone(),
+                    collapsed: is_collapsed,
+                }));
+                if !is_collapsed {
+                    rows.extend(group.iter().map(|&i| JobListRow::Job(i)));
+                }
+            }
+            _ => rows.push(JobListRow::Job(i)),
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    fn jobs() -> Vec<Job> {
+        vec![
+            Job::fixture("101", "train", "RUNNING", "alice"),
+            Job::fixture("102", "sweep_lr1", "RUNNING", "alice"),
+            Job::fixture("103", "sweep_lr2", "PENDING", "alice"),
+            Job::fixture("104", "eval", "PENDING", "bob"),
+        ]
+    }
+
+    fn rules() -> [GroupRule; 1] {
+        [GroupRule {
+            regex: Regex::new("^(sweep)_").unwrap(),
+            name: None,
+        }]
+    }
+
+    /// The job ids of the rows, with `[key]` for group headers.
+    fn ids(rows: &[JobListRow], jobs: &[Job]) -> Vec<String> {
+        rows.iter()
+            .map(|row| match row {
+                JobListRow::Job(_) => row.job(jobs).unwrap().id(),
+                JobListRow::Group(g) => format!("[{}]", g.key),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rows_of_pinned_and_grouped_jobs() {
+        let jobs = jobs();
+        let rows = build_rows(&jobs, &rules(), &HashSet::new(), false, |j| {
+            j.job_id == "104"
+        });
+        assert_eq!(ids(&rows, &jobs), ["104", "101", "[sweep]", "102", "103"]);
+        // rows below a pinned job or a group header are not at their job's index
+        assert_eq!(rows[1].job(&jobs).unwrap().job_id, "101");
+        assert_eq!(rows[3].job(&jobs).unwrap().job_id, "102");
+        assert!(rows[2].job(&jobs).is_none());
+        assert!(rows[3].id(&jobs) == RowId::Job("102".to_string()));
+        assert!(rows[2].id(&jobs) == RowId::Group("sweep".to_string()));
+    }
+
+    #[test]
+    fn rows_of_collapsed_groups() {
+        let jobs = jobs();
+        let collapsed = HashSet::from(["sweep".to_string()]);
+        let rows = build_rows(&jobs, &rules(), &collapsed, false, |_| false);
+        assert_eq!(ids(&rows, &jobs), ["101", "[sweep]", "104"]);
+        match &rows[1] {
+            JobListRow::Group(g) => {
+                assert_eq!((g.jobs.as_slice(), g.collapsed), (&[1, 2][..], true))
+            }
+            JobListRow::Job(_) => panic!("expected the group header"),
+        }
+    }
+
+    #[test]
+    fn groups_need_two_members() {
+        let mut jobs = jobs();
+        jobs.truncate(2);
+        let rows = build_rows(&jobs, &rules(), &HashSet::new(), false, |_| false);
+        assert_eq!(ids(&rows, &jobs), ["101", "102"]);
+    }
+
+    #[test]
+    fn rows_by_user() {
+        let jobs = jobs();
+        let rows = build_rows(&jobs, &rules(), &HashSet::new(), true, |j| {
+            j.job_id == "102"
+        });
+        assert_eq!(
+            ids(&rows, &jobs),
+            ["102", "[alice]", "101", "103", "[bob]", "104"]
+        );
+    }
+}
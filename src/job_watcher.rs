@@ -1,283 +1,262 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::{io::BufRead, process::Command, thread, time::Duration};
+use std::sync::Mutex;
+use std::{thread, time::Duration};
 
-use crossbeam::channel::Sender;
-use regex::Regex;
+use chrono::Local;
+use crossbeam::{
+    channel::{unbounded, Receiver, Sender},
+    select,
+};
 
 use crate::app::AppMessage;
 use crate::app::Job;
+use crate::hostlist;
+use crate::intern::Interned;
+use crate::remote::slurm_command;
+use crate::slurm_time::{parse_duration, parse_timestamp};
+use crate::states;
 
 struct JobWatcher {
     app: Sender<AppMessage>,
+    receiver: Receiver<JobWatcherMessage>,
     interval: Duration,
     squeue_args: Vec<String>,
     sacct_args: Vec<String>,
-    job_cache: HashMap<String, Job>,
+    job_cache: JobCache,
+    pinned: Vec<String>,
+    finished_retention: Duration,
+    /// Whether `sacct` can be used to get recently finished jobs.
+    sacct: bool,
+    /// Without `sacct`: the jobs that left the queue and that `scontrol` reported finished, by
+    /// job id.
+    finished: HashMap<String, Job>,
+    /// Whether the scope changed since the last poll, so that jobs missing from the queue did
+    /// not necessarily finish.
+    scope_changed: bool,
 }
 
-pub struct JobWatcherHandle {}
+pub enum JobWatcherMessage {
+    Args {
+        squeue_args: Vec<String>,
+        sacct_args: Vec<String>,
+    },
+    Pinned(Vec<String>),
+    Settings {
+        interval: Duration,
+        finished_retention: Duration,
+    },
+}
+
+pub struct JobWatcherHandle {
+    sender: Sender<JobWatcherMessage>,
+}
+
+/// Separates the fields in the output of `squeue` and `sacct`, since job names and commands can
+/// contain any other character.
+pub const SEPARATOR: &str = "###turm###";
+
+/// `sacct --format` fields of finished jobs. Not all fields we need to create a Job are
+/// available via `sacct` (most notably, stdout/stderr are missing on our cluster). So we only
+/// grab some from a cache. On the other hand, we still want as many fields as possible so that
+/// these are useful even if turm just started and the cache is empty.
+pub const SACCT_FIELDS: [&str; 17] = [
+    "jobid",
+    "jobname",
+    "state",
+    "user",
+    "elapsed",
+    "alloctres",
+    "partition",
+    "nodelist",
+    "submitline",
+    "reason",
+    "qos",
+    "submit",
+    "start",
+    "end",
+    "timelimit",
+    "workdir",
+    "consumedenergyraw",
+];
+
+/// `squeue --Format` fields and the Slurm version that introduced them, if they are not
+/// available in all supported versions.
+pub const FORMAT_FIELDS: [(&str, Option<(u32, u32)>); 24] = [
+    ("jobid", None),
+    ("name", None),
+    ("state", None),
+    ("username", None),
+    ("timeused", None),
+    ("tres-alloc", Some((19, 5))),
+    ("partition", None),
+    ("nodelist", None),
+    ("stdout", Some((18, 8))),
+    ("stderr", Some((18, 8))),
+    ("command", None),
+    ("statecompact", None),
+    ("reason", None),
+    ("qos", None),
+    ("ArrayJobID", None),  // %A
+    ("ArrayTaskID", None), // %a
+    ("NodeList", None),    // %N
+    ("WorkDir", None),     // for fallback
+    ("SubmitTime", None),
+    ("StartTime", None),
+    ("EndTime", None),
+    ("TimeLimit", None),
+    ("RestartCnt", None),
+    ("dependency", None),
+];
+
+lazy_static::lazy_static! {
+    /// The fields supported by the installed Slurm version.
+    static ref SQUEUE_FIELDS: Mutex<Vec<&'static str>> = {
+        let version = slurm_version();
+        Mutex::new(
+            FORMAT_FIELDS
+                .iter()
+                .filter(|(_, since)| match (since, version) {
+                    (Some(since), Some(version)) => version >= *since,
+                    _ => true,
+                })
+                .map(|(f, _)| *f)
+                .collect(),
+        )
+    };
+}
+
+/// The `(major, minor)` version of Slurm, e.g. `(23, 2)` for `slurm 23.02.7`.
+fn slurm_version() -> Option<(u32, u32)> {
+    let output = slurm_command("squeue").arg("--version").output().ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let mut parts = output.split_whitespace().last()?.split('.');
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
 
 impl JobWatcher {
     fn new(
         app: Sender<AppMessage>,
+        receiver: Receiver<JobWatcherMessage>,
         interval: Duration,
         squeue_args: Vec<String>,
         sacct_args: Vec<String>,
+        finished_retention: Duration,
+        sacct: bool,
     ) -> Self {
         Self {
             app,
+            receiver,
             interval,
             squeue_args,
             sacct_args,
-            job_cache: HashMap::new(),
+            job_cache: JobCache::default(),
+            pinned: Vec::new(),
+            finished_retention,
+            sacct,
+            finished: HashMap::new(),
+            scope_changed: false,
         }
     }
 
-    fn get_running_jobs(&self) -> Vec<Job> {
-        let output_separator = "###turm###";
-        let fields = [
-            "jobid",
-            "name",
-            "state",
-            "username",
-            "timeused",
-            "tres-alloc",
-            "partition",
-            "nodelist",
-            "stdout",
-            "stderr",
-            "command",
-            "statecompact",
-            "reason",
-            "qos",
-            "ArrayJobID",  // %A
-            "ArrayTaskID", // %a
-            "NodeList",    // %N
-            "WorkDir",     // for fallback
-        ];
+    fn get_running_jobs(squeue_args: &[String]) -> Vec<Job> {
+        let (output, fields) = Self::squeue(squeue_args);
+        parse_squeue(&output, &fields)
+    }
+
+    /// The output of `squeue` with the fields that this Slurm version supports.
+    fn squeue(squeue_args: &[String]) -> (String, Vec<&'static str>) {
+        let fields = SQUEUE_FIELDS.lock().unwrap().clone();
         let output_format = fields
-            .map(|s| s.to_owned() + ":" + output_separator)
+            .iter()
+            .map(|s| s.to_string() + ":" + SEPARATOR)
+            .collect::<Vec<_>>()
             .join(",");
-        Command::new("squeue")
-            .args(&self.squeue_args)
+        let output = slurm_command("squeue")
+            .args(squeue_args)
             .arg("--array")
             .arg("--noheader")
             .arg("--Format")
             .arg(&output_format)
             .output()
-            .expect("failed to execute process")
-            .stdout
-            .lines()
-            .map(|l| l.unwrap().trim().to_string())
-            .filter_map(|l| {
-                let parts: Vec<_> = l.split(output_separator).collect();
-
-                if parts.len() != fields.len() + 1 {
-                    return None;
-                }
+            .expect("failed to execute process");
 
-                let id = parts[0];
-                let name = parts[1];
-                let state = parts[2];
-                let user = parts[3];
-                let time = parts[4];
-                let tres = parts[5];
-                let partition = parts[6];
-                let nodelist = parts[7];
-                let stdout = parts[8];
-                let stderr = parts[9];
-                let command = parts[10];
-                let state_compact = parts[11];
-                let reason = parts[12];
-                let qos = parts[13];
-
-                let array_job_id = parts[14];
-                let array_task_id = parts[15];
-                let node_list = parts[16];
-                let working_dir = parts[17];
-
-                Some(Job {
-                    job_id: id.to_owned(),
-                    array_id: array_job_id.to_owned(),
-                    array_step: match array_task_id {
-                        "N/A" => None,
-                        _ => Some(array_task_id.to_owned()),
-                    },
-                    name: name.to_owned(),
-                    state: state.to_owned(),
-                    state_compact: state_compact.to_owned(),
-                    reason: if reason == "None" {
-                        None
-                    } else {
-                        Some(reason.to_owned())
-                    },
-                    qos: qos.to_owned(),
-                    user: user.to_owned(),
-                    time: time.to_owned(),
-                    tres: tres.to_owned(),
-                    partition: partition.to_owned(),
-                    nodelist: nodelist.to_owned(),
-                    command: command.to_owned(),
-                    stdout: Self::resolve_path(
-                        stdout,
-                        array_job_id,
-                        array_task_id,
-                        id,
-                        node_list,
-                        user,
-                        name,
-                        working_dir,
-                    ),
-                    stderr: Self::resolve_path(
-                        stderr,
-                        array_job_id,
-                        array_task_id,
-                        id,
-                        node_list,
-                        user,
-                        name,
-                        working_dir,
-                    ), // TODO fill all fields
-                })
-            })
-            .collect()
+        // Drop a field that this Slurm version rejects and try again
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if let Some(field) = rejected_field(&stderr)
+                .and_then(|rejected| fields.iter().find(|f| f.eq_ignore_ascii_case(rejected)))
+            {
+                SQUEUE_FIELDS.lock().unwrap().retain(|f| f != field);
+                return Self::squeue(squeue_args);
+            }
+        }
+
+        // only copied if it is not valid UTF-8
+        let output = String::from_utf8(output.stdout)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned());
+        (output, fields)
     }
 
-    fn get_finished_jobs(&self) -> Vec<Job> {
-        let output_separator = "###turm###";
-        // Not all fields we need to create a Job are available via `sacct`
-        // (most notably, stdout/stderr are missing on our cluster). So we only grab
-        // some from a cache. On the other hand, we still want as many fields as
-        // possible so that these are useful even if turm just started and the
-        // cache is empty.
-        let fields = [
-            "jobid",
-            "jobname",
-            "state",
-            "user",
-            "elapsed",
-            "alloctres",
-            "partition",
-            "nodelist",
-            "submitline",
-            "reason",
-            "qos",
-        ];
-        let output_format = fields.join(",");
-        Command::new("sacct")
-            .args(&self.sacct_args)
+    fn get_finished_jobs(sacct_args: &[String], since: Duration) -> Vec<Job> {
+        let output = slurm_command("sacct")
+            .args(sacct_args)
             .arg("--array")
             .arg("--noheader")
             .arg("--format")
-            .arg(&output_format)
+            .arg(SACCT_FIELDS.join(","))
             .arg("--delimiter")
-            .arg(output_separator)
+            .arg(SEPARATOR)
             .arg("-X")
             .arg("--parsable")
             .arg("--starttime")
-            .arg("now-1hours")
+            .arg(format!("now-{}seconds", since.as_secs()))
             .arg("--endtime")
             .arg("now")
             .arg("--state")
             .arg("COMPLETED,CANCELLED,FAILED,TIMEOUT,PREEMPTED,OUT_OF_MEMORY")
             .output()
-            .expect("failed to execute process")
-            .stdout
-            .lines()
-            .map(|l| l.unwrap().trim().to_string())
-            .filter_map(|l| {
-                let parts: Vec<_> = l.split(output_separator).collect();
-
-                if parts.len() != fields.len() + 1 {
-                    return None;
-                }
-
-                let id = parts[0];
-                let name = parts[1];
-                let state = parts[2];
-                let user = parts[3];
-                let time = parts[4];
-                let tres = parts[5];
-                let partition = parts[6];
-                let nodelist = parts[7];
-                let command = parts[8]
-                    // Remove the `sbatch` part of the command and slurm arguments.
-                    // That matches the `squeue` "command" field.
-                    .split_whitespace()
-                    .skip_while(|&arg| arg.starts_with("sbatch") || arg.starts_with('-'))
-                    .collect::<Vec<_>>()
-                    .join(" ");
-                let command = if command.is_empty() {
-                    parts[8].to_owned()
-                } else {
-                    command
-                };
-                let reason = parts[9];
-                let qos = parts[10];
-
-                let state_compact = match state {
-                    "RUNNING" => "R",
-                    "PENDING" => "PD",
-                    "COMPLETED" => "CD",
-                    "CANCELLED" => "CA",
-                    "FAILED" => "F",
-                    "TIMEOUT" => "TO",
-                    "NODE_FAIL" => "NF",
-                    "PREEMPTED" => "PR",
-                    "SUSPENDED" => "S",
-                    _ => state, // Use the full state if it's not one of the known ones
-                };
-
-                // It seems sacct doesn't expose array ids, so we get them manually
-                let (array_job_id, array_task_id) = if id.contains('_') {
-                    let parts: Vec<&str> = id.split('_').collect();
-                    if parts.len() == 2 {
-                        (parts[0], parts[1])
-                    } else {
-                        (id, "N/A")
-                    }
-                } else {
-                    (id, "N/A")
-                };
-
-                Some(Job {
-                    job_id: id.to_owned(),
-                    array_id: array_job_id.to_owned(),
-                    array_step: match array_task_id {
-                        "N/A" => None,
-                        _ => Some(array_task_id.to_owned()),
-                    },
-                    name: name.to_owned(),
-                    state: state.to_owned(),
-                    state_compact: state_compact.to_owned(),
-                    reason: if reason == "None" {
-                        None
-                    } else {
-                        Some(reason.to_owned())
-                    },
-                    qos: qos.to_owned(),
-                    user: user.to_owned(),
-                    time: time.to_owned(),
-                    tres: tres.to_owned(),
-                    partition: partition.to_owned(),
-                    nodelist: nodelist.to_owned(),
-                    command: command.to_owned(),
-                    stdout: None,
-                    stderr: None,
-                })
-            })
-            .collect()
+            // sacct is optional, e.g. if accounting is disabled
+            .map(|o| o.stdout)
+            .unwrap_or_default();
+        parse_sacct(&String::from_utf8_lossy(&output))
     }
 
     fn run(&mut self) -> Self {
         loop {
-            let running_jobs = self.get_running_jobs();
-            let finished_jobs = self.get_finished_jobs();
+            let (output, fields) = Self::squeue(&self.squeue_args);
+            let mut running_jobs = self.job_cache.parse_squeue(&output, &fields);
+            let mut finished_jobs = if self.sacct {
+                Self::get_finished_jobs(&self.sacct_args, self.finished_retention)
+            } else {
+                Vec::new()
+            };
 
-            // Update cache with running jobs
-            for job in &running_jobs {
-                self.job_cache.insert(job.job_id.clone(), job.clone());
+            // Pinned jobs are watched even if they are outside of the current scope
+            let missing_pinned = self
+                .pinned
+                .iter()
+                .filter(|id| {
+                    !running_jobs
+                        .iter()
+                        .chain(finished_jobs.iter())
+                        .any(|j| &j.id() == *id)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if !missing_pinned.is_empty() {
+                let jobs_arg = format!("--jobs={}", missing_pinned.join(","));
+                let (output, fields) = Self::squeue(std::slice::from_ref(&jobs_arg));
+                running_jobs.extend(self.job_cache.parse_squeue(&output, &fields));
+                if self.sacct {
+                    finished_jobs.extend(Self::get_finished_jobs(
+                        &[jobs_arg, "--allusers".to_string()],
+                        self.finished_retention,
+                    ));
+                }
+            }
+            if !self.sacct {
+                finished_jobs = self.scontrol_finished(&running_jobs);
             }
 
             // Fill in missing info for finished jobs
@@ -293,84 +272,530 @@ impl JobWatcher {
                 .collect::<Vec<Job>>();
 
             // Combine running and finished jobs
+            let mut seen = HashSet::new();
             let jobs: Vec<Job> = running_jobs
                 .into_iter()
-                .chain(finished_jobs.into_iter())
+                .chain(finished_jobs)
+                .filter(|job| seen.insert(job.id()))
                 .collect();
 
             // Clean up cache (remove jobs that are no longer running or finished)
-            let active_job_ids: std::collections::HashSet<String> =
+            let active_job_ids: HashSet<String> =
                 jobs.iter().map(|job| job.job_id.clone()).collect();
-            self.job_cache
-                .retain(|job_id, _| active_job_ids.contains(job_id));
+            self.job_cache.retain(&active_job_ids);
 
             self.app.send(AppMessage::Jobs(jobs)).unwrap();
-            thread::sleep(self.interval);
+
+            select! {
+                recv(self.receiver) -> msg => match msg {
+                    // re-poll immediately with the new arguments
+                    Ok(JobWatcherMessage::Args { squeue_args, sacct_args }) => {
+                        self.squeue_args = squeue_args;
+                        self.sacct_args = sacct_args;
+                        self.scope_changed = true;
+                    }
+                    Ok(JobWatcherMessage::Pinned(pinned)) => self.pinned = pinned,
+                    Ok(JobWatcherMessage::Settings { interval, finished_retention }) => {
+                        self.interval = interval;
+                        self.finished_retention = finished_retention;
+                    }
+                    // the handle was dropped, keep polling at the regular interval
+                    Err(_) => thread::sleep(self.interval),
+                },
+                default(self.interval) => {}
+            }
         }
     }
 
-    fn resolve_path(
-        path: &str,
-        array_master: &str,
-        array_id: &str,
-        id: &str,
-        host: &str,
-        user: &str,
-        name: &str,
-        working_dir: &str,
-    ) -> Option<PathBuf> {
-        // see https://slurm.schedmd.com/sbatch.html#SECTION_%3CB%3Efilename-pattern%3C/B%3E
-        lazy_static::lazy_static! {
-            static ref RE: Regex = Regex::new(r"%(%|A|a|J|j|N|n|s|t|u|x)").unwrap();
+    /// The finished jobs without `sacct`: the jobs that left the queue since the last poll and
+    /// that `scontrol` reports in a final state, which it does for `MinJobAge` (5 minutes by
+    /// default) after they ended. They are kept for the retention time like with `sacct`.
+    fn scontrol_finished(&mut self, running_jobs: &[Job]) -> Vec<Job> {
+        let running = running_jobs
+            .iter()
+            .map(|j| j.job_id.as_str())
+            .collect::<HashSet<_>>();
+        if !std::mem::take(&mut self.scope_changed) {
+            let left = self
+                .job_cache
+                .jobs()
+                .filter(|j| {
+                    !running.contains(j.job_id.as_str()) && !self.finished.contains_key(&j.job_id)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            for job in left.into_iter().filter_map(scontrol_final_state) {
+                self.finished.insert(job.job_id.clone(), job);
+            }
         }
+        let now = Local::now().naive_local();
+        let retention =
+            chrono::Duration::from_std(self.finished_retention).unwrap_or(chrono::Duration::MAX);
+        // requeued jobs are back in the queue
+        self.finished.retain(|id, job| {
+            !running.contains(id.as_str()) && job.end_time.is_none_or(|end| now - end < retention)
+        });
+        self.finished.values().cloned().collect()
+    }
+}
+
+/// Resolves a filename pattern of `sbatch` or `srun`, such as `%x-%j.out`, for the job step
+/// `step` (`batch` for the batch script). Relative patterns are relative to `working_dir`.
+#[allow(clippy::too_many_arguments)]
+pub fn resolve_path(
+    path: &str,
+    array_master: &str,
+    array_id: &str,
+    id: &str,
+    step: &str,
+    host: &str,
+    user: &str,
+    name: &str,
+    working_dir: &str,
+) -> Option<PathBuf> {
+    // see https://slurm.schedmd.com/sbatch.html#SECTION_%3CB%3Efilename-pattern%3C/B%3E
+    let slurm_no_val = "4294967294";
+    let array_id = if array_id == "N/A" {
+        slurm_no_val
+    } else {
+        array_id
+    };
 
-        let mut path = path.to_owned();
-        let slurm_no_val = "4294967294";
-        let array_id = if array_id == "N/A" {
-            slurm_no_val
+    let default;
+    let pattern = if path.is_empty() {
+        // `squeue -O stdout` seems to always return something, but old Slurm versions
+        // do not support the field
+        // not `Path::join`, the path is on the cluster even if turm runs on Windows
+        let file_name = if array_id == slurm_no_val {
+            "slurm-%J.out"
         } else {
-            array_id
+            "slurm-%A_%a.out"
         };
+        default = format!("{}/{}", working_dir.trim_end_matches('/'), file_name);
+        &default
+    } else if !path.starts_with('/') && !working_dir.is_empty() {
+        // e.g. `srun --output` relative to the working directory of the job
+        default = format!("{}/{}", working_dir.trim_end_matches('/'), path);
+        &default
+    } else {
+        path
+    };
 
-        if path.is_empty() {
-            // never happens right now, because `squeue -O stdout` seems to always return something
-            path = if array_id == slurm_no_val {
-                PathBuf::from(working_dir).join("slurm-%J.out")
-            } else {
-                PathBuf::from(working_dir).join("slurm-%A_%a.out")
+    // the first node of a host list such as `gpu-[01-02]`, which changes when a requeued
+    // job starts on other nodes
+    let first_node = match pattern.contains("%N") {
+        true => hostlist::expand(host)
+            .into_iter()
+            .next()
+            .unwrap_or_default(),
+        false => String::new(),
+    };
+    let job_step = match step {
+        "batch" => id.to_string(),
+        step => format!("{}.{}", id, step),
+    };
+    let mut path = String::with_capacity(pattern.len() + 16);
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            path.push(c);
+            continue;
+        }
+        let replacement = match chars.clone().next() {
+            Some('%') => "%",
+            Some('A') => array_master,
+            Some('a') => array_id,
+            Some('J') => &job_step,
+            Some('j') => id,
+            Some('N') => &first_node,
+            Some('n') => "0",
+            Some('s') => step,
+            Some('t') => "0",
+            Some('u') => user,
+            Some('x') => name,
+            // not a replacement symbol, kept as it is
+            _ => {
+                path.push(c);
+                continue;
             }
-            .to_str()
-            .unwrap()
-            .to_owned()
         };
+        chars.next();
+        path.push_str(replacement);
+    }
 
-        for cap in RE
-            .captures_iter(&path.clone())
-            .collect::<Vec<_>>() // TODO: this is stupid, there has to be a better way to reverse the captures...
-            .iter()
-            .rev()
-        {
-            let m = cap.get(0).unwrap();
-            let replacement = match m.as_str() {
-                "%%" => "%",
-                "%A" => array_master,
-                "%a" => array_id,
-                "%J" => id,
-                "%j" => id,
-                "%N" => host.split(',').next().unwrap_or(host),
-                "%n" => "0",
-                "%s" => "batch",
-                "%t" => "0",
-                "%u" => user,
-                "%x" => name,
-                _ => unreachable!(),
-            };
+    Some(PathBuf::from(path))
+}
+
+/// Splits a line of output into its first `len` fields, each followed by [`SEPARATOR`], without
+/// allocating. Lines that do not have exactly `len` fields are rejected.
+fn split_line<const N: usize>(line: &str, len: usize) -> Option<[&str; N]> {
+    let mut parts = [""; N];
+    let mut split = line.split(SEPARATOR);
+    for part in parts.iter_mut().take(len) {
+        *part = split.next()?;
+    }
+    (split.next().is_some() && split.next().is_none()).then_some(parts)
+}
+
+/// A line of `squeue --Format` output with the fields borrowed from the output. Fields that are
+/// not supported by the installed Slurm version are empty.
+pub struct SqueueRecord<'a> {
+    pub line: &'a str,
+    pub id: &'a str,
+    pub name: &'a str,
+    pub state: &'a str,
+    pub user: &'a str,
+    pub time: &'a str,
+    pub tres: &'a str,
+    pub partition: &'a str,
+    pub nodelist: &'a str,
+    pub stdout: &'a str,
+    pub stderr: &'a str,
+    pub command: &'a str,
+    pub state_compact: &'a str,
+    pub reason: &'a str,
+    pub qos: &'a str,
+    pub array_job_id: &'a str,
+    pub array_task_id: &'a str,
+    pub node_list: &'a str,
+    pub work_dir: &'a str,
+    pub submit_time: &'a str,
+    pub start_time: &'a str,
+    pub end_time: &'a str,
+    pub time_limit: &'a str,
+    pub restarts: &'a str,
+    pub dependency: &'a str,
+}
+
+/// The lines of `squeue --Format` output with the given fields, each followed by
+/// [`SEPARATOR`]. Lines that do not have all fields are skipped.
+pub fn squeue_records<'a>(
+    output: &'a str,
+    fields: &[&str],
+) -> impl Iterator<Item = SqueueRecord<'a>> {
+    // where each of the FORMAT_FIELDS is in the output, if at all
+    let positions: [Option<usize>; FORMAT_FIELDS.len()] =
+        std::array::from_fn(|i| fields.iter().position(|f| *f == FORMAT_FIELDS[i].0));
+    let len = fields.len();
+    output.lines().map(str::trim).filter_map(move |line| {
+        let parts = split_line::<{ FORMAT_FIELDS.len() }>(line, len)?;
+        // in the order of FORMAT_FIELDS
+        let [
+            id,
+            name,
+            state,
+            user,
+            time,
+            tres,
+            partition,
+            nodelist,
+            stdout,
+            stderr,
+            command,
+            state_compact,
+            reason,
+            qos,
+            array_job_id,
+            array_task_id,
+            node_list,
+            work_dir,
+            submit_time,
+            start_time,
+            end_time,
+            time_limit,
+            restarts,
+            dependency,
+        ] = positions.map(|p| p.map_or("", |p| parts[p]));
+        Some(SqueueRecord {
+            line,
+            id,
+            name,
+            state,
+            user,
+            time,
+            tres,
+            partition,
+            nodelist,
+            stdout,
+            stderr,
+            command,
+            state_compact,
+            reason,
+            qos,
+            array_job_id: Some(array_job_id).filter(|a| !a.is_empty()).unwrap_or(id),
+            array_task_id: Some(array_task_id)
+                .filter(|a| !a.is_empty())
+                .unwrap_or("N/A"),
+            node_list,
+            work_dir,
+            submit_time,
+            start_time,
+            end_time,
+            time_limit,
+            restarts,
+            dependency,
+        })
+    })
+}
+
+impl SqueueRecord<'_> {
+    pub fn to_job(&self) -> Job {
+        let resolve = |path| {
+            resolve_path(
+                path,
+                self.array_job_id,
+                self.array_task_id,
+                self.id,
+                "batch",
+                self.node_list,
+                self.user,
+                self.name,
+                self.work_dir,
+            )
+        };
+        Job {
+            job_id: self.id.to_owned(),
+            array_id: self.array_job_id.to_owned(),
+            array_step: match self.array_task_id {
+                "N/A" => None,
+                step => Some(step.to_owned()),
+            },
+            name: self.name.to_owned(),
+            state: Interned::new(self.state),
+            state_compact: Interned::new(self.state_compact),
+            reason: Some(self.reason)
+                .filter(|r| !r.is_empty() && *r != "None")
+                .map(str::to_owned),
+            dependency: Some(self.dependency)
+                .filter(|d| !d.is_empty() && *d != "(null)")
+                .map(str::to_owned),
+            qos: Interned::new(self.qos),
+            user: Interned::new(self.user),
+            time: self.time.to_owned(),
+            tres: self.tres.to_owned(),
+            partition: Interned::new(self.partition),
+            nodelist: self.nodelist.to_owned(),
+            command: self.command.to_owned(),
+            stdout: resolve(self.stdout),
+            stderr: resolve(self.stderr), // TODO fill all fields
+            stdout_pattern: Some(self.stdout)
+                .filter(|p| !p.is_empty())
+                .map(str::to_owned),
+            stderr_pattern: Some(self.stderr)
+                .filter(|p| !p.is_empty())
+                .map(str::to_owned),
+            submit_time: parse_timestamp(self.submit_time),
+            start_time: parse_timestamp(self.start_time),
+            end_time: parse_timestamp(self.end_time),
+            time_used: parse_duration(self.time),
+            time_limit: parse_duration(self.time_limit),
+            work_dir: Some(self.work_dir)
+                .filter(|d| !d.is_empty())
+                .map(PathBuf::from),
+            restarts: self.restarts.parse().unwrap_or(0),
+            // only known once the job finished
+            energy: None,
+        }
+    }
+}
+
+/// Parses the output of `squeue --Format` with the given fields, each followed by
+/// [`SEPARATOR`]. Lines that do not have all fields are skipped.
+pub fn parse_squeue(output: &str, fields: &[&str]) -> Vec<Job> {
+    squeue_records(output, fields)
+        .map(|record| record.to_job())
+        .collect()
+}
+
+/// The running jobs of the recent polls by job id, with the `squeue` line they were parsed
+/// from. A job whose line did not change since the last poll, like most pending jobs in a large
+/// queue, is not parsed again. Finished jobs get the paths that `sacct` does not know from here.
+#[derive(Default)]
+pub struct JobCache {
+    jobs: HashMap<String, (String, Job)>,
+    /// The fields the lines were parsed with. The same line means something else once a field
+    /// this Slurm version rejects was dropped.
+    fields: Vec<String>,
+}
 
-            path.replace_range(m.range(), replacement);
+impl JobCache {
+    /// Like [`parse_squeue`], but only parses the lines that changed since the last poll.
+    pub fn parse_squeue(&mut self, output: &str, fields: &[&str]) -> Vec<Job> {
+        if self.fields != fields {
+            self.fields = fields.iter().map(|f| f.to_string()).collect();
+            for (line, _) in self.jobs.values_mut() {
+                line.clear();
+            }
         }
+        squeue_records(output, fields)
+            .map(|record| match self.jobs.get_mut(record.id) {
+                Some((line, job)) => {
+                    if line != record.line {
+                        line.clear();
+                        line.push_str(record.line);
+                        *job = record.to_job();
+                    }
+                    job.clone()
+                }
+                None => {
+                    let job = record.to_job();
+                    self.jobs
+                        .insert(record.id.to_owned(), (record.line.to_owned(), job.clone()));
+                    job
+                }
+            })
+            .collect()
+    }
+
+    pub fn get(&self, job_id: &str) -> Option<&Job> {
+        self.jobs.get(job_id).map(|(_, job)| job)
+    }
+
+    pub fn jobs(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.values().map(|(_, job)| job)
+    }
+
+    /// Forgets the jobs that are neither running nor recently finished anymore.
+    pub fn retain(&mut self, job_ids: &HashSet<String>) {
+        self.jobs.retain(|job_id, _| job_ids.contains(job_id));
+    }
+}
+
+/// The field in an error of `squeue --Format` about a field this Slurm version does not know.
+fn rejected_field(stderr: &str) -> Option<&str> {
+    let (_, rest) = stderr.split_once("Invalid job format specification: ")?;
+    let spec = rest.split_whitespace().next()?;
+    // the field may be followed by its width, e.g. `statecompact:###turm###`
+    spec.split(':').next()
+}
 
-        Some(PathBuf::from(path))
+/// The job with the final state, run time and end time from `scontrol show job`, if it ended.
+fn scontrol_final_state(mut job: Job) -> Option<Job> {
+    let output = slurm_command("scontrol")
+        .args(["--oneliner", "show", "job", &job.job_id])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let fields = stdout
+        .split_whitespace()
+        .filter_map(|f| f.split_once('='))
+        .collect::<HashMap<_, _>>();
+    let state = *fields.get("JobState")?;
+    if !states::is_final(state) {
+        return None;
     }
+    job.state = Interned::new(state);
+    job.state_compact = Interned::new(&states::compact(state));
+    job.reason = fields
+        .get("Reason")
+        .filter(|r| **r != "None")
+        .map(|r| r.to_string());
+    job.dependency = None;
+    if let Some(run_time) = fields.get("RunTime") {
+        job.time = run_time.to_string();
+        job.time_used = parse_duration(run_time);
+    }
+    job.end_time = fields
+        .get("EndTime")
+        .and_then(|t| parse_timestamp(t))
+        .or(job.end_time);
+    Some(job)
+}
+
+/// Parses the output of `sacct --parsable` with the [`SACCT_FIELDS`], separated by
+/// [`SEPARATOR`].
+pub fn parse_sacct(output: &str) -> Vec<Job> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter_map(|l| {
+            let parts = split_line::<{ SACCT_FIELDS.len() }>(l, SACCT_FIELDS.len())?;
+
+            let id = parts[0];
+            let name = parts[1];
+            let state = parts[2];
+            let user = parts[3];
+            let time = parts[4];
+            let tres = parts[5];
+            let partition = parts[6];
+            let nodelist = parts[7];
+            let command = parts[8]
+                // Remove the `sbatch` part of the command and slurm arguments.
+                // That matches the `squeue` "command" field.
+                .split_whitespace()
+                .skip_while(|&arg| arg.starts_with("sbatch") || arg.starts_with('-'))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let command = if command.is_empty() {
+                parts[8].to_owned()
+            } else {
+                command
+            };
+            let reason = parts[9];
+            let qos = parts[10];
+            let submit_time = parts[11];
+            let start_time = parts[12];
+            let end_time = parts[13];
+            let time_limit = parts[14];
+            let work_dir = parts[15];
+            // 0 (or empty) without energy accounting
+            let energy = parts[16].parse().ok().filter(|&e| e > 0);
+
+            let state_compact = states::compact(state);
+
+            // It seems sacct doesn't expose array ids, so we get them manually
+            let (array_job_id, array_task_id) = if id.contains('_') {
+                let parts: Vec<&str> = id.split('_').collect();
+                if parts.len() == 2 {
+                    (parts[0], parts[1])
+                } else {
+                    (id, "N/A")
+                }
+            } else {
+                (id, "N/A")
+            };
+
+            Some(Job {
+                job_id: id.to_owned(),
+                array_id: array_job_id.to_owned(),
+                array_step: match array_task_id {
+                    "N/A" => None,
+                    _ => Some(array_task_id.to_owned()),
+                },
+                name: name.to_owned(),
+                state: Interned::new(state),
+                state_compact: Interned::new(&state_compact),
+                reason: if reason == "None" {
+                    None
+                } else {
+                    Some(reason.to_owned())
+                },
+                // finished jobs have no dependencies left
+                dependency: None,
+                qos: Interned::new(qos),
+                user: Interned::new(user),
+                time: time.to_owned(),
+                tres: tres.to_owned(),
+                partition: Interned::new(partition),
+                nodelist: nodelist.to_owned(),
+                command: command.to_owned(),
+                stdout: None,
+                stderr: None,
+                stdout_pattern: None,
+                stderr_pattern: None,
+                submit_time: parse_timestamp(submit_time),
+                start_time: parse_timestamp(start_time),
+                end_time: parse_timestamp(end_time),
+                time_used: parse_duration(time),
+                time_limit: parse_duration(time_limit),
+                work_dir: Some(work_dir).filter(|d| !d.is_empty()).map(PathBuf::from),
+                // not available from sacct
+                restarts: 0,
+                energy,
+            })
+        })
+        .collect()
 }
 
 impl JobWatcherHandle {
@@ -379,10 +804,138 @@ impl JobWatcherHandle {
         interval: Duration,
         squeue_args: Vec<String>,
         sacct_args: Vec<String>,
+        finished_retention: Duration,
+        sacct: bool,
     ) -> Self {
-        let mut actor = JobWatcher::new(app, interval, squeue_args, sacct_args);
+        let (sender, receiver) = unbounded();
+        let mut actor = JobWatcher::new(
+            app,
+            receiver,
+            interval,
+            squeue_args,
+            sacct_args,
+            finished_retention,
+            sacct,
+        );
         thread::spawn(move || actor.run());
 
-        Self {}
+        Self { sender }
+    }
+
+    pub fn set_pinned(&self, pinned: Vec<String>) {
+        let _ = self.sender.send(JobWatcherMessage::Pinned(pinned));
+    }
+
+    pub fn set_args(&self, squeue_args: Vec<String>, sacct_args: Vec<String>) {
+        let _ = self.sender.send(JobWatcherMessage::Args {
+            squeue_args,
+            sacct_args,
+        });
+    }
+
+    pub fn set_settings(&self, interval: Duration, finished_retention: Duration) {
+        let _ = self.sender.send(JobWatcherMessage::Settings {
+            interval,
+            finished_retention,
+        });
+    }
+}
+
+/// Lists the current jobs once, without watching them.
+pub fn list_running_jobs(squeue_args: &[String]) -> Vec<Job> {
+    JobWatcher::get_running_jobs(squeue_args)
+}
+
+/// Lists the current and recently finished jobs once, without watching them.
+pub fn list_jobs(squeue_args: &[String], sacct_args: &[String], since: Duration) -> Vec<Job> {
+    let mut jobs = JobWatcher::get_running_jobs(squeue_args);
+    jobs.extend(JobWatcher::get_finished_jobs(sacct_args, since));
+    jobs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: [&str; 9] = [
+        "jobid",
+        "name",
+        "state",
+        "username",
+        "stdout",
+        "ArrayJobID",
+        "ArrayTaskID",
+        "NodeList",
+        "WorkDir",
+    ];
+
+    fn squeue_line(id: &str, state: &str) -> String {
+        [
+            id,
+            "train",
+            state,
+            "alice",
+            "out-%A_%a-%j.log",
+            "10",
+            "2",
+            "gpu-01",
+            "/w",
+        ]
+        .map(|v| format!("{}{}", v, SEPARATOR))
+        .concat()
+    }
+
+    fn output(lines: &[String]) -> String {
+        lines.join("\n")
+    }
+
+    #[test]
+    fn job_cache() {
+        let mut cache = JobCache::default();
+        let first = output(&[squeue_line("12", "PENDING"), squeue_line("13", "PENDING")]);
+        let jobs = cache.parse_squeue(&first, &FIELDS);
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].id(), "10_2");
+        assert_eq!(jobs[0].stdout, Some(PathBuf::from("/w/out-10_2-12.log")));
+
+        // unchanged lines are not parsed again
+        cache.jobs.get_mut("12").unwrap().1.name = "cached".to_string();
+        let jobs = cache.parse_squeue(&first, &FIELDS);
+        assert_eq!(jobs[0].name, "cached");
+
+        // changed lines are
+        let second = output(&[squeue_line("12", "RUNNING")]);
+        let jobs = cache.parse_squeue(&second, &FIELDS);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(
+            (jobs[0].name.as_str(), jobs[0].state.as_str()),
+            ("train", "RUNNING")
+        );
+
+        // removed lines are not returned, and forgotten once the job is not kept anymore
+        assert!(cache.get("13").is_some());
+        cache.retain(&HashSet::from(["12".to_string()]));
+        assert!(cache.get("13").is_none());
+
+        // the same line is parsed again with other fields
+        cache.jobs.get_mut("12").unwrap().1.name = "cached".to_string();
+        let fields = FIELDS.map(|f| if f == "name" { "comment" } else { f });
+        let jobs = cache.parse_squeue(&second, &fields);
+        assert_eq!(jobs[0].name, "");
+    }
+
+    #[test]
+    fn rejected_fields() {
+        let stderr = "squeue: error: Invalid job format specification: statecompact\n";
+        assert_eq!(rejected_field(stderr), Some("statecompact"));
+        let fields = ["state", "statecompact"];
+        let field = rejected_field(stderr)
+            .and_then(|rejected| fields.iter().find(|f| f.eq_ignore_ascii_case(rejected)));
+        assert_eq!(field, Some(&"statecompact"));
+        assert_eq!(
+            rejected_field("Invalid job format specification: tres-alloc:###turm###"),
+            Some("tres-alloc")
+        );
+        assert_eq!(rejected_field("squeue: error: Invalid user: bob"), None);
     }
 }
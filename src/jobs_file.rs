@@ -0,0 +1,110 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crossbeam::channel::Sender;
+use notify::{RecursiveMode, Watcher};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::app::{AppMessage, Job};
+
+lazy_static::lazy_static! {
+    static ref JOB_ID: Regex = Regex::new(r"^\d+(_\d+)?$").unwrap();
+}
+
+/// Jobs imported from a file of ids, e.g. written by a sweep launcher. The view is restricted
+/// to them until the import is cleared, also across restarts.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct JobsFile {
+    pub path: PathBuf,
+    /// Labels by job id, empty for jobs without one.
+    pub labels: BTreeMap<String, String>,
+}
+
+impl JobsFile {
+    /// Reads one job id per line, optionally followed by a label, e.g. `1234 lr=1e-3`. sbatch's
+    /// `Submitted batch job 1234` works too, blank lines and `#` comments are skipped.
+    pub fn read(path: &Path) -> io::Result<JobsFile> {
+        let content = fs::read_to_string(path)?;
+        let labels = content
+            .lines()
+            .map(|l| l.trim())
+            .map(|l| l.strip_prefix("Submitted batch job ").unwrap_or(l))
+            .filter_map(|l| {
+                let (id, label) = l.split_once(char::is_whitespace).unwrap_or((l, ""));
+                JOB_ID
+                    .is_match(id)
+                    .then(|| (id.to_string(), label.trim().to_string()))
+            })
+            .collect();
+        Ok(JobsFile {
+            path: path.to_path_buf(),
+            labels,
+        })
+    }
+
+    /// The ids for `squeue --job`.
+    pub fn ids(&self) -> String {
+        self.labels.keys().cloned().collect::<Vec<_>>().join(",")
+    }
+
+    /// The imported id the job belongs to: its own, or that of its array job.
+    fn imported_id(&self, job: &Job) -> Option<&String> {
+        self.labels
+            .get_key_value(&job.id())
+            .or_else(|| self.labels.get_key_value(&job.array_id))
+            .map(|(id, _)| id)
+    }
+
+    pub fn contains(&self, job: &Job) -> bool {
+        self.imported_id(job).is_some()
+    }
+
+    pub fn label(&self, job: &Job) -> Option<&str> {
+        let label = &self.labels[self.imported_id(job)?];
+        (!label.is_empty()).then_some(label.as_str())
+    }
+}
+
+/// Reads the jobs file again whenever it changes, since launchers append to it while they
+/// submit. The watch stops when the handle is dropped.
+pub struct JobsFileWatcherHandle {
+    _watcher: Option<notify::RecommendedWatcher>,
+}
+
+impl JobsFileWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, path: PathBuf) -> Self {
+        let file_name = path.file_name().map(|n| n.to_owned());
+        let dir = path.parent().unwrap_or(&path).to_path_buf();
+        let mut last_content = fs::read_to_string(&path).ok();
+
+        // watch the directory, since the file may be replaced instead of written to
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+            if event.kind.is_access()
+                || !event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == file_name.as_deref())
+            {
+                return;
+            }
+            let content = fs::read_to_string(&path).ok();
+            if content.is_none() || content == last_content {
+                return;
+            }
+            last_content = content;
+            let _ = app.send(AppMessage::JobsFile(JobsFile::read(&path)));
+        })
+        .and_then(|mut w| w.watch(&dir, RecursiveMode::NonRecursive).map(|_| w));
+
+        Self {
+            _watcher: watcher.ok(),
+        }
+    }
+}
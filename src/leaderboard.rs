@@ -0,0 +1,56 @@
+use crate::app::Job;
+use crate::tres::Tres;
+
+/// What a user occupies, over the jobs in the current scope.
+pub struct UserUsage {
+    pub user: String,
+    pub running: usize,
+    pub pending: usize,
+    /// Allocated to running jobs.
+    pub cpus: u64,
+    pub gpus: u64,
+    /// Consumed by finished jobs, in joules.
+    pub energy: u64,
+}
+
+/// Joules in kWh.
+pub fn kwh(joules: u64) -> f64 {
+    joules as f64 / 3.6e6
+}
+
+/// Usage per user, the heaviest users (by GPUs, then CPUs) first.
+pub fn by_user(jobs: &[Job]) -> Vec<UserUsage> {
+    let mut usage: Vec<UserUsage> = Vec::new();
+    for job in jobs {
+        let i = match usage.iter().position(|u| job.user == u.user) {
+            Some(i) => i,
+            None => {
+                usage.push(UserUsage {
+                    user: job.user.to_string(),
+                    running: 0,
+                    pending: 0,
+                    cpus: 0,
+                    gpus: 0,
+                    energy: 0,
+                });
+                usage.len() - 1
+            }
+        };
+        let u = &mut usage[i];
+        match job.state.as_str() {
+            "RUNNING" => {
+                let tres = Tres::parse(&job.tres);
+                u.running += 1;
+                u.cpus += tres.cpus();
+                u.gpus += tres.gpus();
+            }
+            "PENDING" => u.pending += 1,
+            _ => u.energy += job.energy.unwrap_or(0),
+        }
+    }
+    usage.retain(|u| u.running + u.pending > 0 || u.energy > 0);
+    usage.sort_by(|a, b| {
+        (b.gpus, b.cpus, b.running, b.pending).cmp(&(a.gpus, a.cpus, a.running, a.pending))
+    });
+    usage
+}
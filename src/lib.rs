@@ -0,0 +1,96 @@
+mod actions;
+mod allocation;
+mod announcement;
+mod anonymize;
+mod app;
+mod archive;
+mod audit;
+mod backfill;
+mod budget;
+mod capabilities;
+mod cli;
+mod clock;
+mod commands;
+mod completing;
+mod completion;
+mod config;
+mod config_watcher;
+mod dependency;
+mod dispatcher;
+mod duplicates;
+mod efficiency;
+mod email;
+mod file_watcher;
+mod gantt;
+mod heatmap;
+mod history;
+mod hooks;
+mod hostlist;
+mod idle;
+mod intern;
+mod ipc;
+mod job_commands;
+mod job_list;
+mod job_watcher;
+mod jobs_file;
+mod leaderboard;
+mod links;
+mod log_diagnosis;
+mod log_search;
+mod log_source;
+mod manifest;
+mod matrix;
+mod metrics;
+mod node_health;
+mod node_load;
+mod nodes;
+mod notifications;
+mod pipelines;
+mod plugins;
+mod preemption;
+mod prefetch;
+mod processes;
+mod progress;
+mod ranks;
+mod remote;
+mod requeue;
+mod sacctmgr;
+mod scripting;
+mod settings;
+mod sftp;
+mod slurm_time;
+mod snapshot;
+mod sparkline;
+mod squeue_args;
+mod state;
+mod states;
+mod steps;
+mod suggest;
+mod sweep;
+mod timeline;
+mod tres;
+mod triage;
+mod usage;
+mod usage_report;
+mod view;
+mod what_if;
+mod whose;
+
+pub use cli::main;
+
+/// What the benchmarks need, which are not part of any API.
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::app::{process_terminal_output, Job, ScrollAnchor};
+    pub use crate::config::Column;
+    pub use crate::job_list::build_rows;
+    pub use crate::job_watcher::{
+        parse_sacct, parse_squeue, JobCache, FORMAT_FIELDS, SACCT_FIELDS, SEPARATOR,
+    };
+    pub use crate::metrics::extract as extract_metrics;
+    pub use crate::notifications::transitions;
+    pub use crate::requeue::RestartTracker;
+    pub use crate::timeline::Timelines;
+    pub use crate::tres::Tres;
+    pub use crate::view::{render_job_list, string_for_paragraph, JobListView, JobRow};
+}
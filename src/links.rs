@@ -0,0 +1,84 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+use crossterm::{cursor::MoveTo, queue, style::Print};
+use regex::Regex;
+
+/// Number of links shown per job.
+const MAX_LINKS: usize = 3;
+
+/// Experiment tracker pages that jobs log by default: Weights & Biases and MLflow runs.
+pub fn default_patterns() -> Vec<Regex> {
+    [
+        r"https://wandb\.ai/[^\s/]+/[^\s/]+/runs/[\w-]+",
+        r"https?://\S+/#/experiments/\d+/runs/[0-9a-f]+",
+    ]
+    .iter()
+    .map(|p| Regex::new(p).unwrap())
+    .collect()
+}
+
+/// The distinct links in the log, most recent last.
+pub fn find_links(patterns: &[Regex], log: &str) -> Vec<String> {
+    let mut links = patterns
+        .iter()
+        .flat_map(|p| p.find_iter(log).map(|m| (m.start(), m.as_str())))
+        .collect::<Vec<_>>();
+    links.sort_by_key(|(start, _)| *start);
+    let mut distinct: Vec<String> = Vec::new();
+    for (_, link) in links.into_iter().rev() {
+        if !distinct.iter().any(|l| l == link) {
+            distinct.push(link.to_string());
+        }
+        if distinct.len() == MAX_LINKS {
+            break;
+        }
+    }
+    distinct.reverse();
+    distinct
+}
+
+/// Opens the link in the default browser of the machine turm runs on.
+pub fn open(url: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.args(["/C", "start", ""]);
+        c
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// Text at a position of the screen that is clickable in terminals supporting OSC 8.
+pub struct Hyperlink {
+    pub x: u16,
+    pub y: u16,
+    pub text: String,
+    pub url: String,
+}
+
+/// Rewrites the hyperlinks over the drawn frame, since ratatui cannot draw escape sequences.
+pub fn write_hyperlinks(out: &mut impl Write, links: &[Hyperlink]) -> io::Result<()> {
+    for link in links {
+        queue!(
+            out,
+            MoveTo(link.x, link.y),
+            Print(format!(
+                "\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\",
+                link.url, link.text
+            ))
+        )?;
+    }
+    out.flush()
+}
@@ -0,0 +1,168 @@
+use std::{collections::HashMap, path::PathBuf, thread};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use crate::app::AppMessage;
+use crate::remote::slurm_command;
+
+/// Checks the directory and the file, printing `key=value` lines: exit codes of `test` for
+/// the checks, and the permissions and owner from `ls -ld`.
+const CHECK_SCRIPT: &str = r#"d=$(dirname -- "$1")
+test -d "$d"; echo "dir=$?"
+test -r "$d" -a -x "$d"; echo "dir_access=$?"
+test -e "$1"; echo "file=$?"
+test -r "$1"; echo "file_access=$?"
+echo "dir_mode=$(ls -ld -- "$d" 2>/dev/null | awk '{print $1, $3, $4}')"
+echo "file_mode=$(ls -ld -- "$1" 2>/dev/null | awk '{print $1, $3, $4}')""#;
+
+/// A log that could not be opened, to be diagnosed.
+#[derive(Clone, PartialEq)]
+pub struct LogQuery {
+    pub path: PathBuf,
+    /// The pattern `squeue` reported, which `path` was resolved from.
+    pub pattern: Option<String>,
+    /// Whether the path is on the local storage of the nodes.
+    pub node_local: bool,
+    /// The node it is read on, if the job is running.
+    pub node: Option<String>,
+}
+
+/// What was found out about a log that could not be opened, on the machine logs are read
+/// from (the login node in remote mode).
+pub struct Diagnosis {
+    pub query: LogQuery,
+    pub dir_exists: Option<bool>,
+    /// Whether the directory can be listed and entered.
+    pub dir_accessible: Option<bool>,
+    pub file_exists: Option<bool>,
+    pub file_readable: Option<bool>,
+    /// Permissions, owner and group, e.g. `drwxr-x--- alice users`.
+    pub dir_mode: Option<String>,
+    pub file_mode: Option<String>,
+    /// Why the checks could not be run.
+    pub error: Option<String>,
+}
+
+impl Diagnosis {
+    /// The most likely reason why the log cannot be read.
+    pub fn hint(&self) -> Option<String> {
+        let query = &self.query;
+        if query.node_local && self.file_exists != Some(true) {
+            return Some(match &query.node {
+                Some(node) => format!(
+                    "The file is on the local storage of {} and is read there with srun, see the error above",
+                    node
+                ),
+                None => "The file is on the local storage of a node and cannot be read once \
+                    the job stopped running"
+                    .to_string(),
+            });
+        }
+        if query.path.to_string_lossy().contains('%') {
+            return Some("The pattern contains a placeholder turm cannot resolve".to_string());
+        }
+        match (self.dir_exists, self.dir_accessible, self.file_exists) {
+            (Some(false), _, _) => Some(
+                "The directory does not exist. Slurm does not create it, so the job cannot \
+                    write its output"
+                    .to_string(),
+            ),
+            (_, Some(false), _) => Some("The directory cannot be read by you".to_string()),
+            (_, _, Some(false)) => Some(
+                "The job did not create the file yet, or it was deleted or is written on \
+                    another file system"
+                    .to_string(),
+            ),
+            _ if self.file_readable == Some(false) => {
+                Some("The file is not readable by you".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+fn diagnose(query: LogQuery) -> Diagnosis {
+    let mut diagnosis = Diagnosis {
+        query,
+        dir_exists: None,
+        dir_accessible: None,
+        file_exists: None,
+        file_readable: None,
+        dir_mode: None,
+        file_mode: None,
+        error: None,
+    };
+    // log files are always POSIX paths on the cluster, even if turm runs on Windows
+    let path = diagnosis.query.path.to_string_lossy().replace('\\', "/");
+    let output = match slurm_command("sh")
+        .args(["-c", CHECK_SCRIPT, "sh", &path])
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => {
+            diagnosis.error = Some(format!("Failed to check the path: {}", e));
+            return diagnosis;
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let values = stdout
+        .lines()
+        .filter_map(|l| l.split_once('='))
+        .collect::<HashMap<_, _>>();
+    let check = |key: &str| values.get(key).map(|code| *code == "0");
+    let mode = |key: &str| {
+        values
+            .get(key)
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+    };
+    diagnosis.dir_exists = check("dir");
+    diagnosis.dir_accessible = check("dir_access");
+    diagnosis.file_exists = check("file");
+    diagnosis.file_readable = check("file_access");
+    diagnosis.dir_mode = mode("dir_mode");
+    diagnosis.file_mode = mode("file_mode");
+    if values.is_empty() {
+        diagnosis.error = Some(format!(
+            "Failed to check the path: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    diagnosis
+}
+
+/// Diagnoses logs that could not be opened in the background, one at a time.
+struct LogDiagnostics {
+    app: Sender<AppMessage>,
+    receiver: Receiver<LogQuery>,
+}
+
+impl LogDiagnostics {
+    fn run(&mut self) {
+        while let Ok(mut query) = self.receiver.recv() {
+            // only the latest log matters
+            while let Ok(newer) = self.receiver.try_recv() {
+                query = newer;
+            }
+            let _ = self.app.send(AppMessage::LogDiagnosis(diagnose(query)));
+        }
+    }
+}
+
+pub struct LogDiagnosticsHandle {
+    sender: Sender<LogQuery>,
+}
+
+impl LogDiagnosticsHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = LogDiagnostics { app, receiver };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Checks why the log cannot be opened.
+    pub fn request(&self, query: LogQuery) {
+        let _ = self.sender.send(query);
+    }
+}
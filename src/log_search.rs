@@ -0,0 +1,350 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    thread,
+    time::UNIX_EPOCH,
+};
+
+use chrono::NaiveDateTime;
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+
+use crate::app::AppMessage;
+use crate::archive::ArchivedJob;
+use crate::history::{self, HistoryConfig};
+use crate::log_source::{log_source, LogSource, Throttle};
+use crate::remote;
+use crate::state::state_dir;
+
+/// More hits are not shown.
+pub const MAX_HITS: usize = 500;
+/// Matching lines are cut off after this many characters.
+const MAX_LINE_LEN: usize = 300;
+
+/// A line of a log that mentions the query.
+pub struct Hit {
+    pub job_id: String,
+    pub name: String,
+    pub path: PathBuf,
+    /// Starting at 1.
+    pub line_number: usize,
+    pub line: String,
+}
+
+pub struct SearchResult {
+    pub hits: Vec<Hit>,
+    /// Logs that were searched.
+    pub searched: usize,
+    /// Logs that could not contain the query according to the index.
+    pub skipped: usize,
+}
+
+/// A log of a past job.
+struct Document {
+    job_id: String,
+    name: String,
+    end_time: Option<NaiveDateTime>,
+    path: PathBuf,
+    /// Archived logs are always local and never change.
+    archived: bool,
+}
+
+/// The trigrams of the text, lowercased, as `u32`s.
+fn trigrams(text: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    text.windows(3).map(|w| {
+        let [a, b, c] = [w[0], w[1], w[2]].map(|b| b.to_ascii_lowercase() as u32);
+        a << 16 | b << 8 | c
+    })
+}
+
+/// The trigrams of a local log, valid as long as its size and modification time match.
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    size: u64,
+    modified: u64,
+    trigrams: Vec<u32>,
+}
+
+/// Trigram index of local logs, kept in the state directory, so that a search only reads the
+/// logs that contain every trigram of the query.
+#[derive(Serialize, Deserialize, Default)]
+struct TrigramIndex {
+    files: HashMap<PathBuf, IndexEntry>,
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl TrigramIndex {
+    fn path() -> Option<PathBuf> {
+        state_dir().map(|d| d.join("log_index.json"))
+    }
+
+    fn load() -> TrigramIndex {
+        Self::path()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&mut self) -> io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if !self.changed {
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        self.changed = false;
+        Ok(())
+    }
+
+    fn stat(path: &Path) -> Option<(u64, u64)> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some((metadata.len(), modified.as_secs()))
+    }
+
+    /// Whether the log may contain the trigrams, `None` if it is not indexed.
+    fn may_contain(&self, path: &Path, query: &BTreeSet<u32>) -> Option<bool> {
+        let entry = self.files.get(path)?;
+        if Self::stat(path)? != (entry.size, entry.modified) {
+            return None;
+        }
+        Some(
+            query
+                .iter()
+                .all(|t| entry.trigrams.binary_search(t).is_ok()),
+        )
+    }
+
+    fn insert(&mut self, path: &Path, content: &[u8]) {
+        let Some((size, modified)) = Self::stat(path) else {
+            return;
+        };
+        let trigrams = trigrams(content)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        self.files.insert(
+            path.to_path_buf(),
+            IndexEntry {
+                size,
+                modified,
+                trigrams,
+            },
+        );
+        self.changed = true;
+    }
+
+    /// Forgets the logs that are no longer searched.
+    fn retain(&mut self, paths: &HashSet<&Path>) {
+        let len = self.files.len();
+        self.files.retain(|p, _| paths.contains(p.as_path()));
+        self.changed |= self.files.len() != len;
+    }
+}
+
+struct SearchRequest {
+    query: String,
+    archived: Vec<ArchivedJob>,
+    history: HistoryConfig,
+    index: bool,
+}
+
+/// The archived logs and the logs of the jobs in the history, newest first. A job's archived
+/// logs replace its original ones, which may be gone or overwritten.
+fn documents(request: &SearchRequest) -> Vec<Document> {
+    let records = history::load(&request.history).unwrap_or_default();
+    let by_id = records
+        .iter()
+        .map(|r| (r.id.as_str(), r))
+        .collect::<HashMap<_, _>>();
+    let archived_ids = request
+        .archived
+        .iter()
+        .map(|a| a.job_id.as_str())
+        .collect::<HashSet<_>>();
+    let mut documents = Vec::new();
+    for archived in &request.archived {
+        let record = by_id.get(archived.job_id.as_str());
+        for path in &archived.files {
+            documents.push(Document {
+                job_id: archived.job_id.clone(),
+                name: record.map(|r| r.name.clone()).unwrap_or_default(),
+                end_time: record.and_then(|r| r.end_time),
+                path: path.clone(),
+                archived: true,
+            });
+        }
+    }
+    for record in records
+        .iter()
+        .filter(|r| !archived_ids.contains(r.id.as_str()))
+    {
+        let mut paths = [&record.stdout, &record.stderr]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        paths.dedup();
+        for path in paths {
+            documents.push(Document {
+                job_id: record.id.clone(),
+                name: record.name.clone(),
+                end_time: record.end_time,
+                path: path.clone(),
+                archived: false,
+            });
+        }
+    }
+    documents.sort_by_key(|d| std::cmp::Reverse(d.end_time));
+    documents
+}
+
+/// Searches the logs in the background.
+struct LogSearcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<SearchRequest>,
+    source: Box<dyn LogSource>,
+    index: Option<TrigramIndex>,
+}
+
+impl LogSearcher {
+    fn read(&mut self, document: &Document) -> io::Result<Vec<u8>> {
+        if !document.archived {
+            return self.source.read_all(&document.path);
+        }
+        let mut content = Vec::new();
+        let file = fs::File::open(&document.path)?;
+        if document.path.extension().is_some_and(|e| e == "gz") {
+            GzDecoder::new(file).read_to_end(&mut content)?;
+        } else {
+            io::BufReader::new(file).read_to_end(&mut content)?;
+        }
+        Ok(content)
+    }
+
+    fn search(&mut self, request: &SearchRequest) -> SearchResult {
+        let query = request.query.to_lowercase();
+        // case is only ignored for ASCII in the index
+        let query_trigrams = request
+            .query
+            .as_bytes()
+            .windows(3)
+            .filter(|w| w.is_ascii())
+            .flat_map(trigrams)
+            .collect::<BTreeSet<_>>();
+        let documents = documents(request);
+        let mut index = match request.index {
+            true => Some(self.index.take().unwrap_or_else(TrigramIndex::load)),
+            false => None,
+        };
+        let mut result = SearchResult {
+            hits: Vec::new(),
+            searched: 0,
+            skipped: 0,
+        };
+        for document in &documents {
+            // the logs of other jobs are only indexed where they can be stat'ed cheaply
+            let local = document.archived || remote::host().is_none();
+            if result.hits.len() >= MAX_HITS && index.is_none() {
+                break;
+            }
+            let indexed = index.as_ref().filter(|_| local);
+            if indexed.and_then(|i| i.may_contain(&document.path, &query_trigrams)) == Some(false) {
+                result.skipped += 1;
+                continue;
+            }
+            let Ok(content) = self.read(document) else {
+                continue;
+            };
+            result.searched += 1;
+            if let Some(index) = index.as_mut().filter(|_| local) {
+                if index.may_contain(&document.path, &query_trigrams).is_none() {
+                    index.insert(&document.path, &content);
+                }
+            }
+            if result.hits.len() >= MAX_HITS {
+                continue;
+            }
+            let text = String::from_utf8_lossy(&content);
+            for (i, line) in text.lines().enumerate() {
+                if line.to_lowercase().contains(&query) {
+                    result.hits.push(Hit {
+                        job_id: document.job_id.clone(),
+                        name: document.name.clone(),
+                        path: document.path.clone(),
+                        line_number: i + 1,
+                        line: line.trim().chars().take(MAX_LINE_LEN).collect(),
+                    });
+                    if result.hits.len() >= MAX_HITS {
+                        break;
+                    }
+                }
+            }
+        }
+        if let Some(mut index) = index {
+            let paths = documents
+                .iter()
+                .map(|d| d.path.as_path())
+                .collect::<HashSet<_>>();
+            index.retain(&paths);
+            let _ = index.save();
+            self.index = Some(index);
+        }
+        result
+    }
+
+    fn run(&mut self) {
+        while let Ok(request) = self.receiver.recv() {
+            let result = self.search(&request);
+            if self
+                .app
+                .send(AppMessage::LogSearch(request.query, result))
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+pub struct LogSearchHandle {
+    sender: Sender<SearchRequest>,
+}
+
+impl LogSearchHandle {
+    pub fn new(app: Sender<AppMessage>, throttle: Throttle) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = LogSearcher {
+            app,
+            receiver,
+            source: log_source(throttle),
+            index: None,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Searches the archived logs and those of the jobs in the history for the query,
+    /// ignoring case, answered with [`AppMessage::LogSearch`].
+    pub fn search(
+        &self,
+        query: String,
+        archived: Vec<ArchivedJob>,
+        history: HistoryConfig,
+        index: bool,
+    ) {
+        let _ = self.sender.send(SearchRequest {
+            query,
+            archived,
+            history,
+            index,
+        });
+    }
+}
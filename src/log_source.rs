@@ -0,0 +1,502 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::app::Job;
+use crate::hostlist;
+use crate::remote::{self, slurm_command};
+use crate::sftp::SftpSession;
+
+/// Where log files are read from.
+pub trait LogSource: Send {
+    /// Reads the file starting at byte `offset`.
+    fn read_from(&mut self, path: &Path, offset: u64) -> io::Result<Vec<u8>>;
+
+    /// Size of the file as of the last read, which may be ahead of what was read so far.
+    fn size(&self) -> u64;
+
+    /// Reads up to the last `bytes` bytes of the file.
+    fn read_tail(&mut self, path: &Path, bytes: u64) -> io::Result<Vec<u8>> {
+        let mut content = self.read_from(path, 0)?;
+        content.drain(..content.len().saturating_sub(bytes as usize));
+        Ok(content)
+    }
+
+    /// Reads the whole file, in several reads if they are throttled.
+    fn read_all(&mut self, path: &Path) -> io::Result<Vec<u8>> {
+        let mut content = self.read_from(path, 0)?;
+        while (content.len() as u64) < self.size() {
+            let more = self.read_from(path, content.len() as u64)?;
+            if more.is_empty() {
+                break;
+            }
+            content.extend(more);
+        }
+        Ok(content)
+    }
+}
+
+/// Limits for reading remote log files, so that following a job that writes a lot of output
+/// does not saturate a slow connection.
+#[derive(Clone, Copy)]
+pub struct Throttle {
+    /// Bytes requested per SFTP read.
+    pub chunk_size: u32,
+    pub bytes_per_second: Option<u64>,
+}
+
+/// The log source for the current mode: local files, or SFTP in remote mode.
+/// Compressed files are decompressed transparently.
+pub fn log_source(throttle: Throttle) -> Box<dyn LogSource> {
+    let source: Box<dyn LogSource> = match remote::host() {
+        Some(host) => Box::new(Sftp {
+            host,
+            throttle,
+            allowance: throttle.bytes_per_second.unwrap_or(0),
+            refilled_at: Instant::now(),
+            size: 0,
+        }),
+        None => Box::new(LocalFile { size: 0 }),
+    };
+    Box::new(Decompress {
+        inner: source,
+        path: None,
+        compressed: Vec::new(),
+        content: Vec::new(),
+    })
+}
+
+/// The first node of a running job, where the files on its local storage are read.
+#[derive(Clone, PartialEq)]
+pub struct JobNode {
+    /// Without array index, as `srun --jobid` takes it.
+    pub job_id: String,
+    pub node: String,
+}
+
+impl JobNode {
+    /// The first node of the job if it is running, which is where its batch script runs.
+    pub fn of(job: &Job) -> Option<JobNode> {
+        if job.state != "RUNNING" {
+            return None;
+        }
+        Some(JobNode {
+            job_id: job.job_id.clone(),
+            node: hostlist::expand(&job.nodelist).into_iter().next()?,
+        })
+    }
+}
+
+/// The log source for the logs of a job: like [`log_source`], but files below
+/// `node_local_prefixes` that are missing are read on the node of the job while it runs.
+pub fn job_log_source(
+    throttle: Throttle,
+    node_local_prefixes: &[String],
+    job: Option<JobNode>,
+) -> Box<dyn LogSource> {
+    let source = log_source(throttle);
+    match job {
+        Some(job) if !node_local_prefixes.is_empty() => Box::new(NodeLocal {
+            inner: source,
+            prefixes: node_local_prefixes.to_vec(),
+            job,
+            on_node: false,
+            read_at: None,
+            error: None,
+            size: 0,
+        }),
+        _ => source,
+    }
+}
+
+/// Whether the path is below one of the directories on local storage of the nodes.
+pub fn is_node_local(path: &Path, prefixes: &[String]) -> bool {
+    prefixes.iter().any(|prefix| path.starts_with(prefix))
+}
+
+pub struct LocalFile {
+    size: u64,
+}
+
+impl LogSource for LocalFile {
+    fn read_from(&mut self, path: &Path, offset: u64) -> io::Result<Vec<u8>> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        self.size = file.metadata()?.len();
+        Ok(content)
+    }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_tail(&mut self, path: &Path, bytes: u64) -> io::Result<Vec<u8>> {
+        let len = File::open(path)?.metadata()?.len();
+        self.read_from(path, len.saturating_sub(bytes))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    const ALL: [(Compression, &'static str); 2] =
+        [(Compression::Gzip, "gz"), (Compression::Zstd, "zst")];
+
+    fn of(path: &Path) -> Option<Compression> {
+        let extension = path.extension()?;
+        Compression::ALL
+            .into_iter()
+            .find(|(_, e)| extension == *e)
+            .map(|(c, _)| c)
+    }
+
+    /// Decompresses as much as possible, so that files that are still being written
+    /// can be shown up to where they end.
+    fn decompress(&self, compressed: &[u8]) -> Vec<u8> {
+        let mut content = Vec::new();
+        let _ = match self {
+            Compression::Gzip => {
+                flate2::read::MultiGzDecoder::new(compressed).read_to_end(&mut content)
+            }
+            Compression::Zstd => {
+                zstd::Decoder::new(compressed).and_then(|mut d| d.read_to_end(&mut content))
+            }
+        };
+        content
+    }
+}
+
+/// Reads `.gz` and `.zst` files decompressed. A log that is missing because it was
+/// compressed after the job ended (e.g. `slurm-1.out` rotated into `slurm-1.out.gz`)
+/// is read from the compressed file instead.
+struct Decompress {
+    inner: Box<dyn LogSource>,
+    /// The compressed file that is being read.
+    path: Option<PathBuf>,
+    compressed: Vec<u8>,
+    content: Vec<u8>,
+}
+
+impl LogSource for Decompress {
+    fn read_from(&mut self, path: &Path, offset: u64) -> io::Result<Vec<u8>> {
+        let candidates = match Compression::of(path) {
+            Some(compression) => vec![(path.to_path_buf(), compression)],
+            None => match self.inner.read_from(path, offset) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Compression::ALL
+                    .into_iter()
+                    .map(|(c, e)| {
+                        let mut p = path.as_os_str().to_owned();
+                        p.push(".");
+                        p.push(e);
+                        (PathBuf::from(p), c)
+                    })
+                    .collect(),
+                result => {
+                    self.path = None;
+                    return result;
+                }
+            },
+        };
+
+        for (candidate, compression) in candidates {
+            if self.path.as_ref() != Some(&candidate) {
+                self.compressed.clear();
+                self.content.clear();
+            }
+            match self
+                .inner
+                .read_from(&candidate, self.compressed.len() as u64)
+            {
+                Ok(new) => {
+                    self.path = Some(candidate);
+                    if !new.is_empty() {
+                        self.compressed.extend_from_slice(&new);
+                        self.content = compression.decompress(&self.compressed);
+                    }
+                    return Ok(self.content[(offset as usize).min(self.content.len())..].to_vec());
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => self.path = None,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} not found", path.display()),
+        ))
+    }
+
+    fn size(&self) -> u64 {
+        match self.path {
+            Some(_) => self.content.len() as u64,
+            None => self.inner.size(),
+        }
+    }
+
+    fn read_tail(&mut self, path: &Path, bytes: u64) -> io::Result<Vec<u8>> {
+        // compressed files have to be decompressed from the start
+        match Compression::of(path) {
+            Some(_) => {
+                let mut content = self.read_from(path, 0)?;
+                content.drain(..content.len().saturating_sub(bytes as usize));
+                Ok(content)
+            }
+            None => self.inner.read_tail(path, bytes),
+        }
+    }
+}
+
+/// How often files on the node of a job are read, since every read starts a job step.
+const NODE_LOCAL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Prints the size of the file and its content from the byte given as second argument, or
+/// exits with 2 if it does not exist.
+const NODE_LOCAL_SCRIPT: &str = r#"[ -e "$1" ] || exit 2; wc -c < "$1" && tail -c +"$2" "$1""#;
+
+/// Reads log files that are on the local storage of the node of a running job, and thus
+/// missing on the login node, in an overlapping job step on the node.
+struct NodeLocal {
+    inner: Box<dyn LogSource>,
+    prefixes: Vec<String>,
+    job: JobNode,
+    /// Whether the file was last read on the node.
+    on_node: bool,
+    read_at: Option<Instant>,
+    /// The error of the last read, repeated until the next one.
+    error: Option<(io::ErrorKind, String)>,
+    size: u64,
+}
+
+impl NodeLocal {
+    fn read_on_node(&mut self, path: &Path, offset: u64) -> io::Result<Vec<u8>> {
+        if self
+            .read_at
+            .is_some_and(|t| t.elapsed() < NODE_LOCAL_INTERVAL)
+        {
+            return match &self.error {
+                Some((kind, message)) => Err(io::Error::new(*kind, message.clone())),
+                None => Ok(Vec::new()),
+            };
+        }
+        self.read_at = Some(Instant::now());
+        let result = self.srun_read(path, offset);
+        self.error = result.as_ref().err().map(|e| (e.kind(), e.to_string()));
+        result
+    }
+
+    fn srun_read(&mut self, path: &Path, offset: u64) -> io::Result<Vec<u8>> {
+        let output = slurm_command("srun")
+            .arg(format!("--jobid={}", self.job.job_id))
+            .arg(format!("--nodelist={}", self.job.node))
+            .args([
+                "--overlap",
+                "--nodes=1",
+                "--ntasks=1",
+                "--immediate=10",
+                "--quiet",
+            ])
+            .args(["sh", "-c", NODE_LOCAL_SCRIPT, "sh"])
+            .arg(path.to_string_lossy().replace('\\', "/"))
+            .arg((offset + 1).to_string())
+            .output()?;
+        match output.status.code() {
+            Some(0) => {}
+            Some(2) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} not found on {}", path.display(), self.job.node),
+                ))
+            }
+            _ => {
+                return Err(io::Error::other(format!(
+                    "srun on {} failed: {}",
+                    self.job.node,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )))
+            }
+        }
+        let stdout = output.stdout;
+        let newline = stdout
+            .iter()
+            .position(|b| *b == b'\n')
+            .unwrap_or(stdout.len());
+        let size = String::from_utf8_lossy(&stdout[..newline])
+            .trim()
+            .parse::<u64>()
+            .unwrap_or(0);
+        let content = stdout[(newline + 1).min(stdout.len())..].to_vec();
+        // the file may have grown between measuring and reading it
+        self.size = size.max(offset + content.len() as u64);
+        Ok(content)
+    }
+}
+
+impl LogSource for NodeLocal {
+    fn read_from(&mut self, path: &Path, offset: u64) -> io::Result<Vec<u8>> {
+        if !self.on_node {
+            match self.inner.read_from(path, offset) {
+                Err(e)
+                    if e.kind() == io::ErrorKind::NotFound
+                        && is_node_local(path, &self.prefixes) =>
+                {
+                    self.on_node = true;
+                }
+                result => return result,
+            }
+        }
+        self.read_on_node(path, offset)
+    }
+
+    fn size(&self) -> u64 {
+        match self.on_node {
+            true => self.size,
+            false => self.inner.size(),
+        }
+    }
+
+    fn read_tail(&mut self, path: &Path, bytes: u64) -> io::Result<Vec<u8>> {
+        if !self.on_node {
+            match self.inner.read_tail(path, bytes) {
+                Err(e)
+                    if e.kind() == io::ErrorKind::NotFound
+                        && is_node_local(path, &self.prefixes) =>
+                {
+                    self.on_node = true;
+                }
+                result => return result,
+            }
+        }
+        let mut content = self.read_on_node(path, 0)?;
+        content.drain(..content.len().saturating_sub(bytes as usize));
+        Ok(content)
+    }
+}
+
+/// Number of remote files whose content is kept in memory.
+const CACHED_FILES: usize = 16;
+
+/// The SFTP session and the content of recently read files, shared by all readers so
+/// that switching between jobs only fetches what was appended in the meantime.
+struct SftpCache {
+    session: Option<SftpSession>,
+    /// Least recently read last.
+    files: Vec<(String, Vec<u8>)>,
+}
+
+lazy_static::lazy_static! {
+    static ref SFTP_CACHE: Mutex<SftpCache> = Mutex::new(SftpCache {
+        session: None,
+        files: Vec::new(),
+    });
+}
+
+/// Reads remote files over SFTP, fetching only the bytes that are not cached yet.
+pub struct Sftp {
+    host: &'static str,
+    throttle: Throttle,
+    /// Bytes that may be fetched right now, refilled over time up to one second's worth.
+    allowance: u64,
+    refilled_at: Instant,
+    size: u64,
+}
+
+impl Sftp {
+    /// Bytes that may be fetched now.
+    fn limit(&mut self) -> u64 {
+        let Some(rate) = self.throttle.bytes_per_second else {
+            return u64::MAX;
+        };
+        let refill = (rate as f64 * self.refilled_at.elapsed().as_secs_f64()) as u64;
+        if refill > 0 {
+            self.allowance = (self.allowance + refill).min(rate);
+            self.refilled_at = Instant::now();
+        }
+        self.allowance
+    }
+}
+
+impl LogSource for Sftp {
+    fn read_from(&mut self, path: &Path, offset: u64) -> io::Result<Vec<u8>> {
+        // log files are always POSIX paths on the cluster, even if turm runs on Windows
+        let path = path.to_string_lossy().replace('\\', "/");
+        let limit = self.limit();
+        let chunk_size = self.throttle.chunk_size.max(1);
+        let mut cache = SFTP_CACHE.lock().unwrap();
+        let mut content = match cache.files.iter().position(|(p, _)| *p == path) {
+            Some(i) => cache.files.remove(i).1,
+            None => Vec::new(),
+        };
+
+        let session = match &mut cache.session {
+            Some(session) => session,
+            session => session.insert(remote::sftp_session(self.host)?),
+        };
+        let result = session
+            .read_from(&path, content.len() as u64, chunk_size, limit)
+            .and_then(|(size, new)| {
+                self.size = size;
+                if size < content.len() as u64 {
+                    // the file was truncated, start over
+                    content.clear();
+                    session
+                        .read_from(&path, 0, chunk_size, limit)
+                        .map(|(_, new)| new)
+                } else {
+                    Ok(new)
+                }
+            });
+        let new = match result {
+            Ok(new) => new,
+            Err(e) => {
+                // reconnect next time, unless the file itself is the problem
+                if !matches!(
+                    e.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied
+                ) {
+                    cache.session = None;
+                }
+                return Err(e);
+            }
+        };
+        self.allowance = self.allowance.saturating_sub(new.len() as u64);
+        content.extend_from_slice(&new);
+
+        let read = content[(offset as usize).min(content.len())..].to_vec();
+        cache.files.push((path, content));
+        if cache.files.len() > CACHED_FILES {
+            cache.files.remove(0);
+        }
+        Ok(read)
+    }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Reads only the end of the file, bypassing the cache.
+    fn read_tail(&mut self, path: &Path, bytes: u64) -> io::Result<Vec<u8>> {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let limit = self.limit();
+        let chunk_size = self.throttle.chunk_size.max(1);
+        let mut cache = SFTP_CACHE.lock().unwrap();
+        let session = match &mut cache.session {
+            Some(session) => session,
+            session => session.insert(remote::sftp_session(self.host)?),
+        };
+        // nothing is read past the end, which gives the size
+        let (size, _) = session.read_from(&path, u64::MAX, chunk_size, 0)?;
+        let (_, tail) = session.read_from(&path, size.saturating_sub(bytes), chunk_size, limit)?;
+        self.size = size;
+        self.allowance = self.allowance.saturating_sub(tail.len() as u64);
+        Ok(tail)
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use chrono::{Local, NaiveDateTime};
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::actions::Resubmission;
+use crate::app::{AppMessage, Job};
+use crate::remote::slurm_command;
+use crate::slurm_time::format_slurm_duration;
+
+lazy_static::lazy_static! {
+    static ref ENV_VAR: Regex = Regex::new(r"^([A-Za-z_][A-Za-z0-9_]*)=(.*)$").unwrap();
+}
+
+/// What is needed to reproduce or archive a set of jobs.
+#[derive(Serialize)]
+struct Manifest {
+    exported: NaiveDateTime,
+    jobs: Vec<JobManifest>,
+}
+
+#[derive(Serialize)]
+struct JobManifest {
+    id: String,
+    name: String,
+    user: String,
+    account: Option<String>,
+    partition: String,
+    qos: String,
+    state: String,
+    exit_code: Option<String>,
+    submit_line: Option<String>,
+    work_dir: Option<PathBuf>,
+    script: Option<Script>,
+    /// Only known on clusters that store the environment of batch jobs.
+    environment: Option<BTreeMap<String, String>>,
+    resources: Resources,
+    submit_time: Option<NaiveDateTime>,
+    start_time: Option<NaiveDateTime>,
+    end_time: Option<NaiveDateTime>,
+    stdout: Option<PathBuf>,
+    stderr: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct Script {
+    path: String,
+    /// Of the file when the manifest was written, which may differ from the submitted script.
+    sha256: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Resources {
+    tres: String,
+    time_limit: Option<String>,
+    nodes: String,
+}
+
+/// `sha256sum` of the file, on the cluster in remote mode.
+fn sha256(path: &str) -> Option<String> {
+    let output = slurm_command("sha256sum").arg(path).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.split_whitespace().next().map(str::to_string)
+}
+
+/// Account and exit code of the jobs by id, from `sacct`.
+fn accounting(ids: &[String]) -> HashMap<String, (String, String)> {
+    const SEPARATOR: &str = "###turm###";
+    let Ok(output) = slurm_command("sacct")
+        .arg(format!("--jobs={}", ids.join(",")))
+        .args(["--allocations", "--noheader", "--parsable2"])
+        .args(["--delimiter", SEPARATOR])
+        .arg("--format=JobID,Account,ExitCode")
+        .output()
+    else {
+        return HashMap::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| {
+            let [id, account, exit_code] = l.split(SEPARATOR).collect::<Vec<_>>()[..] else {
+                return None;
+            };
+            Some((id.to_string(), (account.to_string(), exit_code.to_string())))
+        })
+        .collect()
+}
+
+/// The environment the batch job was submitted with, from `sacct --env-vars`.
+fn environment(id: &str) -> Option<BTreeMap<String, String>> {
+    let output = slurm_command("sacct")
+        .args(["--jobs", id, "--env-vars", "--noheader"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let environment = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| {
+            let captures = ENV_VAR.captures(l.trim())?;
+            Some((captures[1].to_string(), captures[2].to_string()))
+        })
+        .collect::<BTreeMap<_, _>>();
+    (!environment.is_empty()).then_some(environment)
+}
+
+fn job_manifest(
+    job: &Job,
+    accounting: &HashMap<String, (String, String)>,
+    sacct: bool,
+) -> JobManifest {
+    let id = job.id();
+    let (account, exit_code) = accounting
+        .get(&id)
+        .map(|(a, e)| (Some(a.clone()), Some(e.clone())))
+        .unwrap_or_default();
+    let submit_line = job
+        .work_dir
+        .as_deref()
+        .and_then(|dir| Resubmission::capture(&id, dir, sacct))
+        .map(|r| r.submit_line().to_string());
+    // the command of a batch job is its script
+    let script =
+        (!job.command.is_empty() && Path::new(&job.command).is_absolute()).then(|| Script {
+            path: job.command.clone(),
+            sha256: sha256(&job.command),
+        });
+    JobManifest {
+        name: job.name.clone(),
+        user: job.user.to_string(),
+        account,
+        partition: job.partition.to_string(),
+        qos: job.qos.to_string(),
+        state: job.state.to_string(),
+        exit_code,
+        submit_line,
+        work_dir: job.work_dir.clone(),
+        script,
+        environment: sacct.then(|| environment(&id)).flatten(),
+        resources: Resources {
+            tres: job.tres.clone(),
+            time_limit: job.time_limit.map(format_slurm_duration),
+            nodes: job.nodelist.clone(),
+        },
+        submit_time: job.submit_time,
+        start_time: job.start_time,
+        end_time: job.end_time,
+        stdout: job.stdout.clone(),
+        stderr: job.stderr.clone(),
+        id,
+    }
+}
+
+/// Writes the manifest of the jobs to `turm-manifest-<timestamp>.json` in the current
+/// directory.
+pub fn export(jobs: &[Job], sacct: bool) -> io::Result<PathBuf> {
+    let ids = jobs.iter().map(|j| j.id()).collect::<Vec<_>>();
+    let accounting = if sacct {
+        accounting(&ids)
+    } else {
+        HashMap::new()
+    };
+    let now = Local::now();
+    let manifest = Manifest {
+        exported: now.naive_local(),
+        jobs: jobs
+            .iter()
+            .map(|j| job_manifest(j, &accounting, sacct))
+            .collect(),
+    };
+    let path = PathBuf::from(format!(
+        "turm-manifest-{}.json",
+        now.format("%Y%m%d-%H%M%S")
+    ));
+    fs::write(&path, serde_json::to_string_pretty(&manifest)?)?;
+    Ok(path)
+}
+
+/// Exports manifests in the background, since they ask `sacct` and `scontrol` about every job.
+struct ManifestExporter {
+    app: Sender<AppMessage>,
+    receiver: Receiver<Vec<Job>>,
+    sacct: bool,
+}
+
+impl ManifestExporter {
+    fn run(&mut self) {
+        while let Ok(jobs) = self.receiver.recv() {
+            let result = export(&jobs, self.sacct);
+            if self
+                .app
+                .send(AppMessage::Manifest(jobs.len(), result))
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+pub struct ManifestExporterHandle {
+    sender: Sender<Vec<Job>>,
+}
+
+impl ManifestExporterHandle {
+    pub fn new(app: Sender<AppMessage>, sacct: bool) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = ManifestExporter {
+            app,
+            receiver,
+            sacct,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Writes the manifest of the jobs, answered with [`AppMessage::Manifest`].
+    pub fn export(&self, jobs: Vec<Job>) {
+        let _ = self.sender.send(jobs);
+    }
+}
@@ -0,0 +1,72 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+use crate::app::Job;
+use crate::states;
+use crate::triage::task_index;
+
+/// The tasks of an array job, ordered by task index.
+pub fn array_tasks<'a>(jobs: &'a [Job], array_id: &str) -> Vec<&'a Job> {
+    let mut tasks = jobs
+        .iter()
+        .filter(|j| j.array_id == array_id && j.array_step.is_some())
+        .collect::<Vec<_>>();
+    tasks.sort_by_key(|j| task_index(j));
+    tasks
+}
+
+pub fn task_color(job: &Job) -> Color {
+    states::color(&job.state)
+}
+
+/// One cell per task, `columns` per line, scrolled so that the cursor is within the first
+/// `rows` lines, followed by a legend.
+pub fn matrix_lines(
+    tasks: &[&Job],
+    cursor: usize,
+    columns: usize,
+    rows: usize,
+) -> Vec<Line<'static>> {
+    let columns = columns.max(1);
+    let first_row = (cursor / columns).saturating_sub(rows.saturating_sub(1));
+    let mut lines = tasks
+        .chunks(columns)
+        .enumerate()
+        .skip(first_row)
+        .take(rows)
+        .map(|(row, chunk)| {
+            Line::from(
+                chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(column, task)| {
+                        let style = Style::default().fg(task_color(task));
+                        if row * columns + column == cursor {
+                            Span::styled("■", style.add_modifier(Modifier::REVERSED))
+                        } else {
+                            Span::styled("■", style)
+                        }
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect::<Vec<_>>();
+    // the states of the tasks, in the order of the state table
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for task in tasks {
+        match counts.iter_mut().find(|(s, _)| task.state_compact == *s) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((&task.state_compact, 1)),
+        }
+    }
+    counts.sort_by_key(|(s, _)| (states::order(s), *s));
+    let mut legend = Vec::new();
+    for (state, count) in counts {
+        legend.push(Span::styled("■", Style::default().fg(states::color(state))));
+        legend.push(Span::raw(format!(" {} {}  ", state, count)));
+    }
+    lines.push(Line::from(legend));
+    lines
+}
@@ -0,0 +1,62 @@
+use regex::Regex;
+
+/// Points kept per metric, the most recent ones.
+const MAX_POINTS: usize = 2000;
+
+/// Values of a metric logged by a job, e.g. `loss=0.31` or `loss: 0.31`.
+pub struct Series {
+    pub name: String,
+    /// Step (or line number if no step is logged) and value.
+    pub points: Vec<(f64, f64)>,
+}
+
+/// Extracts the metrics from a log. Each value is plotted over the step logged in the same
+/// line, the last step logged before it, or else its number of occurrence.
+pub fn extract(log: &str, names: &[String], step: Option<&str>) -> Vec<Series> {
+    if names.is_empty() {
+        return Vec::new();
+    }
+    let alternatives = names
+        .iter()
+        .map(String::as_str)
+        .chain(step)
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join("|");
+    let Ok(re) = Regex::new(&format!(
+        r"(?:^|[^\w.])({})\s*[=:]\s*(-?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)",
+        alternatives
+    )) else {
+        return Vec::new();
+    };
+
+    let mut series = names
+        .iter()
+        .map(|name| Series {
+            name: name.clone(),
+            points: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    let mut last_step = None;
+    for line in log.lines() {
+        let values = re
+            .captures_iter(line)
+            .filter_map(|c| Some((c.get(1)?.as_str(), c.get(2)?.as_str().parse::<f64>().ok()?)))
+            .collect::<Vec<_>>();
+        if let Some((_, s)) = values.iter().find(|(name, _)| Some(*name) == step) {
+            last_step = Some(*s);
+        }
+        for (name, value) in values {
+            if let Some(series) = series.iter_mut().find(|s| s.name == name) {
+                let x = last_step.unwrap_or(series.points.len() as f64);
+                series.points.push((x, value));
+            }
+        }
+    }
+    for s in &mut series {
+        let excess = s.points.len().saturating_sub(MAX_POINTS);
+        s.points.drain(..excess);
+    }
+    series.retain(|s| !s.points.is_empty());
+    series
+}
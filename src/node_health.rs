@@ -0,0 +1,205 @@
+use std::{
+    collections::HashMap,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::{
+    channel::{unbounded, Receiver, Sender},
+    select,
+};
+use serde::Deserialize;
+
+use crate::app::AppMessage;
+
+/// How often the health of the nodes is polled while the node view is open.
+const INTERVAL: Duration = Duration::from_secs(60);
+/// Health commands run in parallel for this many nodes at a time.
+const PARALLEL: usize = 16;
+
+/// A command reporting the health of a node, e.g. its temperature from IPMI or telegraf.
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NodeHealthConfig {
+    /// Shell command with `{node}` replaced by the node name, run locally. It prints a
+    /// temperature in °C or a status such as `ok`, `warning` or `critical`.
+    pub command: String,
+    /// Temperatures from which a node is shown as warning or critical.
+    #[serde(default = "default_warn")]
+    pub warn: f64,
+    #[serde(default = "default_critical")]
+    pub critical: f64,
+}
+
+fn default_warn() -> f64 {
+    70.0
+}
+
+fn default_critical() -> f64 {
+    85.0
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum HealthLevel {
+    Ok,
+    Warn,
+    Critical,
+}
+
+#[derive(Clone)]
+pub struct Health {
+    /// First line of the output, e.g. `72` or `ok`.
+    pub text: String,
+    pub level: HealthLevel,
+}
+
+impl NodeHealthConfig {
+    fn check(&self, node: &str) -> Health {
+        let command = self.command.replace("{node}", node);
+        let mut shell = if cfg!(windows) {
+            let mut c = Command::new("cmd");
+            c.arg("/C");
+            c
+        } else {
+            let mut c = Command::new("sh");
+            c.arg("-c");
+            c
+        };
+        let output = shell
+            .arg(&command)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        let text = match output {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+            Ok(output) => {
+                return Health {
+                    text: format!("failed ({})", output.status),
+                    level: HealthLevel::Critical,
+                }
+            }
+            Err(e) => {
+                return Health {
+                    text: e.to_string(),
+                    level: HealthLevel::Critical,
+                }
+            }
+        };
+        let level = match text.trim_end_matches(['°', 'C']).parse::<f64>() {
+            Ok(t) if t >= self.critical => HealthLevel::Critical,
+            Ok(t) if t >= self.warn => HealthLevel::Warn,
+            Ok(_) => HealthLevel::Ok,
+            Err(_) => match text.to_lowercase().as_str() {
+                "ok" | "healthy" | "good" => HealthLevel::Ok,
+                s if s.starts_with("warn") => HealthLevel::Warn,
+                _ => HealthLevel::Critical,
+            },
+        };
+        Health { text, level }
+    }
+}
+
+enum NodeHealthMessage {
+    /// The nodes shown in the node view, empty while it is closed.
+    Nodes(Vec<String>),
+    Config(Option<NodeHealthConfig>),
+}
+
+struct NodeHealthWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<NodeHealthMessage>,
+    config: Option<NodeHealthConfig>,
+    nodes: Vec<String>,
+    polled_at: Option<Instant>,
+}
+
+impl NodeHealthWatcher {
+    fn poll(&mut self) {
+        self.polled_at = Some(Instant::now());
+        let Some(config) = &self.config else {
+            return;
+        };
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut health = HashMap::new();
+        for chunk in self.nodes.chunks(PARALLEL) {
+            thread::scope(|s| {
+                let checks = chunk
+                    .iter()
+                    .map(|node| (node, s.spawn(|| config.check(node))))
+                    .collect::<Vec<_>>();
+                for (node, check) in checks {
+                    if let Ok(h) = check.join() {
+                        health.insert(node.clone(), h);
+                    }
+                }
+            });
+        }
+        let _ = self.app.send(AppMessage::NodeHealth(health));
+    }
+
+    fn run(&mut self) {
+        loop {
+            let due = self
+                .polled_at
+                .map_or(Duration::ZERO, |t| INTERVAL.saturating_sub(t.elapsed()));
+            select! {
+                recv(self.receiver) -> msg => match msg {
+                    Ok(NodeHealthMessage::Nodes(nodes)) => {
+                        if nodes != self.nodes {
+                            let new = nodes.iter().any(|n| !self.nodes.contains(n));
+                            self.nodes = nodes;
+                            if new {
+                                self.poll();
+                            }
+                        }
+                    }
+                    Ok(NodeHealthMessage::Config(config)) => {
+                        self.config = config;
+                        self.poll();
+                    }
+                    Err(_) => return,
+                },
+                default(due) => self.poll(),
+            }
+        }
+    }
+}
+
+pub struct NodeHealthWatcherHandle {
+    sender: Sender<NodeHealthMessage>,
+    config: Option<NodeHealthConfig>,
+}
+
+impl NodeHealthWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, config: Option<NodeHealthConfig>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = NodeHealthWatcher {
+            app,
+            receiver,
+            config: config.clone(),
+            nodes: Vec::new(),
+            polled_at: None,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender, config }
+    }
+
+    pub fn set_nodes(&self, nodes: Vec<String>) {
+        let _ = self.sender.send(NodeHealthMessage::Nodes(nodes));
+    }
+
+    pub fn set_config(&mut self, config: Option<NodeHealthConfig>) {
+        if self.config != config {
+            self.config = config.clone();
+            let _ = self.sender.send(NodeHealthMessage::Config(config));
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::HashMap,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::{
+    channel::{unbounded, Receiver, Sender},
+    select,
+};
+
+use crate::app::AppMessage;
+use crate::remote::slurm_command;
+use crate::usage::parse_size;
+
+/// How often the load of the nodes of the selected job is polled.
+const INTERVAL: Duration = Duration::from_secs(30);
+
+/// The load and memory of a node.
+#[derive(Clone, Default)]
+pub struct NodeLoad {
+    /// Load average, as `CPULoad` of `scontrol show node` or the first value of the command.
+    pub load: Option<f64>,
+    pub cpus: Option<u64>,
+    pub free_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+}
+
+impl NodeLoad {
+    /// More runnable processes than CPUs, e.g. because of a noisy neighbor.
+    pub fn overloaded(&self) -> bool {
+        self.load
+            .zip(self.cpus)
+            .is_some_and(|(load, cpus)| load > cpus as f64)
+    }
+}
+
+/// Whether the busiest node has more than twice the load of the least busy one, as happens
+/// when the ranks of an MPI job are placed or balanced badly.
+pub fn imbalanced(loads: &[&NodeLoad]) -> bool {
+    let loads = loads.iter().filter_map(|l| l.load).collect::<Vec<_>>();
+    let max = loads.iter().copied().fold(f64::MIN, f64::max);
+    let min = loads.iter().copied().fold(f64::MAX, f64::min);
+    loads.len() > 1 && max > 1.0 && max > 2.0 * min
+}
+
+/// The load of the nodes from one `scontrol show node` call.
+fn scontrol(nodes: &[String]) -> HashMap<String, NodeLoad> {
+    let Ok(output) = slurm_command("scontrol")
+        .args(["--oneliner", "show", "node", &nodes.join(",")])
+        .output()
+    else {
+        return HashMap::new();
+    };
+    let mut loads = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let fields = line
+            .split_whitespace()
+            .filter_map(|f| f.split_once('='))
+            .collect::<HashMap<_, _>>();
+        let Some(name) = fields.get("NodeName") else {
+            continue;
+        };
+        // memory in megabytes
+        let mb = |key: &str| {
+            fields
+                .get(key)
+                .and_then(|m| m.parse::<u64>().ok())
+                .map(|m| m << 20)
+        };
+        loads.insert(
+            name.to_string(),
+            NodeLoad {
+                load: fields.get("CPULoad").and_then(|l| l.parse().ok()),
+                cpus: fields.get("CPUTot").and_then(|c| c.parse().ok()),
+                free_bytes: mb("FreeMem"),
+                total_bytes: mb("RealMemory"),
+            },
+        );
+    }
+    loads
+}
+
+/// The load of a node from the configured command, which prints the load average and the
+/// free memory, e.g. `3.5 12G`.
+fn command(command: &str, node: &str) -> NodeLoad {
+    let command = command.replace("{node}", node);
+    let mut shell = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C");
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c");
+        c
+    };
+    let Ok(output) = shell
+        .arg(&command)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+    else {
+        return NodeLoad::default();
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut values = stdout.split_whitespace();
+    NodeLoad {
+        load: values.next().and_then(|l| l.parse().ok()),
+        cpus: None,
+        free_bytes: values.next().and_then(parse_size),
+        total_bytes: None,
+    }
+}
+
+enum NodeLoadMessage {
+    /// The nodes of the selected running job.
+    Nodes(Vec<String>),
+    Command(Option<String>),
+}
+
+/// Polls the load of the nodes of the selected running job.
+struct NodeLoadWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<NodeLoadMessage>,
+    command: Option<String>,
+    nodes: Vec<String>,
+    polled_at: Option<Instant>,
+}
+
+impl NodeLoadWatcher {
+    fn poll(&mut self) {
+        self.polled_at = Some(Instant::now());
+        if self.nodes.is_empty() {
+            return;
+        }
+        let loads = match &self.command {
+            None => scontrol(&self.nodes),
+            Some(c) => thread::scope(|s| {
+                let polls = self
+                    .nodes
+                    .iter()
+                    .map(|node| (node, s.spawn(|| command(c, node))))
+                    .collect::<Vec<_>>();
+                polls
+                    .into_iter()
+                    .filter_map(|(node, poll)| Some((node.clone(), poll.join().ok()?)))
+                    .collect()
+            }),
+        };
+        let _ = self.app.send(AppMessage::NodeLoad(loads));
+    }
+
+    fn run(&mut self) {
+        loop {
+            let due = self
+                .polled_at
+                .map_or(Duration::ZERO, |t| INTERVAL.saturating_sub(t.elapsed()));
+            select! {
+                recv(self.receiver) -> msg => match msg {
+                    Ok(NodeLoadMessage::Nodes(nodes)) => {
+                        if nodes != self.nodes {
+                            self.nodes = nodes;
+                            self.poll();
+                        }
+                    }
+                    Ok(NodeLoadMessage::Command(command)) => {
+                        if command != self.command {
+                            self.command = command;
+                            self.poll();
+                        }
+                    }
+                    Err(_) => return,
+                },
+                default(due) => self.poll(),
+            }
+        }
+    }
+}
+
+pub struct NodeLoadWatcherHandle {
+    sender: Sender<NodeLoadMessage>,
+}
+
+impl NodeLoadWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, command: Option<String>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = NodeLoadWatcher {
+            app,
+            receiver,
+            command,
+            nodes: Vec::new(),
+            polled_at: None,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    pub fn set_nodes(&self, nodes: Vec<String>) {
+        let _ = self.sender.send(NodeLoadMessage::Nodes(nodes));
+    }
+
+    pub fn set_command(&self, command: Option<String>) {
+        let _ = self.sender.send(NodeLoadMessage::Command(command));
+    }
+}
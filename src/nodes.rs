@@ -0,0 +1,188 @@
+use std::{io, thread};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use crate::app::AppMessage;
+use crate::remote::slurm_command;
+
+pub struct Node {
+    pub name: String,
+    pub state: String,
+    pub partitions: Vec<String>,
+    /// Allocated/idle/other/total.
+    pub cpus: String,
+    /// Why the node is down or drained.
+    pub reason: String,
+}
+
+/// Lists all nodes with `sinfo`.
+pub fn list_nodes() -> io::Result<Vec<Node>> {
+    let separator = "###turm###";
+    let fields = ["NodeList", "StateLong", "Partition", "CPUsState", "Reason"];
+    let output = slurm_command("sinfo")
+        .arg("--Node")
+        .arg("--noheader")
+        .arg("--Format")
+        .arg(
+            fields
+                .iter()
+                .map(|f| format!("{}:{}", f, separator))
+                .collect::<Vec<_>>()
+                .join(","),
+        )
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    // one line per node and partition
+    let mut nodes: Vec<Node> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let parts = line.split(separator).map(str::trim).collect::<Vec<_>>();
+        let [name, state, partition, cpus, reason, ..] = parts[..] else {
+            continue;
+        };
+        match nodes.iter_mut().find(|n| n.name == name) {
+            Some(node) => node.partitions.push(partition.to_string()),
+            None => nodes.push(Node {
+                name: name.to_string(),
+                state: state.to_string(),
+                partitions: vec![partition.to_string()],
+                cpus: cpus.to_string(),
+                reason: Some(reason)
+                    .filter(|r| *r != "none")
+                    .unwrap_or_default()
+                    .to_string(),
+            }),
+        }
+    }
+    Ok(nodes)
+}
+
+/// Actions on nodes, which require Slurm operator or admin rights.
+pub enum NodeAction {
+    /// Drains the node with the given reason, so that no new jobs start on it.
+    Drain(String),
+    Resume,
+}
+
+impl NodeAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeAction::Drain(_) => "drain",
+            NodeAction::Resume => "resume",
+        }
+    }
+
+    pub fn run(&self, node: &str) -> Result<(), String> {
+        let mut command = slurm_command("scontrol");
+        command.arg("update").arg(format!("NodeName={}", node));
+        match self {
+            NodeAction::Drain(reason) => {
+                command.arg("State=DRAIN").arg(format!("Reason={}", reason));
+            }
+            NodeAction::Resume => {
+                command.arg("State=RESUME");
+            }
+        }
+        match command.output() {
+            Ok(output) if output.status.success() => Ok(()),
+            Ok(output) => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// Pings the node once from the login node, e.g. `answered in 0.2 ms`.
+fn ping(node: &str) -> Result<String, String> {
+    let output = slurm_command("ping")
+        .args(["-c", "1", "-W", "2", node])
+        .output()
+        .map_err(|e| format!("Failed to run ping: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("{} did not answer", node));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(match stdout.split("time=").nth(1) {
+        Some(time) => format!(
+            "{} answered in {}",
+            node,
+            time.lines().next().unwrap_or_default().trim()
+        ),
+        None => format!("{} answered", node),
+    })
+}
+
+/// Pings nodes in the background, since a node that does not answer takes seconds.
+struct Pinger {
+    app: Sender<AppMessage>,
+    receiver: Receiver<String>,
+}
+
+impl Pinger {
+    fn run(&mut self) {
+        while let Ok(node) = self.receiver.recv() {
+            let _ = self.app.send(AppMessage::Ping(ping(&node)));
+        }
+    }
+}
+
+pub struct PingerHandle {
+    sender: Sender<String>,
+}
+
+impl PingerHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = Pinger { app, receiver };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    pub fn ping(&self, node: &str) {
+        let _ = self.sender.send(node.to_string());
+    }
+}
+
+/// Lists the nodes in the background, also after node actions.
+struct NodeLister {
+    app: Sender<AppMessage>,
+    receiver: Receiver<Option<String>>,
+}
+
+impl NodeLister {
+    fn run(&mut self) {
+        while let Ok(mut selected) = self.receiver.recv() {
+            while let Ok(newer) = self.receiver.try_recv() {
+                selected = newer;
+            }
+            if self
+                .app
+                .send(AppMessage::Nodes(list_nodes(), selected))
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+pub struct NodeListerHandle {
+    sender: Sender<Option<String>>,
+}
+
+impl NodeListerHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = NodeLister { app, receiver };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Lists all nodes, answered with [`AppMessage::Nodes`] along with the node to select.
+    pub fn list(&self, selected: Option<&str>) {
+        let _ = self.sender.send(selected.map(str::to_string));
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::app::Job;
+
+/// A job that entered a new state since the previous refresh.
+pub struct Transition {
+    pub job: Job,
+    pub from: String,
+    pub to: String,
+}
+
+fn base_state(job: &Job) -> &str {
+    job.state.split_whitespace().next().unwrap_or_default()
+}
+
+/// The state changes between two refreshes. Jobs that (dis)appeared are not transitions.
+pub fn transitions(old: &[Job], new: &[Job]) -> Vec<Transition> {
+    let old: HashMap<_, _> = old.iter().map(|o| (o.id(), o)).collect();
+    new.iter()
+        .filter_map(|job| {
+            let previous = old.get(&job.id())?;
+            let (from, to) = (base_state(previous), base_state(job));
+            (from != to).then(|| Transition {
+                job: job.clone(),
+                from: from.to_string(),
+                to: to.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// States of my jobs that open an alert, as they usually need an action.
+pub const CRITICAL_STATES: [&str; 3] = ["NODE_FAIL", "OUT_OF_MEMORY", "PREEMPTED"];
+
+/// Number of transitions kept in the notification history.
+pub const HISTORY_LEN: usize = 1000;
+
+/// A transition in the notification history.
+pub struct HistoryEntry {
+    pub time: NaiveDateTime,
+    pub job_id: String,
+    pub name: String,
+    pub from: String,
+    pub to: String,
+    /// Whether it was notified or suppressed by the rules.
+    pub notified: bool,
+}
+
+/// Which state changes are notified (in the status bar and by email), managed in the
+/// notification settings (`Z`) and persisted with the local state.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct NotificationRules {
+    /// The rule for a job is the one for its id, or else the first filter matching it.
+    pub rules: Vec<NotificationRule>,
+    /// Nothing is notified in between, e.g. at night.
+    pub quiet_hours: Option<QuietHours>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NotificationRule {
+    pub target: RuleTarget,
+    /// Only transitions into these states are notified, all if empty.
+    #[serde(default)]
+    pub states: Vec<String>,
+    pub snoozed_until: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleTarget {
+    /// A job id, also matching the tasks of an array job.
+    Job(String),
+    /// A search query as in `/`.
+    Filter(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    /// Parses e.g. `22:00-07:00`.
+    pub fn parse(s: &str) -> Result<QuietHours, String> {
+        let time = |t: &str| {
+            NaiveTime::parse_from_str(t.trim(), "%H:%M")
+                .map_err(|_| format!("Invalid time {:?}, expected e.g. 22:00-07:00", t.trim()))
+        };
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| format!("Invalid quiet hours {:?}, expected e.g. 22:00-07:00", s))?;
+        Ok(QuietHours {
+            start: time(start)?,
+            end: time(end)?,
+        })
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            // across midnight
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl std::fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+impl RuleTarget {
+    fn matches(&self, job: &Job) -> bool {
+        match self {
+            RuleTarget::Job(id) => job.id() == *id || job.job_id == *id,
+            RuleTarget::Filter(query) => job.matches(query),
+        }
+    }
+}
+
+impl std::fmt::Display for RuleTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleTarget::Job(id) => write!(f, "job {}", id),
+            RuleTarget::Filter(query) => write!(f, "jobs matching {:?}", query),
+        }
+    }
+}
+
+impl NotificationRule {
+    pub fn new(target: RuleTarget) -> Self {
+        NotificationRule {
+            target,
+            states: Vec::new(),
+            snoozed_until: None,
+        }
+    }
+
+    pub fn is_snoozed(&self, now: NaiveDateTime) -> bool {
+        self.snoozed_until.is_some_and(|t| now < t)
+    }
+}
+
+impl NotificationRules {
+    /// The rule that applies to the job.
+    pub fn rule_for(&self, job: &Job) -> Option<&NotificationRule> {
+        self.rules
+            .iter()
+            .find(|r| matches!(r.target, RuleTarget::Job(_)) && r.target.matches(job))
+            .or_else(|| {
+                self.rules
+                    .iter()
+                    .find(|r| matches!(r.target, RuleTarget::Filter(_)) && r.target.matches(job))
+            })
+    }
+
+    /// Whether the transition should be notified at `now`.
+    pub fn allows(&self, transition: &Transition, now: NaiveDateTime) -> bool {
+        if self.quiet_hours.is_some_and(|q| q.contains(now.time())) {
+            return false;
+        }
+        match self.rule_for(&transition.job) {
+            Some(rule) => {
+                !rule.is_snoozed(now)
+                    && (rule.states.is_empty() || rule.states.contains(&transition.to))
+            }
+            None => true,
+        }
+    }
+
+    /// Adds a rule for the target unless there is one, returning its index.
+    pub fn add(&mut self, target: RuleTarget) -> usize {
+        match self.rules.iter().position(|r| r.target == target) {
+            Some(i) => i,
+            None => {
+                self.rules.push(NotificationRule::new(target));
+                self.rules.len() - 1
+            }
+        }
+    }
+}
+
+/// Parses the end of a snooze, either a duration such as `30m`, `2h` or `1d`, or the next
+/// occurrence of a time of day such as `08:00`.
+pub fn parse_snooze(s: &str, now: NaiveDateTime) -> Result<NaiveDateTime, String> {
+    let s = s.trim();
+    let now = now.with_nanosecond(0).unwrap_or(now);
+    if let Ok(time) = NaiveTime::parse_from_str(s, "%H:%M") {
+        let today = now.date().and_time(time);
+        return Ok(if today > now {
+            today
+        } else {
+            today + Duration::days(1)
+        });
+    }
+    let split = s.len() - s.chars().last().map_or(0, |c| c.len_utf8());
+    let (number, unit) = s.split_at(split);
+    let duration = number.parse::<i64>().ok().and_then(|n| match unit {
+        "m" => Some(Duration::minutes(n)),
+        "h" => Some(Duration::hours(n)),
+        "d" => Some(Duration::days(n)),
+        _ => None,
+    });
+    duration
+        .map(|d| now + d)
+        .ok_or_else(|| format!("Invalid snooze {:?}, expected e.g. 30m, 2h, 1d or 08:00", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn quiet_hours() {
+        let night = QuietHours::parse("22:00-07:00").unwrap();
+        assert_eq!(night.to_string(), "22:00-07:00");
+        for time in ["22:00", "23:59", "00:00", "06:59"] {
+            assert!(night.contains(at(time)), "{}", time);
+        }
+        for time in ["07:00", "12:00", "21:59"] {
+            assert!(!night.contains(at(time)), "{}", time);
+        }
+
+        let lunch = QuietHours::parse(" 12:00 - 13:30 ").unwrap();
+        assert!(lunch.contains(at("12:00")));
+        assert!(lunch.contains(at("13:29")));
+        assert!(!lunch.contains(at("13:30")));
+        assert!(!lunch.contains(at("11:59")));
+
+        for invalid in ["22:00", "22:00-", "25:00-07:00", "evening-morning"] {
+            assert!(QuietHours::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::path::PathBuf;
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::remote::slurm_command;
+
+/// Submitted pipelines that are kept in the state file.
+pub const MAX_RUNS: usize = 20;
+
+/// Batch scripts that are submitted together, each step after the steps it depends on, e.g.
+/// preprocessing, then training, then evaluation.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Pipeline {
+    pub name: String,
+    /// Directory sbatch is run in, the current directory by default.
+    pub work_dir: Option<PathBuf>,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    pub name: String,
+    /// Arguments passed on to sbatch, e.g. `["--time=1:00:00", "train.sh"]`.
+    pub sbatch: Vec<String>,
+    /// Earlier steps that have to finish first.
+    #[serde(default)]
+    pub after: Vec<String>,
+    /// How they have to finish, `afterok` by default.
+    #[serde(default = "default_dependency")]
+    pub dependency: String,
+}
+
+fn default_dependency() -> String {
+    "afterok".to_string()
+}
+
+/// A submitted pipeline.
+#[derive(Serialize, Deserialize)]
+pub struct PipelineRun {
+    pub name: String,
+    pub submitted: NaiveDateTime,
+    pub steps: Vec<StepRun>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StepRun {
+    pub name: String,
+    pub job_id: String,
+    pub after: Vec<String>,
+}
+
+fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Runs sbatch for one step and returns the new job id.
+fn sbatch(pipeline: &Pipeline, step: &Step, dependency: Option<String>) -> Result<String, String> {
+    let mut args = vec!["--parsable".to_string()];
+    args.extend(dependency);
+    args.extend(step.sbatch.iter().cloned());
+    let output = match &pipeline.work_dir {
+        Some(dir) => slurm_command("sh")
+            .arg("-c")
+            .arg(format!(
+                "cd {} && sbatch {}",
+                quote(&dir.to_string_lossy()),
+                args.iter().map(|a| quote(a)).collect::<Vec<_>>().join(" ")
+            ))
+            .output(),
+        None => slurm_command("sbatch").args(&args).output(),
+    }
+    .map_err(|e| format!("Failed to execute sbatch: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    // `--parsable` prints `jobid[;cluster]`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .trim()
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .to_string())
+}
+
+impl Pipeline {
+    /// Checks that every step is only after earlier steps, which also rules out dependency
+    /// cycles, so that nothing is submitted for a pipeline that cannot be submitted in full.
+    fn check(&self) -> Result<(), String> {
+        for (i, step) in self.steps.iter().enumerate() {
+            let earlier = &self.steps[..i];
+            if earlier.iter().any(|s| s.name == step.name) {
+                return Err(format!(
+                    "Pipeline {} has two steps named {}",
+                    self.name, step.name
+                ));
+            }
+            if let Some(name) = step
+                .after
+                .iter()
+                .find(|name| !earlier.iter().any(|s| s.name == **name))
+            {
+                return Err(format!(
+                    "Step {} of pipeline {} is after {}, which is not an earlier step",
+                    step.name, self.name, name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Submits the steps in order, with `--dependency` on the job ids of the earlier steps
+    /// they are after. If a step fails, the steps submitted so far are cancelled again.
+    pub fn submit(&self) -> Result<PipelineRun, String> {
+        self.check()?;
+        let mut steps: Vec<StepRun> = Vec::new();
+        for step in &self.steps {
+            let after = step
+                .after
+                .iter()
+                .map(|name| {
+                    steps
+                        .iter()
+                        .find(|s| s.name == *name)
+                        .map(|s| s.job_id.as_str())
+                        .ok_or_else(|| {
+                            format!(
+                                "Step {} is after {}, which is not an earlier step",
+                                step.name, name
+                            )
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>();
+            let dependency = after.map(|ids| {
+                (!ids.is_empty())
+                    .then(|| format!("--dependency={}:{}", step.dependency, ids.join(":")))
+            });
+            match dependency.and_then(|d| sbatch(self, step, d)) {
+                Ok(job_id) => steps.push(StepRun {
+                    name: step.name.clone(),
+                    job_id,
+                    after: step.after.clone(),
+                }),
+                Err(e) => {
+                    if !steps.is_empty() {
+                        let _ = slurm_command("scancel")
+                            .args(steps.iter().map(|s| &s.job_id))
+                            .output();
+                    }
+                    return Err(format!(
+                        "Failed to submit step {} of pipeline {}: {}",
+                        step.name, self.name, e
+                    ));
+                }
+            }
+        }
+        Ok(PipelineRun {
+            name: self.name.clone(),
+            submitted: Local::now().naive_local(),
+            steps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline(steps: &[(&str, &[&str])]) -> Pipeline {
+        Pipeline {
+            name: "train".to_string(),
+            work_dir: None,
+            steps: steps
+                .iter()
+                .map(|(name, after)| Step {
+                    name: name.to_string(),
+                    sbatch: vec![format!("{}.sh", name)],
+                    after: after.iter().map(|a| a.to_string()).collect(),
+                    dependency: default_dependency(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn dependencies() {
+        assert!(pipeline(&[]).check().is_ok());
+        assert!(pipeline(&[
+            ("prep", &[]),
+            ("fit", &["prep"]),
+            ("eval", &["prep", "fit"])
+        ])
+        .check()
+        .is_ok());
+
+        let unknown = pipeline(&[("prep", &[]), ("fit", &["data"])]).check();
+        assert_eq!(
+            unknown.unwrap_err(),
+            "Step fit of pipeline train is after data, which is not an earlier step"
+        );
+        let duplicate = pipeline(&[("prep", &[]), ("prep", &[])]).check();
+        assert_eq!(
+            duplicate.unwrap_err(),
+            "Pipeline train has two steps named prep"
+        );
+    }
+
+    #[test]
+    fn dependency_cycles() {
+        let own = pipeline(&[("prep", &["prep"])]).check();
+        assert!(own.unwrap_err().contains("after prep"));
+        let cycle = pipeline(&[("prep", &["eval"]), ("fit", &["prep"]), ("eval", &["fit"])]);
+        assert!(cycle.check().unwrap_err().starts_with("Step prep "));
+        // A later step is rejected even without a cycle, since steps are submitted in order.
+        let later = pipeline(&[("prep", &["fit"]), ("fit", &[])]);
+        assert!(later.check().is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::app::{AppMessage, Job};
+
+/// How long a plugin may take to answer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// How long to wait before restarting a plugin that crashed or misbehaved.
+const RESTART_DELAY: Duration = Duration::from_secs(30);
+
+/// An external executable that contributes per-job annotations and/or a panel.
+///
+/// Plugins run locally, also in remote mode, and live as long as turm. After every poll,
+/// they get a line with a JSON object `{"jobs": [...]}` on stdin, each job having `id`,
+/// `name`, `state`, `user`, `partition`, `nodes`, `command`, `work_dir`, `stdout` and
+/// `stderr`. They answer with one line `{"annotations": {"<job id>": "text"}, "panel":
+/// {"title": "...", "lines": ["..."]}}` on stdout, where both keys are optional.
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Panel {
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Response {
+    annotations: HashMap<String, String>,
+    panel: Option<Panel>,
+}
+
+/// The last answer of a plugin, or why there is none.
+#[derive(Clone)]
+pub struct PluginOutput {
+    pub name: String,
+    pub annotations: HashMap<String, String>,
+    pub panel: Option<Panel>,
+    pub error: Option<String>,
+}
+
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<io::Result<String>>,
+}
+
+impl Process {
+    fn spawn(config: &PluginConfig) -> io::Result<Process> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            // would garble the UI
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = unbounded();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if sender.send(line).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Process {
+            child,
+            stdin,
+            lines,
+        })
+    }
+
+    fn request(&mut self, request: &str) -> Result<Response, String> {
+        writeln!(self.stdin, "{}", request)
+            .and_then(|()| self.stdin.flush())
+            .map_err(|e| format!("failed to write: {}", e))?;
+        let line = match self.lines.recv_timeout(TIMEOUT) {
+            Ok(Ok(line)) => line,
+            Ok(Err(e)) => return Err(format!("failed to read: {}", e)),
+            Err(RecvTimeoutError::Timeout) => return Err("no answer in time".to_string()),
+            Err(RecvTimeoutError::Disconnected) => return Err("exited".to_string()),
+        };
+        serde_json::from_str(&line).map_err(|e| format!("invalid answer: {}", e))
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+struct Plugin {
+    config: PluginConfig,
+    process: Option<Process>,
+    /// When a failed plugin may be started again.
+    restart_at: Instant,
+    output: PluginOutput,
+}
+
+impl Plugin {
+    fn new(config: PluginConfig) -> Plugin {
+        Plugin {
+            output: PluginOutput {
+                name: config.name.clone(),
+                annotations: HashMap::new(),
+                panel: None,
+                error: None,
+            },
+            config,
+            process: None,
+            restart_at: Instant::now(),
+        }
+    }
+
+    /// Hands the jobs to the plugin. A plugin that fails in any way is stopped and
+    /// restarted later, keeping its last answer.
+    fn update(&mut self, request: &str) {
+        if self.process.is_none() {
+            if Instant::now() < self.restart_at {
+                return;
+            }
+            match Process::spawn(&self.config) {
+                Ok(process) => self.process = Some(process),
+                Err(e) => {
+                    self.fail(format!("failed to start {}: {}", self.config.command, e));
+                    return;
+                }
+            }
+        }
+        match self.process.as_mut().unwrap().request(request) {
+            Ok(response) => {
+                self.output.annotations = response.annotations;
+                self.output.panel = response.panel;
+                self.output.error = None;
+            }
+            Err(e) => self.fail(e),
+        }
+    }
+
+    fn fail(&mut self, error: String) {
+        self.process = None;
+        self.restart_at = Instant::now() + RESTART_DELAY;
+        self.output.error = Some(error);
+    }
+}
+
+enum PluginHostMessage {
+    Jobs(String),
+    Plugins(Vec<PluginConfig>),
+}
+
+/// Runs the plugins in a separate thread, so that slow or broken plugins cannot block
+/// the UI.
+struct PluginHost {
+    app: Sender<AppMessage>,
+    receiver: Receiver<PluginHostMessage>,
+    plugins: Vec<Plugin>,
+}
+
+impl PluginHost {
+    fn run(&mut self) {
+        while let Ok(message) = self.receiver.recv() {
+            match message {
+                PluginHostMessage::Jobs(request) => {
+                    // only answer the latest poll if the plugins fell behind
+                    let mut request = request;
+                    while let Ok(message) = self.receiver.try_recv() {
+                        match message {
+                            PluginHostMessage::Jobs(r) => request = r,
+                            PluginHostMessage::Plugins(configs) => self.set_plugins(configs),
+                        }
+                    }
+                    for plugin in &mut self.plugins {
+                        plugin.update(&request);
+                    }
+                    let outputs = self.plugins.iter().map(|p| p.output.clone()).collect();
+                    if self.app.send(AppMessage::Plugins(outputs)).is_err() {
+                        return;
+                    }
+                }
+                PluginHostMessage::Plugins(configs) => self.set_plugins(configs),
+            }
+        }
+    }
+
+    /// Keeps the plugins whose config did not change running.
+    fn set_plugins(&mut self, configs: Vec<PluginConfig>) {
+        let mut old = std::mem::take(&mut self.plugins);
+        self.plugins = configs
+            .into_iter()
+            .map(|config| match old.iter().position(|p| p.config == config) {
+                Some(i) => old.remove(i),
+                None => Plugin::new(config),
+            })
+            .collect();
+    }
+}
+
+pub struct PluginHostHandle {
+    sender: Sender<PluginHostMessage>,
+    configs: Vec<PluginConfig>,
+}
+
+impl PluginHostHandle {
+    pub fn new(app: Sender<AppMessage>, configs: Vec<PluginConfig>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut host = PluginHost {
+            app,
+            receiver,
+            plugins: Vec::new(),
+        };
+        host.set_plugins(configs.clone());
+        thread::spawn(move || host.run());
+        Self { sender, configs }
+    }
+
+    pub fn set_plugins(&mut self, configs: &[PluginConfig]) {
+        if self.configs != configs {
+            self.configs = configs.to_vec();
+            let _ = self
+                .sender
+                .send(PluginHostMessage::Plugins(configs.to_vec()));
+        }
+    }
+
+    pub fn update(&self, jobs: &[Job]) {
+        if self.configs.is_empty() {
+            return;
+        }
+        let jobs = jobs.iter().map(Job::to_json).collect::<Vec<_>>();
+        let request = json!({ "jobs": jobs }).to_string();
+        let _ = self.sender.send(PluginHostMessage::Jobs(request));
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    collections::{HashMap, HashSet},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use crate::app::AppMessage;
+use crate::remote::slurm_command;
+
+/// How long the preemption settings of the cluster, its partitions and QOS are kept.
+const POLICY_TTL: Duration = Duration::from_secs(300);
+
+/// What happens to a job when it is preempted.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Requeue,
+    Cancel,
+    Suspend,
+}
+
+impl Outcome {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Outcome::Requeue => "requeue",
+            Outcome::Cancel => "cancel",
+            Outcome::Suspend => "suspend",
+        }
+    }
+
+    /// The outcome of a preempt mode such as `REQUEUE` or `SUSPEND,GANG`, if it preempts.
+    fn of_mode(mode: &str) -> Option<Outcome> {
+        let modes = mode.split(',').collect::<Vec<_>>();
+        if modes.contains(&"REQUEUE") {
+            Some(Outcome::Requeue)
+        } else if modes.contains(&"CANCEL") {
+            Some(Outcome::Cancel)
+        } else if modes.contains(&"SUSPEND") || modes.contains(&"GANG") {
+            Some(Outcome::Suspend)
+        } else {
+            None
+        }
+    }
+}
+
+/// A running job that other jobs may preempt.
+#[derive(Clone, PartialEq)]
+pub struct Preemptible {
+    pub outcome: Outcome,
+    /// What preempts it, e.g. `QOS high` or `partitions with a higher priority tier`.
+    pub by: String,
+    /// Whether the job may be requeued (`--requeue`), if it was looked up.
+    pub requeue: Option<bool>,
+}
+
+/// A running job whose preemption is looked up, with its QOS and partition.
+#[derive(PartialEq)]
+pub struct PreemptQuery {
+    pub id: String,
+    pub qos: String,
+    pub partition: String,
+    /// Whether its requeue flag is looked up with `scontrol`, i.e. for my jobs.
+    pub mine: bool,
+}
+
+struct Qos {
+    mode: String,
+    /// The QOS it may preempt.
+    preempts: Vec<String>,
+}
+
+struct Partition {
+    mode: String,
+    tier: u32,
+}
+
+/// The preemption settings of the cluster.
+#[derive(Default)]
+struct Policy {
+    /// `PreemptType`, e.g. `preempt/qos` or `preempt/partition_prio`.
+    preempt_type: String,
+    /// `PreemptMode`, which applies where partitions and QOS do not set their own.
+    mode: String,
+    /// `JobRequeue`, whether jobs may be requeued unless they set `--no-requeue`.
+    requeue_default: bool,
+    partitions: HashMap<String, Partition>,
+    qos: HashMap<String, Qos>,
+}
+
+/// `key=value` fields of `scontrol` output.
+fn fields(line: &str) -> HashMap<&str, &str> {
+    line.split_whitespace()
+        .filter_map(|f| f.split_once('='))
+        .collect()
+}
+
+impl Policy {
+    fn load() -> Policy {
+        let mut policy = Policy {
+            requeue_default: true,
+            ..Policy::default()
+        };
+        if let Ok(output) = slurm_command("scontrol").args(["show", "config"]).output() {
+            for line in String::from_utf8_lossy(&output.stdout).lines() {
+                let Some((key, value)) = line.split_once('=') else {
+                    continue;
+                };
+                match key.trim() {
+                    "PreemptType" => policy.preempt_type = value.trim().to_string(),
+                    "PreemptMode" => policy.mode = value.trim().to_string(),
+                    "JobRequeue" => policy.requeue_default = value.trim() != "0",
+                    _ => {}
+                }
+            }
+        }
+        if let Ok(output) = slurm_command("scontrol")
+            .args(["--oneliner", "show", "partition"])
+            .output()
+        {
+            for line in String::from_utf8_lossy(&output.stdout).lines() {
+                let fields = fields(line);
+                if let Some(name) = fields.get("PartitionName") {
+                    policy.partitions.insert(
+                        name.to_string(),
+                        Partition {
+                            mode: fields.get("PreemptMode").unwrap_or(&"").to_string(),
+                            tier: fields
+                                .get("PriorityTier")
+                                .and_then(|t| t.parse().ok())
+                                .unwrap_or(0),
+                        },
+                    );
+                }
+            }
+        }
+        if let Ok(output) = slurm_command("sacctmgr")
+            .args(["show", "qos", "format=Name,Preempt,PreemptMode"])
+            .args(["--parsable2", "--noheader"])
+            .output()
+        {
+            for line in String::from_utf8_lossy(&output.stdout).lines() {
+                let [name, preempts, mode] = line.split('|').collect::<Vec<_>>()[..] else {
+                    continue;
+                };
+                policy.qos.insert(
+                    name.to_string(),
+                    Qos {
+                        mode: mode.to_uppercase(),
+                        preempts: preempts
+                            .split(',')
+                            .filter(|q| !q.is_empty())
+                            .map(String::from)
+                            .collect(),
+                    },
+                );
+            }
+        }
+        policy
+    }
+
+    /// How a job of the QOS in the partition is preempted, and by what.
+    fn risk(&self, qos: &str, partition: &str) -> Option<(Outcome, String)> {
+        Outcome::of_mode(&self.mode)?;
+        if self.preempt_type.contains("qos") {
+            let mut by = self
+                .qos
+                .iter()
+                .filter(|(_, q)| q.preempts.iter().any(|p| p == qos))
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>();
+            if by.is_empty() {
+                return None;
+            }
+            by.sort();
+            let mode = self
+                .qos
+                .get(qos)
+                .map(|q| q.mode.as_str())
+                .filter(|m| !m.is_empty() && *m != "CLUSTER")
+                .unwrap_or(&self.mode);
+            Some((Outcome::of_mode(mode)?, format!("QOS {}", by.join(", "))))
+        } else if self.preempt_type.contains("partition_prio") {
+            let own = self.partitions.get(partition)?;
+            if !self.partitions.values().any(|p| p.tier > own.tier) {
+                return None;
+            }
+            let mode = match own.mode.as_str() {
+                "" => &self.mode,
+                mode => mode,
+            };
+            Some((
+                Outcome::of_mode(mode)?,
+                "partitions with a higher priority tier".to_string(),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// The requeue flag of a job from `scontrol show job`.
+fn requeue_flag(id: &str) -> Option<bool> {
+    let output = slurm_command("scontrol")
+        .args(["--oneliner", "show", "job", id])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    fields(&stdout).get("Requeue").map(|r| *r != "0")
+}
+
+/// Works out which running jobs may be preempted and what happens to them then.
+struct PreemptionWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<Vec<PreemptQuery>>,
+    policy: Policy,
+    loaded_at: Option<Instant>,
+    /// The requeue flags of my jobs, which only change on `scontrol update`.
+    requeue: HashMap<String, Option<bool>>,
+    sent: HashMap<String, Preemptible>,
+}
+
+impl PreemptionWatcher {
+    fn update(&mut self, jobs: Vec<PreemptQuery>) {
+        if self.loaded_at.is_none_or(|t| t.elapsed() > POLICY_TTL) {
+            self.policy = Policy::load();
+            self.loaded_at = Some(Instant::now());
+        }
+        let mut preemptible = HashMap::new();
+        for job in &jobs {
+            let Some((mut outcome, by)) = self.policy.risk(&job.qos, &job.partition) else {
+                continue;
+            };
+            let requeue = match job.mine && outcome == Outcome::Requeue {
+                true => *self
+                    .requeue
+                    .entry(job.id.clone())
+                    .or_insert_with(|| requeue_flag(&job.id)),
+                false => None,
+            };
+            // jobs that may not be requeued are cancelled instead
+            if outcome == Outcome::Requeue && !requeue.unwrap_or(self.policy.requeue_default) {
+                outcome = Outcome::Cancel;
+            }
+            preemptible.insert(
+                job.id.clone(),
+                Preemptible {
+                    outcome,
+                    by,
+                    requeue,
+                },
+            );
+        }
+        let ids = jobs.iter().map(|j| j.id.as_str()).collect::<HashSet<_>>();
+        self.requeue.retain(|id, _| ids.contains(id.as_str()));
+        if preemptible != self.sent {
+            self.sent = preemptible.clone();
+            let _ = self.app.send(AppMessage::Preemption(preemptible));
+        }
+    }
+
+    fn run(&mut self) {
+        while let Ok(mut jobs) = self.receiver.recv() {
+            // only the latest jobs matter
+            while let Ok(newer) = self.receiver.try_recv() {
+                jobs = newer;
+            }
+            self.update(jobs);
+        }
+    }
+}
+
+pub struct PreemptionWatcherHandle {
+    sender: Sender<Vec<PreemptQuery>>,
+}
+
+impl PreemptionWatcherHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = PreemptionWatcher {
+            app,
+            receiver,
+            policy: Policy::default(),
+            loaded_at: None,
+            requeue: HashMap::new(),
+            sent: HashMap::new(),
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Looks up whether the running jobs may be preempted.
+    pub fn set_jobs(&self, jobs: Vec<PreemptQuery>) {
+        let _ = self.sender.send(jobs);
+    }
+}
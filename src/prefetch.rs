@@ -0,0 +1,246 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::{
+    channel::{unbounded, Receiver, Sender},
+    select,
+};
+
+use crate::app::AppMessage;
+use crate::file_watcher::format_bytes;
+use crate::job_watcher::SEPARATOR;
+use crate::remote::slurm_command;
+use crate::slurm_time::{format_slurm_duration, parse_duration};
+use crate::usage::parse_size;
+
+/// Jobs near the selection that are fetched in one `sacct` call.
+const BATCH: usize = 10;
+
+/// Pause between background `sacct` calls, so that prefetching stays gentle on slurmctld.
+const PACE: Duration = Duration::from_secs(3);
+
+/// How long the details of an unfinished job are kept before they are fetched again, since
+/// it may start new steps.
+const ACTIVE_TTL: Duration = Duration::from_secs(60);
+
+/// A step of a job as reported by `sacct`, e.g. `batch` or `0`.
+pub struct Step {
+    pub id: String,
+    pub name: String,
+    pub state: String,
+    pub exit_code: String,
+    pub elapsed: Option<chrono::Duration>,
+    pub max_rss: Option<u64>,
+}
+
+/// Accounting details of a job that are shown in the details pane.
+pub struct JobDetails {
+    /// `code:signal` as reported by `sacct`, e.g. `0:0` or `0:9`.
+    pub exit_code: String,
+    pub steps: Vec<Step>,
+    pub finished: bool,
+}
+
+/// An exit code as a human readable string, e.g. `1` or `signal 9`.
+pub fn format_exit_code(exit_code: &str) -> String {
+    match exit_code.split_once(':') {
+        Some((_, signal)) if signal != "0" => format!("signal {}", signal),
+        Some((code, _)) => code.to_string(),
+        None => exit_code.to_string(),
+    }
+}
+
+impl Step {
+    /// The step in a few words, e.g. `0 python COMPLETED (1:02:03, 1.2GB RSS)`.
+    pub fn summary(&self) -> String {
+        let mut summary = match self.name == self.id {
+            true => format!("{} {}", self.id, self.state),
+            false => format!("{} {} {}", self.id, self.name, self.state),
+        };
+        if self.exit_code != "0:0" && !self.exit_code.is_empty() {
+            summary += &format!(" exit {}", format_exit_code(&self.exit_code));
+        }
+        let usage = self
+            .elapsed
+            .map(format_slurm_duration)
+            .into_iter()
+            .chain(
+                self.max_rss
+                    .map(|rss| format!("{} RSS", format_bytes(rss as f64))),
+            )
+            .collect::<Vec<_>>();
+        if !usage.is_empty() {
+            summary += &format!(" ({})", usage.join(", "));
+        }
+        summary
+    }
+}
+
+/// The details of the jobs from one `sacct` call, by the ids they were requested with.
+fn sacct(ids: &[String]) -> Result<HashMap<String, JobDetails>, String> {
+    let output = slurm_command("sacct")
+        .arg(format!("--jobs={}", ids.join(",")))
+        .args(["--noheader", "--parsable2", "--delimiter", SEPARATOR])
+        .arg("--format=JobID,JobName,State,ExitCode,Elapsed,MaxRSS")
+        .output()
+        .map_err(|e| format!("Failed to execute sacct: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "sacct failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let mut details: HashMap<String, JobDetails> = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let [id, name, state, exit_code, elapsed, rss] =
+            line.split(SEPARATOR).collect::<Vec<_>>()[..]
+        else {
+            continue;
+        };
+        match id.split_once('.') {
+            // the external step only tracks processes outside of Slurm's steps
+            Some((_, "extern")) => {}
+            Some((job, step)) => {
+                if let Some(details) = details.get_mut(job) {
+                    details.steps.push(Step {
+                        id: step.to_string(),
+                        name: name.to_string(),
+                        state: state.to_string(),
+                        exit_code: exit_code.to_string(),
+                        elapsed: parse_duration(elapsed),
+                        max_rss: parse_size(rss).filter(|_| !rss.is_empty()),
+                    });
+                }
+            }
+            None if ids.iter().any(|i| i == id) => {
+                details.insert(
+                    id.to_string(),
+                    JobDetails {
+                        exit_code: exit_code.to_string(),
+                        steps: Vec::new(),
+                        finished: !matches!(
+                            state,
+                            "PENDING" | "RUNNING" | "REQUEUED" | "RESIZING" | "SUSPENDED"
+                        ),
+                    },
+                );
+            }
+            None => {}
+        }
+    }
+    Ok(details)
+}
+
+enum PrefetcherMessage {
+    /// The selected job and the jobs around it, closest first.
+    Selection(Option<String>, Vec<String>),
+}
+
+/// Fetches the `sacct` details of the selected job right away, and those of the jobs around
+/// the selection in the background, so that they are ready when the selection moves there.
+struct Prefetcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<PrefetcherMessage>,
+    queue: VecDeque<String>,
+    /// When the details of a job were fetched, and whether they can still change.
+    fetched: HashMap<String, (Instant, bool)>,
+    fetched_at: Option<Instant>,
+}
+
+impl Prefetcher {
+    fn is_fresh(&self, id: &str) -> bool {
+        self.fetched
+            .get(id)
+            .is_some_and(|(at, active)| !active || at.elapsed() < ACTIVE_TTL)
+    }
+
+    fn fetch(&mut self, ids: Vec<String>) {
+        self.fetched_at = Some(Instant::now());
+        match sacct(&ids) {
+            Ok(details) => {
+                for id in &ids {
+                    let active = details.get(id).is_none_or(|d| !d.finished);
+                    self.fetched.insert(id.clone(), (Instant::now(), active));
+                }
+                let _ = self.app.send(AppMessage::Details(details));
+            }
+            // tried again once the details would be stale
+            Err(_) => {
+                for id in ids {
+                    self.fetched.insert(id, (Instant::now(), true));
+                }
+            }
+        }
+    }
+
+    fn select(&mut self, selected: Option<String>, nearby: Vec<String>) {
+        self.queue = nearby.into_iter().filter(|id| !self.is_fresh(id)).collect();
+        if let Some(selected) = selected.filter(|id| !self.is_fresh(id)) {
+            self.queue.retain(|id| *id != selected);
+            self.fetch(vec![selected]);
+        }
+    }
+
+    fn run(&mut self) {
+        loop {
+            if self.queue.is_empty() {
+                match self.receiver.recv() {
+                    Ok(PrefetcherMessage::Selection(selected, nearby)) => {
+                        self.select(selected, nearby)
+                    }
+                    Err(_) => return,
+                }
+                continue;
+            }
+            let due = self
+                .fetched_at
+                .map_or(Duration::ZERO, |t| PACE.saturating_sub(t.elapsed()));
+            select! {
+                recv(self.receiver) -> msg => match msg {
+                    Ok(PrefetcherMessage::Selection(selected, nearby)) => {
+                        self.select(selected, nearby)
+                    }
+                    Err(_) => return,
+                },
+                default(due) => {
+                    let batch = self
+                        .queue
+                        .drain(..self.queue.len().min(BATCH))
+                        .collect::<Vec<_>>();
+                    self.fetch(batch);
+                }
+            }
+        }
+    }
+}
+
+pub struct PrefetcherHandle {
+    sender: Sender<PrefetcherMessage>,
+}
+
+impl PrefetcherHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = Prefetcher {
+            app,
+            receiver,
+            queue: VecDeque::new(),
+            fetched: HashMap::new(),
+            fetched_at: None,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Fetches the details of the selected job, if they are not known yet, and queues the
+    /// jobs around it.
+    pub fn set_selection(&self, selected: Option<String>, nearby: Vec<String>) {
+        let _ = self
+            .sender
+            .send(PrefetcherMessage::Selection(selected, nearby));
+    }
+}
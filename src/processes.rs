@@ -0,0 +1,116 @@
+use std::thread;
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use crate::app::AppMessage;
+use crate::remote::slurm_command;
+
+/// Lists the processes of the user on every node of the job, except for the shell running `ps`
+/// and its children. Runs as an overlapping job step, so it works while the job uses all of
+/// its resources.
+const PS_SCRIPT: &str = r#"ps -u "$(id -u)" -o pid=,ppid=,pcpu=,pmem=,rss=,etime=,args= | awk -v self=$$ -v node="$SLURMD_NODENAME" '$1 != self && $2 != self { print node, $0 }'"#;
+
+/// A process running on one of the nodes of a job.
+pub struct Process {
+    pub node: String,
+    pub pid: u32,
+    pub cpu: f64,
+    pub mem: f64,
+    pub rss_kib: u64,
+    pub elapsed: String,
+    pub command: String,
+}
+
+/// The processes of a job, or why they could not be listed.
+pub type ProcessList = Result<Vec<Process>, String>;
+
+fn parse_process(line: &str) -> Option<Process> {
+    let mut rest = line.trim();
+    let mut fields = Vec::with_capacity(7);
+    for _ in 0..7 {
+        let (field, tail) = rest.split_once(char::is_whitespace)?;
+        fields.push(field);
+        rest = tail.trim_start();
+    }
+    Some(Process {
+        node: fields[0].to_string(),
+        pid: fields[1].parse().ok()?,
+        cpu: fields[3].parse().ok()?,
+        mem: fields[4].parse().ok()?,
+        rss_kib: fields[5].parse().ok()?,
+        elapsed: fields[6].to_string(),
+        command: rest.to_string(),
+    })
+}
+
+/// The processes of the job (`job_id` without array index), busiest first.
+pub fn list_processes(job_id: &str) -> ProcessList {
+    let output = slurm_command("srun")
+        .args([
+            &format!("--jobid={}", job_id),
+            "--overlap",
+            "--ntasks-per-node=1",
+            "--immediate=10",
+            "--quiet",
+            "sh",
+            "-c",
+            PS_SCRIPT,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to execute srun: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "srun failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let mut processes = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_process)
+        .collect::<Vec<_>>();
+    processes.sort_by(|a, b| b.cpu.total_cmp(&a.cpu));
+    Ok(processes)
+}
+
+/// Runs `ps` in the allocation of a job when asked to, without blocking the UI.
+struct ProcessWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<String>,
+}
+
+impl ProcessWatcher {
+    fn run(&mut self) {
+        while let Ok(mut job_id) = self.receiver.recv() {
+            // only the latest request matters
+            while let Ok(newer) = self.receiver.try_recv() {
+                job_id = newer;
+            }
+            let processes = list_processes(&job_id);
+            if self
+                .app
+                .send(AppMessage::Processes(job_id, processes))
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+pub struct ProcessWatcherHandle {
+    sender: Sender<String>,
+}
+
+impl ProcessWatcherHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = ProcessWatcher { app, receiver };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    pub fn refresh(&self, job_id: &str) {
+        let _ = self.sender.send(job_id.to_string());
+    }
+}
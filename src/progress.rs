@@ -0,0 +1,135 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::{
+    channel::{unbounded, Receiver, Sender},
+    select,
+};
+use regex::Regex;
+
+use crate::app::AppMessage;
+use crate::log_source::{log_source, LogSource, Throttle};
+
+/// How often the logs of running jobs are sampled.
+const INTERVAL: Duration = Duration::from_secs(30);
+/// Bytes read from the end of each log, where the latest progress is.
+const TAIL_BYTES: u64 = 16 * 1024;
+
+/// Current and total, e.g. epoch 3 of 10.
+#[derive(Clone, Copy)]
+pub struct Progress {
+    pub current: f64,
+    pub total: f64,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f64 {
+        (self.current / self.total).clamp(0.0, 1.0)
+    }
+}
+
+/// The last progress in the log, from the first two capture groups of the pattern.
+fn last_progress(pattern: &Regex, log: &str) -> Option<Progress> {
+    pattern
+        .captures_iter(log)
+        .filter_map(|c| {
+            let current = c.get(1)?.as_str().parse().ok()?;
+            let total: f64 = c.get(2)?.as_str().parse().ok()?;
+            (total > 0.0).then_some(Progress { current, total })
+        })
+        .last()
+}
+
+enum ProgressWatcherMessage {
+    /// Ids and log files of the running jobs.
+    Jobs(Vec<(String, PathBuf)>),
+    Pattern(Option<Regex>),
+}
+
+/// Samples the end of the logs of all running jobs, not only of the selected one, for the
+/// progress bars in the job list.
+struct ProgressWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<ProgressWatcherMessage>,
+    source: Box<dyn LogSource>,
+    jobs: Vec<(String, PathBuf)>,
+    pattern: Option<Regex>,
+    sampled_at: Option<Instant>,
+}
+
+impl ProgressWatcher {
+    fn sample(&mut self) {
+        let Some(pattern) = &self.pattern else {
+            return;
+        };
+        let mut progress = HashMap::new();
+        for (id, path) in &self.jobs {
+            let Ok(tail) = self.source.read_tail(path, TAIL_BYTES) else {
+                continue;
+            };
+            if let Some(p) = last_progress(pattern, &String::from_utf8_lossy(&tail)) {
+                progress.insert(id.clone(), p);
+            }
+        }
+        self.sampled_at = Some(Instant::now());
+        let _ = self.app.send(AppMessage::Progress(progress));
+    }
+
+    fn run(&mut self) {
+        loop {
+            let due = self
+                .sampled_at
+                .map_or(Duration::ZERO, |t| INTERVAL.saturating_sub(t.elapsed()));
+            select! {
+                recv(self.receiver) -> msg => match msg {
+                    Ok(ProgressWatcherMessage::Jobs(jobs)) => {
+                        // sample new jobs right away
+                        let new = jobs.iter().any(|j| !self.jobs.contains(j));
+                        self.jobs = jobs;
+                        if new {
+                            self.sample();
+                        }
+                    }
+                    Ok(ProgressWatcherMessage::Pattern(pattern)) => {
+                        self.pattern = pattern;
+                        self.sample();
+                    }
+                    Err(_) => return,
+                },
+                default(due) => self.sample(),
+            }
+        }
+    }
+}
+
+pub struct ProgressWatcherHandle {
+    sender: Sender<ProgressWatcherMessage>,
+}
+
+impl ProgressWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, pattern: Option<Regex>, throttle: Throttle) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = ProgressWatcher {
+            app,
+            receiver,
+            source: log_source(throttle),
+            jobs: Vec::new(),
+            pattern,
+            sampled_at: None,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    pub fn set_jobs(&self, jobs: Vec<(String, PathBuf)>) {
+        let _ = self.sender.send(ProgressWatcherMessage::Jobs(jobs));
+    }
+
+    pub fn set_pattern(&self, pattern: Option<Regex>) {
+        let _ = self.sender.send(ProgressWatcherMessage::Pattern(pattern));
+    }
+}
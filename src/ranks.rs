@@ -0,0 +1,35 @@
+use ratatui::style::Color;
+
+/// Colors of the ranks, repeating for larger jobs. Red is left for errors.
+const PALETTE: [Color; 8] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Green,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::LightGreen,
+];
+
+/// The rank of a line of `srun --label` output, which starts with the right-aligned task rank
+/// and a colon, e.g. ` 3: loss 0.42`.
+pub fn rank_of(line: &str) -> Option<u32> {
+    let (rank, _) = line.trim_start().split_once(": ")?;
+    match rank.is_empty() || !rank.bytes().all(|b| b.is_ascii_digit()) {
+        true => None,
+        false => rank.parse().ok(),
+    }
+}
+
+/// The lines of the rank, keeping the delimiter after the last line.
+pub fn filter(content: &str, rank: u32) -> String {
+    content
+        .split_inclusive('\n')
+        .filter(|line| rank_of(line) == Some(rank))
+        .collect()
+}
+
+pub fn rank_color(rank: u32) -> Color {
+    PALETTE[rank as usize % PALETTE.len()]
+}
@@ -0,0 +1,191 @@
+use std::{
+    ffi::{OsStr, OsString},
+    io,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::OnceLock,
+};
+
+use crate::dispatcher;
+use crate::sftp::SftpSession;
+
+/// Host that Slurm commands are run on in remote mode (`--ssh`).
+static HOST: OnceLock<String> = OnceLock::new();
+
+/// Enables remote mode, which runs all Slurm commands and reads all log files over ssh.
+/// This lets turm run on machines without Slurm, e.g. a macOS or Windows workstation.
+pub fn set_host(host: String) {
+    let _ = HOST.set(host);
+}
+
+pub fn host() -> Option<&'static str> {
+    HOST.get().map(|h| h.as_str())
+}
+
+/// The user running turm, or in remote mode the user on the remote host.
+pub fn current_user() -> &'static str {
+    static USER: OnceLock<String> = OnceLock::new();
+    USER.get_or_init(|| match host() {
+        Some(_) => slurm_command("whoami")
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+            .unwrap_or_default(),
+        None => std::env::var("USER").unwrap_or_default(),
+    })
+}
+
+/// Quotes an argument for the remote shell, which ssh passes the command line to.
+fn quote(arg: &OsStr) -> String {
+    format!("'{}'", arg.to_string_lossy().replace('\'', r"'\''"))
+}
+
+fn ssh_options() -> Command {
+    let mut ssh = Command::new("ssh");
+    // never prompt for passwords, which would garble the UI
+    ssh.args(["-T", "-o", "BatchMode=yes"]);
+    // share a single connection between all commands (not supported on Windows)
+    #[cfg(unix)]
+    ssh.args([
+        "-o",
+        "ControlMaster=auto",
+        "-o",
+        "ControlPath=~/.ssh/turm-%C",
+        "-o",
+        "ControlPersist=60",
+    ]);
+    ssh
+}
+
+fn ssh(host: &str) -> Command {
+    let mut ssh = ssh_options();
+    ssh.arg(host).arg("--");
+    ssh
+}
+
+/// Starts an SFTP session with the remote host.
+pub fn sftp_session(host: &str) -> io::Result<SftpSession> {
+    let mut ssh = ssh_options();
+    ssh.arg("-s").arg(host).arg("sftp");
+    SftpSession::start(ssh)
+}
+
+/// A command that runs locally or, in remote mode, on the remote host.
+pub struct SlurmCommand {
+    program: String,
+    args: Vec<OsString>,
+    current_dir: Option<PathBuf>,
+    stdin: Option<Stdio>,
+    stdout: Option<Stdio>,
+    stderr: Option<Stdio>,
+}
+
+/// Creates a command for a Slurm program such as `squeue`.
+pub fn slurm_command(program: &str) -> SlurmCommand {
+    SlurmCommand {
+        program: program.to_string(),
+        args: Vec::new(),
+        current_dir: None,
+        stdin: None,
+        stdout: None,
+        stderr: None,
+    }
+}
+
+impl SlurmCommand {
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|a| a.as_ref().to_owned()));
+        self
+    }
+
+    /// The directory the program is run in, on the remote host in remote mode.
+    pub fn current_dir(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.current_dir = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    pub fn stdin(&mut self, cfg: Stdio) -> &mut Self {
+        self.stdin = Some(cfg);
+        self
+    }
+
+    pub fn stdout(&mut self, cfg: Stdio) -> &mut Self {
+        self.stdout = Some(cfg);
+        self
+    }
+
+    pub fn stderr(&mut self, cfg: Stdio) -> &mut Self {
+        self.stderr = Some(cfg);
+        self
+    }
+
+    /// The command that is actually run: the program itself or ssh.
+    fn resolve(&mut self) -> Command {
+        let mut command = match host() {
+            None => {
+                let mut command = Command::new(&self.program);
+                command.args(&self.args);
+                if let Some(dir) = &self.current_dir {
+                    command.current_dir(dir);
+                }
+                command
+            }
+            Some(host) => {
+                let mut ssh = ssh(host);
+                if let Some(dir) = &self.current_dir {
+                    ssh.args(["cd", &quote(dir.as_os_str()), "&&"]);
+                }
+                ssh.arg(quote(OsStr::new(&self.program)));
+                ssh.args(self.args.iter().map(|a| quote(a)));
+                ssh
+            }
+        };
+        if let Some(cfg) = self.stdin.take() {
+            command.stdin(cfg);
+        }
+        if let Some(cfg) = self.stdout.take() {
+            command.stdout(cfg);
+        }
+        if let Some(cfg) = self.stderr.take() {
+            command.stderr(cfg);
+        }
+        command
+    }
+
+    pub fn output(&mut self) -> io::Result<Output> {
+        let _permit = dispatcher::acquire(&self.program, &self.args);
+        self.resolve().output()
+    }
+
+    pub fn status(&mut self) -> io::Result<ExitStatus> {
+        let _permit = dispatcher::acquire(&self.program, &self.args);
+        self.resolve().status()
+    }
+
+    pub fn spawn(&mut self) -> io::Result<Child> {
+        self.resolve().spawn()
+    }
+}
+
+/// Checks that the remote host can be reached without interaction.
+pub fn check_connection(host: &str) -> io::Result<()> {
+    let output = ssh(host).arg("true").stdin(Stdio::null()).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "Cannot connect to {} via ssh: {}",
+            host,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
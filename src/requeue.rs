@@ -0,0 +1,78 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+use crate::app::Job;
+
+/// Alerts when a job is restarted over and over, e.g. because it is preempted right away or
+/// requeues itself after crashing, which silently burns allocation.
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RequeueStormConfig {
+    pub enabled: bool,
+    /// A job is flapping once it was restarted more often than this within `minutes`.
+    pub restarts: usize,
+    pub minutes: u64,
+}
+
+impl Default for RequeueStormConfig {
+    fn default() -> Self {
+        RequeueStormConfig {
+            enabled: true,
+            restarts: 3,
+            minutes: 60,
+        }
+    }
+}
+
+/// When the restart counters of the jobs went up, as seen between two polls. They are only
+/// kept while turm is running.
+#[derive(Default)]
+pub struct RestartTracker {
+    restarts: HashMap<String, Vec<NaiveDateTime>>,
+}
+
+impl RestartTracker {
+    /// Records the restarts between two polls and forgets the jobs that are gone.
+    pub fn observe(&mut self, old: &[Job], new: &[Job], now: NaiveDateTime) {
+        let old: HashMap<_, _> = old.iter().map(|o| (o.id(), o)).collect();
+        for job in new {
+            let Some(previous) = old.get(&job.id()) else {
+                continue;
+            };
+            let count = job.restarts.saturating_sub(previous.restarts) as usize;
+            if count > 0 {
+                self.restarts
+                    .entry(job.id())
+                    .or_default()
+                    .extend(std::iter::repeat_n(now, count));
+            }
+        }
+        let ids: HashSet<_> = new.iter().map(|j| j.id()).collect();
+        self.restarts.retain(|id, _| ids.contains(id));
+    }
+
+    /// How often the job was restarted within the configured window.
+    pub fn recent(&self, config: &RequeueStormConfig, id: &str, now: NaiveDateTime) -> usize {
+        let since = now - chrono::Duration::minutes(config.minutes as i64);
+        self.restarts
+            .get(id)
+            .map_or(0, |times| times.iter().filter(|t| **t > since).count())
+    }
+
+    /// The jobs that were restarted more often than allowed within the window.
+    pub fn flapping(&self, config: &RequeueStormConfig, now: NaiveDateTime) -> Vec<String> {
+        if !config.enabled {
+            return Vec::new();
+        }
+        let mut ids = self
+            .restarts
+            .keys()
+            .filter(|id| self.recent(config, id, now) > config.restarts)
+            .cloned()
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+}
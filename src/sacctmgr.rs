@@ -0,0 +1,117 @@
+use std::{io, thread};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use crate::app::AppMessage;
+use crate::remote::{self, slurm_command};
+
+#[derive(Clone)]
+pub struct Association {
+    pub cluster: String,
+    pub account: String,
+    pub partition: String,
+    pub qos: String,
+    pub default_qos: String,
+    pub grp_tres: String,
+    pub max_tres: String,
+}
+
+/// The account jobs are charged to when no account is given.
+pub fn default_account() -> io::Result<String> {
+    let output = slurm_command("sacctmgr")
+        .args(["show", "user", remote::current_user()])
+        .args(["format=defaultaccount", "--parsable2", "--noheader"])
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Lists the associations (accounts, QOS access and limits) of the current user.
+pub fn user_associations() -> io::Result<Vec<Association>> {
+    let user = remote::current_user();
+    let fields = [
+        "cluster",
+        "account",
+        "partition",
+        "qos",
+        "defaultqos",
+        "grptres",
+        "maxtres",
+    ];
+    let output = slurm_command("sacctmgr")
+        .arg("show")
+        .arg("assoc")
+        .arg(format!("user={}", user))
+        .arg(format!("format={}", fields.join(",")))
+        .arg("--parsable2")
+        .arg("--noheader")
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| {
+            let parts: Vec<_> = l.trim().split('|').collect();
+            if parts.len() != fields.len() {
+                return None;
+            }
+            Some(Association {
+                cluster: parts[0].to_owned(),
+                account: parts[1].to_owned(),
+                partition: parts[2].to_owned(),
+                qos: parts[3].to_owned(),
+                default_qos: parts[4].to_owned(),
+                grp_tres: parts[5].to_owned(),
+                max_tres: parts[6].to_owned(),
+            })
+        })
+        .collect())
+}
+
+/// Lists the associations in the background, since sacctmgr asks slurmdbd.
+struct AssociationLister {
+    app: Sender<AppMessage>,
+    receiver: Receiver<()>,
+}
+
+impl AssociationLister {
+    fn run(&mut self) {
+        while self.receiver.recv().is_ok() {
+            // requests that came in meanwhile get the same answer
+            while self.receiver.try_recv().is_ok() {}
+            if self
+                .app
+                .send(AppMessage::Associations(user_associations()))
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+pub struct AssociationListerHandle {
+    sender: Sender<()>,
+}
+
+impl AssociationListerHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = AssociationLister { app, receiver };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Lists my associations, answered with [`AppMessage::Associations`].
+    pub fn request(&self) {
+        let _ = self.sender.send(());
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use serde::{Deserialize, Deserializer};
+
+use crate::app::{AppMessage, Job};
+use crate::remote;
+
+/// How long a script may run for a single job.
+const TIME_LIMIT: Duration = Duration::from_millis(20);
+/// Bytes at the end of the log that scripts see as `log_tail`.
+const LOG_TAIL_BYTES: u64 = 4096;
+
+thread_local! {
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+lazy_static::lazy_static! {
+    /// Scripts run sandboxed: Rhai has no access to files or processes, and runaway scripts
+    /// are stopped by the limits below.
+    static ref ENGINE: Engine = {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(100_000)
+            .set_max_call_levels(16)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(1 << 16)
+            .set_max_array_size(10_000)
+            .set_max_map_size(10_000)
+            .on_progress(|_| {
+                let expired = DEADLINE.with(|d| d.get().is_some_and(|d| Instant::now() > d));
+                expired.then(|| "time limit exceeded".into())
+            });
+        engine
+    };
+}
+
+/// A Rhai script from the config, evaluated per job. It sees the job as `job` (a map with
+/// `id`, `name`, `state`, `user`, `partition`, `qos`, `nodes`, `reason`, `command`, `stdout`,
+/// `time_used` and `time_limit` in seconds, and `restarts`) and, if it mentions it,
+/// `log_tail`, the end of the job's output (local log files only).
+#[derive(Clone)]
+pub struct Script {
+    ast: AST,
+    uses_log_tail: bool,
+}
+
+/// A column of the job list computed by a script, e.g.
+/// `{ name = "loss", script = 'log_tail.split("loss=")[-1].sub_string(0, 6)' }`.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptColumn {
+    pub name: String,
+    #[serde(deserialize_with = "deserialize_script")]
+    pub script: Script,
+}
+
+pub fn deserialize_script<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Script, D::Error> {
+    let source = String::deserialize(deserializer)?;
+    let ast = ENGINE
+        .compile(&source)
+        .map_err(|e| serde::de::Error::custom(format!("invalid script: {}", e)))?;
+    Ok(Script {
+        ast,
+        uses_log_tail: source.contains("log_tail"),
+    })
+}
+
+pub fn deserialize_optional_script<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Script>, D::Error> {
+    deserialize_script(deserializer).map(Some)
+}
+
+fn log_tail(path: &Path) -> String {
+    let read = || {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        file.seek(SeekFrom::Start(len.saturating_sub(LOG_TAIL_BYTES)))?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+        std::io::Result::Ok(String::from_utf8_lossy(&tail).into_owned())
+    };
+    read().unwrap_or_default()
+}
+
+fn job_map(job: &Job) -> Map {
+    let mut map = Map::new();
+    let mut set = |key: &str, value: Dynamic| {
+        map.insert(key.into(), value);
+    };
+    set("id", job.id().into());
+    set("name", job.name.clone().into());
+    set("state", job.state.to_string().into());
+    set("user", job.user.to_string().into());
+    set("partition", job.partition.to_string().into());
+    set("qos", job.qos.to_string().into());
+    set("nodes", job.nodelist.clone().into());
+    set("reason", job.reason.clone().unwrap_or_default().into());
+    set("command", job.command.clone().into());
+    set(
+        "stdout",
+        job.stdout
+            .as_ref()
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default()
+            .into(),
+    );
+    set(
+        "time_used",
+        job.time_used.map_or(0, |t| t.num_seconds()).into(),
+    );
+    set(
+        "time_limit",
+        job.time_limit.map_or(0, |t| t.num_seconds()).into(),
+    );
+    set("restarts", (job.restarts as i64).into());
+    map
+}
+
+impl Script {
+    pub fn eval(&self, job: &Job) -> Result<Dynamic, String> {
+        let mut scope = Scope::new();
+        scope.push_constant("job", job_map(job));
+        if self.uses_log_tail {
+            let tail = match (&job.stdout, remote::host()) {
+                (Some(path), None) => log_tail(path),
+                _ => String::new(),
+            };
+            scope.push_constant("log_tail", tail);
+        }
+        DEADLINE.with(|d| d.set(Some(Instant::now() + TIME_LIMIT)));
+        let result = ENGINE.eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast);
+        DEADLINE.with(|d| d.set(None));
+        result.map_err(|e| e.to_string())
+    }
+
+    /// Evaluates a filter, which has to return a boolean.
+    pub fn matches(&self, job: &Job) -> Result<bool, String> {
+        let value = self.eval(job)?;
+        value
+            .as_bool()
+            .map_err(|t| format!("filter returned {} instead of a boolean", t))
+    }
+}
+
+/// The values of the script columns of each job by id, and whether the script filter shows it.
+pub type ScriptValues = HashMap<String, (Vec<String>, bool)>;
+
+/// Evaluates the script columns and filter for all jobs, reporting the first error.
+fn evaluate(
+    columns: &[ScriptColumn],
+    filter: Option<&Script>,
+    jobs: &[Job],
+) -> (ScriptValues, Option<String>) {
+    let mut error = None;
+    let values = jobs
+        .iter()
+        .map(|job| {
+            let values = columns
+                .iter()
+                .map(|column| match column.script.eval(job) {
+                    Ok(value) if value.is_unit() => String::new(),
+                    Ok(value) => value.to_string(),
+                    Err(e) => {
+                        error
+                            .get_or_insert_with(|| format!("Script column {}: {}", column.name, e));
+                        "⚠".to_string()
+                    }
+                })
+                .collect();
+            let visible = match filter {
+                Some(filter) => filter.matches(job).unwrap_or_else(|e| {
+                    error.get_or_insert_with(|| format!("Script filter: {}", e));
+                    true
+                }),
+                None => true,
+            };
+            (job.id(), (values, visible))
+        })
+        .collect();
+    (values, error)
+}
+
+enum ScriptCommand {
+    SetScripts(Vec<ScriptColumn>, Option<Script>),
+    Evaluate(Vec<Job>),
+}
+
+/// Evaluates the scripts in the background, since each one may take up to its time limit and
+/// read the log of the job.
+struct ScriptEvaluator {
+    app: Sender<AppMessage>,
+    receiver: Receiver<ScriptCommand>,
+    columns: Vec<ScriptColumn>,
+    filter: Option<Script>,
+}
+
+impl ScriptEvaluator {
+    fn run(&mut self) {
+        while let Ok(command) = self.receiver.recv() {
+            let mut jobs = None;
+            // only the latest jobs matter
+            for command in std::iter::once(command).chain(self.receiver.try_iter()) {
+                match command {
+                    ScriptCommand::SetScripts(columns, filter) => {
+                        self.columns = columns;
+                        self.filter = filter;
+                    }
+                    ScriptCommand::Evaluate(j) => jobs = Some(j),
+                }
+            }
+            if let Some(jobs) = jobs {
+                let (values, error) = evaluate(&self.columns, self.filter.as_ref(), &jobs);
+                let _ = self.app.send(AppMessage::ScriptValues(values, error));
+            }
+        }
+    }
+}
+
+pub struct ScriptEvaluatorHandle {
+    sender: Sender<ScriptCommand>,
+}
+
+impl ScriptEvaluatorHandle {
+    pub fn new(
+        app: Sender<AppMessage>,
+        columns: Vec<ScriptColumn>,
+        filter: Option<Script>,
+    ) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = ScriptEvaluator {
+            app,
+            receiver,
+            columns,
+            filter,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    pub fn set_scripts(&self, columns: Vec<ScriptColumn>, filter: Option<Script>) {
+        let _ = self.sender.send(ScriptCommand::SetScripts(columns, filter));
+    }
+
+    pub fn evaluate(&self, jobs: Vec<Job>) {
+        let _ = self.sender.send(ScriptCommand::Evaluate(jobs));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::config::{Column, Config};
+
+/// An option that can be changed in the settings dialog.
+#[derive(Clone, Copy)]
+pub enum Setting {
+    SlurmRefresh,
+    FinishedRetention,
+    OnlyMine,
+    HideFinished,
+    UserColors,
+    Column(Column),
+}
+
+impl Setting {
+    pub fn all() -> Vec<Setting> {
+        let mut settings = vec![
+            Setting::SlurmRefresh,
+            Setting::FinishedRetention,
+            Setting::OnlyMine,
+            Setting::HideFinished,
+            Setting::UserColors,
+        ];
+        settings.extend(Column::ALL.map(Setting::Column));
+        settings
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Setting::SlurmRefresh => "Refresh interval (seconds)".to_string(),
+            Setting::FinishedRetention => "Keep finished jobs for (minutes)".to_string(),
+            Setting::OnlyMine => "Only my jobs on startup".to_string(),
+            Setting::HideFinished => "Hide finished jobs on startup".to_string(),
+            Setting::UserColors => "Color jobs by user when several are shown".to_string(),
+            Setting::Column(c) => format!("Column: {}", c.key()),
+        }
+    }
+
+    pub fn value(&self, config: &Config) -> String {
+        let on_off = |b: bool| if b { "on" } else { "off" }.to_string();
+        match self {
+            Setting::SlurmRefresh => config.slurm_refresh_seconds.to_string(),
+            Setting::FinishedRetention => config.finished_retention_minutes.to_string(),
+            Setting::OnlyMine => on_off(config.only_mine),
+            Setting::HideFinished => on_off(config.hide_finished),
+            Setting::UserColors => on_off(config.user_colors),
+            Setting::Column(c) => on_off(config.columns.contains(c)),
+        }
+    }
+
+    /// Whether the value is entered as text rather than toggled.
+    pub fn is_number(&self) -> bool {
+        matches!(self, Setting::SlurmRefresh | Setting::FinishedRetention)
+    }
+
+    /// The config key and the new value after toggling an on/off setting.
+    pub fn toggled(&self, config: &Config) -> Option<(&'static str, toml_edit::Value)> {
+        match self {
+            Setting::OnlyMine => Some(("only_mine", (!config.only_mine).into())),
+            Setting::HideFinished => Some(("hide_finished", (!config.hide_finished).into())),
+            Setting::UserColors => Some(("user_colors", (!config.user_colors).into())),
+            Setting::Column(column) => {
+                let mut columns = config.columns.clone();
+                if let Some(i) = columns.iter().position(|c| c == column) {
+                    columns.remove(i);
+                } else {
+                    // keep the custom order, placing the column before the next one by default
+                    let rank = |c: &Column| Column::ALL.iter().position(|a| a == c);
+                    let i = columns
+                        .iter()
+                        .position(|c| rank(c) > rank(column))
+                        .unwrap_or(columns.len());
+                    columns.insert(i, *column);
+                }
+                let columns = columns
+                    .iter()
+                    .map(|c| c.key())
+                    .collect::<toml_edit::Array>();
+                Some(("columns", columns.into()))
+            }
+            Setting::SlurmRefresh | Setting::FinishedRetention => None,
+        }
+    }
+
+    /// The config key and the value entered for a number setting.
+    pub fn parse(&self, input: &str) -> Result<(&'static str, toml_edit::Value), String> {
+        let key = match self {
+            Setting::SlurmRefresh => "slurm_refresh_seconds",
+            Setting::FinishedRetention => "finished_retention_minutes",
+            _ => unreachable!("only number settings are entered as text"),
+        };
+        match input.trim().parse::<u32>() {
+            Ok(0) if matches!(self, Setting::SlurmRefresh) => {
+                Err("The refresh interval must be at least one second".to_string())
+            }
+            Ok(n) => Ok((key, i64::from(n).into())),
+            Err(_) => Err(format!("Not a number: {}", input.trim())),
+        }
+    }
+}
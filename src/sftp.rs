@@ -0,0 +1,328 @@
+use std::{
+    io::{self, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Stdio},
+};
+
+// A minimal SFTP (version 3) client, just enough to read files.
+// See https://datatracker.ietf.org/doc/html/draft-ietf-secsh-filexfer-02
+const SSH_FXP_INIT: u8 = 1;
+const SSH_FXP_VERSION: u8 = 2;
+const SSH_FXP_OPEN: u8 = 3;
+const SSH_FXP_CLOSE: u8 = 4;
+const SSH_FXP_READ: u8 = 5;
+const SSH_FXP_FSTAT: u8 = 8;
+const SSH_FXP_STATUS: u8 = 101;
+const SSH_FXP_HANDLE: u8 = 102;
+const SSH_FXP_DATA: u8 = 103;
+const SSH_FXP_ATTRS: u8 = 105;
+
+const SSH_FXF_READ: u32 = 0x1;
+const SSH_FILEXFER_ATTR_SIZE: u32 = 0x1;
+const SSH_FX_EOF: u32 = 1;
+const SSH_FX_NO_SUCH_FILE: u32 = 2;
+const SSH_FX_PERMISSION_DENIED: u32 = 3;
+
+/// The most data requested per read. Servers only have to support 32 KiB, OpenSSH sends up to
+/// 256 KiB.
+const MAX_CHUNK_SIZE: u32 = 256 * 1024;
+/// Larger packets are rejected before anything is allocated for them: a read response is the
+/// data plus the type, request id and data length.
+const MAX_PACKET_LEN: usize = MAX_CHUNK_SIZE as usize + 1024;
+
+/// An SFTP session over the `sftp` subsystem of an ssh process,
+/// so that the user's ssh config and shared connections apply.
+pub struct SftpSession {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+    next_id: u32,
+}
+
+struct Packet {
+    kind: u8,
+    data: Vec<u8>,
+}
+
+/// Reads big-endian values from a packet.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "truncated SFTP packet",
+            ));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+/// Reads a packet: its length, type and data.
+fn read_packet(input: &mut impl Read) -> io::Result<Packet> {
+    let mut len = [0; 4];
+    input.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "SFTP: empty packet",
+        ));
+    }
+    if len > MAX_PACKET_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("SFTP: packet of {} bytes is too large", len),
+        ));
+    }
+    let mut data = vec![0; len];
+    input.read_exact(&mut data)?;
+    let kind = data.remove(0);
+    Ok(Packet { kind, data })
+}
+
+fn put_string(buf: &mut Vec<u8>, s: &[u8]) {
+    buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    buf.extend_from_slice(s);
+}
+
+/// Turns a status packet into an error. `SSH_FX_OK` is an error too, since it is never the
+/// expected response to the requests sent here.
+fn status_error(data: &[u8]) -> io::Error {
+    let mut r = Reader(data);
+    let code = r.u32().unwrap_or(0);
+    let message = r
+        .string()
+        .map(|m| String::from_utf8_lossy(m).into_owned())
+        .unwrap_or_default();
+    let kind = match code {
+        SSH_FX_EOF => io::ErrorKind::UnexpectedEof,
+        SSH_FX_NO_SUCH_FILE => io::ErrorKind::NotFound,
+        SSH_FX_PERMISSION_DENIED => io::ErrorKind::PermissionDenied,
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, format!("SFTP: {}", message))
+}
+
+impl SftpSession {
+    /// Starts the session, `ssh` being the command that runs the sftp subsystem.
+    pub fn start(mut ssh: std::process::Command) -> io::Result<SftpSession> {
+        let mut child = ssh
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let mut session = SftpSession {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+        };
+        session.send(SSH_FXP_INIT, &3u32.to_be_bytes())?;
+        match session.receive()? {
+            Packet {
+                kind: SSH_FXP_VERSION,
+                ..
+            } => Ok(session),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "SFTP: unexpected response to init",
+            )),
+        }
+    }
+
+    fn send(&mut self, kind: u8, payload: &[u8]) -> io::Result<()> {
+        let mut packet = Vec::with_capacity(payload.len() + 5);
+        packet.extend_from_slice(&(payload.len() as u32 + 1).to_be_bytes());
+        packet.push(kind);
+        packet.extend_from_slice(payload);
+        self.stdin.write_all(&packet)?;
+        self.stdin.flush()
+    }
+
+    fn receive(&mut self) -> io::Result<Packet> {
+        read_packet(&mut self.stdout)
+    }
+
+    /// Sends a request and returns the response without the request id.
+    fn request(&mut self, kind: u8, payload: &[u8]) -> io::Result<Packet> {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        let mut buf = id.to_be_bytes().to_vec();
+        buf.extend_from_slice(payload);
+        self.send(kind, &buf)?;
+        let mut response = self.receive()?;
+        if Reader(&response.data).u32()? != id {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "SFTP: unexpected response id",
+            ));
+        }
+        response.data.drain(..4);
+        Ok(response)
+    }
+
+    fn open(&mut self, path: &str) -> io::Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        put_string(&mut payload, path.as_bytes());
+        payload.extend_from_slice(&SSH_FXF_READ.to_be_bytes());
+        payload.extend_from_slice(&0u32.to_be_bytes()); // no attributes
+        let response = self.request(SSH_FXP_OPEN, &payload)?;
+        match response.kind {
+            SSH_FXP_HANDLE => Ok(Reader(&response.data).string()?.to_vec()),
+            _ => Err(status_error(&response.data)),
+        }
+    }
+
+    fn close(&mut self, handle: &[u8]) -> io::Result<()> {
+        let mut payload = Vec::new();
+        put_string(&mut payload, handle);
+        // the status is always OK unless the connection is broken
+        self.request(SSH_FXP_CLOSE, &payload).map(|_| ())
+    }
+
+    fn size(&mut self, handle: &[u8]) -> io::Result<Option<u64>> {
+        let mut payload = Vec::new();
+        put_string(&mut payload, handle);
+        let response = self.request(SSH_FXP_FSTAT, &payload)?;
+        if response.kind != SSH_FXP_ATTRS {
+            return Err(status_error(&response.data));
+        }
+        let mut r = Reader(&response.data);
+        let flags = r.u32()?;
+        if flags & SSH_FILEXFER_ATTR_SIZE == 0 {
+            return Ok(None);
+        }
+        Ok(Some(r.u64()?))
+    }
+
+    /// Reads from `offset` until the end of the file or until at least `limit` bytes were read,
+    /// requesting `chunk_size` bytes at a time.
+    fn read_to_end(
+        &mut self,
+        handle: &[u8],
+        mut offset: u64,
+        chunk_size: u32,
+        limit: u64,
+    ) -> io::Result<Vec<u8>> {
+        let mut content = Vec::new();
+        while (content.len() as u64) < limit {
+            let chunk_size = chunk_size.min(MAX_CHUNK_SIZE).min(
+                (limit - content.len() as u64)
+                    .try_into()
+                    .unwrap_or(u32::MAX),
+            );
+            let mut payload = Vec::new();
+            put_string(&mut payload, handle);
+            payload.extend_from_slice(&offset.to_be_bytes());
+            payload.extend_from_slice(&chunk_size.to_be_bytes());
+            let response = self.request(SSH_FXP_READ, &payload)?;
+            match response.kind {
+                SSH_FXP_DATA => {
+                    let data = Reader(&response.data).string()?;
+                    offset += data.len() as u64;
+                    content.extend_from_slice(data);
+                }
+                SSH_FXP_STATUS if Reader(&response.data).u32()? == SSH_FX_EOF => {
+                    return Ok(content)
+                }
+                _ => return Err(status_error(&response.data)),
+            }
+        }
+        Ok(content)
+    }
+
+    /// Returns the size of the file and up to `limit` bytes of its content from `offset` on.
+    /// Nothing is read if the file is smaller than `offset`.
+    pub fn read_from(
+        &mut self,
+        path: &str,
+        offset: u64,
+        chunk_size: u32,
+        limit: u64,
+    ) -> io::Result<(u64, Vec<u8>)> {
+        let handle = self.open(path)?;
+        let result = self.size(&handle).and_then(|size| match size {
+            Some(size) if size < offset => Ok((size, Vec::new())),
+            _ => {
+                let content = self.read_to_end(&handle, offset, chunk_size, limit)?;
+                Ok((size.unwrap_or(offset + content.len() as u64), content))
+            }
+        });
+        self.close(&handle)?;
+        result
+    }
+}
+
+impl Drop for SftpSession {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(kind: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = (payload.len() as u32 + 1).to_be_bytes().to_vec();
+        packet.push(kind);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    #[test]
+    fn packets() {
+        let mut payload = 7u32.to_be_bytes().to_vec();
+        put_string(&mut payload, b"log");
+        let bytes = packet(SSH_FXP_DATA, &payload);
+        let read = read_packet(&mut &bytes[..]).unwrap();
+        assert_eq!(read.kind, SSH_FXP_DATA);
+        let mut r = Reader(&read.data);
+        assert_eq!(r.u32().unwrap(), 7);
+        assert_eq!(r.string().unwrap(), b"log");
+        assert!(r.u32().is_err());
+
+        // cut off in the length, in the data, and a string longer than the packet
+        assert!(read_packet(&mut &bytes[..3]).is_err());
+        assert!(read_packet(&mut &bytes[..bytes.len() - 1]).is_err());
+        let mut r = Reader(&read.data[4..6]);
+        assert_eq!(r.string().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        assert!(read_packet(&mut &0u32.to_be_bytes()[..]).is_err());
+        let huge = u32::MAX.to_be_bytes();
+        assert!(matches!(
+            read_packet(&mut &huge[..]),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test]
+    fn status_errors() {
+        let mut payload = SSH_FX_NO_SUCH_FILE.to_be_bytes().to_vec();
+        put_string(&mut payload, b"No such file");
+        let error = status_error(&payload);
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(error.to_string(), "SFTP: No such file");
+        // a truncated status is still an error
+        assert_eq!(status_error(&[0, 0]).kind(), io::ErrorKind::Other);
+    }
+}
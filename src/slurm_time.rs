@@ -0,0 +1,132 @@
+use chrono::{Duration, NaiveDateTime};
+
+/// Parses a timestamp as printed by `squeue`/`sacct` (e.g. `2024-04-23T13:37:00`).
+/// Placeholders such as `N/A`, `Unknown` or `None` yield `None`.
+pub fn parse_timestamp(s: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(s.trim(), "%Y-%m-%dT%H:%M:%S").ok()
+}
+
+/// Formats a duration in a compact, human readable way, e.g. `2d 3h`, `1h 5m` or `42s`.
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.num_seconds().abs();
+    let (days, hours, minutes, seconds) = (
+        secs / 86400,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+    );
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Formats `t` relative to `now`, e.g. `2h 5m ago` or `in 3m 10s`.
+pub fn format_relative(t: NaiveDateTime, now: NaiveDateTime) -> String {
+    let d = now - t;
+    if d >= Duration::zero() {
+        format!("{} ago", format_duration(d))
+    } else {
+        format!("in {}", format_duration(d))
+    }
+}
+
+/// Parses a Slurm duration such as `1-02:03:04`, `02:03:04`, `03:04` or `5`
+/// (a bare number is minutes, as in `--time`).
+/// `UNLIMITED`, `INVALID`, `NOT_SET` and friends yield `None`.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let (days, rest) = match s.split_once('-') {
+        Some((d, rest)) => (Some(d.parse::<i64>().ok()?), rest),
+        None => (None, s),
+    };
+    let parts = rest
+        .split(':')
+        .map(|p| p.parse::<i64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let (hours, minutes, seconds) = match (parts.as_slice(), days.is_some()) {
+        ([h, m, s], _) => (*h, *m, *s),
+        ([h, m], true) => (*h, *m, 0),
+        ([m, s], false) => (0, *m, *s),
+        ([h], true) => (*h, 0, 0),
+        ([m], false) => (0, *m, 0),
+        _ => return None,
+    };
+    Some(
+        Duration::days(days.unwrap_or(0))
+            + Duration::hours(hours)
+            + Duration::minutes(minutes)
+            + Duration::seconds(seconds),
+    )
+}
+
+/// Formats a duration the way `squeue` does, e.g. `1-02:03:04`, `2:03:04` or `3:04`.
+pub fn format_slurm_duration(d: Duration) -> String {
+    let secs = d.num_seconds().max(0);
+    let (days, hours, minutes, seconds) = (
+        secs / 86400,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+    );
+    if days > 0 {
+        format!("{}-{:02}:{:02}:{:02}", days, hours, minutes, seconds)
+    } else if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hms(h: i64, m: i64, s: i64) -> Duration {
+        Duration::hours(h) + Duration::minutes(m) + Duration::seconds(s)
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("1-02:03:04"), Some(hms(26, 3, 4)));
+        assert_eq!(parse_duration("2-05:30"), Some(hms(53, 30, 0)));
+        assert_eq!(parse_duration("3-12"), Some(hms(84, 0, 0)));
+        assert_eq!(parse_duration("02:03:04"), Some(hms(2, 3, 4)));
+        assert_eq!(parse_duration("03:04"), Some(hms(0, 3, 4)));
+        assert_eq!(parse_duration(" 90 "), Some(hms(1, 30, 0)));
+        for invalid in [
+            "UNLIMITED",
+            "INVALID",
+            "NOT_SET",
+            "N/A",
+            "",
+            "1:2:3:4",
+            "1-",
+            "-5",
+        ] {
+            assert_eq!(parse_duration(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn slurm_durations_round_trip() {
+        for s in ["1-02:03:04", "2:03:04", "3:04", "0:00"] {
+            assert_eq!(format_slurm_duration(parse_duration(s).unwrap()), s);
+        }
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(
+            parse_timestamp("2024-04-23T13:37:00"),
+            NaiveDateTime::parse_from_str("2024-04-23 13:37:00", "%Y-%m-%d %H:%M:%S").ok()
+        );
+        assert_eq!(parse_timestamp("Unknown"), None);
+        assert_eq!(parse_timestamp("N/A"), None);
+    }
+}
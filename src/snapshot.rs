@@ -0,0 +1,213 @@
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+use ratatui::{
+    buffer::Buffer,
+    style::{Color, Modifier},
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::clock;
+
+const BACKGROUND: &str = "#1e1e1e";
+const FOREGROUND: &str = "#d4d4d4";
+
+/// CSS color of a terminal color, using the xterm palette. `None` for the default color.
+fn css_color(color: Color) -> Option<String> {
+    const ANSI: [&str; 16] = [
+        "#000000", "#cd0000", "#00cd00", "#cdcd00", "#0000ee", "#cd00cd", "#00cdcd", "#e5e5e5",
+        "#7f7f7f", "#ff0000", "#00ff00", "#ffff00", "#5c5cff", "#ff00ff", "#00ffff", "#ffffff",
+    ];
+    let index = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => return Some(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+        Color::Indexed(i) => i,
+    };
+    Some(match index {
+        0..=15 => ANSI[index as usize].to_string(),
+        // 6x6x6 color cube
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let i = index - 16;
+            format!(
+                "#{:02x}{:02x}{:02x}",
+                level(i / 36),
+                level(i / 6 % 6),
+                level(i % 6)
+            )
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            format!("#{:02x}{:02x}{:02x}", gray, gray, gray)
+        }
+    })
+}
+
+fn css(fg: Color, bg: Color, modifier: Modifier) -> String {
+    let (mut fg, mut bg) = (css_color(fg), css_color(bg));
+    if modifier.contains(Modifier::REVERSED) {
+        (fg, bg) = (
+            Some(bg.unwrap_or_else(|| BACKGROUND.to_string())),
+            Some(fg.unwrap_or_else(|| FOREGROUND.to_string())),
+        );
+    }
+    let mut css = String::new();
+    if let Some(fg) = fg {
+        let _ = write!(css, "color:{};", fg);
+    }
+    if let Some(bg) = bg {
+        let _ = write!(css, "background:{};", bg);
+    }
+    if modifier.contains(Modifier::BOLD) {
+        css.push_str("font-weight:bold;");
+    }
+    if modifier.contains(Modifier::DIM) {
+        css.push_str("opacity:0.6;");
+    }
+    if modifier.contains(Modifier::ITALIC) {
+        css.push_str("font-style:italic;");
+    }
+    if modifier.contains(Modifier::UNDERLINED) {
+        css.push_str("text-decoration:underline;");
+    }
+    css
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn push_span(html: &mut String, style: &str, text: &str) {
+    if style.is_empty() {
+        html.push_str(&escape(text));
+    } else {
+        let _ = write!(html, "<span style=\"{}\">{}</span>", style, escape(text));
+    }
+}
+
+/// Renders a drawn frame as a standalone HTML page that looks like the terminal.
+pub fn to_html(buffer: &Buffer) -> String {
+    let mut body = String::new();
+    let area = buffer.area;
+    for y in area.top()..area.bottom() {
+        let mut current: Option<(String, String)> = None;
+        let mut skip = 0;
+        for x in area.left()..area.right() {
+            // cells hidden by a wide character before them
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            let cell = buffer.get(x, y);
+            skip = cell.symbol().width().saturating_sub(1);
+            let style = css(cell.fg, cell.bg, cell.modifier);
+            match &mut current {
+                Some((s, text)) if *s == style => text.push_str(cell.symbol()),
+                _ => {
+                    if let Some((s, text)) = current.take() {
+                        push_span(&mut body, &s, &text);
+                    }
+                    current = Some((style, cell.symbol().to_string()));
+                }
+            }
+        }
+        if let Some((s, text)) = current {
+            push_span(&mut body, &s, &text);
+        }
+        body.push('\n');
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>turm {}</title>\n</head>\n\
+         <body style=\"background:{bg};margin:0\">\n<pre style=\"color:{fg};background:{bg};\
+         font-family:monospace;line-height:1.2;padding:1em;margin:0\">\n{}</pre>\n</body>\n</html>\n",
+        clock::now().format("%Y-%m-%d %H:%M"),
+        body,
+        bg = BACKGROUND,
+        fg = FOREGROUND,
+    )
+}
+
+/// Renders a drawn frame as plain text without styles, one line per row without trailing
+/// spaces, e.g. for comparing it with a golden file.
+pub fn to_text(buffer: &Buffer) -> String {
+    let area = buffer.area;
+    let mut text = String::new();
+    for y in area.top()..area.bottom() {
+        let mut line = String::new();
+        let mut skip = 0;
+        for x in area.left()..area.right() {
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            let symbol = buffer.get(x, y).symbol();
+            skip = symbol.width().saturating_sub(1);
+            line.push_str(symbol);
+        }
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+/// Writes every frame that differs from the previous one to `frame-<n>.txt` in a directory
+/// (`--dump-frames`), numbered from 1, for snapshot tests of the renderer.
+pub struct FrameDumper {
+    dir: PathBuf,
+    count: usize,
+    last: String,
+}
+
+impl FrameDumper {
+    pub fn new(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(FrameDumper {
+            dir: dir.to_path_buf(),
+            count: 0,
+            last: String::new(),
+        })
+    }
+
+    pub fn dump(&mut self, buffer: &Buffer) -> io::Result<()> {
+        let text = to_text(buffer);
+        if text == self.last {
+            return Ok(());
+        }
+        self.count += 1;
+        fs::write(self.dir.join(format!("frame-{:04}.txt", self.count)), &text)?;
+        self.last = text;
+        Ok(())
+    }
+}
+
+/// Writes the frame to `turm-<timestamp>.html` in the current directory.
+pub fn save(buffer: &Buffer) -> io::Result<PathBuf> {
+    let path = PathBuf::from(format!(
+        "turm-{}.html",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::write(&path, to_html(buffer))?;
+    Ok(path)
+}
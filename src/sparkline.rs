@@ -0,0 +1,71 @@
+use std::collections::VecDeque;
+
+use crate::app::Job;
+
+/// Number of polls shown in the sparklines.
+const SAMPLES: usize = 20;
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Clone, Copy, Default)]
+pub struct QueueDepth {
+    pub running: usize,
+    pub pending: usize,
+}
+
+impl QueueDepth {
+    fn count<'a>(jobs: impl Iterator<Item = &'a Job>) -> QueueDepth {
+        jobs.fold(QueueDepth::default(), |mut depth, j| {
+            match j.state.as_str() {
+                "RUNNING" => depth.running += 1,
+                "PENDING" => depth.pending += 1,
+                _ => {}
+            }
+            depth
+        })
+    }
+}
+
+/// Running and pending jobs over the last polls, to see whether the queue drains or grows.
+#[derive(Default)]
+pub struct QueueHistory {
+    /// Oldest first.
+    mine: VecDeque<QueueDepth>,
+    /// All jobs in the current scope.
+    all: VecDeque<QueueDepth>,
+}
+
+fn push(samples: &mut VecDeque<QueueDepth>, depth: QueueDepth) {
+    samples.push_back(depth);
+    if samples.len() > SAMPLES {
+        samples.pop_front();
+    }
+}
+
+impl QueueHistory {
+    pub fn record(&mut self, jobs: &[Job], user: &str) {
+        push(
+            &mut self.mine,
+            QueueDepth::count(jobs.iter().filter(|j| j.user == user)),
+        );
+        push(&mut self.all, QueueDepth::count(jobs.iter()));
+    }
+
+    pub fn mine(&self) -> &VecDeque<QueueDepth> {
+        &self.mine
+    }
+
+    pub fn all(&self) -> &VecDeque<QueueDepth> {
+        &self.all
+    }
+}
+
+/// Renders the values as a line of block characters, scaled to the largest one.
+pub fn sparkline(values: impl Iterator<Item = usize> + Clone) -> String {
+    let max = values.clone().max().unwrap_or(0).max(1);
+    values
+        .map(|v| match v {
+            0 => ' ',
+            v => BARS[(v * BARS.len()).div_ceil(max) - 1],
+        })
+        .collect()
+}
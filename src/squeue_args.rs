@@ -1,89 +1,143 @@
 use clap::Args;
+
+use crate::remote;
 /// Doc comment
-#[derive(Args, Debug)]
+#[derive(Args, Debug, Clone)]
 pub struct SqueueArgs {
     /// |squeue arg| Comma separated list of accounts to view, default is all accounts.
-    #[arg(short = 'A', long)]
+    #[arg(short = 'A', long, global = true)]
     account: Option<String>,
 
     /// |squeue arg| Display jobs in hidden partitions.
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     all: bool,
 
     /// |squeue arg| Report federated information if a member of one.
-    #[arg(long)]
+    #[arg(long, global = true)]
     federation: bool,
 
     /// |squeue arg| Do not display jobs in hidden partitions.
-    #[arg(long)]
+    #[arg(long, global = true)]
     hide: bool,
 
     /// |squeue arg| Comma separated list of jobs IDs to view, default is all.
-    #[arg(short, long, value_name = "JOBID")]
+    #[arg(short, long, value_name = "JOBID", global = true)]
     job: Option<String>,
 
     /// |squeue arg| Report information only about jobs on the local cluster. Overrides `--federation`.
-    #[arg(long)]
+    #[arg(long, global = true)]
     local: bool,
 
     /// |squeue arg| Comma separated list of license names to view.
-    #[arg(short = 'L', long)]
+    #[arg(short = 'L', long, global = true)]
     licenses: Option<String>,
 
     /// |squeue arg| Cluster to issue commands to. Default is current cluster. Cluster with no name will reset to default. Implies `--local`.
-    #[arg(short = 'M', long)]
+    #[arg(short = 'M', long, global = true)]
     clusters: Option<String>,
 
     /// |squeue arg| Equivalent to `--user=<my username>`.
-    #[arg(long)]
+    #[arg(long, global = true)]
     me: bool,
 
     /// |squeue arg| Comma separated list of job names to view.
-    #[arg(short = 'n', long)]
+    #[arg(short = 'n', long, global = true)]
     name: Option<String>,
 
     /// |squeue arg| Don't convert units from their original type (e.g. 2048M won't be converted to 2G).
-    #[arg(long)]
+    #[arg(long, global = true)]
     noconvert: bool,
 
     /// |squeue arg| Comma separated list of partitions to view, default is all partitions.
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     partition: Option<String>,
 
     /// |squeue arg| Comma separated list of qos's to view, default is all qos's.
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     qos: Option<String>,
 
     /// |squeue arg| Reservation to view, default is all.
-    #[arg(short = 'R', long)]
+    #[arg(short = 'R', long, global = true)]
     reservation: Option<String>,
 
     /// |squeue arg| Report information about all sibling jobs on a federated cluster. Implies --federation.
-    #[arg(long)]
+    #[arg(long, global = true)]
     sibling: bool,
 
     /// |squeue arg| Comma separated list of job steps to view, default is all.
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     step: Option<String>,
 
     /// |squeue arg| Comma separated list of fields to sort on.
-    #[arg(short = 'S', long, value_name = "FIELDS")]
+    #[arg(short = 'S', long, value_name = "FIELDS", global = true)]
     sort: Option<String>,
 
     /// |squeue arg| Comma separated list of states to view, default is pending and running, `--states=all` reports all states.
-    #[arg(short = 't', long)]
+    #[arg(short = 't', long, global = true)]
     states: Option<String>,
 
     /// |squeue arg| Comma separated list of users to view.
-    #[arg(short = 'u', long)]
+    #[arg(short = 'u', long, global = true)]
     user: Option<String>,
 
     /// |squeue arg| List of nodes to view, default is all nodes.
-    #[arg(short = 'w', long, value_name = "NODES")]
+    #[arg(short = 'w', long, value_name = "NODES", global = true)]
     nodelist: Option<String>,
+
+    /// Teammates whose jobs are shown instead of `--me` or `--user`, in the team view.
+    #[arg(skip)]
+    team: Option<Vec<String>>,
 }
 
 impl SqueueArgs {
+    pub fn toggle_me(&mut self) {
+        self.me = !self.me;
+        self.team = None;
+    }
+
+    pub fn team(&self) -> Option<&[String]> {
+        self.team.as_deref()
+    }
+
+    pub fn set_team(&mut self, team: Option<Vec<String>>) {
+        if team.is_some() {
+            self.me = false;
+        }
+        self.team = team;
+    }
+
+    pub fn set_me(&mut self, me: bool) {
+        self.me = me;
+    }
+
+    pub fn partition(&self) -> Option<&str> {
+        self.partition.as_deref()
+    }
+
+    pub fn set_partition(&mut self, partition: Option<String>) {
+        self.partition = partition;
+    }
+
+    pub fn account(&self) -> Option<&str> {
+        self.account.as_deref()
+    }
+
+    pub fn set_account(&mut self, account: Option<String>) {
+        self.account = account;
+    }
+
+    pub fn set_job(&mut self, job: Option<String>) {
+        self.job = job;
+    }
+
+    pub fn states(&self) -> Option<&str> {
+        self.states.as_deref()
+    }
+
+    pub fn set_states(&mut self, states: Option<String>) {
+        self.states = states;
+    }
+
     pub fn to_vec(&self) -> Vec<String> {
         let mut args = Vec::new();
         if let Some(account) = &self.account {
@@ -110,7 +164,9 @@ impl SqueueArgs {
         if let Some(clusters) = &self.clusters {
             args.push(format!("--clusters={}", clusters));
         }
-        if self.me {
+        if let Some(team) = &self.team {
+            args.push(format!("--user={}", team.join(",")));
+        } else if self.me {
             args.push("--me".to_string());
         }
         if let Some(name) = &self.name {
@@ -140,7 +196,7 @@ impl SqueueArgs {
         if let Some(states) = &self.states {
             args.push(format!("--states={}", states));
         }
-        if let Some(user) = &self.user {
+        if let Some(user) = self.user.as_ref().filter(|_| self.team.is_none()) {
             args.push(format!("--user={}", user));
         }
         if let Some(nodelist) = &self.nodelist {
@@ -175,12 +231,11 @@ impl SqueueArgs {
         if let Some(clusters) = &self.clusters {
             args.push(format!("--clusters={}", clusters));
         }
-        if self.me {
+        if let Some(team) = &self.team {
+            args.push(format!("--user={}", team.join(",")));
+        } else if self.me {
             // --me is only supported by squeue, not sacct, so we make it explicit
-            args.push(format!(
-                "--user={}",
-                std::env::var("USER").unwrap_or_default()
-            ));
+            args.push(format!("--user={}", remote::current_user()));
         } else {
             args.push("--allusers".to_string());
         }
@@ -211,7 +266,7 @@ impl SqueueArgs {
         if let Some(states) = &self.states {
             args.push(format!("--states={}", states));
         }
-        if let Some(user) = &self.user {
+        if let Some(user) = self.user.as_ref().filter(|_| self.team.is_none()) {
             args.push(format!("--user={}", user));
         }
         if let Some(nodelist) = &self.nodelist {
@@ -220,3 +275,62 @@ impl SqueueArgs {
         args
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        squeue_args: SqueueArgs,
+    }
+
+    fn parse(args: &[&str]) -> SqueueArgs {
+        Cli::parse_from(std::iter::once("turm").chain(args.iter().copied())).squeue_args
+    }
+
+    #[test]
+    fn squeue_and_sacct_args() {
+        let args = parse(&["-p", "gpu", "--states=R", "-A", "lab"]);
+        assert_eq!(
+            args.to_vec(),
+            ["--account=lab", "--partition=gpu", "--states=R"]
+        );
+        // sacct lists only the user's jobs unless told otherwise
+        assert_eq!(
+            args.to_sacct_vec(),
+            [
+                "--account=lab",
+                "--allusers",
+                "--partition=gpu",
+                "--states=R"
+            ]
+        );
+
+        let args = parse(&["--me"]);
+        assert_eq!(args.to_vec(), ["--me"]);
+        assert_eq!(
+            args.to_sacct_vec(),
+            [format!("--user={}", remote::current_user())]
+        );
+
+        let args = parse(&["-u", "bob"]);
+        assert_eq!(args.to_vec(), ["--user=bob"]);
+        assert_eq!(args.to_sacct_vec(), ["--allusers", "--user=bob"]);
+    }
+
+    #[test]
+    fn team() {
+        let mut args = parse(&["--me", "-u", "bob"]);
+        args.set_team(Some(vec!["alice".to_string(), "carol".to_string()]));
+        assert_eq!(args.to_vec(), ["--user=alice,carol"]);
+        assert_eq!(args.to_sacct_vec(), ["--user=alice,carol"]);
+
+        args.toggle_me();
+        assert!(args.team().is_none());
+        assert_eq!(args.to_vec(), ["--me", "--user=bob"]);
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs, io,
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::archive::ArchivedJob;
+use crate::jobs_file::JobsFile;
+use crate::notifications::NotificationRules;
+use crate::pipelines::PipelineRun;
+
+/// Local, user-editable state that is persisted across restarts
+/// in `$XDG_STATE_HOME/turm/state.json`.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct State {
+    /// Annotations keyed by job id (including the array task, e.g. `1234_5`).
+    pub jobs: HashMap<String, JobAnnotations>,
+    /// Ids of the jobs on the watchlist.
+    pub pinned: BTreeSet<String>,
+    /// The announcement that was dismissed, so that it stays hidden until it changes.
+    pub dismissed_announcement: Option<String>,
+    pub notifications: NotificationRules,
+    /// Submitted pipelines, newest first.
+    pub pipeline_runs: Vec<PipelineRun>,
+    /// Jobs the view is restricted to, see `--jobs-file`.
+    pub jobs_file: Option<JobsFile>,
+    /// Jobs whose logs were archived, newest first.
+    pub archived: Vec<ArchivedJob>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct JobAnnotations {
+    pub tags: BTreeSet<String>,
+    pub note: String,
+}
+
+impl JobAnnotations {
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.note.is_empty()
+    }
+}
+
+/// Where turm keeps its local state, `$XDG_STATE_HOME/turm` on Linux.
+pub fn state_dir() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|d| d.join("turm"))
+}
+
+impl State {
+    fn default_path() -> Option<PathBuf> {
+        state_dir().map(|d| d.join("state.json"))
+    }
+
+    /// Loads the state, starting from scratch if there is none yet.
+    pub fn load() -> io::Result<State> {
+        let path = Self::default_path();
+        let mut state = match path.as_ref().map(fs::read_to_string) {
+            Some(Ok(content)) => serde_json::from_str(&content)?,
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => State::default(),
+        };
+        state.path = path;
+        Ok(state)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn annotations(&self, job_id: &str) -> Option<&JobAnnotations> {
+        self.jobs.get(job_id)
+    }
+
+    pub fn set_tags(&mut self, job_id: &str, tags: BTreeSet<String>) {
+        self.jobs.entry(job_id.to_owned()).or_default().tags = tags;
+        self.prune(job_id);
+    }
+
+    pub fn set_note(&mut self, job_id: &str, note: String) {
+        self.jobs.entry(job_id.to_owned()).or_default().note = note;
+        self.prune(job_id);
+    }
+
+    pub fn is_pinned(&self, job_id: &str) -> bool {
+        self.pinned.contains(job_id)
+    }
+
+    pub fn toggle_pinned(&mut self, job_id: &str) {
+        if !self.pinned.remove(job_id) {
+            self.pinned.insert(job_id.to_owned());
+        }
+    }
+
+    pub fn has_tag(&self, job_id: &str, tag: &str) -> bool {
+        self.jobs.get(job_id).is_some_and(|a| a.tags.contains(tag))
+    }
+
+    fn prune(&mut self, job_id: &str) {
+        if self.jobs.get(job_id).is_some_and(|a| a.is_empty()) {
+            self.jobs.remove(job_id);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::{str::FromStr, sync::RwLock};
+
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer};
+
+/// How a job state is shown: its compact form (as in `squeue --format=%t`), where it sorts
+/// among the other states and its color, and whether jobs in it ended.
+#[derive(Clone)]
+pub struct JobState {
+    pub name: String,
+    pub compact: String,
+    /// Lower states come first, e.g. running before pending before finished.
+    pub order: u32,
+    pub color: Color,
+    /// Whether the job ended, so that its final record can be looked up, archived or emailed.
+    pub r#final: bool,
+}
+
+/// A state added to or overriding the built-in ones, e.g. a site-specific state.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StateConfig {
+    pub name: String,
+    pub compact: Option<String>,
+    pub order: Option<u32>,
+    /// A color name such as `lightred`, an index such as `208` or `#rrggbb`.
+    #[serde(default, deserialize_with = "deserialize_color")]
+    pub color: Option<Color>,
+    pub r#final: Option<bool>,
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Color>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    Color::from_str(&s)
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("invalid color: {}", s)))
+}
+
+/// Where states that are not in the table sort: after all known ones.
+const UNKNOWN_ORDER: u32 = 1000;
+
+/// All job states of Slurm: active ones first, then waiting ones, then final ones.
+const BUILTIN: [(&str, &str, u32, Color, bool); 24] = [
+    ("RUNNING", "R", 0, Color::Green, false),
+    ("CONFIGURING", "CF", 1, Color::Green, false),
+    ("COMPLETING", "CG", 2, Color::Green, false),
+    ("STAGE_OUT", "SO", 3, Color::Green, false),
+    ("SIGNALING", "SI", 4, Color::Green, false),
+    ("RESIZING", "RS", 5, Color::Green, false),
+    ("PENDING", "PD", 10, Color::Yellow, false),
+    ("REQUEUED", "RQ", 11, Color::Yellow, false),
+    ("REQUEUE_HOLD", "RH", 12, Color::Yellow, false),
+    ("REQUEUE_FED", "RF", 13, Color::Yellow, false),
+    ("RESV_DEL_HOLD", "RD", 14, Color::Yellow, false),
+    ("SUSPENDED", "S", 15, Color::Yellow, false),
+    ("STOPPED", "ST", 16, Color::Yellow, false),
+    ("COMPLETED", "CD", 20, Color::Blue, true),
+    ("CANCELLED", "CA", 21, Color::DarkGray, true),
+    ("REVOKED", "RV", 22, Color::DarkGray, true),
+    ("TIMEOUT", "TO", 23, Color::LightRed, true),
+    ("PREEMPTED", "PR", 24, Color::LightRed, true),
+    ("FAILED", "F", 25, Color::Red, true),
+    ("OUT_OF_MEMORY", "OOM", 26, Color::Red, true),
+    ("NODE_FAIL", "NF", 27, Color::Red, true),
+    ("BOOT_FAIL", "BF", 28, Color::Red, true),
+    ("DEADLINE", "DL", 29, Color::Red, true),
+    ("SPECIAL_EXIT", "SE", 30, Color::Red, true),
+];
+
+lazy_static::lazy_static! {
+    static ref STATES: RwLock<Vec<JobState>> = RwLock::new(builtin());
+}
+
+fn builtin() -> Vec<JobState> {
+    BUILTIN
+        .iter()
+        .map(|&(name, compact, order, color, r#final)| JobState {
+            name: name.to_string(),
+            compact: compact.to_string(),
+            order,
+            color,
+            r#final,
+        })
+        .collect()
+}
+
+/// Replaces the table with the built-in states, extended and overridden by the configured
+/// ones.
+pub fn configure(custom: &[StateConfig]) {
+    let mut states = builtin();
+    for c in custom {
+        let name = c.name.to_uppercase();
+        match states.iter_mut().find(|s| s.name == name) {
+            Some(state) => {
+                if let Some(compact) = &c.compact {
+                    state.compact = compact.clone();
+                }
+                state.order = c.order.unwrap_or(state.order);
+                state.color = c.color.unwrap_or(state.color);
+                state.r#final = c.r#final.unwrap_or(state.r#final);
+            }
+            None => states.push(JobState {
+                compact: c.compact.clone().unwrap_or_else(|| name.clone()),
+                name,
+                order: c.order.unwrap_or(UNKNOWN_ORDER),
+                color: c.color.unwrap_or(Color::Red),
+                r#final: c.r#final.unwrap_or(false),
+            }),
+        }
+    }
+    *STATES.write().unwrap() = states;
+}
+
+/// The entry of a state given by its name (e.g. `CANCELLED by 1234`) or compact form.
+fn lookup<T>(state: &str, f: impl FnOnce(Option<&JobState>) -> T) -> T {
+    let state = state.split_whitespace().next().unwrap_or_default();
+    let states = STATES.read().unwrap();
+    f(states
+        .iter()
+        .find(|s| s.name == state)
+        .or_else(|| states.iter().find(|s| s.compact == state)))
+}
+
+/// The compact form of a state, or the state itself if it is unknown.
+pub fn compact(state: &str) -> String {
+    lookup(state, |s| s.map(|s| s.compact.clone())).unwrap_or_else(|| {
+        state
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    })
+}
+
+pub fn order(state: &str) -> u32 {
+    lookup(state, |s| s.map_or(UNKNOWN_ORDER, |s| s.order))
+}
+
+/// The color of a state; unknown states are red, as they are most likely failures.
+pub fn color(state: &str) -> Color {
+    lookup(state, |s| s.map_or(Color::Red, |s| s.color))
+}
+
+/// Whether jobs in the state ended; unknown states are assumed to be active.
+pub fn is_final(state: &str) -> bool {
+    lookup(state, |s| s.is_some_and(|s| s.r#final))
+}
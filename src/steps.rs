@@ -0,0 +1,251 @@
+use std::{path::PathBuf, thread};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use crate::app::{AppMessage, Job};
+use crate::duplicates::batch_script;
+use crate::job_watcher::resolve_path;
+use crate::remote::slurm_command;
+
+/// A job step started with `srun` and where it writes its output.
+#[derive(Clone)]
+pub struct StepOutput {
+    /// The step id, e.g. `0`.
+    pub id: String,
+    pub name: String,
+    /// The output files of the step, or `None` if it writes to the output of the job.
+    pub stdout: Option<PathBuf>,
+    pub stderr: Option<PathBuf>,
+}
+
+/// The options of an `srun` line of a batch script that decide where its output goes.
+#[derive(Default, Debug, PartialEq)]
+struct Srun {
+    name: Option<String>,
+    output: Option<String>,
+    error: Option<String>,
+}
+
+/// Short options of `srun` that take a value, which may be given as the next word.
+const SHORT_WITH_VALUE: [&str; 22] = [
+    "-A", "-B", "-c", "-C", "-d", "-D", "-e", "-G", "-i", "-J", "-L", "-m", "-M", "-n", "-N", "-o",
+    "-p", "-q", "-S", "-t", "-w", "-x",
+];
+
+/// Long options of `srun` that take no value. All others are assumed to take one.
+const LONG_FLAGS: [&str; 18] = [
+    "contiguous",
+    "exact",
+    "exclusive",
+    "kill-on-bad-exit",
+    "label",
+    "multi-prog",
+    "no-kill",
+    "overcommit",
+    "overlap",
+    "preserve-env",
+    "pty",
+    "quiet",
+    "quit-on-interrupt",
+    "spread-job",
+    "test-only",
+    "unbuffered",
+    "verbose",
+    "wait-all-nodes",
+];
+
+/// The `srun` lines of a batch script, in order. Lines continued with `\` are joined, and
+/// only the options before the command are considered.
+fn parse_sruns(script: &str) -> Vec<Srun> {
+    let script = script.replace("\\\n", " ");
+    let mut sruns = Vec::new();
+    for line in script
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.starts_with('#'))
+    {
+        let mut words = line
+            .split_whitespace()
+            .skip_while(|w| *w != "srun" && !w.ends_with("/srun"));
+        if words.next().is_none() {
+            continue;
+        }
+        let mut srun = Srun::default();
+        while let Some(word) = words.next() {
+            if !word.starts_with('-') {
+                break;
+            }
+            let (option, inline) = match word.split_once('=') {
+                Some((option, value)) => (option, Some(value)),
+                None if word.len() > 2 && !word.starts_with("--") => (&word[..2], Some(&word[2..])),
+                None => (word, None),
+            };
+            let takes_value = match option.strip_prefix("--") {
+                Some(long) => !LONG_FLAGS.contains(&long),
+                None => SHORT_WITH_VALUE.contains(&option),
+            };
+            let value = match inline {
+                Some(value) => value,
+                None if takes_value => words.next().unwrap_or_default(),
+                None => continue,
+            };
+            let value = Some(value.trim_matches(['"', '\'']).to_string());
+            match option {
+                "--output" | "-o" => srun.output = value,
+                "--error" | "-e" => srun.error = value,
+                "--job-name" | "-J" => srun.name = value,
+                _ => {}
+            }
+        }
+        sruns.push(srun);
+    }
+    sruns
+}
+
+/// The steps of a job as `(id, name)`, from `sacct` if available since it also knows the
+/// steps that already ended, or from `squeue`. The batch and extern steps are left out.
+fn list_steps(job_id: &str, sacct: bool) -> Result<Vec<(String, String)>, String> {
+    let output = match sacct {
+        true => slurm_command("sacct")
+            .args(["--jobs", job_id, "--format=JobID,JobName"])
+            .args(["--parsable2", "--noheader"])
+            .output(),
+        false => slurm_command("squeue")
+            .args(["--steps", "--jobs", job_id, "--noheader", "--format=%i|%j"])
+            .output(),
+    }
+    .map_err(|e| format!("Failed to list the steps: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to list the steps: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (id, name) = line.split_once('|')?;
+            let (_, step) = id.rsplit_once('.')?;
+            step.parse::<u32>().ok()?;
+            Some((step.to_string(), name.to_string()))
+        })
+        .collect())
+}
+
+/// The steps of a job with their output files. Steps are matched to the `srun` lines of the
+/// batch script by `--job-name`, or else in order, with the last line standing in for the
+/// steps of a loop.
+fn steps(job: &Job, sacct: bool) -> Result<Vec<StepOutput>, String> {
+    let sruns = batch_script(&job.id())
+        .map(|script| parse_sruns(&script))
+        .unwrap_or_default();
+    let work_dir = job
+        .work_dir
+        .as_ref()
+        .map(|d| d.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let resolve = |pattern: &str, step: &str| {
+        resolve_path(
+            pattern,
+            &job.array_id,
+            job.array_step.as_deref().unwrap_or("N/A"),
+            &job.job_id,
+            step,
+            &job.nodelist,
+            &job.user,
+            &job.name,
+            &work_dir,
+        )
+    };
+    let mut steps = list_steps(&job.id(), sacct)?
+        .into_iter()
+        .map(|(id, name)| {
+            let index = id.parse::<usize>().unwrap_or(0);
+            let srun = sruns
+                .iter()
+                .find(|s| s.name.as_deref() == Some(name.as_str()))
+                .or_else(|| sruns.get(index))
+                .or(sruns.last());
+            let output = srun.and_then(|s| s.output.as_deref());
+            // stderr goes to the output file unless it is redirected separately
+            let error = srun.and_then(|s| s.error.as_deref()).or(output);
+            StepOutput {
+                stdout: output.and_then(|o| resolve(o, &id)),
+                stderr: error.and_then(|e| resolve(e, &id)),
+                id,
+                name,
+            }
+        })
+        .collect::<Vec<_>>();
+    steps.sort_by_key(|s| s.id.parse::<u32>().unwrap_or(u32::MAX));
+    Ok(steps)
+}
+
+/// Looks up the steps of jobs in the background, one at a time.
+struct StepWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<Job>,
+    sacct: bool,
+}
+
+impl StepWatcher {
+    fn run(&mut self) {
+        while let Ok(job) = self.receiver.recv() {
+            let result = steps(&job, self.sacct);
+            let _ = self.app.send(AppMessage::Steps(job.id(), result));
+        }
+    }
+}
+
+pub struct StepWatcherHandle {
+    sender: Sender<Job>,
+}
+
+impl StepWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, sacct: bool) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = StepWatcher {
+            app,
+            receiver,
+            sacct,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Looks up the steps of a job and where they write their output.
+    pub fn request(&self, job: Job) {
+        let _ = self.sender.send(job);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sruns() {
+        let script = "#!/bin/bash\n\
+            #SBATCH --output=job-%j.out\n\
+            # srun --output=commented.out\n\
+            srun -n 4 --label python prepare.py\n\
+            srun -o prep-%j.%s.out --error=prep.err python train.py -o ignored\n\
+            srun --job-name=eval \\\n  -o\"eval-%s.log\" python eval.py\n";
+        assert_eq!(
+            parse_sruns(script),
+            [
+                Srun::default(),
+                Srun {
+                    name: None,
+                    output: Some("prep-%j.%s.out".to_string()),
+                    error: Some("prep.err".to_string()),
+                },
+                Srun {
+                    name: Some("eval".to_string()),
+                    output: Some("eval-%s.log".to_string()),
+                    error: None,
+                },
+            ]
+        );
+    }
+}
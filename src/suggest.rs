@@ -0,0 +1,191 @@
+use std::{
+    collections::HashMap,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use regex::Regex;
+
+use crate::app::AppMessage;
+use crate::remote::{self, slurm_command};
+use crate::slurm_time::{format_slurm_duration, parse_duration};
+use crate::usage::parse_size;
+
+/// How far back my jobs are considered.
+const HISTORY_DAYS: u32 = 30;
+/// The history is fetched again after this long.
+const HISTORY_TTL: Duration = Duration::from_secs(10 * 60);
+/// Fewer similar jobs are not a reliable basis.
+const MIN_SAMPLES: usize = 3;
+/// Added on top of the 95th percentile of the usage.
+const MARGIN: f64 = 1.2;
+
+lazy_static::lazy_static! {
+    static ref DIGITS: Regex = Regex::new(r"\d+").unwrap();
+}
+
+/// Jobs of a sweep share the name up to numbers, e.g. `sweep42-lr1` and `sweep43-lr2`.
+pub fn name_pattern(name: &str) -> String {
+    DIGITS.replace_all(name, "#").into_owned()
+}
+
+/// `--mem` and `--time` for a job, from the usage of my completed jobs with a similar name.
+#[derive(Clone)]
+pub struct Suggestion {
+    pub mem: Option<u64>,
+    pub time: Option<chrono::Duration>,
+    pub samples: usize,
+}
+
+impl Suggestion {
+    /// The suggestion as sbatch options, e.g. `--mem=5G --time=1:15:00`.
+    pub fn options(&self) -> String {
+        let mut options = Vec::new();
+        if let Some(mem) = self.mem {
+            options.push(format!("--mem={}", crate::efficiency::format_size(mem)));
+        }
+        if let Some(time) = self.time {
+            options.push(format!("--time={}", format_slurm_duration(time)));
+        }
+        options.join(" ")
+    }
+}
+
+struct Completed {
+    name: String,
+    max_rss: Option<u64>,
+    elapsed: Option<chrono::Duration>,
+}
+
+fn percentile_95<T: Ord + Copy>(mut values: Vec<T>) -> Option<T> {
+    values.sort();
+    let rank = (values.len() as f64 * 0.95).ceil() as usize;
+    values.get(rank.checked_sub(1)?).copied()
+}
+
+fn suggest(history: &[Completed], pattern: &str) -> Option<Suggestion> {
+    let similar = history
+        .iter()
+        .filter(|j| name_pattern(&j.name) == pattern)
+        .collect::<Vec<_>>();
+    if similar.len() < MIN_SAMPLES {
+        return None;
+    }
+    let mem = percentile_95(similar.iter().filter_map(|j| j.max_rss).collect()).map(|rss| {
+        let mem = (rss as f64 * MARGIN) as u64;
+        // whole GiB, or multiples of 128 MiB for small jobs
+        let unit = if mem >= 1 << 30 { 1 << 30 } else { 128 << 20 };
+        mem.div_ceil(unit) * unit
+    });
+    let time = percentile_95(similar.iter().filter_map(|j| j.elapsed).collect()).map(|elapsed| {
+        let seconds = (elapsed.num_seconds() as f64 * MARGIN) as i64;
+        // multiples of 5 minutes
+        chrono::Duration::seconds((seconds.max(1) + 299) / 300 * 300)
+    });
+    Some(Suggestion {
+        mem,
+        time,
+        samples: similar.len(),
+    })
+}
+
+/// My completed jobs of the last days, with the largest `MaxRSS` of their steps.
+fn history() -> Result<Vec<Completed>, String> {
+    const SEPARATOR: &str = "###turm###";
+    let output = slurm_command("sacct")
+        .arg(format!("--user={}", remote::current_user()))
+        .arg(format!("--starttime=now-{}days", HISTORY_DAYS))
+        .args(["--state=COMPLETED", "--noheader", "--parsable2"])
+        .args(["--delimiter", SEPARATOR])
+        .arg("--format=JobID,JobName,MaxRSS,Elapsed")
+        .output()
+        .map_err(|e| format!("Failed to execute sacct: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "sacct failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let mut jobs: Vec<(String, Completed)> = Vec::new();
+    let mut max_rss: HashMap<String, u64> = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let [id, name, rss, elapsed] = line.split(SEPARATOR).collect::<Vec<_>>()[..] else {
+            continue;
+        };
+        match id.split_once('.') {
+            Some((job, _step)) => {
+                if let Some(rss) = parse_size(rss) {
+                    let max = max_rss.entry(job.to_string()).or_default();
+                    *max = (*max).max(rss);
+                }
+            }
+            None => jobs.push((
+                id.to_string(),
+                Completed {
+                    name: name.to_string(),
+                    max_rss: None,
+                    elapsed: parse_duration(elapsed),
+                },
+            )),
+        }
+    }
+    Ok(jobs
+        .into_iter()
+        .map(|(id, mut job)| {
+            job.max_rss = max_rss.get(&id).copied();
+            job
+        })
+        .collect())
+}
+
+/// Computes suggestions from the sacct history in the background.
+struct Suggester {
+    app: Sender<AppMessage>,
+    receiver: Receiver<String>,
+    history: Vec<Completed>,
+    fetched_at: Option<Instant>,
+}
+
+impl Suggester {
+    fn run(&mut self) {
+        while let Ok(pattern) = self.receiver.recv() {
+            if self.fetched_at.is_none_or(|t| t.elapsed() > HISTORY_TTL) {
+                // without history, there are simply no suggestions
+                self.history = history().unwrap_or_default();
+                self.fetched_at = Some(Instant::now());
+            }
+            let suggestion = suggest(&self.history, &pattern);
+            if self
+                .app
+                .send(AppMessage::Suggestion(pattern, suggestion))
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+pub struct SuggesterHandle {
+    sender: Sender<String>,
+}
+
+impl SuggesterHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = Suggester {
+            app,
+            receiver,
+            history: Vec::new(),
+            fetched_at: None,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Asks for the suggestion for jobs with the name pattern, see [`name_pattern`].
+    pub fn request(&self, pattern: String) {
+        let _ = self.sender.send(pattern);
+    }
+}
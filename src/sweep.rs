@@ -0,0 +1,189 @@
+use std::{collections::BTreeSet, str::FromStr};
+
+use regex::Regex;
+
+lazy_static::lazy_static! {
+    static ref PLACEHOLDER: Regex = Regex::new(r"\{\{\s*(\w+)\s*\}\}").unwrap();
+}
+
+/// A swept parameter, e.g. `lr={1e-3,1e-4}` or `seed=0..4` (both ends included).
+#[derive(Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl FromStr for Parameter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, values) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=VALUES, got `{}`", s))?;
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(format!("invalid parameter name `{}`", name));
+        }
+        let values = values.trim();
+        let values = values
+            .strip_prefix('{')
+            .and_then(|v| v.strip_suffix('}'))
+            .unwrap_or(values);
+        let range = values.split_once("..").and_then(|(start, end)| {
+            Some((start.trim().parse::<i64>().ok()?, end.trim().parse().ok()?))
+        });
+        let values = match range {
+            Some((start, end)) if start <= end => (start..=end).map(|i| i.to_string()).collect(),
+            Some(_) => return Err(format!("empty range for `{}`", name)),
+            None => values
+                .split(',')
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<_>>(),
+        };
+        if values.is_empty() {
+            return Err(format!("no values for `{}`", name));
+        }
+        Ok(Parameter {
+            name: name.to_string(),
+            values,
+        })
+    }
+}
+
+/// One value per parameter, for every combination, the last parameter varying fastest.
+pub fn combinations(parameters: &[Parameter]) -> Vec<Vec<(&str, &str)>> {
+    parameters.iter().fold(vec![Vec::new()], |combinations, p| {
+        combinations
+            .iter()
+            .flat_map(|c| {
+                p.values.iter().map(move |v| {
+                    let mut c = c.clone();
+                    c.push((p.name.as_str(), v.as_str()));
+                    c
+                })
+            })
+            .collect()
+    })
+}
+
+/// Names in `{{name}}` placeholders that are not parameters.
+pub fn unknown_placeholders(text: &str, parameters: &[Parameter]) -> BTreeSet<String> {
+    PLACEHOLDER
+        .captures_iter(text)
+        .map(|c| c[1].to_string())
+        .filter(|name| parameters.iter().all(|p| p.name != *name))
+        .collect()
+}
+
+/// Replaces the `{{name}}` placeholders with the values of the combination.
+pub fn render(text: &str, combination: &[(&str, &str)]) -> String {
+    PLACEHOLDER
+        .replace_all(text, |c: &regex::Captures| {
+            combination
+                .iter()
+                .find(|(name, _)| *name == &c[1])
+                .map_or_else(|| c[0].to_string(), |(_, value)| value.to_string())
+        })
+        .into_owned()
+}
+
+/// Byte offset of the first line that is neither blank nor a comment, where sbatch stops
+/// reading `#SBATCH` lines.
+fn header_end(script: &str) -> usize {
+    let mut offset = 0;
+    for line in script.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            break;
+        }
+        offset += line.len();
+    }
+    offset
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// One script for an array job with a task per combination: the parameters are exported as
+/// environment variables chosen by `$SLURM_ARRAY_TASK_ID` after the `#SBATCH` lines, and the
+/// placeholders become variable references. Placeholders in `#SBATCH` lines cannot differ
+/// between tasks.
+pub fn array_script(script: &str, parameters: &[Parameter]) -> Result<String, String> {
+    let end = header_end(script);
+    if PLACEHOLDER.is_match(&script[..end]) {
+        return Err("placeholders in #SBATCH lines need one job per combination".to_string());
+    }
+    let mut prelude = String::from(
+        "# parameters of the sweep, by array task\ncase \"$SLURM_ARRAY_TASK_ID\" in\n",
+    );
+    for (i, combination) in combinations(parameters).iter().enumerate() {
+        let assignments = combination
+            .iter()
+            .map(|(name, value)| format!("export {}={}", name, shell_quote(value)))
+            .collect::<Vec<_>>()
+            .join("; ");
+        prelude.push_str(&format!("  {}) {} ;;\n", i, assignments));
+    }
+    prelude.push_str("esac\n\n");
+    let body = PLACEHOLDER.replace_all(&script[end..], "$${${1}}");
+    Ok(format!("{}{}{}", &script[..end], prelude, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameter(s: &str) -> Parameter {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parameters() {
+        assert_eq!(parameter("lr={1e-3, 1e-4}").values, ["1e-3", "1e-4"]);
+        assert_eq!(parameter("seed=0..3").values, ["0", "1", "2", "3"]);
+        assert_eq!(parameter("seed={-1..1}").values, ["-1", "0", "1"]);
+        assert_eq!(parameter("opt=adam").values, ["adam"]);
+        for invalid in ["lr", "=1", "l r=1", "seed=3..1", "lr={}", "lr={,}"] {
+            assert!(invalid.parse::<Parameter>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn cross_product() {
+        let parameters = [parameter("lr={1e-3,1e-4}"), parameter("seed=0..2")];
+        let all = combinations(&parameters);
+        assert_eq!(all.len(), 6);
+        assert_eq!(all[0], [("lr", "1e-3"), ("seed", "0")]);
+        assert_eq!(all[1], [("lr", "1e-3"), ("seed", "1")]);
+        assert_eq!(all[5], [("lr", "1e-4"), ("seed", "2")]);
+        assert_eq!(combinations(&[]), [Vec::<(&str, &str)>::new()]);
+    }
+
+    #[test]
+    fn placeholders() {
+        let parameters = [parameter("lr={1e-3,1e-4}")];
+        let text = "--lr {{lr}} --seed {{ seed }}";
+        assert_eq!(
+            unknown_placeholders(text, &parameters),
+            BTreeSet::from(["seed".to_string()])
+        );
+        assert_eq!(
+            render(text, &[("lr", "1e-3")]),
+            "--lr 1e-3 --seed {{ seed }}"
+        );
+    }
+
+    #[test]
+    fn array_scripts() {
+        let parameters = [parameter("lr={1e-3,1e-4}"), parameter("seed=0..1")];
+        let script = "#!/bin/bash\n#SBATCH --time=1:00:00\n\npython train.py --lr {{lr}}\n";
+        let array = array_script(script, &parameters).unwrap();
+        assert!(array.starts_with("#!/bin/bash\n#SBATCH --time=1:00:00\n\n# parameters"));
+        assert!(array.contains("  3) export lr='1e-4'; export seed='1' ;;\n"));
+        assert!(array.ends_with("python train.py --lr ${lr}\n"));
+        let in_header = "#!/bin/bash\n#SBATCH --job-name={{lr}}\necho\n";
+        assert!(array_script(in_header, &parameters).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+
+use crate::app::Job;
+
+/// Something that happened to a job, e.g. it started or was requeued.
+pub struct Event {
+    pub time: NaiveDateTime,
+    pub label: String,
+}
+
+/// Events of a job beyond submit, start and end, which are only visible as state
+/// transitions between two polls (requeues, preemptions, node failures, ...).
+/// They are only kept while turm is running.
+#[derive(Default)]
+pub struct Timelines {
+    observed: HashMap<String, Vec<Event>>,
+}
+
+/// The state without details such as `CANCELLED by 1234`.
+fn base_state(state: &str) -> &str {
+    state.split_whitespace().next().unwrap_or_default()
+}
+
+fn label(state: &str) -> String {
+    match base_state(state) {
+        "RUNNING" => "started".to_string(),
+        state => state.to_lowercase().replace('_', " "),
+    }
+}
+
+impl Timelines {
+    /// Records the state transitions between two polls.
+    pub fn observe(&mut self, old: &[Job], new: &[Job], now: NaiveDateTime) {
+        let old: HashMap<_, _> = old.iter().map(|o| (o.id(), o)).collect();
+        for job in new {
+            let Some(previous) = old.get(&job.id()) else {
+                continue;
+            };
+            if base_state(&previous.state) == base_state(&job.state) {
+                continue;
+            }
+            let events = self.observed.entry(job.id()).or_default();
+            // a job only becomes pending again if it was requeued
+            let label = match base_state(&job.state) {
+                "PENDING" if events.last().is_some_and(|e| e.label == "requeued") => continue,
+                "PENDING" => "requeued".to_string(),
+                _ => label(&job.state),
+            };
+            events.push(Event { time: now, label });
+        }
+    }
+
+    /// The events of `job` in chronological order, from its submit, start and end time and
+    /// the observed transitions that these do not cover.
+    pub fn events(&self, job: &Job, now: NaiveDateTime) -> Vec<Event> {
+        let observed = self
+            .observed
+            .get(&job.id())
+            .map_or(&[][..], |e| e.as_slice());
+        // earlier starts belong to previous runs of a requeued job
+        let last_start = observed.iter().rposition(|e| e.label == "started");
+        let finished = !matches!(base_state(&job.state), "PENDING" | "RUNNING");
+
+        let mut events = Vec::new();
+        events.extend(job.submit_time.map(|time| Event {
+            time,
+            label: "submitted".to_string(),
+        }));
+        for (i, event) in observed.iter().enumerate() {
+            let covered = match event.label.as_str() {
+                "started" => job.start_time.is_some() && Some(i) == last_start,
+                label => finished && label == self::label(&job.state),
+            };
+            if !covered {
+                events.push(Event {
+                    time: event.time,
+                    label: event.label.clone(),
+                });
+            }
+        }
+        // squeue reports the expected start and end time of jobs that have not started or ended
+        events.extend(job.start_time.filter(|t| *t <= now).map(|time| Event {
+            time,
+            label: "started".to_string(),
+        }));
+        if finished {
+            events.extend(job.end_time.filter(|t| *t <= now).map(|time| Event {
+                time,
+                label: label(&job.state),
+            }));
+        }
+        events.sort_by_key(|e| e.time);
+        events
+    }
+}
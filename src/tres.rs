@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+/// Trackable resources as reported by Slurm, e.g. `cpu=4,mem=16G,node=1,gres/gpu=2`.
+#[derive(Clone, Default)]
+pub struct Tres(BTreeMap<String, String>);
+
+impl Tres {
+    pub fn parse(s: &str) -> Tres {
+        Tres(
+            s.split(',')
+                .filter_map(|entry| entry.split_once('='))
+                .map(|(k, v)| (k.trim().to_owned(), v.trim().to_owned()))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|v| v.as_str())
+    }
+
+    pub fn cpus(&self) -> u64 {
+        self.get("cpu").and_then(|v| v.parse().ok()).unwrap_or(0)
+    }
+
+    /// Number of GPUs. Slurm reports both `gres/gpu` and typed entries such as `gres/gpu:a100`,
+    /// so the typed ones are only summed up if the untyped one is missing.
+    pub fn gpus(&self) -> u64 {
+        match self.get("gres/gpu") {
+            Some(v) => v.parse().unwrap_or(0),
+            None => self
+                .0
+                .iter()
+                .filter(|(k, _)| k.starts_with("gres/gpu:"))
+                .filter_map(|(_, v)| v.parse::<u64>().ok())
+                .sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries() {
+        let tres = Tres::parse("cpu=4,mem=16G,node=1,billing=4");
+        assert_eq!(tres.cpus(), 4);
+        assert_eq!(tres.get("mem"), Some("16G"));
+        assert_eq!(tres.get("node"), Some("1"));
+        assert_eq!(tres.get("gres/gpu"), None);
+        assert_eq!(tres.gpus(), 0);
+
+        let empty = Tres::parse("");
+        assert_eq!(empty.cpus(), 0);
+        assert_eq!(empty.gpus(), 0);
+        assert_eq!(Tres::parse("cpu=many,junk").cpus(), 0);
+    }
+
+    #[test]
+    fn gpus() {
+        assert_eq!(Tres::parse("cpu=8,gres/gpu=2").gpus(), 2);
+        // The untyped count already includes the typed ones.
+        let both = Tres::parse("cpu=8,gres/gpu=3,gres/gpu:a100=2,gres/gpu:v100=1");
+        assert_eq!(both.gpus(), 3);
+        assert_eq!(both.get("gres/gpu:a100"), Some("2"));
+        let typed = Tres::parse("cpu=8,gres/gpu:a100=2,gres/gpu:v100=1,gres/gpumem=40G");
+        assert_eq!(typed.gpus(), 3);
+    }
+}
@@ -0,0 +1,209 @@
+use std::{collections::HashMap, path::PathBuf, thread};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use regex::Regex;
+
+use crate::app::{AppMessage, Job};
+use crate::log_source::{log_source, LogSource, Throttle};
+
+/// At most this many stderr files are read per triage, evenly spread over the failed tasks.
+const MAX_SAMPLES: usize = 200;
+/// Bytes read from the end of each stderr file.
+const TAIL_BYTES: u64 = 16 * 1024;
+/// Longer error lines are cut, they rarely differ only at the end.
+const MAX_ERROR_LEN: usize = 160;
+
+lazy_static::lazy_static! {
+    static ref ERROR: Regex = Regex::new(
+        r"(?i)(error|exception|fatal|killed|traceback|out of memory|segmentation fault|abort)"
+    ).unwrap();
+    static ref NUMBER: Regex = Regex::new(r"0x[0-9a-fA-F]+|\d+(\.\d+)?").unwrap();
+}
+
+/// Whether the task failed rather than completed or was cancelled by the user.
+pub fn is_failed(job: &Job) -> bool {
+    matches!(
+        job.state.split_whitespace().next().unwrap_or_default(),
+        "FAILED" | "TIMEOUT" | "OUT_OF_MEMORY" | "NODE_FAIL" | "PREEMPTED" | "BOOT_FAIL"
+    )
+}
+
+/// The failed tasks of the array, ordered by task index.
+pub fn failed_tasks<'a>(jobs: &'a [Job], array_id: &str) -> Vec<&'a Job> {
+    let mut tasks = jobs
+        .iter()
+        .filter(|j| j.array_id == array_id && j.array_step.is_some() && is_failed(j))
+        .collect::<Vec<_>>();
+    tasks.sort_by_key(|j| task_index(j));
+    tasks
+}
+
+pub fn task_index(job: &Job) -> Option<u32> {
+    job.array_step.as_deref()?.parse().ok()
+}
+
+/// Compresses task indices the way `--array` takes them, e.g. `3,7,19-22`.
+pub fn compress_indices(indices: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &i in indices {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == i => *end = i,
+            _ => ranges.push((i, i)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The stderr file of a task. Tasks that finished before turm saw them have no paths, those
+/// are derived from a sibling task, assuming the usual `%A_%a` in the file name.
+fn stderr_path(task: &Job, siblings: &[Job]) -> Option<PathBuf> {
+    if let Some(path) = task.stderr.as_ref().or(task.stdout.as_ref()) {
+        return Some(path.clone());
+    }
+    let step = task.array_step.as_deref()?;
+    let mut siblings = siblings
+        .iter()
+        .filter(|s| s.array_id == task.array_id && s.array_step.is_some());
+    siblings.find_map(|s| {
+        let path = s.stderr.as_ref().or(s.stdout.as_ref())?.to_str()?;
+        let sibling = format!("{}_{}", s.array_id, s.array_step.as_deref()?);
+        path.contains(&sibling)
+            .then(|| PathBuf::from(path.replace(&sibling, &format!("{}_{}", task.array_id, step))))
+    })
+}
+
+/// The last line that looks like an error, or else the last line, with numbers replaced so
+/// that e.g. allocation sizes or file names of different tasks end up in the same bucket.
+fn error_line(stderr: &str) -> String {
+    let mut lines = stderr.lines().map(str::trim).filter(|l| !l.is_empty());
+    let line = lines
+        .clone()
+        .rfind(|l| ERROR.is_match(l))
+        .or_else(|| lines.next_back());
+    match line {
+        Some(line) => NUMBER
+            .replace_all(line, "#")
+            .chars()
+            .take(MAX_ERROR_LEN)
+            .collect(),
+        None => "(empty stderr)".to_string(),
+    }
+}
+
+/// Failed tasks with the same error.
+pub struct Bucket {
+    pub error: String,
+    pub tasks: Vec<String>,
+    pub indices: Vec<u32>,
+}
+
+pub struct Triage {
+    /// Largest first.
+    pub buckets: Vec<Bucket>,
+    pub sampled: usize,
+    pub failed: usize,
+}
+
+struct TriageRequest {
+    array_id: String,
+    /// Ids, indices and stderr files of the sampled tasks.
+    tasks: Vec<(String, u32, Option<PathBuf>)>,
+    failed: usize,
+}
+
+/// Reads the stderr files of failed array tasks in the background.
+struct TriageWorker {
+    app: Sender<AppMessage>,
+    receiver: Receiver<TriageRequest>,
+    source: Box<dyn LogSource>,
+}
+
+impl TriageWorker {
+    fn triage(&mut self, request: TriageRequest) -> Triage {
+        let mut buckets: HashMap<String, Bucket> = HashMap::new();
+        let sampled = request.tasks.len();
+        for (id, index, path) in request.tasks {
+            let error = match path {
+                Some(path) => match self.source.read_tail(&path, TAIL_BYTES) {
+                    Ok(tail) => error_line(&String::from_utf8_lossy(&tail)),
+                    Err(_) => "(stderr not readable)".to_string(),
+                },
+                None => "(stderr unknown)".to_string(),
+            };
+            let bucket = buckets.entry(error.clone()).or_insert_with(|| Bucket {
+                error,
+                tasks: Vec::new(),
+                indices: Vec::new(),
+            });
+            bucket.tasks.push(id);
+            bucket.indices.push(index);
+        }
+        let mut buckets = buckets.into_values().collect::<Vec<_>>();
+        buckets.sort_by(|a, b| {
+            b.tasks
+                .len()
+                .cmp(&a.tasks.len())
+                .then(a.error.cmp(&b.error))
+        });
+        Triage {
+            buckets,
+            sampled,
+            failed: request.failed,
+        }
+    }
+
+    fn run(&mut self) {
+        while let Ok(request) = self.receiver.recv() {
+            let array_id = request.array_id.clone();
+            let triage = self.triage(request);
+            if self.app.send(AppMessage::Triage(array_id, triage)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+pub struct TriageHandle {
+    sender: Sender<TriageRequest>,
+}
+
+impl TriageHandle {
+    pub fn new(app: Sender<AppMessage>, throttle: Throttle) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = TriageWorker {
+            app,
+            receiver,
+            source: log_source(throttle),
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Buckets the failed tasks of the array by error, answered with [`AppMessage::Triage`].
+    /// Returns the number of failed tasks.
+    pub fn request(&self, jobs: &[Job], array_id: &str) -> usize {
+        let failed = failed_tasks(jobs, array_id);
+        let step = failed.len().div_ceil(MAX_SAMPLES).max(1);
+        let tasks = failed
+            .iter()
+            .step_by(step)
+            .filter_map(|t| Some((t.id(), task_index(t)?, stderr_path(t, jobs))))
+            .collect();
+        let _ = self.sender.send(TriageRequest {
+            array_id: array_id.to_string(),
+            tasks,
+            failed: failed.len(),
+        });
+        failed.len()
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    fmt, thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::{
+    channel::{unbounded, Receiver, Sender},
+    select,
+};
+
+use crate::app::AppMessage;
+use crate::file_watcher::format_bytes;
+use crate::leaderboard::kwh;
+use crate::remote::slurm_command;
+use crate::slurm_time::{format_slurm_duration, parse_duration};
+
+/// How often the usage of the selected job is refreshed.
+const INTERVAL: Duration = Duration::from_secs(30);
+
+/// Sums the Lustre and NFS client counters of every node of the job. They count the I/O of
+/// everything running on the node, not only of the job.
+const FS_STATS_SCRIPT: &str = r#"cat /proc/fs/lustre/llite/*/stats 2>/dev/null | awk '$1 == "read_bytes" { r += $7 } $1 == "write_bytes" { w += $7 } END { print "lustre", r + 0, w + 0 }'; awk '$1 == "bytes:" { r += $2; w += $3 } END { print "nfs", r + 0, w + 0 }' /proc/self/mountstats 2>/dev/null"#;
+
+/// Resource usage of the steps of a running job, as reported by `sstat`.
+#[derive(Default)]
+pub struct Usage {
+    /// Average CPU time of the tasks, summed up over the steps.
+    pub cpu: chrono::Duration,
+    /// Largest resident set size of any task.
+    pub max_rss: u64,
+    /// Bytes read and written by the task with the most I/O, summed up over the steps.
+    pub disk_read: u64,
+    pub disk_write: u64,
+    /// Energy consumed so far in joules, 0 without energy accounting.
+    pub energy: u64,
+    /// Bytes read and written through network file system clients, by file system.
+    pub fs: Vec<(String, u64, u64)>,
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "CPU {}, RSS {}, read {}, written {}",
+            format_slurm_duration(self.cpu),
+            format_bytes(self.max_rss as f64),
+            format_bytes(self.disk_read as f64),
+            format_bytes(self.disk_write as f64)
+        )?;
+        if self.energy > 0 {
+            write!(f, ", energy {:.2} kWh", kwh(self.energy))?;
+        }
+        for (fs, read, written) in &self.fs {
+            write!(
+                f,
+                ", {} {}/{} (node-wide)",
+                fs,
+                format_bytes(*read as f64),
+                format_bytes(*written as f64)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses sizes such as `1.50M` or `2048K` (powers of 1024, bytes without suffix).
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (number, factor) = match s.chars().last()? {
+        'K' => (&s[..s.len() - 1], 1u64 << 10),
+        'M' => (&s[..s.len() - 1], 1 << 20),
+        'G' => (&s[..s.len() - 1], 1 << 30),
+        'T' => (&s[..s.len() - 1], 1 << 40),
+        'P' => (&s[..s.len() - 1], 1 << 50),
+        _ => (s, 1),
+    };
+    Some((number.parse::<f64>().ok()? * factor as f64) as u64)
+}
+
+fn sstat(job_id: &str) -> Result<Usage, String> {
+    let output = slurm_command("sstat")
+        .args([
+            "--noheader",
+            "--parsable2",
+            "--allsteps",
+            &format!("--jobs={}", job_id),
+            "--format=AveCPU,MaxRSS,MaxDiskRead,MaxDiskWrite,ConsumedEnergyRaw",
+        ])
+        .output()
+        .map_err(|e| format!("Failed to execute sstat: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "sstat failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let mut usage = Usage::default();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let fields = line.split('|').collect::<Vec<_>>();
+        let [cpu, rss, read, write, energy] = fields.as_slice() else {
+            continue;
+        };
+        // short CPU times come with milliseconds, e.g. `00:01.234`
+        if let Some(cpu) = parse_duration(cpu.split('.').next().unwrap_or_default()) {
+            usage.cpu += cpu;
+        }
+        usage.max_rss = usage.max_rss.max(parse_size(rss).unwrap_or(0));
+        usage.disk_read += parse_size(read).unwrap_or(0);
+        usage.disk_write += parse_size(write).unwrap_or(0);
+        usage.energy += energy.parse::<u64>().unwrap_or(0);
+    }
+    Ok(usage)
+}
+
+/// Lustre and NFS client counters of the nodes of the job, via an overlapping job step.
+fn fs_stats(job_id: &str) -> Vec<(String, u64, u64)> {
+    let Ok(output) = slurm_command("srun")
+        .args([
+            &format!("--jobid={}", job_id),
+            "--overlap",
+            "--ntasks-per-node=1",
+            "--immediate=10",
+            "--quiet",
+            "sh",
+            "-c",
+            FS_STATS_SCRIPT,
+        ])
+        .output()
+    else {
+        return Vec::new();
+    };
+    let mut stats: Vec<(String, u64, u64)> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let [fs, read, written] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+            continue;
+        };
+        let (Ok(read), Ok(written)) = (read.parse::<u64>(), written.parse::<u64>()) else {
+            continue;
+        };
+        match stats.iter_mut().find(|(name, _, _)| name == fs) {
+            Some(entry) => {
+                entry.1 += read;
+                entry.2 += written;
+            }
+            None => stats.push((fs.to_string(), read, written)),
+        }
+    }
+    stats.retain(|(_, read, written)| read + written > 0);
+    stats
+}
+
+enum UsageWatcherMessage {
+    /// The running job to watch (without array index), if any.
+    Job(Option<String>),
+    FsStats(bool),
+}
+
+/// Polls `sstat` for the selected running job.
+struct UsageWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<UsageWatcherMessage>,
+    job_id: Option<String>,
+    fs_stats: bool,
+    polled_at: Option<Instant>,
+}
+
+impl UsageWatcher {
+    fn poll(&mut self) {
+        self.polled_at = Some(Instant::now());
+        let Some(job_id) = &self.job_id else {
+            return;
+        };
+        let usage = sstat(job_id).map(|mut usage| {
+            if self.fs_stats {
+                usage.fs = fs_stats(job_id);
+            }
+            usage
+        });
+        let _ = self.app.send(AppMessage::Usage(job_id.clone(), usage));
+    }
+
+    fn run(&mut self) {
+        loop {
+            let due = self
+                .polled_at
+                .map_or(Duration::ZERO, |t| INTERVAL.saturating_sub(t.elapsed()));
+            select! {
+                recv(self.receiver) -> msg => match msg {
+                    Ok(UsageWatcherMessage::Job(job_id)) => {
+                        if job_id != self.job_id {
+                            self.job_id = job_id;
+                            self.poll();
+                        }
+                    }
+                    Ok(UsageWatcherMessage::FsStats(fs_stats)) => self.fs_stats = fs_stats,
+                    Err(_) => return,
+                },
+                default(due) => self.poll(),
+            }
+        }
+    }
+}
+
+pub struct UsageWatcherHandle {
+    sender: Sender<UsageWatcherMessage>,
+}
+
+impl UsageWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, fs_stats: bool) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = UsageWatcher {
+            app,
+            receiver,
+            job_id: None,
+            fs_stats,
+            polled_at: None,
+        };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    pub fn set_job(&self, job_id: Option<String>) {
+        let _ = self.sender.send(UsageWatcherMessage::Job(job_id));
+    }
+
+    pub fn set_fs_stats(&self, fs_stats: bool) {
+        let _ = self.sender.send(UsageWatcherMessage::FsStats(fs_stats));
+    }
+}
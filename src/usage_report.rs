@@ -0,0 +1,214 @@
+use std::{collections::HashMap, thread};
+
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use crate::app::AppMessage;
+use crate::remote::slurm_command;
+use crate::sacctmgr;
+
+/// The periods the usage report can be shown for.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReportPeriod {
+    ThisMonth,
+    LastMonth,
+    ThisQuarter,
+    LastQuarter,
+    ThisYear,
+    Last30Days,
+}
+
+impl ReportPeriod {
+    pub const ALL: [ReportPeriod; 6] = [
+        ReportPeriod::ThisMonth,
+        ReportPeriod::LastMonth,
+        ReportPeriod::ThisQuarter,
+        ReportPeriod::LastQuarter,
+        ReportPeriod::ThisYear,
+        ReportPeriod::Last30Days,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReportPeriod::ThisMonth => "this month",
+            ReportPeriod::LastMonth => "last month",
+            ReportPeriod::ThisQuarter => "this quarter",
+            ReportPeriod::LastQuarter => "last quarter",
+            ReportPeriod::ThisYear => "this year",
+            ReportPeriod::Last30Days => "last 30 days",
+        }
+    }
+
+    /// The next (or with `-1` the previous) period in [`ReportPeriod::ALL`], wrapping around.
+    pub fn cycle(self, step: isize) -> ReportPeriod {
+        let len = Self::ALL.len() as isize;
+        let i = Self::ALL.iter().position(|p| *p == self).unwrap_or(0) as isize;
+        Self::ALL[(i + step).rem_euclid(len) as usize]
+    }
+
+    /// The first day of the period and its end, `None` if it lasts until now.
+    pub fn range(self, today: NaiveDate) -> (NaiveDate, Option<NaiveDate>) {
+        let month_start = |year, month| NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(today);
+        let this_month = month_start(today.year(), today.month());
+        let quarter_month = (today.month() - 1) / 3 * 3 + 1;
+        let this_quarter = month_start(today.year(), quarter_month);
+        match self {
+            ReportPeriod::ThisMonth => (this_month, None),
+            ReportPeriod::LastMonth => {
+                let last = this_month - Duration::days(1);
+                (month_start(last.year(), last.month()), Some(this_month))
+            }
+            ReportPeriod::ThisQuarter => (this_quarter, None),
+            ReportPeriod::LastQuarter => {
+                let last = this_quarter - Duration::days(1);
+                let month = (last.month() - 1) / 3 * 3 + 1;
+                (month_start(last.year(), month), Some(this_quarter))
+            }
+            ReportPeriod::ThisYear => (month_start(today.year(), 1), None),
+            ReportPeriod::Last30Days => (today - Duration::days(30), None),
+        }
+    }
+}
+
+/// CPU hours used by an account, or by one of its users.
+pub struct Utilization {
+    pub account: String,
+    /// `None` for the account as a whole.
+    pub user: Option<String>,
+    pub name: String,
+    pub core_hours: f64,
+}
+
+/// The utilization of my accounts (of all accounts without sacctmgr) and their users in the
+/// period, accounts by usage and their users below them by usage.
+fn account_utilization(period: ReportPeriod, sacctmgr: bool) -> Result<Vec<Utilization>, String> {
+    let (start, end) = period.range(Local::now().date_naive());
+    let mut command = slurm_command("sreport");
+    command
+        .args([
+            "--noheader",
+            "--parsable2",
+            "cluster",
+            "AccountUtilizationByUser",
+        ])
+        .arg(format!("Start={}", start.format("%Y-%m-%d")))
+        .arg(format!(
+            "End={}",
+            end.map_or("now".to_string(), |e| e.format("%Y-%m-%d").to_string())
+        ))
+        .args([
+            "--tres=cpu",
+            "-t",
+            "Hours",
+            "Format=Accounts,Login,Proper,Used",
+        ]);
+    if sacctmgr {
+        let mut accounts = sacctmgr::user_associations()
+            .map_err(|e| format!("Failed to list my accounts: {}", e))?
+            .into_iter()
+            .map(|a| a.account)
+            .collect::<Vec<_>>();
+        accounts.sort();
+        accounts.dedup();
+        if !accounts.is_empty() {
+            command.arg(format!("Accounts={}", accounts.join(",")));
+        }
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to execute sreport: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "sreport failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // lines of several clusters are summed up
+    let mut rows: Vec<Utilization> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let [account, user, name, used] = line.split('|').collect::<Vec<_>>()[..] else {
+            continue;
+        };
+        let Ok(core_hours) = used.trim().parse::<f64>() else {
+            continue;
+        };
+        let account = account.trim();
+        let user = Some(user.trim()).filter(|u| !u.is_empty());
+        match rows
+            .iter_mut()
+            .find(|r| r.account == account && r.user.as_deref() == user)
+        {
+            Some(row) => row.core_hours += core_hours,
+            None => rows.push(Utilization {
+                account: account.to_string(),
+                user: user.map(str::to_string),
+                name: name.trim().to_string(),
+                core_hours,
+            }),
+        }
+    }
+    let totals = rows
+        .iter()
+        .filter(|r| r.user.is_none())
+        .map(|r| (r.account.clone(), r.core_hours))
+        .collect::<HashMap<_, _>>();
+    let total = |r: &Utilization| totals.get(&r.account).copied().unwrap_or_default();
+    rows.sort_by(|a, b| {
+        total(b)
+            .total_cmp(&total(a))
+            .then_with(|| a.account.cmp(&b.account))
+            .then_with(|| a.user.is_some().cmp(&b.user.is_some()))
+            .then_with(|| b.core_hours.total_cmp(&a.core_hours))
+    });
+    Ok(rows)
+}
+
+struct ReportRequest {
+    period: ReportPeriod,
+    sacctmgr: bool,
+}
+
+/// Runs `sreport` in the background, which can take a while for long periods.
+struct UsageReporter {
+    app: Sender<AppMessage>,
+    receiver: Receiver<ReportRequest>,
+}
+
+impl UsageReporter {
+    fn run(&mut self) {
+        while let Ok(mut request) = self.receiver.recv() {
+            // only the last selected period is shown
+            while let Ok(newer) = self.receiver.try_recv() {
+                request = newer;
+            }
+            let report = account_utilization(request.period, request.sacctmgr);
+            if self
+                .app
+                .send(AppMessage::UsageReport(request.period, report))
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+pub struct UsageReportHandle {
+    sender: Sender<ReportRequest>,
+}
+
+impl UsageReportHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = UsageReporter { app, receiver };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Reports the utilization of my accounts by user in the period, answered with
+    /// [`AppMessage::UsageReport`].
+    pub fn request(&self, period: ReportPeriod, sacctmgr: bool) {
+        let _ = self.sender.send(ReportRequest { period, sacctmgr });
+    }
+}
@@ -0,0 +1,1092 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::NaiveDateTime;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Margin, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, StatefulWidget, Widget, Wrap},
+};
+
+use crate::app::{process_terminal_output, Job, OutputFileView, ScrollAnchor};
+use crate::budget::{format_core_hours, BudgetStatus};
+use crate::config::Column;
+use crate::file_watcher::{JobOutput, OutputSize, HEX_PREVIEW_BYTES};
+use crate::hostlist;
+use crate::job_list::JobListRow;
+use crate::links::Hyperlink;
+use crate::log_diagnosis::Diagnosis;
+use crate::preemption::Outcome;
+use crate::progress::Progress;
+use crate::ranks;
+use crate::slurm_time::{format_duration, format_relative, format_slurm_duration};
+use crate::sparkline::{sparkline, QueueDepth};
+use crate::states;
+use crate::timeline::Event;
+use crate::tres::Tres;
+
+/// Output growth (bytes per second) above which the job is likely logging out of control.
+const RUNAWAY_OUTPUT_RATE: f64 = 5_000_000.0;
+
+/// A color for the user that stays the same across runs, to tell users apart at a glance.
+pub fn user_color(user: &str) -> Color {
+    const COLORS: [Color; 8] = [
+        Color::Cyan,
+        Color::Magenta,
+        Color::Yellow,
+        Color::LightBlue,
+        Color::LightGreen,
+        Color::LightRed,
+        Color::LightMagenta,
+        Color::LightCyan,
+    ];
+    // FNV-1a, since the std hashers may change between Rust versions
+    let hash = user.bytes().fold(0x811c9dc5u32, |hash, b| {
+        (hash ^ b as u32).wrapping_mul(0x01000193)
+    });
+    COLORS[hash as usize % COLORS.len()]
+}
+
+/// A compact progress bar with percentage and, once there is some progress, the estimated time
+/// until the job is done, extrapolated from its run time so far.
+pub fn progress_bar(progress: &Progress, time_used: Option<chrono::Duration>) -> String {
+    const WIDTH: usize = 8;
+    let fraction = progress.fraction();
+    let filled = (fraction * WIDTH as f64).round() as usize;
+    let eta = time_used
+        .filter(|_| progress.current > 0.0 && fraction < 1.0)
+        .map(|used| {
+            let left =
+                used.num_seconds() as f64 * (progress.total - progress.current) / progress.current;
+            format!(
+                " ~{}",
+                format_duration(chrono::Duration::seconds(left as i64))
+            )
+        })
+        .unwrap_or_default();
+    format!(
+        "{}{} {:>3.0}%{} ",
+        "█".repeat(filled),
+        "░".repeat(WIDTH - filled),
+        fraction * 100.0,
+        eta
+    )
+}
+
+/// What the summary line at the top shows.
+pub struct SummaryView<'a> {
+    pub jobs: &'a [Job],
+    pub now: NaiveDateTime,
+    pub budgets: &'a [BudgetStatus],
+    /// Queue depths over time, e.g. of my jobs and of all jobs.
+    pub histories: Vec<(&'static str, Vec<QueueDepth>)>,
+}
+
+pub fn summary_line(view: &SummaryView) -> Line<'static> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for j in view.jobs {
+        match counts.iter_mut().find(|(s, _)| j.state_compact == *s) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((&j.state_compact, 1)),
+        }
+    }
+    counts.sort_by_key(|(s, _)| (states::order(s), *s));
+
+    let running = view.jobs.iter().filter(|j| j.state == "RUNNING");
+    let (cpus, gpus) = running.fold((0, 0), |(cpus, gpus), j| {
+        let tres = Tres::parse(&j.tres);
+        (cpus + tres.cpus(), gpus + tres.gpus())
+    });
+
+    let now = view.now;
+    let next_start = view
+        .jobs
+        .iter()
+        .filter(|j| j.state == "PENDING")
+        .filter_map(|j| j.start_time)
+        .filter(|&t| t > now)
+        .min();
+
+    let dim = Style::default().add_modifier(Modifier::DIM);
+    let mut spans = vec![];
+    for (i, (state, count)) in counts.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::styled(" / ", dim));
+        }
+        spans.push(Span::styled(
+            format!("{} {}", count, state),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+    }
+    if counts.is_empty() {
+        spans.push(Span::styled("no jobs", dim));
+    }
+    spans.push(Span::styled(" | ", dim));
+    spans.push(Span::raw(format!("{} CPUs", cpus)));
+    if gpus > 0 {
+        spans.push(Span::raw(format!(", {} GPUs", gpus)));
+    }
+    spans.push(Span::styled(" allocated", dim));
+    if let Some(t) = next_start {
+        spans.push(Span::styled(" | est. next start ", dim));
+        spans.push(Span::raw(if t.date() == now.date() {
+            t.format("%H:%M").to_string()
+        } else {
+            t.format("%Y-%m-%d %H:%M").to_string()
+        }));
+    }
+
+    for status in view.budgets {
+        spans.push(Span::styled(format!(" | {} ", status.budget.account), dim));
+        match (&status.used, status.percent()) {
+            (Ok(used), Some(percent)) => {
+                spans.push(Span::styled(
+                    format!("{:.0}%", percent),
+                    match status.reached() {
+                        Some(r) if r >= 100.0 => Style::default().fg(Color::Red),
+                        Some(_) => Style::default().fg(Color::Yellow),
+                        None => Style::default().fg(Color::Green),
+                    },
+                ));
+                spans.push(Span::styled(
+                    format!(
+                        " of {} core-h, {} left",
+                        format_core_hours(status.budget.core_hours),
+                        format_core_hours((status.budget.core_hours - used).max(0.0))
+                    ),
+                    dim,
+                ));
+            }
+            _ => spans.push(Span::styled("budget unknown", dim)),
+        }
+    }
+
+    for (label, history) in &view.histories {
+        if history.len() < 2 {
+            continue;
+        }
+        spans.push(Span::styled(format!(" | {} R ", label), dim));
+        spans.push(Span::styled(
+            sparkline(history.iter().map(|d| d.running)),
+            Style::default().fg(Color::Green),
+        ));
+        spans.push(Span::styled(" PD ", dim));
+        spans.push(Span::styled(
+            sparkline(history.iter().map(|d| d.pending)),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    Line::from(spans)
+}
+
+/// A job in the job list, with its columns already formatted.
+pub struct JobRow {
+    pub id: String,
+    pub state: String,
+    pub qos: String,
+    pub user: String,
+    pub time_used: String,
+    pub time_left: Option<chrono::Duration>,
+    /// What happens to the job when it is preempted, if it may be.
+    pub preemption: Option<Outcome>,
+    /// The state is highlighted, e.g. for a dependency that can never be satisfied.
+    pub alert: bool,
+    pub script_values: Vec<String>,
+    pub progress: Option<String>,
+    pub name: String,
+    pub tags: Vec<String>,
+    pub pinned: bool,
+}
+
+/// What the job list shows: the jobs, and the rows they are arranged in.
+pub struct JobListView<'a> {
+    pub columns: &'a [Column],
+    pub jobs: Vec<JobRow>,
+    pub rows: &'a [JobListRow],
+    pub me: &'a str,
+    /// Jobs are grouped by user in the team view.
+    pub team: bool,
+    /// Users are told apart by color when several are shown.
+    pub user_colors: bool,
+    pub focused: bool,
+}
+
+pub fn render_job_list(view: &JobListView, area: Rect, buf: &mut Buffer, state: &mut ListState) {
+    let jobs = &view.jobs;
+    let width = |f: fn(&JobRow) -> usize| jobs.iter().map(f).max().unwrap_or(0);
+    let max_id_len = width(|j| j.id.len());
+    let max_user_len = width(|j| j.user.len());
+    let max_qos_len = width(|j| j.qos.len());
+    let max_time_len = width(|j| j.time_used.len());
+    let time_left_str = jobs
+        .iter()
+        .map(|j| j.time_left.map(format_slurm_duration).unwrap_or_default())
+        .collect::<Vec<_>>();
+    let max_time_left_len = time_left_str.iter().map(|t| t.len()).max().unwrap_or(0);
+    let max_state_compact_len = width(|j| j.state.len());
+    let max_preempt_len = width(|j| j.preemption.map_or(0, |p| p.label().len()));
+    let script_widths = (0..width(|j| j.script_values.len()))
+        .map(|i| {
+            jobs.iter()
+                .map(|j| j.script_values.get(i).map_or(0, |v| v.chars().count()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+    // users by number of jobs, told apart by color when several are shown
+    let mut users = jobs.iter().fold(HashMap::new(), |mut users, j| {
+        *users.entry(j.user.as_str()).or_insert(0) += 1;
+        users
+    });
+    let color_users = view.team || (view.user_colors && users.len() > 1);
+    let user_style = |user: &str| match (color_users, user == view.me) {
+        (false, _) => Style::default().fg(Color::Green),
+        (true, true) => Style::default().add_modifier(Modifier::BOLD),
+        (true, false) => Style::default().fg(user_color(user)),
+    };
+    let job_lines: Vec<Line> = jobs
+        .iter()
+        .zip(time_left_str.iter())
+        .map(|(j, left_str)| {
+            let mut spans = Vec::new();
+            for column in view.columns {
+                // only shown once a job may be preempted
+                if *column == Column::Preempt && max_preempt_len == 0 {
+                    continue;
+                }
+                spans.push(match column {
+                    Column::State => Span::styled(
+                        format!("{:<max$.max$}", j.state, max = max_state_compact_len),
+                        if j.alert {
+                            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+                        } else {
+                            Style::default().fg(states::color(&j.state))
+                        },
+                    ),
+                    Column::Id => Span::styled(
+                        format!("{:<max$.max$}", j.id, max = max_id_len),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    Column::Qos => Span::styled(
+                        format!("{:<max$.max$}", j.qos, max = max_qos_len),
+                        Style::default().fg(Color::Blue),
+                    ),
+                    Column::User => Span::styled(
+                        format!("{:<max$.max$}", j.user, max = max_user_len),
+                        user_style(&j.user),
+                    ),
+                    Column::TimeUsed => Span::styled(
+                        format!("{:>max$.max$}", j.time_used, max = max_time_len),
+                        Style::default().fg(Color::Red),
+                    ),
+                    Column::Preempt => Span::styled(
+                        format!(
+                            "{:<max$}",
+                            j.preemption.map_or("", |p| p.label()),
+                            max = max_preempt_len
+                        ),
+                        match j.preemption {
+                            Some(Outcome::Cancel) => Style::default().fg(Color::Red),
+                            _ => Style::default().fg(Color::LightRed),
+                        },
+                    ),
+                    Column::TimeLeft => Span::styled(
+                        format!("{:>max$.max$}", left_str, max = max_time_left_len),
+                        if j.time_left
+                            .is_some_and(|l| l < chrono::Duration::minutes(15))
+                        {
+                            Style::default()
+                                .fg(Color::LightRed)
+                                .add_modifier(Modifier::BOLD)
+                        } else {
+                            Style::default().fg(Color::Magenta)
+                        },
+                    ),
+                });
+                spans.push(Span::raw(" "));
+            }
+            for (value, width) in j.script_values.iter().zip(&script_widths) {
+                spans.push(Span::styled(
+                    format!("{:<w$} ", value, w = width),
+                    Style::default().fg(Color::LightCyan),
+                ));
+            }
+            if let Some(progress) = &j.progress {
+                spans.push(Span::styled(
+                    progress.as_str(),
+                    Style::default().fg(Color::LightGreen),
+                ));
+            }
+            spans.push(Span::styled(
+                j.name.as_str(),
+                match color_users && j.user != view.me {
+                    true => Style::default().fg(user_color(&j.user)),
+                    false => Style::default(),
+                },
+            ));
+            if !j.tags.is_empty() {
+                spans.push(Span::styled(
+                    format!(" [{}]", j.tags.join(",")),
+                    Style::default().fg(Color::Cyan),
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    let grouped: HashSet<usize> = view
+        .rows
+        .iter()
+        .filter_map(|r| match r {
+            JobListRow::Group(g) => Some(g.jobs.iter().copied()),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    let items: Vec<ListItem> = view
+        .rows
+        .iter()
+        .map(|row| match row {
+            JobListRow::Job(i) => {
+                let mut line = job_lines[*i].clone();
+                if grouped.contains(i) {
+                    line.spans.insert(0, Span::raw("  "));
+                } else if jobs[*i].pinned {
+                    line.spans
+                        .insert(0, Span::styled("★ ", Style::default().fg(Color::Yellow)));
+                }
+                ListItem::new(line)
+            }
+            JobListRow::Group(g) => {
+                let counts = g
+                    .jobs
+                    .iter()
+                    .fold(BTreeMap::new(), |mut counts, &i| {
+                        *counts.entry(jobs[i].state.as_str()).or_insert(0) += 1;
+                        counts
+                    })
+                    .iter()
+                    .map(|(state, count)| format!("{} {}", count, state))
+                    .collect::<Vec<_>>()
+                    .join(" / ");
+                ListItem::new(Line::from(vec![
+                    Span::raw(if g.collapsed { "▸ " } else { "▾ " }),
+                    Span::styled(
+                        g.key.as_str(),
+                        match view.team {
+                            true => user_style(&g.key),
+                            false => Style::default(),
+                        }
+                        .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(format!(" ({}) ", g.jobs.len())),
+                    Span::styled(counts, Style::default().add_modifier(Modifier::DIM)),
+                ]))
+            }
+        })
+        .collect();
+    let mut legend = Vec::new();
+    if color_users {
+        let mut users = users.drain().collect::<Vec<_>>();
+        users.sort_by(|(a, a_jobs), (b, b_jobs)| b_jobs.cmp(a_jobs).then(a.cmp(b)));
+        let mut width = 0;
+        let max_width = area.width.saturating_sub(4) as usize;
+        for (i, (user, _)) in users.iter().enumerate() {
+            let label = format!("■ {} ", user);
+            let more = format!("+{} more", users.len() - i);
+            if width + label.chars().count() + more.len() > max_width && i + 1 < users.len() {
+                legend.push(Span::styled(
+                    more,
+                    Style::default().add_modifier(Modifier::DIM),
+                ));
+                break;
+            }
+            width += label.chars().count();
+            legend.push(Span::styled(label, user_style(user)));
+        }
+    }
+    let job_list = List::new(items)
+        .block(
+            Block::default()
+                .title(format!("Jobs ({})", jobs.len()))
+                .title_bottom(Line::from(legend))
+                .borders(Borders::ALL)
+                .border_style(match view.focused {
+                    true => Style::default().fg(Color::Green),
+                    false => Style::default(),
+                }),
+        )
+        .highlight_style(Style::default().bg(Color::Green).fg(Color::Black));
+    StatefulWidget::render(job_list, area, buf, state);
+}
+
+/// A line of the details, e.g. `Name      train`.
+pub struct Field<'a> {
+    pub label: String,
+    pub value: Vec<Span<'a>>,
+    /// Written as a hyperlink over the value.
+    pub link: Option<String>,
+}
+
+impl<'a> Field<'a> {
+    pub fn new(label: impl Into<String>, value: Vec<Span<'a>>) -> Self {
+        Field {
+            label: label.into(),
+            value,
+            link: None,
+        }
+    }
+}
+
+/// The details every job has, from its state to its output file.
+pub fn job_fields<'a>(
+    job: &'a Job,
+    now: NaiveDateTime,
+    stream: &OutputFileView,
+    size: Option<OutputSize>,
+) -> Vec<Field<'a>> {
+    let dim = Style::default().add_modifier(Modifier::DIM);
+    let state = Field::new(
+        "State",
+        vec![
+            Span::raw(job.state.as_str()),
+            match job.reason.as_deref() {
+                Some(s) => Span::styled(format!(" ({s})"), dim),
+                None => Span::raw(""),
+            },
+            match job.restarts {
+                0 => Span::raw(""),
+                1 => Span::styled(" restarted once", Style::default().fg(Color::Yellow)),
+                n => Span::styled(
+                    format!(" restarted {n} times"),
+                    Style::default().fg(Color::Yellow),
+                ),
+            },
+        ],
+    );
+    let (stream_label, path) = match stream {
+        OutputFileView::Stdout => ("stdout", &job.stdout),
+        OutputFileView::Stderr => ("stderr", &job.stderr),
+    };
+    let output = Field::new(
+        stream_label,
+        vec![
+            Span::raw(
+                path.as_ref()
+                    .map(|p| p.to_str().unwrap_or_default())
+                    .unwrap_or_default(),
+            ),
+            match size {
+                Some(size) if size.rate > RUNAWAY_OUTPUT_RATE => Span::styled(
+                    format!(" ({size}, runaway output?)"),
+                    Style::default().fg(Color::Red),
+                ),
+                Some(size) => Span::styled(format!(" ({size})"), dim),
+                None => Span::raw(""),
+            },
+        ],
+    );
+
+    let timestamp = |t: NaiveDateTime, extra: Option<String>| {
+        let relative = format_relative(t, now);
+        let relative = match extra {
+            Some(extra) => format!("{relative}, {extra}"),
+            None => relative,
+        };
+        vec![
+            Span::raw(t.format("%Y-%m-%d %H:%M:%S").to_string()),
+            Span::styled(format!(" ({relative})"), dim),
+        ]
+    };
+    let started = job.started_at(now).is_some();
+    let wait = job.queue_wait(now).map(|d| {
+        format!(
+            "{} {}",
+            if started { "waited" } else { "waiting" },
+            format_duration(d)
+        )
+    });
+    let run = job.run_time(now).map(|d| {
+        format!(
+            "{} {}",
+            if job.end_time.is_some_and(|t| t <= now) {
+                "ran"
+            } else {
+                "running"
+            },
+            format_duration(d)
+        )
+    });
+
+    let hosts = hostlist::expand(&job.nodelist);
+    let mut nodes = Vec::new();
+    if hosts.len() > 1 {
+        nodes.push(Span::styled(format!("{} nodes ", hosts.len()), dim));
+    }
+    nodes.push(Span::raw(hosts.join(" ")));
+
+    vec![
+        state,
+        Field::new("Name", vec![Span::raw(&job.name)]),
+        Field::new("Command", vec![Span::raw(&job.command)]),
+        Field::new("Nodes", nodes),
+        Field::new("TRES", vec![Span::raw(&job.tres)]),
+        Field::new("Partition", vec![Span::raw(job.partition.as_str())]),
+        Field::new(
+            "Submit",
+            job.submit_time
+                .map(|t| timestamp(t, None))
+                .unwrap_or_default(),
+        ),
+        Field::new(
+            "Start",
+            match job.start_time {
+                Some(t) => timestamp(t, wait),
+                None => vec![Span::styled(
+                    wait.map(|w| format!("({w})")).unwrap_or_default(),
+                    dim,
+                )],
+            },
+        ),
+        Field::new(
+            "End",
+            job.end_time.map(|t| timestamp(t, run)).unwrap_or_default(),
+        ),
+        output,
+    ]
+}
+
+/// The events of a job in one line, e.g. `08:00 submitted → 09:00 started`.
+pub fn events_field(events: &[Event], now: NaiveDateTime) -> Option<Field<'static>> {
+    if events.is_empty() {
+        return None;
+    }
+    let mut timeline = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        if i > 0 {
+            timeline.push(Span::styled(
+                " → ",
+                Style::default().add_modifier(Modifier::DIM),
+            ));
+        }
+        let format = if event.time.date() == now.date() {
+            "%H:%M"
+        } else {
+            "%m-%d %H:%M"
+        };
+        timeline.push(Span::styled(
+            event.time.format(format).to_string(),
+            Style::default().add_modifier(Modifier::DIM),
+        ));
+        timeline.push(Span::raw(" "));
+        timeline.push(Span::styled(
+            event.label.clone(),
+            match event.label.as_str() {
+                "submitted" | "started" | "completed" => Style::default(),
+                "requeued" | "preempted" | "suspended" => Style::default().fg(Color::Yellow),
+                _ => Style::default().fg(Color::Red),
+            },
+        ));
+    }
+    Some(Field::new("Events", timeline))
+}
+
+/// Height of the details pane with its borders.
+pub fn details_height(fields: &[Field]) -> u16 {
+    fields.len() as u16 + 2
+}
+
+/// Renders the details with their labels, and returns where the links go.
+pub fn render_details(fields: &[Field], area: Rect, buf: &mut Buffer) -> Vec<Hyperlink> {
+    let lines = fields
+        .iter()
+        .map(|field| {
+            let mut spans = vec![
+                Span::styled(
+                    format!("{:<9}", field.label),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::raw(" "),
+            ];
+            spans.extend(field.value.iter().cloned());
+            Line::from(spans)
+        })
+        .collect::<Vec<_>>();
+    Paragraph::new(lines)
+        .block(Block::default().title("Details").borders(Borders::ALL))
+        .render(area, buf);
+
+    let inner = area.inner(&Margin::new(1, 1));
+    // after the label
+    let link_x = inner.x + 10;
+    fields
+        .iter()
+        .enumerate()
+        .filter_map(|(line, field)| Some((line, field.link.clone()?)))
+        .filter(|(line, _)| (*line as u16) < inner.height && link_x < inner.right())
+        .map(|(line, url)| Hyperlink {
+            x: link_x,
+            y: inner.y + line as u16,
+            text: url
+                .chars()
+                .take((inner.right() - link_x) as usize)
+                .collect(),
+            url,
+        })
+        .collect()
+}
+
+/// What the log pane shows: the end or the beginning of the output, scrolled by `offset`.
+pub struct LogView<'a> {
+    pub stream: &'a OutputFileView,
+    pub anchor: ScrollAnchor,
+    pub offset: usize,
+    pub output: Result<&'a JobOutput, String>,
+    pub focused: bool,
+    /// Only the lines of this rank of `srun --label` output.
+    pub rank: Option<u32>,
+    /// The step whose output is shown instead of the output of the job.
+    pub step: Option<&'a str>,
+    /// Why the log cannot be opened, once it was checked.
+    pub diagnosis: Option<&'a Diagnosis>,
+}
+
+pub fn string_for_paragraph(s: &str, lines: usize, anchor: ScrollAnchor, offset: usize) -> String {
+    // skip everything after last line delimiter
+    let s = s.rsplit_once(['\r', '\n']).map_or(s, |(p, _)| p);
+
+    let l = process_terminal_output(s);
+    let l = match anchor {
+        ScrollAnchor::Top => l
+            .iter()
+            .skip(offset)
+            .take(lines)
+            .map(|l| l.chars().collect::<String>())
+            .collect::<Vec<_>>(),
+        ScrollAnchor::Bottom => l
+            .iter()
+            .rev()
+            .skip(offset)
+            .take(lines)
+            .map(|l| l.chars().collect::<String>())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect::<Vec<_>>(),
+    };
+    l.join("\n")
+}
+
+pub fn render_log(view: &LogView, area: Rect, buf: &mut Buffer) {
+    let title = Line::from(vec![
+        Span::raw(match view.stream {
+            OutputFileView::Stdout => "stdout",
+            OutputFileView::Stderr => "stderr",
+        }),
+        match view.step {
+            Some(step) => Span::raw(format!(" of step {}", step)),
+            None => Span::raw(""),
+        },
+        Span::styled(
+            match view.anchor {
+                ScrollAnchor::Top if view.offset == 0 => "[T]".to_string(),
+                ScrollAnchor::Top => format!("[T+{}]", view.offset),
+                ScrollAnchor::Bottom if view.offset == 0 => "".to_string(),
+                ScrollAnchor::Bottom => format!("[B-{}]", view.offset),
+            },
+            Style::default().add_modifier(Modifier::DIM),
+        ),
+        match view.rank {
+            Some(rank) => Span::styled(
+                format!("[rank {}]", rank),
+                Style::default().fg(ranks::rank_color(rank)),
+            ),
+            None => Span::raw(""),
+        },
+        if view.output.as_ref().is_ok_and(|o| o.binary) {
+            Span::styled(
+                format!(
+                    " binary data, showing the last {} KiB as hex ",
+                    HEX_PREVIEW_BYTES / 1024
+                ),
+                Style::default().fg(Color::Black).bg(Color::Yellow),
+            )
+        } else {
+            Span::raw("")
+        },
+    ]);
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(match view.focused {
+            true => Style::default().fg(Color::Green),
+            false => Style::default(),
+        });
+
+    match &view.output {
+        Ok(output) => {
+            let filtered;
+            let content = match view.rank {
+                Some(rank) if !output.binary => {
+                    filtered = ranks::filter(&output.content, rank);
+                    &filtered
+                }
+                _ => &output.content,
+            };
+            let text = string_for_paragraph(
+                content,
+                block.inner(area).height as usize,
+                view.anchor,
+                view.offset,
+            );
+            // interleaved `srun --label` output is easier to follow with a color per rank
+            let lines = text
+                .split('\n')
+                .map(|line| {
+                    let style = match ranks::rank_of(line).filter(|_| !output.binary) {
+                        Some(rank) => Style::default().fg(ranks::rank_color(rank)),
+                        None => Style::default(),
+                    };
+                    Line::styled(line.to_string(), style)
+                })
+                .collect::<Vec<_>>();
+            Paragraph::new(lines)
+        }
+        Err(e) => match view.diagnosis {
+            Some(diagnosis) => Paragraph::new(diagnosis_lines(e, diagnosis)),
+            None => Paragraph::new(e.as_str()).style(Style::default().fg(Color::Red)),
+        }
+        .wrap(Wrap { trim: true }),
+    }
+    .block(block)
+    .render(area, buf);
+}
+
+/// What was tried to open a log that cannot be opened, one check per line.
+fn diagnosis_lines<'a>(error: &'a str, diagnosis: &'a Diagnosis) -> Vec<Line<'a>> {
+    let red = Style::default().fg(Color::Red);
+    let line = |label: &'a str, value: Vec<Span<'a>>| {
+        let mut spans = vec![Span::styled(
+            format!("{:<9} ", label),
+            Style::default().fg(Color::Yellow),
+        )];
+        spans.extend(value);
+        Line::from(spans)
+    };
+    let check = |ok: Option<bool>, yes: &'a str, no: &'a str, mode: &'a Option<String>| {
+        let mut spans = vec![match ok {
+            Some(true) => Span::raw(yes),
+            Some(false) => Span::styled(no, red),
+            None => Span::styled("unknown", Style::default().add_modifier(Modifier::DIM)),
+        }];
+        if let Some(mode) = mode {
+            spans.push(Span::raw(format!(" ({})", mode)));
+        }
+        spans
+    };
+    let query = &diagnosis.query;
+    let mut lines = vec![
+        Line::styled(error, red),
+        line(
+            "Pattern",
+            vec![match &query.pattern {
+                Some(pattern) => Span::raw(pattern.as_str()),
+                None => Span::styled(
+                    "not reported by squeue",
+                    Style::default().add_modifier(Modifier::DIM),
+                ),
+            }],
+        ),
+        line(
+            "Path",
+            vec![Span::raw(query.path.to_string_lossy().into_owned())],
+        ),
+        line(
+            "Directory",
+            match diagnosis.dir_exists {
+                Some(true) => check(
+                    diagnosis.dir_accessible,
+                    "exists",
+                    "exists, not accessible",
+                    &diagnosis.dir_mode,
+                ),
+                exists => check(exists, "exists", "missing", &None),
+            },
+        ),
+        line(
+            "File",
+            match diagnosis.file_exists {
+                Some(true) => check(
+                    diagnosis.file_readable,
+                    "exists, readable",
+                    "exists, not readable",
+                    &diagnosis.file_mode,
+                ),
+                exists => check(exists, "exists", "missing", &None),
+            },
+        ),
+        line(
+            "Storage",
+            vec![Span::raw(match (query.node_local, &query.node) {
+                (true, Some(node)) => format!("node-local, read on {}", node),
+                (true, None) => "node-local".to_string(),
+                (false, _) => "shared".to_string(),
+            })],
+        ),
+    ];
+    if let Some(error) = &diagnosis.error {
+        lines.push(line("Checks", vec![Span::styled(error.as_str(), red)]));
+    }
+    if let Some(hint) = diagnosis.hint() {
+        lines.push(line("Hint", vec![Span::raw(hint)]));
+    }
+    lines
+}
+
+/// Snapshots of the panes with synthetic jobs, in `src/snapshots`. After an intended change of
+/// the UI, review them with `cargo insta review` or accept them with `INSTA_UPDATE=always`.
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use insta::assert_snapshot;
+    use regex::Regex;
+
+    use super::*;
+    use crate::budget::{Budget, Period};
+    use crate::config::GroupRule;
+    use crate::heatmap::heatmap_lines;
+    use crate::job_list::build_rows;
+    use crate::nodes::Node;
+    use crate::snapshot::to_text;
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 1)
+            .and_then(|d| d.and_hms_opt(hour, minute, 0))
+            .unwrap()
+    }
+
+    fn jobs() -> Vec<Job> {
+        vec![
+            Job::fixture("101", "train", "RUNNING", "alice"),
+            Job::fixture("102", "sweep_lr1", "RUNNING", "alice"),
+            Job::fixture("103", "sweep_lr2", "PENDING", "alice"),
+            Job::fixture("104", "eval", "PENDING", "bob"),
+        ]
+    }
+
+    fn render(width: u16, height: u16, f: impl FnOnce(Rect, &mut Buffer)) -> String {
+        let area = Rect::new(0, 0, width, height);
+        let mut buf = Buffer::empty(area);
+        f(area, &mut buf);
+        to_text(&buf)
+    }
+
+    fn job_list(jobs: &[Job], rows: &[JobListRow], team: bool) -> String {
+        let view = JobListView {
+            columns: &Column::ALL,
+            jobs: jobs
+                .iter()
+                .map(|j| JobRow {
+                    id: j.id(),
+                    state: j.state_compact.to_string(),
+                    qos: j.qos.to_string(),
+                    user: j.user.to_string(),
+                    time_used: j.time_used_display(std::time::Duration::ZERO),
+                    time_left: j.time_left(std::time::Duration::ZERO),
+                    preemption: None,
+                    alert: false,
+                    script_values: Vec::new(),
+                    progress: None,
+                    name: j.name.clone(),
+                    tags: match j.job_id.as_str() {
+                        "101" => vec!["baseline".to_string()],
+                        _ => Vec::new(),
+                    },
+                    pinned: j.job_id == "101",
+                })
+                .collect(),
+            rows,
+            me: "alice",
+            team,
+            user_colors: true,
+            focused: true,
+        };
+        let mut state = ListState::default();
+        state.select(Some(0));
+        render(70, 9, |area, buf| {
+            render_job_list(&view, area, buf, &mut state)
+        })
+    }
+
+    #[test]
+    fn job_list_with_groups() {
+        let jobs = jobs();
+        let rules = [GroupRule {
+            regex: Regex::new("^(sweep)_").unwrap(),
+            name: None,
+        }];
+        let rows = build_rows(&jobs, &rules, &HashSet::new(), false, |j| j.job_id == "101");
+        assert_snapshot!(job_list(&jobs, &rows, false));
+    }
+
+    #[test]
+    fn job_list_of_team() {
+        let jobs = jobs();
+        let rows = build_rows(&jobs, &[], &HashSet::new(), true, |_| false);
+        assert_snapshot!(job_list(&jobs, &rows, true));
+    }
+
+    #[test]
+    fn details_of_running_job() {
+        let job = Job::fixture("101", "train", "RUNNING", "alice");
+        let mut fields = job_fields(
+            &job,
+            at(12, 0),
+            &OutputFileView::Stdout,
+            Some(OutputSize {
+                bytes: 2048,
+                rate: 0.0,
+            }),
+        );
+        let events = [
+            Event {
+                time: at(8, 0),
+                label: "submitted".to_string(),
+            },
+            Event {
+                time: at(9, 0),
+                label: "started".to_string(),
+            },
+        ];
+        fields.extend(events_field(&events, at(12, 0)));
+        let mut run = Field::new("Run", vec![Span::raw("https://wandb.ai/runs/101")]);
+        run.link = Some("https://wandb.ai/runs/101".to_string());
+        fields.push(run);
+        let mut links = Vec::new();
+        let text = render(80, details_height(&fields), |area, buf| {
+            links = render_details(&fields, area, buf);
+        });
+        assert_snapshot!(text);
+        assert_eq!(links.len(), 1);
+        assert_eq!((links[0].x, links[0].y), (11, 12));
+    }
+
+    #[test]
+    fn details_of_row_below_group_header() {
+        let jobs = jobs();
+        let rules = [GroupRule {
+            regex: Regex::new("^(sweep)_").unwrap(),
+            name: None,
+        }];
+        // eval is pinned, then train, the sweep header and its members
+        let rows = build_rows(&jobs, &rules, &HashSet::new(), false, |j| j.job_id == "104");
+        let job = rows[3].job(&jobs).unwrap();
+        let fields = job_fields(job, at(12, 0), &OutputFileView::Stdout, None);
+        let text = render(80, details_height(&fields), |area, buf| {
+            render_details(&fields, area, buf);
+        });
+        assert!(text.contains("sweep_lr1"), "{text}");
+        assert!(!text.contains("eval"), "{text}");
+    }
+
+    #[test]
+    fn details_of_pending_job() {
+        let job = Job::fixture("103", "sweep_lr2", "PENDING", "alice");
+        let fields = job_fields(&job, at(12, 0), &OutputFileView::Stderr, None);
+        assert_snapshot!(render(80, details_height(&fields), |area, buf| {
+            render_details(&fields, area, buf);
+        }));
+    }
+
+    fn log(anchor: ScrollAnchor, offset: usize, output: Result<&JobOutput, String>) -> String {
+        let view = LogView {
+            stream: &OutputFileView::Stdout,
+            anchor,
+            offset,
+            output,
+            focused: false,
+            rank: None,
+            step: None,
+            diagnosis: None,
+        };
+        render(40, 6, |area, buf| render_log(&view, area, buf))
+    }
+
+    fn output() -> JobOutput {
+        JobOutput {
+            content: (1..=10)
+                .map(|i| format!("epoch {i} loss 0.{:02}\n", 100 - 9 * i))
+                .collect::<String>()
+                + "epoch 11 (progress)\r50%\rnot yet",
+            size: None,
+            binary: false,
+        }
+    }
+
+    #[test]
+    fn log_tail() {
+        assert_snapshot!(log(ScrollAnchor::Bottom, 0, Ok(&output())));
+    }
+
+    #[test]
+    fn log_scrolled() {
+        assert_snapshot!(log(ScrollAnchor::Top, 2, Ok(&output())));
+    }
+
+    #[test]
+    fn log_error() {
+        assert_snapshot!(log(
+            ScrollAnchor::Bottom,
+            0,
+            Err("File error: No such file or directory (os error 2)".to_string())
+        ));
+    }
+
+    #[test]
+    fn dashboard() {
+        let mut jobs = jobs();
+        jobs[3].start_time = Some(at(14, 30));
+        let budgets = [BudgetStatus {
+            budget: Budget {
+                account: "proj".to_string(),
+                core_hours: 100_000.0,
+                period: Period::Quarter,
+                warn_at: vec![75.0, 90.0, 100.0],
+            },
+            since: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            used: Ok(80_000.0),
+        }];
+        let history = (0..8)
+            .map(|i| QueueDepth {
+                running: i,
+                pending: 8 - i,
+            })
+            .collect();
+        let view = SummaryView {
+            jobs: &jobs,
+            now: at(12, 0),
+            budgets: &budgets,
+            histories: vec![("mine", history)],
+        };
+        let nodes = (1..=12)
+            .map(|i| Node {
+                name: format!("gpu-{i:02}"),
+                state: match i {
+                    1..=6 => "allocated",
+                    7..=9 => "mixed",
+                    10 => "drained",
+                    _ => "idle",
+                }
+                .to_string(),
+                partitions: vec!["gpu".to_string()],
+                cpus: String::new(),
+                reason: String::new(),
+            })
+            .collect::<Vec<_>>();
+        let mut lines = vec![summary_line(&view)];
+        lines.extend(heatmap_lines(&nodes, 120));
+        assert_snapshot!(render(120, lines.len() as u16, |area, buf| {
+            Paragraph::new(lines).render(area, buf)
+        }));
+    }
+}
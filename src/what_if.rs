@@ -0,0 +1,187 @@
+use std::{collections::BTreeSet, thread};
+
+use chrono::NaiveDateTime;
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use regex::Regex;
+
+use crate::actions::Resubmission;
+use crate::app::AppMessage;
+use crate::remote::slurm_command;
+use crate::sacctmgr;
+use crate::slurm_time::parse_timestamp;
+
+/// `sbatch --test-only` is run once per candidate, so their number is limited.
+const MAX_CANDIDATES: usize = 16;
+
+lazy_static::lazy_static! {
+    static ref START: Regex = Regex::new(r" to start at (\S+)").unwrap();
+}
+
+/// When a pending job would start in another partition or with another QOS.
+pub struct Estimate {
+    pub partition: String,
+    pub qos: String,
+    /// The estimated start, or why the job could not be submitted there.
+    pub start: Result<Option<NaiveDateTime>, String>,
+    /// Where the job waits now.
+    pub current: bool,
+}
+
+struct WhatIfRequest {
+    job_id: String,
+    resubmission: Resubmission,
+    partition: String,
+    qos: String,
+    sacctmgr: bool,
+}
+
+/// The partitions shown by `sinfo`.
+fn partitions() -> Result<BTreeSet<String>, String> {
+    let output = slurm_command("sinfo")
+        .args(["--noheader", "--format=%R"])
+        .output()
+        .map_err(|e| format!("Failed to execute sinfo: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "sinfo failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+/// Every partition with the job's QOS, and the job's partition with every QOS of my
+/// associations. Associations restricted to partitions restrict the partitions, too.
+fn candidates(request: &WhatIfRequest) -> Result<Vec<(String, String)>, String> {
+    let mut partitions = partitions()?;
+    let mut qos = BTreeSet::new();
+    if request.sacctmgr {
+        // without associations, only the partitions are compared
+        let associations = sacctmgr::user_associations().unwrap_or_default();
+        if !associations.is_empty() && associations.iter().all(|a| !a.partition.is_empty()) {
+            partitions.retain(|p| associations.iter().any(|a| a.partition == *p));
+        }
+        qos.extend(
+            associations
+                .iter()
+                .flat_map(|a| a.qos.split(','))
+                .filter(|q| !q.is_empty())
+                .map(str::to_string),
+        );
+    }
+    // pending jobs may be submitted to several partitions, e.g. `gpu,cpu`
+    let current = request.partition.split(',').next().unwrap_or_default();
+    let mut candidates = vec![(current.to_string(), request.qos.clone())];
+    candidates.extend(
+        partitions
+            .into_iter()
+            .filter(|p| p != current)
+            .map(|p| (p, request.qos.clone())),
+    );
+    candidates.extend(
+        qos.into_iter()
+            .filter(|q| *q != request.qos)
+            .map(|q| (current.to_string(), q)),
+    );
+    candidates.truncate(MAX_CANDIDATES);
+    Ok(candidates)
+}
+
+fn estimates(request: &WhatIfRequest) -> Result<Vec<Estimate>, String> {
+    let candidates = candidates(request)?;
+    let mut estimates = thread::scope(|s| {
+        let tests = candidates
+            .iter()
+            .map(|(partition, qos)| {
+                s.spawn(move || {
+                    request
+                        .resubmission
+                        .with_option("--partition", "-p", partition)
+                        .with_option("--qos", "-q", qos)
+                        .test_only()
+                })
+            })
+            .collect::<Vec<_>>();
+        candidates
+            .iter()
+            .zip(tests)
+            .enumerate()
+            .map(|(i, ((partition, qos), test))| Estimate {
+                partition: partition.clone(),
+                qos: qos.clone(),
+                start: match test.join() {
+                    Ok(Ok(answer)) => {
+                        Ok(START.captures(&answer).and_then(|c| parse_timestamp(&c[1])))
+                    }
+                    Ok(Err(e)) => Err(e),
+                    Err(_) => Err("sbatch --test-only failed".to_string()),
+                },
+                current: i == 0,
+            })
+            .collect::<Vec<_>>()
+    });
+    // earliest first, then those without estimate, then the rejected ones
+    estimates.sort_by_key(|e| match &e.start {
+        Ok(Some(start)) => (0, Some(*start)),
+        Ok(None) => (1, None),
+        Err(_) => (2, None),
+    });
+    Ok(estimates)
+}
+
+/// Asks `sbatch --test-only` in the background.
+struct WhatIfWorker {
+    app: Sender<AppMessage>,
+    receiver: Receiver<WhatIfRequest>,
+}
+
+impl WhatIfWorker {
+    fn run(&mut self) {
+        while let Ok(request) = self.receiver.recv() {
+            let estimates = estimates(&request);
+            if self
+                .app
+                .send(AppMessage::WhatIf(request.job_id, estimates))
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+pub struct WhatIfHandle {
+    sender: Sender<WhatIfRequest>,
+}
+
+impl WhatIfHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = WhatIfWorker { app, receiver };
+        thread::spawn(move || actor.run());
+        Self { sender }
+    }
+
+    /// Estimates the start of the submission in the other partitions and QOS I have access
+    /// to, answered with [`AppMessage::WhatIf`].
+    pub fn request(
+        &self,
+        job_id: String,
+        resubmission: Resubmission,
+        partition: String,
+        qos: String,
+        sacctmgr: bool,
+    ) {
+        let _ = self.sender.send(WhatIfRequest {
+            job_id,
+            resubmission,
+            partition,
+            qos,
+            sacctmgr,
+        });
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use regex::Regex;
+
+use crate::app::Job;
+use crate::job_watcher::list_jobs;
+use crate::squeue_args::SqueueArgs;
+
+/// How a file was attributed to a job, from most to least certain.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Match {
+    Stdout,
+    Stderr,
+    DefaultOutputName,
+    WorkDir,
+}
+
+impl Match {
+    fn describe(&self) -> &'static str {
+        match self {
+            Match::Stdout => "stdout",
+            Match::Stderr => "stderr",
+            Match::DefaultOutputName => "default output file name",
+            Match::WorkDir => "inside working directory",
+        }
+    }
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn match_job(job: &Job, path: &Path) -> Option<Match> {
+    lazy_static::lazy_static! {
+        static ref DEFAULT_OUTPUT: Regex = Regex::new(r"^slurm-(\d+(?:_\d+)?)\.out$").unwrap();
+    }
+
+    if job.stdout.as_deref().map(normalize).as_deref() == Some(path) {
+        return Some(Match::Stdout);
+    }
+    if job.stderr.as_deref().map(normalize).as_deref() == Some(path) {
+        return Some(Match::Stderr);
+    }
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    if let Some(caps) = DEFAULT_OUTPUT.captures(file_name) {
+        if caps[1] == job.id() || caps[1] == job.job_id {
+            return Some(Match::DefaultOutputName);
+        }
+    }
+    job.work_dir
+        .as_deref()
+        .map(normalize)
+        .filter(|d| path.starts_with(d))
+        .map(|_| Match::WorkDir)
+}
+
+/// Prints the jobs that likely produced the file at `path`, best matches first.
+/// Returns whether any job was found.
+pub fn whose(path: &Path, squeue_args: &SqueueArgs, since: Duration) -> bool {
+    let path = normalize(path);
+    let jobs = list_jobs(&squeue_args.to_vec(), &squeue_args.to_sacct_vec(), since);
+
+    let mut matches = jobs
+        .iter()
+        .filter_map(|j| match_job(j, &path).map(|m| (m, j)))
+        .collect::<Vec<_>>();
+    matches.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    // only report working directory matches if there is nothing better
+    let only_work_dir = matches.iter().all(|(m, _)| *m == Match::WorkDir);
+    for (m, j) in &matches {
+        if !only_work_dir && *m == Match::WorkDir {
+            break;
+        }
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            j.id(),
+            j.name,
+            j.user,
+            j.state,
+            m.describe()
+        );
+    }
+    !matches.is_empty()
+}